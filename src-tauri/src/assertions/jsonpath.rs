@@ -0,0 +1,244 @@
+use serde_json::Value;
+
+/// One step of a parsed path
+enum Segment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+}
+
+fn parse(path: &str) -> Result<Vec<Segment>, String> {
+    let invalid = || format!("Invalid JSONPath: {}", path);
+    let rest = path.trim();
+    let mut rest = rest.strip_prefix('$').ok_or_else(invalid)?;
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let name = &after[..end];
+            segments.push(match name {
+                "" => return Err(invalid()),
+                "*" => Segment::Wildcard,
+                name => Segment::Key(name.to_string()),
+            });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let after = after.trim_start();
+            if let Some((key, after)) = quoted_key(after) {
+                segments.push(Segment::Key(key));
+                rest = after.trim_start().strip_prefix(']').ok_or_else(invalid)?;
+                continue;
+            }
+            let end = after.find(']').ok_or_else(invalid)?;
+            let inner = after[..end].trim();
+            segments.push(if inner == "*" {
+                Segment::Wildcard
+            } else {
+                Segment::Index(inner.parse().map_err(|_| invalid())?)
+            });
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+    Ok(segments)
+}
+
+/// Reads a `'…'` or `"…"` key at the start of `input`, where a backslash escapes the next
+/// character. Returns the key and what follows the closing quote.
+fn quoted_key(input: &str) -> Option<(String, &str)> {
+    let quote = input.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let mut key = String::new();
+    let mut chars = input[1..].char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => key.push(chars.next()?.1),
+            c if c == quote => return Some((key, &input[1 + i + 1..])),
+            c => key.push(c),
+        }
+    }
+    None
+}
+
+/// Evaluates the dot/bracket subset of JSONPath: `$.items[0].id`, `$['a b']`, `$.items[*]`,
+/// and negative indexes counting from the end
+pub fn select<'a>(root: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let mut current = vec![root];
+    for segment in parse(path)? {
+        current = current
+            .into_iter()
+            .flat_map(|value| -> Vec<&Value> {
+                match (&segment, value) {
+                    (Segment::Key(key), Value::Object(map)) => map.get(key).into_iter().collect(),
+                    (Segment::Index(index), Value::Array(items)) => {
+                        let index = if *index < 0 {
+                            items.len() as i64 + index
+                        } else {
+                            *index
+                        };
+                        usize::try_from(index)
+                            .ok()
+                            .and_then(|i| items.get(i))
+                            .into_iter()
+                            .collect()
+                    }
+                    (Segment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (Segment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    Ok(current)
+}
+
+/// One step of a concrete location in a JSON document
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathStep {
+    Key(String),
+    Index(usize),
+}
+
+/// A parsed path, for checking many locations against it
+pub struct Pattern(Vec<Segment>);
+
+impl Pattern {
+    pub fn parse(path: &str) -> Result<Self, String> {
+        parse(path).map(Self)
+    }
+
+    /// Whether a concrete location is one the path selects. Negative indexes never match,
+    /// since they depend on the array's length.
+    pub fn matches(&self, location: &[PathStep]) -> bool {
+        self.0.len() == location.len()
+            && self
+                .0
+                .iter()
+                .zip(location)
+                .all(|(segment, step)| match (segment, step) {
+                    (Segment::Wildcard, _) => true,
+                    (Segment::Key(key), PathStep::Key(name)) => key == name,
+                    (Segment::Index(index), PathStep::Index(i)) => {
+                        usize::try_from(*index) == Ok(*i)
+                    }
+                    _ => false,
+                })
+    }
+}
+
+/// Formats a location as a path `select` accepts, quoting keys that aren't plain names and
+/// escaping quotes and backslashes inside them
+pub fn format(location: &[PathStep]) -> String {
+    let mut path = String::from("$");
+    for step in location {
+        match step {
+            PathStep::Key(key)
+                if !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+            {
+                path.push('.');
+                path.push_str(key);
+            }
+            PathStep::Key(key) => {
+                path.push_str("['");
+                for c in key.chars() {
+                    if c == '\'' || c == '\\' {
+                        path.push('\\');
+                    }
+                    path.push(c);
+                }
+                path.push_str("']");
+            }
+            PathStep::Index(i) => path.push_str(&format!("[{}]", i)),
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(name: &str) -> PathStep {
+        PathStep::Key(name.to_string())
+    }
+
+    #[test]
+    fn selects_keys_indexes_and_wildcards() {
+        let body = json!({"items": [{"id": 1}, {"id": 2}], "a b": true});
+        assert_eq!(select(&body, "$.items[0].id").unwrap(), vec![&json!(1)]);
+        assert_eq!(select(&body, "$.items[-1].id").unwrap(), vec![&json!(2)]);
+        assert_eq!(
+            select(&body, "$.items[*].id").unwrap(),
+            vec![&json!(1), &json!(2)]
+        );
+        assert_eq!(select(&body, "$['a b']").unwrap(), vec![&json!(true)]);
+        assert_eq!(select(&body, "$[\"a b\"]").unwrap(), vec![&json!(true)]);
+        assert!(select(&body, "$.missing").unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        for path in ["items", "$.", "$[0", "$[x]", "$['a'", "$['a'b]", "$..a"] {
+            assert!(parse(path).is_err(), "{path} should be rejected");
+        }
+    }
+
+    #[test]
+    fn reads_escaped_quotes_and_backslashes_in_keys() {
+        let body = json!({"it's": 1, "a\\b": 2, "x]y": 3});
+        assert_eq!(select(&body, r"$['it\'s']").unwrap(), vec![&json!(1)]);
+        assert_eq!(select(&body, r#"$["it's"]"#).unwrap(), vec![&json!(1)]);
+        assert_eq!(select(&body, r"$['a\\b']").unwrap(), vec![&json!(2)]);
+        assert_eq!(select(&body, "$['x]y']").unwrap(), vec![&json!(3)]);
+    }
+
+    #[test]
+    fn formats_plain_names_with_dots_and_others_in_brackets() {
+        let location = [key("items"), PathStep::Index(0), key("first name")];
+        assert_eq!(format(&location), "$.items[0]['first name']");
+        assert_eq!(format(&[key("it's"), key(r"a\b")]), r"$['it\'s']['a\\b']");
+        assert_eq!(format(&[key("")]), "$['']");
+    }
+
+    #[test]
+    fn formatted_locations_parse_back_to_themselves() {
+        let keys = [
+            "id",
+            "first name",
+            "it's",
+            r"a\b",
+            r"\'",
+            "x]y",
+            "a.b",
+            "[0]",
+            "\"",
+            "",
+            "ключ",
+        ];
+        for name in keys {
+            let location = [key("data"), PathStep::Index(3), key(name)];
+            let path = format(&location);
+            let pattern = Pattern::parse(&path).unwrap_or_else(|e| panic!("{e}"));
+            assert!(pattern.matches(&location), "{path} should match {name:?}");
+
+            let body = json!({"data": [0, 1, 2, {name: "found"}]});
+            assert_eq!(select(&body, &path).unwrap(), vec![&json!("found")]);
+        }
+    }
+
+    #[test]
+    fn patterns_match_locations_by_shape() {
+        let pattern = Pattern::parse("$.items[*].updatedAt").unwrap();
+        assert!(pattern.matches(&[key("items"), PathStep::Index(4), key("updatedAt")]));
+        assert!(!pattern.matches(&[key("items"), PathStep::Index(4)]));
+        assert!(!pattern.matches(&[key("items"), key("x"), key("createdAt")]));
+        assert!(!Pattern::parse("$.items[-1]")
+            .unwrap()
+            .matches(&[key("items"), PathStep::Index(0)]));
+    }
+}
@@ -0,0 +1,364 @@
+pub mod jsonpath;
+
+use crate::network::request::BackendResponse;
+use crate::validation;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A check run against an operation's response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Assertion {
+    Status {
+        equals: u16,
+    },
+    /// Passes when the header is present and its value matches the regex
+    Header {
+        name: String,
+        matches: String,
+    },
+    /// Compares the values selected from the JSON body; several matches compare as an array
+    #[serde(rename_all = "camelCase")]
+    JsonPath {
+        path: String,
+        equals: Option<Value>,
+        /// Regex the selected value must match, applied to strings as-is and to
+        /// other values as JSON text
+        matches: Option<String>,
+    },
+    /// Passes when the response matches the operation's declared responses in the spec
+    Schema,
+    #[serde(rename_all = "camelCase")]
+    ResponseTime {
+        below_ms: f64,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// The value that was checked, when there was one
+    pub actual: Option<Value>,
+    pub message: Option<String>,
+}
+
+impl AssertionResult {
+    fn pass(assertion: &Assertion, actual: Option<Value>) -> Self {
+        Self {
+            assertion: assertion.clone(),
+            passed: true,
+            actual,
+            message: None,
+        }
+    }
+
+    fn fail(assertion: &Assertion, actual: Option<Value>, message: impl Into<String>) -> Self {
+        Self {
+            assertion: assertion.clone(),
+            passed: false,
+            actual,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// Reads the assertions saved for an operation; none when nothing was saved
+pub async fn load(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+) -> Result<Vec<Assertion>, String> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT assertions_json FROM operation_assertions WHERE workspace_id = ? AND operation_key = ?",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match row {
+        Some((json,)) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Replaces the assertions saved for an operation
+pub async fn save(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    assertions: &[Assertion],
+) -> Result<(), String> {
+    if assertions.is_empty() {
+        sqlx::query(
+            "DELETE FROM operation_assertions WHERE workspace_id = ? AND operation_key = ?",
+        )
+        .bind(workspace_id)
+        .bind(operation_key)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let json = serde_json::to_string(assertions).map_err(|e| e.to_string())?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    sqlx::query(
+        "INSERT OR REPLACE INTO operation_assertions (workspace_id, operation_key, assertions_json, last_modified)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .bind(json)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Compares JSON values, with numbers compared by value so `1` equals `1.0`
+fn json_equals(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_i64(), y.as_i64()) {
+            (Some(x), Some(y)) => x == y,
+            _ => x.as_f64() == y.as_f64(),
+        },
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| json_equals(x, y))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(key, x)| y.get(key).is_some_and(|y| json_equals(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Runs every assertion against a response. `spec` is only needed for `Schema` assertions.
+pub fn evaluate(
+    assertions: &[Assertion],
+    response: &BackendResponse,
+    spec: Option<&Value>,
+    operation_key: &str,
+) -> Vec<AssertionResult> {
+    let body: Option<Value> = serde_json::from_str(&response.body_text).ok();
+    assertions
+        .iter()
+        .map(|assertion| evaluate_one(assertion, response, body.as_ref(), spec, operation_key))
+        .collect()
+}
+
+fn evaluate_one(
+    assertion: &Assertion,
+    response: &BackendResponse,
+    body: Option<&Value>,
+    spec: Option<&Value>,
+    operation_key: &str,
+) -> AssertionResult {
+    match assertion {
+        Assertion::Status { equals } => {
+            let actual = Some(Value::from(response.status));
+            if response.status == *equals {
+                AssertionResult::pass(assertion, actual)
+            } else {
+                AssertionResult::fail(
+                    assertion,
+                    actual,
+                    format!("Expected status {}, got {}", equals, response.status),
+                )
+            }
+        }
+        Assertion::Header { name, matches } => {
+            let value = response
+                .headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone());
+            let Some(value) = value else {
+                return AssertionResult::fail(assertion, None, format!("Missing header {}", name));
+            };
+            let actual = Some(Value::String(value.clone()));
+            match Regex::new(matches) {
+                Ok(re) if re.is_match(&value) => AssertionResult::pass(assertion, actual),
+                Ok(_) => AssertionResult::fail(
+                    assertion,
+                    actual,
+                    format!("Header {} does not match {}", name, matches),
+                ),
+                Err(e) => AssertionResult::fail(assertion, actual, e.to_string()),
+            }
+        }
+        Assertion::JsonPath {
+            path,
+            equals,
+            matches,
+        } => {
+            let Some(body) = body else {
+                return AssertionResult::fail(assertion, None, "Response body is not JSON");
+            };
+            let selected = match jsonpath::select(body, path) {
+                Ok(selected) => selected,
+                Err(e) => return AssertionResult::fail(assertion, None, e),
+            };
+            let actual = match selected.as_slice() {
+                [] => {
+                    return AssertionResult::fail(
+                        assertion,
+                        None,
+                        format!("{} matched nothing", path),
+                    )
+                }
+                [single] => (*single).clone(),
+                many => Value::Array(many.iter().map(|v| (*v).clone()).collect()),
+            };
+
+            if let Some(expected) = equals {
+                if !json_equals(&actual, expected) {
+                    return AssertionResult::fail(
+                        assertion,
+                        Some(actual),
+                        format!("Expected {} to equal {}", path, expected),
+                    );
+                }
+            }
+            if let Some(pattern) = matches {
+                let text = match &actual {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                match Regex::new(pattern) {
+                    Ok(re) if re.is_match(&text) => {}
+                    Ok(_) => {
+                        return AssertionResult::fail(
+                            assertion,
+                            Some(actual),
+                            format!("{} does not match {}", path, pattern),
+                        )
+                    }
+                    Err(e) => return AssertionResult::fail(assertion, Some(actual), e.to_string()),
+                }
+            }
+            AssertionResult::pass(assertion, Some(actual))
+        }
+        Assertion::Schema => {
+            let Some(spec) = spec else {
+                return AssertionResult::fail(assertion, None, "Workspace has no active spec");
+            };
+            match validation::validate_response(spec, operation_key, response) {
+                Ok(report) if report.valid => AssertionResult::pass(assertion, None),
+                Ok(report) => {
+                    let messages: Vec<String> = report
+                        .violations
+                        .iter()
+                        .map(|v| format!("{} {}: {}", v.location, v.pointer, v.message))
+                        .collect();
+                    AssertionResult::fail(assertion, None, messages.join("; "))
+                }
+                Err(e) => AssertionResult::fail(assertion, None, e),
+            }
+        }
+        Assertion::ResponseTime { below_ms } => {
+            let total = response.timings.total_ms;
+            let actual = serde_json::Number::from_f64(total).map(Value::Number);
+            if total < *below_ms {
+                AssertionResult::pass(assertion, actual)
+            } else {
+                AssertionResult::fail(
+                    assertion,
+                    actual,
+                    format!("Took {:.0} ms, limit is {} ms", total, below_ms),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(status: u16, body: Value) -> BackendResponse {
+        serde_json::from_value(json!({
+            "status": status,
+            "status_text": "",
+            "headers": { "Content-Type": "application/json" },
+            "body_text": body.to_string(),
+            "timings": {
+                "prepare_ms": 0.0, "dns_lookup_ms": 0.0, "tcp_connect_ms": 0.0,
+                "tls_handshake_ms": 0.0, "ttfb_ms": 0.0, "download_ms": 0.0,
+                "process_ms": 0.0, "total_ms": 12.0
+            },
+            "wire_size_bytes": 0,
+            "body_size_bytes": 0,
+            "session_id": null
+        }))
+        .unwrap()
+    }
+
+    fn json_path(path: &str, equals: Value) -> Assertion {
+        Assertion::JsonPath {
+            path: path.to_string(),
+            equals: Some(equals),
+            matches: None,
+        }
+    }
+
+    fn passed(assertions: &[Assertion], response: &BackendResponse) -> Vec<bool> {
+        evaluate(assertions, response, None, "get:/pets")
+            .iter()
+            .map(|r| r.passed)
+            .collect()
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        let response = response(200, json!({ "price": 1.0, "count": 3, "sizes": [1, 2.5] }));
+        let assertions = [
+            json_path("$.price", json!(1)),
+            json_path("$.count", json!(3.0)),
+            json_path("$.sizes", json!([1.0, 2.5])),
+            json_path("$", json!({ "count": 3, "price": 1, "sizes": [1, 2.5] })),
+            json_path("$.count", json!(4)),
+            json_path("$.count", json!("3")),
+        ];
+        assert_eq!(
+            passed(&assertions, &response),
+            [true, true, true, true, false, false]
+        );
+    }
+
+    #[test]
+    fn checks_status_headers_and_timing() {
+        let response = response(201, json!({}));
+        let assertions = [
+            Assertion::Status { equals: 201 },
+            Assertion::Status { equals: 200 },
+            Assertion::Header {
+                name: "content-type".to_string(),
+                matches: "^application/json".to_string(),
+            },
+            Assertion::Header {
+                name: "etag".to_string(),
+                matches: ".*".to_string(),
+            },
+            Assertion::ResponseTime { below_ms: 50.0 },
+            Assertion::Schema,
+        ];
+        assert_eq!(
+            passed(&assertions, &response),
+            [true, false, true, false, true, false]
+        );
+    }
+}
@@ -0,0 +1,808 @@
+//! Rotating snapshots of the application database, integrity checks at startup, and restore
+
+pub mod online;
+
+use crate::db::migrations::{self, latest_version};
+use crate::db::Database;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::{ConnectOptions, Connection};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Snapshots kept in the backup directory; the oldest are deleted past this
+pub const KEEP_BACKUPS: usize = 5;
+/// Directory inside the app config directory that holds the snapshots
+pub const BACKUP_DIR_NAME: &str = "backups";
+/// Minimum age of the newest scheduled snapshot before another is taken
+const BACKUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How often the background task checks whether a scheduled snapshot is due
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Delay before the first check so the copy doesn't compete with startup
+const STARTUP_DELAY: Duration = Duration::from_secs(5 * 60);
+const FILE_PREFIX: &str = "cogeass-";
+const FILE_EXTENSION: &str = ".db";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupReason {
+    Scheduled,
+    PreMigration,
+    Manual,
+    /// The database as it was just before a restore replaced it
+    PreRestore,
+}
+
+impl BackupReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Scheduled => "scheduled",
+            Self::PreMigration => "pre-migration",
+            Self::Manual => "manual",
+            Self::PreRestore => "pre-restore",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        [
+            Self::Scheduled,
+            Self::PreMigration,
+            Self::Manual,
+            Self::PreRestore,
+        ]
+        .into_iter()
+        .find(|reason| reason.as_str() == s)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub file_name: String,
+    pub created_at: i64,
+    pub reason: BackupReason,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// `PRAGMA integrity_check` (or `quick_check` at startup) found nothing wrong
+    pub ok: bool,
+    /// SQLite could open the file and read its schema at all
+    pub readable: bool,
+    pub problems: Vec<String>,
+}
+
+/// What the startup check found and did around migrations
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseHealth {
+    pub integrity: IntegrityReport,
+    /// Where an unreadable database was moved so a fresh one could be created
+    pub quarantined: Option<String>,
+    pub pre_migration_backup: Option<String>,
+    /// Why a migration failed. The pre-migration backup then replaces the partly migrated
+    /// database, which stays at its previous schema.
+    pub migration_error: Option<String>,
+    /// Why quarantining, the pre-migration backup or restoring it failed
+    pub error: Option<String>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn file_name(created_at: i64, reason: BackupReason) -> String {
+    format!(
+        "{}{}-{}{}",
+        FILE_PREFIX,
+        created_at,
+        reason.as_str(),
+        FILE_EXTENSION
+    )
+}
+
+fn parse_file_name(name: &str) -> Option<(i64, BackupReason)> {
+    let stem = name
+        .strip_prefix(FILE_PREFIX)?
+        .strip_suffix(FILE_EXTENSION)?;
+    let (created_at, reason) = stem.split_once('-')?;
+    Some((created_at.parse().ok()?, BackupReason::parse(reason)?))
+}
+
+/// Opens an existing database without creating it
+async fn connect(path: &Path) -> Result<SqliteConnection, String> {
+    SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))
+        .map_err(|e| e.to_string())?
+        .connect()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Runs `PRAGMA integrity_check` against the database at `path`
+pub async fn check(path: &Path) -> IntegrityReport {
+    run_check(path, "PRAGMA integrity_check").await
+}
+
+/// Runs `PRAGMA quick_check`, which skips verifying indexes against their tables so it
+/// stays fast on a large database
+pub async fn quick_check(path: &Path) -> IntegrityReport {
+    run_check(path, "PRAGMA quick_check").await
+}
+
+async fn run_check(path: &Path, pragma: &str) -> IntegrityReport {
+    let unreadable = |problem: String| IntegrityReport {
+        ok: false,
+        readable: false,
+        problems: vec![problem],
+    };
+    let mut conn = match connect(path).await {
+        Ok(conn) => conn,
+        Err(e) => return unreadable(e),
+    };
+    let report = match sqlx::query("SELECT COUNT(*) FROM sqlite_master")
+        .execute(&mut conn)
+        .await
+    {
+        Err(e) => unreadable(e.to_string()),
+        Ok(_) => match sqlx::query_as::<_, (String,)>(pragma)
+            .fetch_all(&mut conn)
+            .await
+        {
+            Ok(rows) => {
+                let problems: Vec<String> = rows
+                    .into_iter()
+                    .map(|(row,)| row)
+                    .filter(|row| row != "ok")
+                    .collect();
+                IntegrityReport {
+                    ok: problems.is_empty(),
+                    readable: true,
+                    problems,
+                }
+            }
+            Err(e) => IntegrityReport {
+                ok: false,
+                readable: true,
+                problems: vec![e.to_string()],
+            },
+        },
+    };
+    let _ = conn.close().await;
+    report
+}
+
+/// Highest migration applied to the database, or 0 before the first
+pub async fn schema_version(path: &Path) -> Result<i64, String> {
+    let mut conn = connect(path).await?;
+    let (has_table,): (bool,) = sqlx::query_as(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(&mut conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    let version = if has_table {
+        let (version,): (Option<i64>,) =
+            sqlx::query_as("SELECT MAX(version) FROM _sqlx_migrations WHERE success = 1")
+                .fetch_one(&mut conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        version.unwrap_or(0)
+    } else {
+        0
+    };
+    let _ = conn.close().await;
+    Ok(version)
+}
+
+/// Backups in `dir`, newest first
+pub fn list(dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut backups = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        let Some((created_at, reason)) = parse_file_name(&name) else {
+            continue;
+        };
+        let size_bytes = entry.metadata().map_err(|e| e.to_string())?.len();
+        backups.push(BackupInfo {
+            file_name: name,
+            created_at,
+            reason,
+            size_bytes,
+        });
+    }
+    backups.sort_by_key(|b| std::cmp::Reverse(b.created_at));
+    Ok(backups)
+}
+
+/// Deletes all but the `keep` newest backups
+pub fn rotate(dir: &Path, keep: usize) -> Result<(), String> {
+    for backup in list(dir)?.into_iter().skip(keep) {
+        std::fs::remove_file(dir.join(&backup.file_name)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Copies the database into a new file in `dir` without rotating
+async fn snapshot(db_path: &Path, dir: &Path, reason: BackupReason) -> Result<BackupInfo, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let mut created_at = now_ms();
+    while dir.join(file_name(created_at, reason)).exists() {
+        created_at += 1;
+    }
+    let name = file_name(created_at, reason);
+    let dest = dir.join(&name);
+    // Written under another name first so a crash mid-copy never leaves a backup that looks whole
+    let partial = dir.join(format!("{}.partial", name));
+    let _ = std::fs::remove_file(&partial);
+
+    let (source, target) = (db_path.to_path_buf(), partial.clone());
+    let copied = tokio::task::spawn_blocking(move || online::copy(&source, &target))
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&partial);
+        return Err(format!("Failed to back up the database: {}", e));
+    }
+    std::fs::rename(&partial, &dest).map_err(|e| e.to_string())?;
+
+    let size_bytes = std::fs::metadata(&dest).map_err(|e| e.to_string())?.len();
+    Ok(BackupInfo {
+        file_name: name,
+        created_at,
+        reason,
+        size_bytes,
+    })
+}
+
+/// Snapshots the database at `db_path` into `dir` and keeps the `keep` newest backups
+pub async fn create(
+    db_path: &Path,
+    dir: &Path,
+    reason: BackupReason,
+    keep: usize,
+) -> Result<BackupInfo, String> {
+    let backup = snapshot(db_path, dir, reason).await?;
+    rotate(dir, keep)?;
+    Ok(backup)
+}
+
+/// Moves a database and its journal files aside, returning the new database path
+pub fn quarantine(db_path: &Path) -> Result<PathBuf, String> {
+    let suffix = format!(".corrupt-{}", now_ms());
+    let mut moved = None;
+    for extension in ["", "-journal", "-wal", "-shm"] {
+        let from = PathBuf::from(format!("{}{}", db_path.display(), extension));
+        if !from.exists() {
+            continue;
+        }
+        let to = PathBuf::from(format!("{}{}", from.display(), suffix));
+        std::fs::rename(&from, &to)
+            .map_err(|e| format!("Failed to move {} aside: {}", from.display(), e))?;
+        if extension.is_empty() {
+            moved = Some(to);
+        }
+    }
+    moved.ok_or_else(|| format!("{} does not exist", db_path.display()))
+}
+
+/// Replaces the database at `db_path` with the backup `file_name` from `dir`. The current
+/// database is backed up first, or quarantined if SQLite can't read it.
+pub async fn restore(
+    db_path: &Path,
+    dir: &Path,
+    file_name: &str,
+    keep: usize,
+) -> Result<(), String> {
+    if !list(dir)?.iter().any(|b| b.file_name == file_name) {
+        return Err(format!("Backup not found: {}", file_name));
+    }
+    let backup = dir.join(file_name);
+    let integrity = check(&backup).await;
+    if !integrity.ok {
+        return Err(format!(
+            "Backup {} is damaged: {}",
+            file_name,
+            integrity.problems.join("; ")
+        ));
+    }
+
+    if db_path.exists() {
+        if check(db_path).await.readable {
+            snapshot(db_path, dir, BackupReason::PreRestore).await?;
+        } else {
+            quarantine(db_path)?;
+        }
+    }
+
+    let target = db_path.to_path_buf();
+    tokio::task::spawn_blocking(move || online::copy(&backup, &target))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to restore the database: {}", e))?;
+    rotate(dir, keep)
+}
+
+/// Checks the database before migrations run. An unreadable file is quarantined so the app
+/// can start with an empty one; a healthy one with pending migrations is backed up first.
+pub async fn prepare(db_path: &Path, dir: &Path, keep: usize) -> DatabaseHealth {
+    let mut health = DatabaseHealth {
+        integrity: IntegrityReport {
+            ok: true,
+            readable: true,
+            problems: Vec::new(),
+        },
+        quarantined: None,
+        pre_migration_backup: None,
+        migration_error: None,
+        error: None,
+    };
+    if !db_path.exists() {
+        return health;
+    }
+
+    health.integrity = quick_check(db_path).await;
+    if !health.integrity.readable {
+        match quarantine(db_path) {
+            Ok(path) => health.quarantined = Some(path.display().to_string()),
+            Err(e) => health.error = Some(e),
+        }
+    } else if health.integrity.ok {
+        let pending = match schema_version(db_path).await {
+            Ok(version) => version < latest_version(),
+            Err(e) => {
+                health.error = Some(e);
+                false
+            }
+        };
+        if pending {
+            match create(db_path, dir, BackupReason::PreMigration, keep).await {
+                Ok(backup) => health.pre_migration_backup = Some(backup.file_name),
+                Err(e) => health.error = Some(e),
+            }
+        }
+    }
+    health
+}
+
+/// Applies pending migrations to the database at `db_path`, creating it if missing. When
+/// one fails, the pre-migration backup from [`prepare`] is restored so the app starts on
+/// the data as it was rather than a half-upgraded schema.
+pub async fn migrate(db_path: &Path, dir: &Path, keep: usize, health: &mut DatabaseHealth) {
+    let migrated = match Database::open(db_path).await {
+        Ok(db) => {
+            let result = migrations::apply_pending(db.pool(), latest_version()).await;
+            db.pool().close().await;
+            result
+        }
+        Err(e) => Err(e),
+    };
+    let Err(e) = migrated else {
+        return;
+    };
+    health.migration_error = Some(e);
+    let Some(backup) = &health.pre_migration_backup else {
+        return;
+    };
+    if let Err(e) = restore(db_path, dir, backup, keep).await {
+        health.error = Some(format!("Failed to restore {}: {}", backup, e));
+    }
+}
+
+/// Managed state for the backup commands
+pub struct BackupManager {
+    pub db_path: PathBuf,
+    pub dir: PathBuf,
+    pub keep: usize,
+    health: std::sync::Mutex<DatabaseHealth>,
+    /// Held while a backup or restore runs so two never overlap
+    busy: tokio::sync::Mutex<()>,
+}
+
+impl BackupManager {
+    pub fn new(db_path: PathBuf, dir: PathBuf, keep: usize, health: DatabaseHealth) -> Self {
+        Self {
+            db_path,
+            dir,
+            keep,
+            health: std::sync::Mutex::new(health),
+            busy: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn health(&self) -> DatabaseHealth {
+        self.health
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn list(&self) -> Result<Vec<BackupInfo>, String> {
+        list(&self.dir)
+    }
+
+    pub async fn create(&self, reason: BackupReason) -> Result<BackupInfo, String> {
+        let _busy = self.busy.lock().await;
+        create(&self.db_path, &self.dir, reason, self.keep).await
+    }
+
+    pub async fn restore(&self, file_name: &str) -> Result<(), String> {
+        let _busy = self.busy.lock().await;
+        restore(&self.db_path, &self.dir, file_name, self.keep).await
+    }
+
+    /// Takes a scheduled backup if the last one is old enough and the database is healthy,
+    /// so a damaged database never rotates good backups out
+    async fn backup_if_due(&self) -> Result<(), String> {
+        let last = self
+            .list()?
+            .into_iter()
+            .find(|b| b.reason == BackupReason::Scheduled)
+            .map(|b| b.created_at);
+        if last.is_some_and(|at| now_ms() - at < BACKUP_INTERVAL.as_millis() as i64) {
+            return Ok(());
+        }
+        let integrity = check(&self.db_path).await;
+        let ok = integrity.ok;
+        self.health
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .integrity = integrity;
+        if ok {
+            self.create(BackupReason::Scheduled).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Background task that takes the scheduled backups
+pub async fn run_periodically<R: Runtime>(app: AppHandle<R>) {
+    tokio::time::sleep(STARTUP_DELAY).await;
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        // A failed backup is retried on the next tick
+        let _ = app.state::<BackupManager>().backup_if_due().await;
+    }
+}
+
+/// Checks, backs up and migrates the database. Registered ahead of the SQL plugin so the
+/// frontend never loads a file that hasn't been checked.
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new("backup")
+        .setup(|app, _api| {
+            let db_path = crate::db::database_path(app)?;
+            let dir = app
+                .path()
+                .app_config_dir()
+                .map_err(|e| e.to_string())?
+                .join(BACKUP_DIR_NAME);
+            let health = tauri::async_runtime::block_on(async {
+                let mut health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+                migrate(&db_path, &dir, KEEP_BACKUPS, &mut health).await;
+                health
+            });
+            app.manage(BackupManager::new(db_path, dir, KEEP_BACKUPS, health));
+            tauri::async_runtime::spawn(run_periodically(app.clone()));
+            Ok(())
+        })
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{migrations, Database};
+    use std::io::{Seek, SeekFrom, Write};
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("cogeass-backup-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A migrated database with one workspace in it
+    async fn seeded(path: &Path, workspace: &str) {
+        let db = Database::open(path).await.unwrap();
+        migrations::apply_all(db.pool()).await.unwrap();
+        sqlx::query("INSERT INTO workspaces (id, name, sort_order) VALUES (?, ?, 0)")
+            .bind(workspace)
+            .bind(workspace)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        db.pool().close().await;
+    }
+
+    /// A database migrated up to `version` the way the app does it, with one workspace in it
+    async fn seeded_at(path: &Path, version: i64, workspace: &str) {
+        let db = Database::open(path).await.unwrap();
+        migrations::apply_pending(db.pool(), version).await.unwrap();
+        sqlx::query("INSERT INTO workspaces (id, name, sort_order) VALUES (?, ?, 0)")
+            .bind(workspace)
+            .bind(workspace)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        db.pool().close().await;
+    }
+
+    async fn workspace_names(path: &Path) -> Vec<String> {
+        let db = Database::open(path).await.unwrap();
+        let names: Vec<(String,)> = sqlx::query_as("SELECT name FROM workspaces ORDER BY name")
+            .fetch_all(db.pool())
+            .await
+            .unwrap();
+        db.pool().close().await;
+        names.into_iter().map(|(name,)| name).collect()
+    }
+
+    /// Overwrites `len` bytes at `offset` with garbage
+    fn corrupt(path: &Path, offset: u64, len: usize) {
+        let mut file = std::fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&vec![0xA5; len]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rotation_keeps_the_newest_backups() {
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+        seeded(&db_path, "Main").await;
+
+        let mut created = Vec::new();
+        for _ in 0..4 {
+            created.push(
+                create(&db_path, &dir, BackupReason::Manual, 3)
+                    .await
+                    .unwrap(),
+            );
+        }
+        std::fs::write(dir.join("notes.txt"), "not a backup").unwrap();
+
+        let kept: Vec<String> = list(&dir)
+            .unwrap()
+            .into_iter()
+            .map(|b| b.file_name)
+            .collect();
+        let newest: Vec<String> = created
+            .iter()
+            .rev()
+            .take(3)
+            .map(|b| b.file_name.clone())
+            .collect();
+        assert_eq!(kept, newest);
+        assert!(dir.join("notes.txt").exists());
+        assert!(check(&dir.join(&kept[0])).await.ok);
+    }
+
+    #[tokio::test]
+    async fn corrupted_database_is_detected_and_restored() {
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+        seeded(&db_path, "Main").await;
+        let backup = create(&db_path, &dir, BackupReason::Manual, KEEP_BACKUPS)
+            .await
+            .unwrap();
+        assert!(check(&db_path).await.ok);
+
+        // Garbage over one table's root page leaves the schema readable but the table broken
+        let mut conn = connect(&db_path).await.unwrap();
+        let (page_size,): (i64,) = sqlx::query_as("PRAGMA page_size")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        let (root_page,): (i64,) =
+            sqlx::query_as("SELECT rootpage FROM sqlite_master WHERE name = 'workspaces'")
+                .fetch_one(&mut conn)
+                .await
+                .unwrap();
+        conn.close().await.unwrap();
+        corrupt(
+            &db_path,
+            ((root_page - 1) * page_size) as u64,
+            page_size as usize,
+        );
+        let damaged = check(&db_path).await;
+        assert!(damaged.readable);
+        assert!(!damaged.ok);
+        assert!(!damaged.problems.is_empty());
+
+        restore(&db_path, &dir, &backup.file_name, KEEP_BACKUPS)
+            .await
+            .unwrap();
+        assert!(check(&db_path).await.ok);
+        assert_eq!(workspace_names(&db_path).await, vec!["Main"]);
+
+        let backups = list(&dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].reason, BackupReason::PreRestore);
+        assert!(!check(&dir.join(&backups[0].file_name)).await.ok);
+    }
+
+    #[tokio::test]
+    async fn unreadable_database_is_quarantined_and_can_be_restored() {
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+        seeded(&db_path, "Main").await;
+        let backup = create(&db_path, &dir, BackupReason::Manual, KEEP_BACKUPS)
+            .await
+            .unwrap();
+
+        corrupt(&db_path, 0, 100);
+        let health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+        assert!(!health.integrity.readable);
+        assert!(health.pre_migration_backup.is_none());
+        let quarantined = PathBuf::from(health.quarantined.unwrap());
+        assert!(quarantined.exists());
+        assert!(!db_path.exists());
+
+        // The app would start on a fresh database, which the backup then replaces
+        seeded(&db_path, "Scratch").await;
+        restore(&db_path, &dir, &backup.file_name, KEEP_BACKUPS)
+            .await
+            .unwrap();
+        assert_eq!(workspace_names(&db_path).await, vec!["Main"]);
+    }
+
+    #[tokio::test]
+    async fn restore_rejects_unknown_and_damaged_backups() {
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+        seeded(&db_path, "Main").await;
+        let backup = create(&db_path, &dir, BackupReason::Manual, KEEP_BACKUPS)
+            .await
+            .unwrap();
+
+        for name in ["../cogeass.db", "cogeass-1-manual.db", "notes.txt"] {
+            let err = restore(&db_path, &dir, name, KEEP_BACKUPS)
+                .await
+                .unwrap_err();
+            assert!(err.contains("not found"), "{}", err);
+        }
+
+        corrupt(&dir.join(&backup.file_name), 0, 100);
+        let err = restore(&db_path, &dir, &backup.file_name, KEEP_BACKUPS)
+            .await
+            .unwrap_err();
+        assert!(err.contains("damaged"), "{}", err);
+        assert_eq!(workspace_names(&db_path).await, vec!["Main"]);
+        assert_eq!(list(&dir).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn pending_migrations_are_backed_up_first() {
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+
+        let health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+        assert!(health.integrity.ok);
+        assert!(health.pre_migration_backup.is_none());
+
+        seeded(&db_path, "Main").await;
+        let record = |version: i64| {
+            let db_path = db_path.clone();
+            async move {
+                let mut conn = connect(&db_path).await.unwrap();
+                sqlx::query(
+                    "CREATE TABLE IF NOT EXISTS _sqlx_migrations (version BIGINT PRIMARY KEY, success BOOLEAN NOT NULL)",
+                )
+                .execute(&mut conn)
+                .await
+                .unwrap();
+                sqlx::query("INSERT INTO _sqlx_migrations (version, success) VALUES (?, TRUE)")
+                    .bind(version)
+                    .execute(&mut conn)
+                    .await
+                    .unwrap();
+                conn.close().await.unwrap();
+            }
+        };
+
+        record(latest_version() - 1).await;
+        let health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+        let name = health.pre_migration_backup.unwrap();
+        let backups = list(&dir).unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].file_name, name);
+        assert_eq!(backups[0].reason, BackupReason::PreMigration);
+
+        record(latest_version()).await;
+        let health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+        assert!(health.integrity.ok);
+        assert!(health.pre_migration_backup.is_none());
+        assert_eq!(list(&dir).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn startup_applies_pending_migrations() {
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+
+        let mut health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+        migrate(&db_path, &dir, KEEP_BACKUPS, &mut health).await;
+        assert!(health.migration_error.is_none());
+        assert_eq!(schema_version(&db_path).await.unwrap(), latest_version());
+        assert!(list(&dir).unwrap().is_empty());
+
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+        seeded_at(&db_path, latest_version() - 1, "Main").await;
+        let mut health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+        migrate(&db_path, &dir, KEEP_BACKUPS, &mut health).await;
+        assert!(health.migration_error.is_none());
+        assert!(health.pre_migration_backup.is_some());
+        assert_eq!(schema_version(&db_path).await.unwrap(), latest_version());
+        assert_eq!(workspace_names(&db_path).await, vec!["Main"]);
+    }
+
+    #[tokio::test]
+    async fn failed_migration_restores_the_pre_migration_backup() {
+        let tmp = TempDir::new();
+        let (db_path, dir) = (tmp.0.join("cogeass.db"), tmp.0.join("backups"));
+        let version = latest_version() - 2;
+        seeded_at(&db_path, version, "Main").await;
+        // Recording the latest migration aborts, so it fails after the one before succeeds
+        // whatever its SQL does
+        let mut conn = connect(&db_path).await.unwrap();
+        sqlx::query(&format!(
+            "CREATE TRIGGER fail_latest BEFORE INSERT ON _sqlx_migrations
+             WHEN NEW.version = {}
+             BEGIN SELECT RAISE(ABORT, 'injected failure'); END",
+            latest_version()
+        ))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+        conn.close().await.unwrap();
+
+        let mut health = prepare(&db_path, &dir, KEEP_BACKUPS).await;
+        let backup = health.pre_migration_backup.clone().unwrap();
+        migrate(&db_path, &dir, KEEP_BACKUPS, &mut health).await;
+        let error = health.migration_error.unwrap();
+        assert!(error.contains("injected failure"), "{}", error);
+        assert!(health.error.is_none());
+        assert!(check(&db_path).await.ok);
+        assert_eq!(schema_version(&db_path).await.unwrap(), version);
+        assert_eq!(workspace_names(&db_path).await, vec!["Main"]);
+
+        // The half-migrated database is kept next to the backup that replaced it
+        let backups = list(&dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].reason, BackupReason::PreRestore);
+        assert_eq!(backups[1].file_name, backup);
+        assert_eq!(
+            schema_version(&dir.join(&backups[0].file_name))
+                .await
+                .unwrap(),
+            latest_version() - 1
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! SQLite's online backup API, which copies a database page by page while other
+//! connections keep using it
+
+use libsqlite3_sys as ffi;
+use std::ffi::{CStr, CString};
+use std::path::Path;
+use std::ptr;
+use std::time::Duration;
+
+/// How long a copy waits on locks held by other connections before giving up
+const BUSY_TIMEOUT_MS: i32 = 5_000;
+const BUSY_RETRIES: u32 = 50;
+const BUSY_WAIT: Duration = Duration::from_millis(100);
+
+/// An open `sqlite3*`, closed on drop
+struct Handle(*mut ffi::sqlite3);
+
+impl Handle {
+    fn open(path: &Path, flags: i32) -> Result<Self, String> {
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| format!("Path is not valid UTF-8: {}", path.display()))?;
+        let c_path = CString::new(path_str).map_err(|e| e.to_string())?;
+        let mut db = ptr::null_mut();
+        // SAFETY: `c_path` is a valid C string and `db` receives the new handle, which
+        // sqlite3_open_v2 allocates even when it fails so the error can be read and closed
+        let rc = unsafe { ffi::sqlite3_open_v2(c_path.as_ptr(), &mut db, flags, ptr::null()) };
+        let handle = Self(db);
+        if rc != ffi::SQLITE_OK {
+            return Err(format!(
+                "Failed to open {}: {}",
+                path.display(),
+                handle.error()
+            ));
+        }
+        // SAFETY: the handle is open
+        unsafe { ffi::sqlite3_busy_timeout(handle.0, BUSY_TIMEOUT_MS) };
+        Ok(handle)
+    }
+
+    fn error(&self) -> String {
+        if self.0.is_null() {
+            return "out of memory".to_string();
+        }
+        // SAFETY: the handle is non-null and sqlite3_errmsg returns a NUL-terminated string
+        // owned by the connection
+        unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.0)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from sqlite3_open_v2 and every backup using it has finished
+        unsafe { ffi::sqlite3_close(self.0) };
+    }
+}
+
+fn error_string(rc: i32) -> String {
+    // SAFETY: sqlite3_errstr returns a static NUL-terminated string for any code
+    unsafe { CStr::from_ptr(ffi::sqlite3_errstr(rc)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Replaces the database at `dest` with a consistent copy of the one at `source`,
+/// creating `dest` if needed. Blocks until the copy completes.
+pub fn copy(source: &Path, dest: &Path) -> Result<(), String> {
+    let source = Handle::open(source, ffi::SQLITE_OPEN_READONLY)?;
+    let dest = Handle::open(dest, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE)?;
+    let main = c"main";
+
+    // SAFETY: both handles are open for the whole block and used by this thread only
+    unsafe {
+        let backup = ffi::sqlite3_backup_init(dest.0, main.as_ptr(), source.0, main.as_ptr());
+        if backup.is_null() {
+            return Err(dest.error());
+        }
+        let mut retries = 0;
+        let rc = loop {
+            match ffi::sqlite3_backup_step(backup, -1) {
+                ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED if retries < BUSY_RETRIES => {
+                    retries += 1;
+                    std::thread::sleep(BUSY_WAIT);
+                }
+                rc => break rc,
+            }
+        };
+        let finished = ffi::sqlite3_backup_finish(backup);
+        if rc != ffi::SQLITE_DONE {
+            return Err(error_string(rc));
+        }
+        if finished != ffi::SQLITE_OK {
+            return Err(dest.error());
+        }
+    }
+    Ok(())
+}
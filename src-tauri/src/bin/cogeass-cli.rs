@@ -0,0 +1,398 @@
+//! Command-line runner for CI: lists and runs a workspace's saved operations with the same
+//! engine the desktop app uses.
+
+use app_lib::db::{connection::DB_FILE_NAME, migrations, Database};
+use app_lib::runner::{self, report, RunConfig, RunEvent, RunSummary};
+use app_lib::secrets::{SecretVault, VaultCredential};
+use app_lib::spec::{self, index};
+use app_lib::templating::WorkspaceContext;
+use app_lib::{backup, bundle};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+
+/// Bundle identifier from `tauri.conf.json`; the app keeps its database under this directory
+const APP_IDENTIFIER: &str = "cogeass.io";
+
+/// Every step ran and at least one failed
+const EXIT_FAILED: u8 = 1;
+/// The run could not start or stopped on an error
+const EXIT_ERROR: u8 = 2;
+
+#[derive(Parser)]
+#[command(
+    name = "cogeass-cli",
+    version,
+    about = "Run saved cogeass operations from the command line"
+)]
+struct Cli {
+    /// Database to open; defaults to the desktop app's `cogeass.db`
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+    /// Workspace bundle exported from the app, opened in memory instead of a database
+    #[arg(long, global = true, conflicts_with = "db")]
+    bundle: Option<PathBuf>,
+    /// Workspace id or name; may be omitted when the database has a single workspace
+    #[arg(long, short, global = true)]
+    workspace: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the workspaces in the database
+    Workspaces,
+    /// List the operations in the workspace's active spec
+    Operations {
+        #[arg(long, value_enum, default_value_t = ListFormat::Text)]
+        format: ListFormat,
+    },
+    /// Run operations with their saved form data, assertions, scripts and extraction rules
+    Run(RunArgs),
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ListFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Junit,
+    Json,
+}
+
+#[derive(Args)]
+struct RunArgs {
+    /// Operation keys such as `get:/pets/{petId}`, run in the order given
+    operations: Vec<String>,
+    /// Run every operation in the spec
+    #[arg(long, conflicts_with = "operations")]
+    all: bool,
+    /// Environment id or name; defaults to the workspace's active environment
+    #[arg(long, short)]
+    env: Option<String>,
+    /// CSV or JSON file with one iteration per row
+    #[arg(long)]
+    data_file: Option<String>,
+    #[arg(long)]
+    iterations: Option<u32>,
+    #[arg(long)]
+    concurrency: Option<usize>,
+    #[arg(long)]
+    delay_ms: Option<u64>,
+    #[arg(long)]
+    stop_on_failure: bool,
+    /// Write a report in this format to `--output`, or to stdout
+    #[arg(long, value_enum)]
+    report: Option<ReportFormat>,
+    #[arg(long, short, requires = "report")]
+    output: Option<PathBuf>,
+    /// Unlocks the vault when the environment has secret variables
+    #[arg(long, env = "COGEASS_VAULT_PASSPHRASE", hide_env_values = true)]
+    vault_passphrase: Option<String>,
+    #[arg(long, conflicts_with = "vault_passphrase")]
+    vault_key_file: Option<String>,
+}
+
+fn default_database_path() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER).join(DB_FILE_NAME))
+        .ok_or_else(|| "Could not find the config directory; pass --db".to_string())
+}
+
+async fn open_database(path: Option<PathBuf>) -> Result<Database, String> {
+    let path = match path {
+        Some(path) => path,
+        None => default_database_path()?,
+    };
+    // `Database::open` creates missing files, which would only hide a wrong path here
+    if !path.is_file() {
+        return Err(format!("Database not found: {}", path.display()));
+    }
+    // Only the app migrates the database, and queries against another schema fail obscurely
+    let version = backup::schema_version(&path).await?;
+    let expected = migrations::latest_version();
+    if version < expected {
+        return Err(format!(
+            "Database schema is at version {} but cogeass-cli needs {}; open it in the desktop app once to upgrade it",
+            version, expected
+        ));
+    }
+    if version > expected {
+        return Err(format!(
+            "Database schema is at version {}, newer than the {} cogeass-cli supports; update cogeass-cli",
+            version, expected
+        ));
+    }
+    Database::open(&path).await
+}
+
+/// Imports a bundle into a throwaway in-memory database. Secret values it carries are
+/// kept as they are, since that database has no vault.
+async fn open_bundle(path: &PathBuf) -> Result<Database, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let bundle = bundle::parse(&text)?;
+    let database = Database::open_in_memory().await?;
+    bundle::import(database.pool(), &SecretVault::new(), &bundle).await?;
+    Ok(database)
+}
+
+/// Finds a row by id or, failing that, by name
+fn find_by_id_or_name(
+    rows: Vec<(String, String)>,
+    wanted: &str,
+    kind: &str,
+) -> Result<String, String> {
+    if let Some((id, _)) = rows.iter().find(|(id, _)| id == wanted) {
+        return Ok(id.clone());
+    }
+    let matches: Vec<_> = rows
+        .into_iter()
+        .filter(|(_, name)| name == wanted)
+        .collect();
+    match matches.as_slice() {
+        [(id, _)] => Ok(id.clone()),
+        [] => Err(format!("{} not found: {}", kind, wanted)),
+        _ => Err(format!(
+            "{} name is ambiguous, use its id: {}",
+            kind, wanted
+        )),
+    }
+}
+
+async fn list_workspaces(pool: &SqlitePool) -> Result<Vec<(String, String)>, String> {
+    sqlx::query_as("SELECT id, name FROM workspaces ORDER BY sort_order")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn resolve_workspace(pool: &SqlitePool, wanted: Option<&str>) -> Result<String, String> {
+    let workspaces = list_workspaces(pool).await?;
+    match wanted {
+        Some(wanted) => find_by_id_or_name(workspaces, wanted, "Workspace"),
+        None => match workspaces.as_slice() {
+            [(id, _)] => Ok(id.clone()),
+            [] => Err("The database has no workspaces".to_string()),
+            _ => {
+                Err("The database has several workspaces; choose one with --workspace".to_string())
+            }
+        },
+    }
+}
+
+async fn resolve_environment(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    wanted: &str,
+) -> Result<String, String> {
+    let environments: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, name FROM environments WHERE workspace_id = ?")
+            .bind(workspace_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    find_by_id_or_name(environments, wanted, "Environment")
+}
+
+async fn print_operations(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    format: ListFormat,
+) -> Result<(), String> {
+    let spec = spec::load_active(pool, workspace_id).await?;
+    let operations = index::list_operations(&spec);
+    match format {
+        ListFormat::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&operations).map_err(|e| e.to_string())?
+            )
+        }
+        ListFormat::Text => {
+            for op in operations {
+                println!("{}\t{}", op.key, op.summary.unwrap_or_default());
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn unlock_vault(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    args: &RunArgs,
+) -> Result<(), String> {
+    let credential = match (&args.vault_passphrase, &args.vault_key_file) {
+        (Some(passphrase), _) => VaultCredential::Passphrase(passphrase.clone()),
+        (None, Some(path)) => VaultCredential::KeyFile(path.clone()),
+        (None, None) => return Ok(()),
+    };
+    // `unlock` initializes a missing vault, which a CI run should never do
+    if !vault.status(pool).await?.initialized {
+        return Err("The vault has not been set up in this database".to_string());
+    }
+    vault.unlock(pool, &credential).await
+}
+
+fn print_step(event: RunEvent) {
+    let RunEvent::StepFinished(step) = event else {
+        return;
+    };
+    let status = step
+        .status
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "---".to_string());
+    eprintln!(
+        "{} #{} {} {} {} ({:.0} ms)",
+        if step.passed { "PASS" } else { "FAIL" },
+        step.iteration + 1,
+        step.method.as_deref().unwrap_or("").to_uppercase(),
+        step.url.as_deref().unwrap_or(&step.operation_key),
+        status,
+        step.duration_ms,
+    );
+    for message in report::failure_messages(&step) {
+        eprintln!("    {}", message);
+    }
+}
+
+fn write_report(
+    summary: &RunSummary,
+    format: ReportFormat,
+    suite: &str,
+    output: Option<&PathBuf>,
+) -> Result<(), String> {
+    let text = match format {
+        ReportFormat::Junit => report::junit(summary, suite),
+        ReportFormat::Json => serde_json::to_string_pretty(summary).map_err(|e| e.to_string())?,
+    };
+    match output {
+        Some(path) => std::fs::write(path, text).map_err(|e| e.to_string()),
+        None => {
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+async fn run_operations(
+    pool: &SqlitePool,
+    workspace_id: String,
+    args: RunArgs,
+) -> Result<ExitCode, String> {
+    let vault = SecretVault::new();
+    unlock_vault(pool, &vault, &args).await?;
+
+    let environment_id = match &args.env {
+        Some(env) => Some(resolve_environment(pool, &workspace_id, env).await?),
+        None => None,
+    };
+    let context = WorkspaceContext::load_for_environment(
+        pool,
+        &vault,
+        &workspace_id,
+        environment_id.as_deref(),
+    )
+    .await?;
+
+    let operation_keys = if args.all {
+        let spec = context
+            .spec
+            .as_ref()
+            .ok_or_else(|| "Workspace has no active spec".to_string())?;
+        index::list_operations(spec)
+            .into_iter()
+            .filter(|op| !op.webhook)
+            .map(|op| op.key)
+            .collect()
+    } else {
+        args.operations.clone()
+    };
+    if operation_keys.is_empty() {
+        return Err("Nothing to run; pass operation keys or --all".to_string());
+    }
+
+    let config = RunConfig {
+        workspace_id: workspace_id.clone(),
+        environment_id,
+        operation_keys,
+        concurrency: args.concurrency,
+        delay_ms: args.delay_ms,
+        iterations: args.iterations,
+        data_file: args.data_file.clone(),
+        stop_on_failure: args.stop_on_failure,
+    };
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let cancel = AtomicBool::new(false);
+    let summary = runner::run(
+        pool,
+        &vault,
+        context,
+        &run_id,
+        &config,
+        &cancel,
+        &print_step,
+    )
+    .await?;
+
+    eprintln!(
+        "{} passed, {} failed, {} skipped in {:.0} ms",
+        summary.passed, summary.failed, summary.skipped, summary.duration_ms
+    );
+    if let Some(format) = args.report {
+        write_report(&summary, format, &workspace_id, args.output.as_ref())?;
+    }
+
+    let errored = summary.results.iter().any(|step| step.status.is_none());
+    Ok(if errored {
+        ExitCode::from(EXIT_ERROR)
+    } else if summary.failed > 0 || summary.skipped > 0 {
+        ExitCode::from(EXIT_FAILED)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+async fn execute(cli: Cli) -> Result<ExitCode, String> {
+    let database = match &cli.bundle {
+        Some(path) => open_bundle(path).await?,
+        None => open_database(cli.db).await?,
+    };
+    let pool = database.pool();
+
+    match cli.command {
+        Command::Workspaces => {
+            for (id, name) in list_workspaces(pool).await? {
+                println!("{}\t{}", id, name);
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Operations { format } => {
+            let workspace_id = resolve_workspace(pool, cli.workspace.as_deref()).await?;
+            print_operations(pool, &workspace_id, format).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Run(args) => {
+            let workspace_id = resolve_workspace(pool, cli.workspace.as_deref()).await?;
+            run_operations(pool, workspace_id, args).await
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match execute(Cli::parse()).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
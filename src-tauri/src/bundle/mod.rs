@@ -0,0 +1,1034 @@
+use crate::db::migrations;
+use crate::history::retention::{self, RetentionPolicy};
+use crate::history::snapshot::{self, Redactions};
+use crate::history::store;
+use crate::secrets::SecretVault;
+use crate::templating::WorkspaceContext;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Identifies a file as a workspace bundle
+pub const BUNDLE_FORMAT: &str = "cogeass-workspace";
+/// Schema version of the first build that wrote bundles; older ones are never seen
+const FIRST_BUNDLE_SCHEMA_VERSION: i64 = 20;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExportOptions {
+    /// Include secret variable values and secret auth values, decrypted
+    pub include_secrets: bool,
+    /// Include the request log and stored responses
+    pub include_history: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            include_secrets: false,
+            include_history: true,
+        }
+    }
+}
+
+/// A workspace as a single portable document.
+///
+/// `schema_version` is the `db::migrations` version of the database it was exported from.
+/// Sections for tables added by later migrations default to empty, so a bundle imports into
+/// any build at or above its version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceBundle {
+    pub format: String,
+    pub schema_version: i64,
+    pub exported_at: i64,
+    pub workspace: BundledWorkspace,
+    #[serde(default)]
+    pub spec: Option<String>,
+    #[serde(default)]
+    pub environments: Vec<BundledEnvironment>,
+    #[serde(default)]
+    pub variables: Vec<BundledVariable>,
+    #[serde(default)]
+    pub global_headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub auth_values: Vec<BundledAuthValue>,
+    #[serde(default)]
+    pub operation_states: Vec<BundledOperationState>,
+    #[serde(default)]
+    pub assertions: Vec<BundledOperationJson>,
+    #[serde(default)]
+    pub extraction_rules: Vec<BundledOperationJson>,
+    #[serde(default)]
+    pub scripts: Vec<BundledScripts>,
+    #[serde(default)]
+    pub history: Vec<BundledHistoryEntry>,
+    #[serde(default)]
+    pub response_history: Vec<BundledResponse>,
+    #[serde(default)]
+    pub retention: RetentionPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledWorkspace {
+    pub id: String,
+    pub name: String,
+    pub base_url: Option<String>,
+    pub spec_url: Option<String>,
+    pub selected_operation_key: Option<String>,
+    pub active_environment_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledEnvironment {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledVariable {
+    pub name: String,
+    pub is_secret: bool,
+    /// Values keyed by environment id; secret ones are left out unless exported with secrets
+    pub values: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledAuthValue {
+    pub environment_id: Option<String>,
+    pub scheme_name: String,
+    pub is_secret: bool,
+    pub values: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledOperationState {
+    pub operation_key: String,
+    pub form_data: Value,
+    pub response: Option<Value>,
+    pub last_modified: i64,
+}
+
+/// A per-operation JSON document: assertions or extraction rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledOperationJson {
+    pub operation_key: String,
+    pub value: Value,
+    pub last_modified: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledScripts {
+    pub operation_key: String,
+    pub pre_request: Option<String>,
+    pub post_response: Option<String>,
+    pub last_modified: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledHistoryEntry {
+    pub operation_key: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledResponse {
+    pub operation_key: String,
+    pub response_json: String,
+    /// The request a replay recording matches on; exported without secrets only when they
+    /// could be redacted
+    pub request_json: Option<String>,
+    pub request_snapshot: Option<Value>,
+    pub timestamp: i64,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn parse_json(text: &str) -> Result<Value, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+/// Reads a workspace and everything that belongs to it into a bundle
+pub async fn export(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    options: &ExportOptions,
+) -> Result<WorkspaceBundle, String> {
+    type WorkspaceRow = (
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    );
+    let workspace: Option<WorkspaceRow> = sqlx::query_as(
+        "SELECT name, active_spec_id, active_environment_id, base_url, selected_operation_key, spec_url
+         FROM workspaces WHERE id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let (name, active_spec_id, active_environment_id, base_url, selected_operation_key, spec_url) =
+        workspace.ok_or_else(|| format!("Workspace not found: {}", workspace_id))?;
+
+    let spec = match &active_spec_id {
+        Some(spec_id) => {
+            sqlx::query_as::<_, (String,)>("SELECT spec_content FROM specs WHERE id = ?")
+                .bind(spec_id)
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| e.to_string())?
+                .map(|(content,)| content)
+        }
+        None => None,
+    };
+
+    let environments: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, name FROM environments WHERE workspace_id = ? ORDER BY name")
+            .bind(workspace_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let keys: Vec<(i64, String, bool)> = sqlx::query_as(
+        "SELECT id, key_name, is_secret FROM workspace_variable_keys WHERE workspace_id = ? ORDER BY key_name",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut variables = Vec::with_capacity(keys.len());
+    for (key_id, key_name, is_secret) in keys {
+        let mut values = BTreeMap::new();
+        if !is_secret || options.include_secrets {
+            let rows: Vec<(String, String)> = sqlx::query_as(
+                "SELECT environment_id, value FROM environment_variable_values WHERE variable_key_id = ?",
+            )
+            .bind(key_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+            for (environment_id, value) in rows {
+                values.insert(environment_id, vault.reveal(&value)?);
+            }
+        }
+        variables.push(BundledVariable {
+            name: key_name,
+            is_secret,
+            values,
+        });
+    }
+
+    let global_headers: Vec<(String, String)> =
+        sqlx::query_as("SELECT key, value FROM global_headers WHERE workspace_id = ?")
+            .bind(workspace_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+    let auth_rows: Vec<(Option<String>, String, String, bool)> = sqlx::query_as(
+        "SELECT environment_id, scheme_name, value_json, is_secret FROM auth_values
+         WHERE workspace_id = ? ORDER BY scheme_name, environment_id",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut auth_values = Vec::new();
+    for (environment_id, scheme_name, value_json, is_secret) in auth_rows {
+        if is_secret && !options.include_secrets {
+            continue;
+        }
+        auth_values.push(BundledAuthValue {
+            environment_id,
+            scheme_name,
+            is_secret,
+            values: serde_json::from_str(&vault.reveal(&value_json)?).map_err(|e| e.to_string())?,
+        });
+    }
+
+    let state_rows: Vec<(String, String, Option<String>, i64)> = sqlx::query_as(
+        "SELECT operation_key, form_data, response, last_modified FROM operation_states
+         WHERE workspace_id = ? ORDER BY operation_key",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut operation_states = Vec::with_capacity(state_rows.len());
+    for (operation_key, form_data, response, last_modified) in state_rows {
+        operation_states.push(BundledOperationState {
+            operation_key,
+            form_data: parse_json(&form_data)?,
+            response: response.as_deref().map(parse_json).transpose()?,
+            last_modified,
+        });
+    }
+
+    let assertions = export_operation_json(
+        pool,
+        workspace_id,
+        "operation_assertions",
+        "assertions_json",
+    )
+    .await?;
+    let extraction_rules =
+        export_operation_json(pool, workspace_id, "operation_extractions", "rules_json").await?;
+
+    let scripts: Vec<(String, Option<String>, Option<String>, i64)> = sqlx::query_as(
+        "SELECT operation_key, pre_request, post_response, last_modified FROM operation_scripts
+         WHERE workspace_id = ? ORDER BY operation_key",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (history, response_history) = if options.include_history {
+        let requests = if options.include_secrets {
+            RecordedRequests::Verbatim
+        } else {
+            // Secrets can only be recognised while none are locked away
+            let context = WorkspaceContext::load(pool, vault, workspace_id).await?;
+            if context.locked_variables.is_empty() && context.locked_auth.is_empty() {
+                RecordedRequests::Redacted(Redactions::new(&context))
+            } else {
+                RecordedRequests::Omitted
+            }
+        };
+        export_history(pool, workspace_id, &requests).await?
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    let retention = retention::load(pool, workspace_id).await?;
+
+    Ok(WorkspaceBundle {
+        format: BUNDLE_FORMAT.to_string(),
+        schema_version: migrations::latest_version(),
+        exported_at: now_ms(),
+        workspace: BundledWorkspace {
+            id: workspace_id.to_string(),
+            name,
+            base_url,
+            spec_url,
+            selected_operation_key,
+            active_environment_id,
+        },
+        spec,
+        environments: environments
+            .into_iter()
+            .map(|(id, name)| BundledEnvironment { id, name })
+            .collect(),
+        variables,
+        global_headers: global_headers.into_iter().collect(),
+        auth_values,
+        operation_states,
+        assertions,
+        extraction_rules,
+        scripts: scripts
+            .into_iter()
+            .map(
+                |(operation_key, pre_request, post_response, last_modified)| BundledScripts {
+                    operation_key,
+                    pre_request,
+                    post_response,
+                    last_modified,
+                },
+            )
+            .collect(),
+        history,
+        response_history,
+        retention,
+    })
+}
+
+/// Reads one of the per-operation JSON tables; `table` and `column` are never user input
+async fn export_operation_json(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    table: &str,
+    column: &str,
+) -> Result<Vec<BundledOperationJson>, String> {
+    let rows: Vec<(String, String, i64)> = sqlx::query_as(&format!(
+        "SELECT operation_key, {}, last_modified FROM {} WHERE workspace_id = ? ORDER BY operation_key",
+        column, table
+    ))
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    rows.into_iter()
+        .map(|(operation_key, json, last_modified)| {
+            Ok(BundledOperationJson {
+                operation_key,
+                value: parse_json(&json)?,
+                last_modified,
+            })
+        })
+        .collect()
+}
+
+/// Strips sensitive headers and secret values from a replay recording's request
+fn redact_recorded_request(request_json: &str, redactions: &Redactions) -> Result<String, String> {
+    let mut request = parse_json(request_json)?;
+    if let Some(headers) = request.get_mut("headers").and_then(Value::as_object_mut) {
+        headers.retain(|name, _| !snapshot::is_sensitive_header(name));
+    }
+    Ok(redactions.apply(&request.to_string()))
+}
+
+/// How replay recordings' requests are exported
+enum RecordedRequests {
+    Verbatim,
+    Redacted(Redactions),
+    Omitted,
+}
+
+async fn export_history(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    requests: &RecordedRequests,
+) -> Result<(Vec<BundledHistoryEntry>, Vec<BundledResponse>), String> {
+    let history: Vec<(String, i64)> = sqlx::query_as(
+        "SELECT operation_key, timestamp FROM history WHERE workspace_id = ? ORDER BY timestamp, id",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    type ResponseRow = (
+        String,
+        String,
+        Option<Vec<u8>>,
+        Option<String>,
+        Option<String>,
+        i64,
+    );
+    let rows: Vec<ResponseRow> = sqlx::query_as(
+        "SELECT operation_key, response_json, response_blob, request_json, request_snapshot, timestamp
+         FROM response_history WHERE workspace_id = ? ORDER BY timestamp, id",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut responses = Vec::with_capacity(rows.len());
+    for (operation_key, json, blob, request_json, snapshot, timestamp) in rows {
+        let request_json = match (request_json, requests) {
+            (Some(json), RecordedRequests::Redacted(redactions)) => {
+                Some(redact_recorded_request(&json, redactions)?)
+            }
+            (_, RecordedRequests::Omitted) => None,
+            (request_json, _) => request_json,
+        };
+        responses.push(BundledResponse {
+            operation_key,
+            response_json: store::response_json(json, blob)?,
+            request_json,
+            request_snapshot: snapshot.as_deref().map(parse_json).transpose()?,
+            timestamp,
+        });
+    }
+
+    Ok((
+        history
+            .into_iter()
+            .map(|(operation_key, timestamp)| BundledHistoryEntry {
+                operation_key,
+                timestamp,
+            })
+            .collect(),
+        responses,
+    ))
+}
+
+/// Parses a bundle, rejecting files that aren't bundles or were written by a newer schema
+pub fn parse(text: &str) -> Result<WorkspaceBundle, String> {
+    let value = parse_json(text)?;
+    if value.get("format").and_then(Value::as_str) != Some(BUNDLE_FORMAT) {
+        return Err("Not a cogeass workspace bundle".to_string());
+    }
+    let version = value
+        .get("schemaVersion")
+        .and_then(Value::as_i64)
+        .ok_or_else(|| "Bundle has no schema version".to_string())?;
+    let latest = migrations::latest_version();
+    if version > latest {
+        return Err(format!(
+            "Bundle was exported with schema version {}, newer than this build's {}; update the app to import it",
+            version, latest
+        ));
+    }
+    if version < FIRST_BUNDLE_SCHEMA_VERSION {
+        return Err(format!("Unsupported bundle schema version: {}", version));
+    }
+    serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+/// Prepares a value for storage: secrets are sealed, which needs the vault unlocked, unless
+/// this database has no vault to seal them with
+fn encode_secret(
+    vault: &SecretVault,
+    vault_initialized: bool,
+    value: &str,
+    is_secret: bool,
+) -> Result<String, String> {
+    if !is_secret || !vault_initialized {
+        return Ok(value.to_string());
+    }
+    vault.seal(value)
+}
+
+/// Imports a bundle as a new workspace and returns its id. Every id is regenerated, so a
+/// bundle can be imported next to the workspace it came from. The bundled retention policy
+/// is applied to the imported history straight away rather than on the next periodic pass.
+pub async fn import(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    bundle: &WorkspaceBundle,
+) -> Result<String, String> {
+    let vault_initialized = vault.status(pool).await?.initialized;
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let workspace_id = uuid::Uuid::new_v4().to_string();
+    let environment_ids: HashMap<&str, String> = bundle
+        .environments
+        .iter()
+        .map(|env| (env.id.as_str(), uuid::Uuid::new_v4().to_string()))
+        .collect();
+    let map_environment = |id: &Option<String>| -> Option<String> {
+        id.as_deref()
+            .and_then(|id| environment_ids.get(id))
+            .cloned()
+    };
+
+    let spec_id = match &bundle.spec {
+        Some(content) => {
+            let spec_id = uuid::Uuid::new_v4().to_string();
+            sqlx::query("INSERT INTO specs (id, spec_content) VALUES (?, ?)")
+                .bind(&spec_id)
+                .bind(content)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+            Some(spec_id)
+        }
+        None => None,
+    };
+
+    let workspace = &bundle.workspace;
+    sqlx::query(
+        "INSERT INTO workspaces (id, name, active_spec_id, active_environment_id, base_url, selected_operation_key, spec_url, sort_order)
+         VALUES (?, ?, ?, ?, ?, ?, ?, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM workspaces))",
+    )
+    .bind(&workspace_id)
+    .bind(&workspace.name)
+    .bind(&spec_id)
+    .bind(map_environment(&workspace.active_environment_id))
+    .bind(&workspace.base_url)
+    .bind(&workspace.selected_operation_key)
+    .bind(&workspace.spec_url)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for env in &bundle.environments {
+        sqlx::query("INSERT INTO environments (id, workspace_id, name) VALUES (?, ?, ?)")
+            .bind(&environment_ids[env.id.as_str()])
+            .bind(&workspace_id)
+            .bind(&env.name)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    for variable in &bundle.variables {
+        let key_id = sqlx::query(
+            "INSERT INTO workspace_variable_keys (workspace_id, key_name, is_secret) VALUES (?, ?, ?)",
+        )
+        .bind(&workspace_id)
+        .bind(&variable.name)
+        .bind(variable.is_secret)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?
+        .last_insert_rowid();
+
+        for (environment_id, value) in &variable.values {
+            let Some(environment_id) = environment_ids.get(environment_id.as_str()) else {
+                continue;
+            };
+            sqlx::query(
+                "INSERT INTO environment_variable_values (environment_id, variable_key_id, value) VALUES (?, ?, ?)",
+            )
+            .bind(environment_id)
+            .bind(key_id)
+            .bind(encode_secret(vault, vault_initialized, value, variable.is_secret)?)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    for (key, value) in &bundle.global_headers {
+        sqlx::query("INSERT INTO global_headers (workspace_id, key, value) VALUES (?, ?, ?)")
+            .bind(&workspace_id)
+            .bind(key)
+            .bind(value)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    for auth in &bundle.auth_values {
+        // Values for environments missing from the bundle would have nothing to apply to
+        let environment_id = map_environment(&auth.environment_id);
+        if auth.environment_id.is_some() && environment_id.is_none() {
+            continue;
+        }
+        let value_json = serde_json::to_string(&auth.values).map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT INTO auth_values (workspace_id, environment_id, scheme_name, value_json, is_secret)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&workspace_id)
+        .bind(environment_id)
+        .bind(&auth.scheme_name)
+        .bind(encode_secret(vault, vault_initialized, &value_json, auth.is_secret)?)
+        .bind(auth.is_secret)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    for state in &bundle.operation_states {
+        sqlx::query(
+            "INSERT INTO operation_states (workspace_id, operation_key, form_data, response, last_modified)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&workspace_id)
+        .bind(&state.operation_key)
+        .bind(state.form_data.to_string())
+        .bind(state.response.as_ref().map(Value::to_string))
+        .bind(state.last_modified)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    import_operation_json(
+        &mut tx,
+        &workspace_id,
+        &bundle.assertions,
+        "operation_assertions",
+        "assertions_json",
+    )
+    .await?;
+    import_operation_json(
+        &mut tx,
+        &workspace_id,
+        &bundle.extraction_rules,
+        "operation_extractions",
+        "rules_json",
+    )
+    .await?;
+
+    for scripts in &bundle.scripts {
+        sqlx::query(
+            "INSERT INTO operation_scripts (workspace_id, operation_key, pre_request, post_response, last_modified)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&workspace_id)
+        .bind(&scripts.operation_key)
+        .bind(&scripts.pre_request)
+        .bind(&scripts.post_response)
+        .bind(scripts.last_modified)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    for entry in &bundle.history {
+        sqlx::query(
+            "INSERT INTO history (workspace_id, operation_key, timestamp) VALUES (?, ?, ?)",
+        )
+        .bind(&workspace_id)
+        .bind(&entry.operation_key)
+        .bind(entry.timestamp)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    for response in &bundle.response_history {
+        // Snapshots name the environment they were sent in, which now has a new id
+        let snapshot = response.request_snapshot.clone().map(|mut snapshot| {
+            if let Some(id) = snapshot.get("environmentId").and_then(Value::as_str) {
+                snapshot["environmentId"] = match environment_ids.get(id) {
+                    Some(new_id) => Value::String(new_id.clone()),
+                    None => Value::Null,
+                };
+            }
+            snapshot.to_string()
+        });
+        sqlx::query(
+            "INSERT INTO response_history (workspace_id, operation_key, response_json, status, request_json, request_snapshot, timestamp)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&workspace_id)
+        .bind(&response.operation_key)
+        .bind(&response.response_json)
+        .bind(store::response_status(&response.response_json))
+        .bind(&response.request_json)
+        .bind(snapshot)
+        .bind(response.timestamp)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    retention::save(pool, &workspace_id, &bundle.retention).await?;
+    retention::prune_workspace(pool, &workspace_id).await?;
+    store::compress_large(pool).await?;
+    Ok(workspace_id)
+}
+
+/// Writes one of the per-operation JSON tables; `table` and `column` are never user input
+async fn import_operation_json(
+    tx: &mut Transaction<'_, Sqlite>,
+    workspace_id: &str,
+    rows: &[BundledOperationJson],
+    table: &str,
+    column: &str,
+) -> Result<(), String> {
+    let sql = format!(
+        "INSERT INTO {} (workspace_id, operation_key, {}, last_modified) VALUES (?, ?, ?, ?)",
+        table, column
+    );
+    for row in rows {
+        sqlx::query(&sql)
+            .bind(workspace_id)
+            .bind(&row.operation_key)
+            .bind(row.value.to_string())
+            .bind(row.last_modified)
+            .execute(&mut **tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::secrets::{store as secrets, VaultCredential};
+    use serde_json::json;
+
+    /// A workspace with a secret variable and auth value, and a replay recording whose
+    /// request carries both
+    async fn seeded(pool: &SqlitePool, vault: &SecretVault) {
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        repository::save_spec(pool, "s1", r#"{"openapi":"3.0.0","paths":{}}"#)
+            .await
+            .unwrap();
+        repository::create_environment(pool, "e1", "w1", "dev")
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE workspaces SET active_spec_id = 's1', active_environment_id = 'e1', base_url = 'https://{{host}}' WHERE id = 'w1'",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        vault
+            .unlock(pool, &VaultCredential::Passphrase("pw".to_string()))
+            .await
+            .unwrap();
+        let host = repository::add_variable_key(pool, "w1", "host")
+            .await
+            .unwrap();
+        let token = repository::add_variable_key(pool, "w1", "token")
+            .await
+            .unwrap();
+        repository::set_variable_value(pool, vault, "e1", host, "api.test")
+            .await
+            .unwrap();
+        secrets::set_variable_secret(pool, vault, token, true)
+            .await
+            .unwrap();
+        repository::set_variable_value(pool, vault, "e1", token, "s3cret-token")
+            .await
+            .unwrap();
+        secrets::set_auth_value(
+            pool,
+            vault,
+            "w1",
+            None,
+            "bearer",
+            r#"{"token":"s3cret-auth"}"#,
+            true,
+        )
+        .await
+        .unwrap();
+        repository::set_global_header(pool, "w1", "x-app", "pets")
+            .await
+            .unwrap();
+        repository::save_operation_state(
+            pool,
+            "w1",
+            "get:/pets",
+            r#"{"queryData":{"q":"1"}}"#,
+            None,
+            5,
+        )
+        .await
+        .unwrap();
+        repository::add_history_entry(pool, "w1", "get:/pets", 5)
+            .await
+            .unwrap();
+
+        let recorded = json!({
+            "method": "GET",
+            "path": "/pets",
+            "query": [["key", "s3cret-token"]],
+            "headers": { "authorization": "Bearer s3cret-auth", "x-trace": "s3cret-token" },
+            "bodyHash": null,
+        });
+        store::insert(
+            pool,
+            "w1",
+            "get:/pets",
+            r#"{"status":200,"headers":{},"bodyText":"[]"}"#,
+            Some(&recorded.to_string()),
+            None,
+            6,
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn round_trip(pool: &SqlitePool, vault: &SecretVault, options: &ExportOptions) -> String {
+        let exported = export(pool, vault, "w1", options).await.unwrap();
+        let text = serde_json::to_string(&exported).unwrap();
+        import(pool, vault, &parse(&text).unwrap()).await.unwrap()
+    }
+
+    async fn variable(
+        pool: &SqlitePool,
+        vault: &SecretVault,
+        workspace_id: &str,
+        name: &str,
+    ) -> Option<String> {
+        let row: Option<(String,)> = sqlx::query_as(
+            "SELECT v.value FROM environment_variable_values v
+             JOIN workspace_variable_keys k ON k.id = v.variable_key_id
+             WHERE k.workspace_id = ? AND k.key_name = ?",
+        )
+        .bind(workspace_id)
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+        .unwrap();
+        row.map(|(value,)| vault.reveal(&value).unwrap())
+    }
+
+    async fn recorded_request(pool: &SqlitePool, workspace_id: &str) -> Option<String> {
+        let (request_json, status): (Option<String>, Option<i64>) = sqlx::query_as(
+            "SELECT request_json, status FROM response_history WHERE workspace_id = ?",
+        )
+        .bind(workspace_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!(status, Some(200));
+        request_json
+    }
+
+    #[tokio::test]
+    async fn exports_and_imports_a_workspace_without_its_secrets() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        seeded(pool, &vault).await;
+
+        let imported = round_trip(pool, &vault, &ExportOptions::default()).await;
+        assert_ne!(imported, "w1");
+
+        let context = WorkspaceContext::load(pool, &vault, &imported)
+            .await
+            .unwrap();
+        assert_eq!(context.base_url, "https://{{host}}");
+        assert_eq!(
+            context.spec,
+            Some(json!({ "openapi": "3.0.0", "paths": {} }))
+        );
+        assert_eq!(
+            context.variables.get("host").map(String::as_str),
+            Some("api.test")
+        );
+        let (token_is_secret,): (bool,) = sqlx::query_as(
+            "SELECT is_secret FROM workspace_variable_keys WHERE workspace_id = ? AND key_name = 'token'",
+        )
+        .bind(&imported)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert!(token_is_secret);
+        assert_eq!(variable(pool, &vault, &imported, "token").await, None);
+        assert!(context.auth_values.is_empty());
+        assert_eq!(context.global_headers["x-app"], "pets");
+        assert!(
+            repository::get_operation_state(pool, &imported, "get:/pets")
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        let request: Value =
+            serde_json::from_str(&recorded_request(pool, &imported).await.unwrap()).unwrap();
+        assert_eq!(request["query"], json!([["key", "{{token}}"]]));
+        assert_eq!(request["headers"], json!({ "x-trace": "{{token}}" }));
+    }
+
+    #[tokio::test]
+    async fn recorded_requests_are_left_out_while_secrets_are_locked() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        seeded(pool, &vault).await;
+        vault.lock();
+
+        let bundle = export(pool, &vault, "w1", &ExportOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(bundle.response_history.len(), 1);
+        assert_eq!(bundle.response_history[0].request_json, None);
+    }
+
+    #[tokio::test]
+    async fn exports_secrets_when_asked() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        seeded(pool, &vault).await;
+
+        let options = ExportOptions {
+            include_secrets: true,
+            include_history: true,
+        };
+        let imported = round_trip(pool, &vault, &options).await;
+
+        let context = WorkspaceContext::load(pool, &vault, &imported)
+            .await
+            .unwrap();
+        assert_eq!(context.variables["token"], "s3cret-token");
+        assert_eq!(context.auth_values["bearer"]["token"], "s3cret-auth");
+        assert!(recorded_request(pool, &imported)
+            .await
+            .unwrap()
+            .contains("Bearer s3cret-auth"));
+    }
+
+    fn bundle_text(schema_version: i64) -> String {
+        json!({
+            "format": BUNDLE_FORMAT,
+            "schemaVersion": schema_version,
+            "exportedAt": 1,
+            "workspace": {
+                "id": "w1",
+                "name": "Pets",
+                "baseUrl": null,
+                "specUrl": null,
+                "selectedOperationKey": null,
+                "activeEnvironmentId": null,
+            },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn rejects_bundles_from_a_newer_schema() {
+        let latest = migrations::latest_version();
+        let error = parse(&bundle_text(latest + 1)).unwrap_err();
+        assert!(error.contains("update the app"), "{}", error);
+        assert!(parse(&bundle_text(latest)).is_ok());
+    }
+
+    #[test]
+    fn rejects_bundles_older_than_the_first_bundle_schema() {
+        let error = parse(&bundle_text(FIRST_BUNDLE_SCHEMA_VERSION - 1)).unwrap_err();
+        assert_eq!(
+            error,
+            format!(
+                "Unsupported bundle schema version: {}",
+                FIRST_BUNDLE_SCHEMA_VERSION - 1
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn imports_an_older_bundle_and_applies_its_retention() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+
+        // From the first schema that wrote bundles, before response statuses were stored
+        let mut bundle: Value =
+            serde_json::from_str(&bundle_text(FIRST_BUNDLE_SCHEMA_VERSION)).unwrap();
+        bundle["responseHistory"] = (1..=3)
+            .map(|timestamp| {
+                json!({
+                    "operationKey": "get:/pets",
+                    "responseJson": r#"{"status":200,"headers":{},"bodyText":"[]"}"#,
+                    "requestJson": null,
+                    "timestamp": timestamp,
+                })
+            })
+            .collect();
+        bundle["retention"] = json!({
+            "maxEntriesPerOperation": 2,
+            "maxAgeDays": null,
+            "maxTotalBytes": null,
+        });
+
+        let imported = import(pool, &vault, &parse(&bundle.to_string()).unwrap())
+            .await
+            .unwrap();
+        let kept: Vec<(i64,)> = sqlx::query_as(
+            "SELECT timestamp FROM response_history WHERE workspace_id = ? ORDER BY timestamp",
+        )
+        .bind(&imported)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        assert_eq!(kept, vec![(2,), (3,)]);
+    }
+}
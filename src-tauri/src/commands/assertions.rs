@@ -0,0 +1,50 @@
+use crate::assertions::{self, Assertion, AssertionResult};
+use crate::db::Database;
+use crate::network::request::BackendResponse;
+use crate::spec;
+
+#[tauri::command]
+pub async fn get_assertions(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+) -> Result<Vec<Assertion>, String> {
+    assertions::load(db.pool(), &workspace_id, &operation_key).await
+}
+
+#[tauri::command]
+pub async fn save_assertions(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+    assertions: Vec<Assertion>,
+) -> Result<(), String> {
+    assertions::save(db.pool(), &workspace_id, &operation_key, &assertions).await
+}
+
+/// Checks a response from `make_request` against the given assertions, or the ones saved
+/// for the operation when none are given
+#[tauri::command]
+pub async fn evaluate_assertions(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+    response: BackendResponse,
+    assertions: Option<Vec<Assertion>>,
+) -> Result<Vec<AssertionResult>, String> {
+    let assertions = match assertions {
+        Some(assertions) => assertions,
+        None => assertions::load(db.pool(), &workspace_id, &operation_key).await?,
+    };
+    let spec = if assertions.iter().any(|a| matches!(a, Assertion::Schema)) {
+        Some(spec::load_active(db.pool(), &workspace_id).await?)
+    } else {
+        None
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        assertions::evaluate(&assertions, &response, spec.as_ref(), &operation_key)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
@@ -0,0 +1,29 @@
+use crate::backup::{BackupInfo, BackupManager, BackupReason, DatabaseHealth};
+use tauri::{AppHandle, Runtime};
+
+/// What the startup check found, so the UI can offer a restore
+#[tauri::command]
+pub fn get_database_health(backups: tauri::State<'_, BackupManager>) -> DatabaseHealth {
+    backups.health()
+}
+
+#[tauri::command]
+pub fn list_backups(backups: tauri::State<'_, BackupManager>) -> Result<Vec<BackupInfo>, String> {
+    backups.list()
+}
+
+#[tauri::command]
+pub async fn create_backup(backups: tauri::State<'_, BackupManager>) -> Result<BackupInfo, String> {
+    backups.create(BackupReason::Manual).await
+}
+
+/// Replaces the database with a backup and restarts the app so every connection reopens it
+#[tauri::command]
+pub async fn restore_backup<R: Runtime>(
+    app: AppHandle<R>,
+    backups: tauri::State<'_, BackupManager>,
+    file_name: String,
+) -> Result<(), String> {
+    backups.restore(&file_name).await?;
+    app.restart()
+}
@@ -0,0 +1,39 @@
+use crate::bundle::{self, ExportOptions};
+use crate::db::Database;
+use crate::secrets::SecretVault;
+
+/// Writes a workspace to a bundle file at `path`
+#[tauri::command]
+pub async fn export_workspace(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    path: String,
+    options: Option<ExportOptions>,
+) -> Result<(), String> {
+    let bundle = bundle::export(
+        db.pool(),
+        &vault,
+        &workspace_id,
+        &options.unwrap_or_default(),
+    )
+    .await?;
+    let text = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    tokio::fs::write(&path, text)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Imports a bundle file as a new workspace and returns its id
+#[tauri::command]
+pub async fn import_workspace(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    path: String,
+) -> Result<String, String> {
+    let text = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bundle = bundle::parse(&text)?;
+    bundle::import(db.pool(), &vault, &bundle).await
+}
@@ -0,0 +1,50 @@
+use crate::db::Database;
+use crate::diff::environments::{self, EnvironmentComparison};
+use crate::diff::{self, ComparableResponse, DiffOptions, DiffSource, ResponseDiff};
+use crate::history::store;
+use crate::secrets::SecretVault;
+use sqlx::SqlitePool;
+
+async fn load(pool: &SqlitePool, source: &DiffSource) -> Result<ComparableResponse, String> {
+    match source {
+        DiffSource::History { id } => {
+            ComparableResponse::from_history_json(&store::get_response(pool, *id).await?)
+        }
+        DiffSource::Live { response } => Ok(response.as_ref().into()),
+    }
+}
+
+/// Compares two responses, each from history or live, skipping volatile headers and fields
+#[tauri::command]
+pub async fn diff_responses(
+    db: tauri::State<'_, Database>,
+    left: DiffSource,
+    right: DiffSource,
+    options: Option<DiffOptions>,
+) -> Result<ResponseDiff, String> {
+    let left = load(db.pool(), &left).await?;
+    let right = load(db.pool(), &right).await?;
+    diff::diff(&left, &right, &options.unwrap_or_default())
+}
+
+/// Sends an operation in several environments in parallel and diffs each response
+/// against the first environment's
+#[tauri::command]
+pub async fn compare_environments(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    operation_key: String,
+    environment_ids: Vec<String>,
+    options: Option<DiffOptions>,
+) -> Result<EnvironmentComparison, String> {
+    environments::compare(
+        db.pool(),
+        &vault,
+        &workspace_id,
+        &operation_key,
+        &environment_ids,
+        &options.unwrap_or_default(),
+    )
+    .await
+}
@@ -0,0 +1,54 @@
+use crate::db::Database;
+use crate::extraction::{self, ExtractionReport, ExtractionRule};
+use crate::network::request::BackendResponse;
+use crate::secrets::SecretVault;
+
+#[tauri::command]
+pub async fn get_extraction_rules(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+) -> Result<Vec<ExtractionRule>, String> {
+    extraction::load(db.pool(), &workspace_id, &operation_key).await
+}
+
+#[tauri::command]
+pub async fn save_extraction_rules(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+    rules: Vec<ExtractionRule>,
+) -> Result<(), String> {
+    extraction::save(db.pool(), &workspace_id, &operation_key, &rules).await
+}
+
+/// Runs an operation's saved extraction rules against a response from `make_request`,
+/// storing the values in the workspace's active environment
+#[tauri::command]
+pub async fn apply_extractions(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    operation_key: String,
+    response: BackendResponse,
+) -> Result<ExtractionReport, String> {
+    let rules = extraction::load(db.pool(), &workspace_id, &operation_key).await?;
+    let environment: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT active_environment_id FROM workspaces WHERE id = ?")
+            .bind(&workspace_id)
+            .fetch_optional(db.pool())
+            .await
+            .map_err(|e| e.to_string())?;
+    let (environment_id,) =
+        environment.ok_or_else(|| format!("Workspace not found: {}", workspace_id))?;
+
+    extraction::apply(
+        db.pool(),
+        &vault,
+        &workspace_id,
+        environment_id.as_deref(),
+        &rules,
+        &response,
+    )
+    .await
+}
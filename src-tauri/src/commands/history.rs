@@ -0,0 +1,120 @@
+use crate::db::Database;
+use crate::history::retention::{self, RetentionPolicy, StorageStats};
+use crate::history::snapshot;
+use crate::history::store::{self, ResponseHistoryEntry};
+use crate::history::{self, HistoryMatch, HistorySearch};
+use crate::network::request::{self, BackendResponse, PreparedRequest};
+use crate::secrets::SecretVault;
+use crate::templating::WorkspaceContext;
+use tauri::Emitter;
+
+#[tauri::command]
+pub async fn search_history(
+    db: tauri::State<'_, Database>,
+    search: HistorySearch,
+) -> Result<Vec<HistoryMatch>, String> {
+    history::search(db.pool(), &search).await
+}
+
+/// Stores a response and trims the operation's history to the workspace's limit.
+/// The request that was sent, if given, is kept with its secrets redacted so it can be replayed.
+#[tauri::command]
+pub async fn add_response_history(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    operation_key: String,
+    response_json: String,
+    request: Option<PreparedRequest>,
+    timestamp: i64,
+) -> Result<i64, String> {
+    // Secrets can't be recognised without a context or while the vault keeps some of them
+    // locked away, so nothing is kept then
+    let request_snapshot = match request {
+        Some(request) => WorkspaceContext::load(db.pool(), &vault, &workspace_id)
+            .await
+            .ok()
+            .filter(|context| context.locked_variables.is_empty() && context.locked_auth.is_empty())
+            .map(|context| snapshot::capture(&request, &context, &operation_key)),
+        None => None,
+    };
+    let id = store::insert(
+        db.pool(),
+        &workspace_id,
+        &operation_key,
+        &response_json,
+        None,
+        request_snapshot.as_ref(),
+        timestamp,
+    )
+    .await?;
+    let policy = retention::load(db.pool(), &workspace_id).await?;
+    retention::prune_operation(db.pool(), &workspace_id, &operation_key, &policy).await?;
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn get_response_history(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+    limit: Option<u32>,
+) -> Result<Vec<ResponseHistoryEntry>, String> {
+    store::list(db.pool(), &workspace_id, &operation_key, limit).await
+}
+
+/// Resends a history entry's request, with secrets and auth filled from its environment
+#[tauri::command]
+pub async fn replay_history_entry(
+    app: tauri::AppHandle,
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    id: i64,
+) -> Result<BackendResponse, String> {
+    let (workspace_id, operation_key, snapshot) = store::get_snapshot(db.pool(), id).await?;
+    let snapshot =
+        snapshot.ok_or_else(|| format!("History entry {} has no request snapshot", id))?;
+    let context = WorkspaceContext::load_for_environment(
+        db.pool(),
+        &vault,
+        &workspace_id,
+        snapshot.environment_id.as_deref(),
+    )
+    .await?;
+    let prepared = snapshot::restore(&snapshot, &context, &operation_key)?;
+
+    let client = request::build_client()?;
+    request::execute(&client, prepared, None, &|event| {
+        let _ = app.emit("sse_event", event);
+    })
+    .await
+}
+
+#[tauri::command]
+pub async fn get_retention_policy(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+) -> Result<RetentionPolicy, String> {
+    retention::load(db.pool(), &workspace_id).await
+}
+
+/// Saves a workspace's policy and applies it right away, returning how many entries it removed
+#[tauri::command]
+pub async fn save_retention_policy(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    policy: RetentionPolicy,
+) -> Result<u64, String> {
+    retention::save(db.pool(), &workspace_id, &policy).await?;
+    retention::prune_workspace(db.pool(), &workspace_id).await
+}
+
+#[tauri::command]
+pub async fn get_storage_stats(db: tauri::State<'_, Database>) -> Result<StorageStats, String> {
+    retention::stats(db.pool()).await
+}
+
+#[tauri::command]
+pub async fn vacuum_database(db: tauri::State<'_, Database>) -> Result<StorageStats, String> {
+    retention::vacuum(db.pool()).await
+}
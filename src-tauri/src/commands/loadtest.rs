@@ -0,0 +1,52 @@
+use crate::db::Database;
+use crate::loadtest::{self, LoadTestConfig, LoadTestEvent};
+use crate::network::request;
+use crate::runner::RunManager;
+use crate::secrets::SecretVault;
+use crate::templating::{self, FormData, WorkspaceContext};
+use tauri::ipc::Channel;
+use tauri::Manager;
+use uuid::Uuid;
+
+/// Starts a load test of one operation in the background and returns its id. The request
+/// is resolved once from `form_data`; scripts, assertions and extraction rules don't run.
+/// An invalid config is rejected before anything starts. Progress and the final summary
+/// are sent on `on_event`, and `cancel_run` stops the test.
+#[tauri::command]
+pub async fn start_load_test(
+    app: tauri::AppHandle,
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    runs: tauri::State<'_, RunManager>,
+    config: LoadTestConfig,
+    form_data: FormData,
+    on_event: Channel<LoadTestEvent>,
+) -> Result<String, String> {
+    config.validate()?;
+    let context = WorkspaceContext::load_for_environment(
+        db.pool(),
+        &vault,
+        &config.workspace_id,
+        config.environment_id.as_deref(),
+    )
+    .await?;
+    let prepared = templating::build_request(&context, &config.operation_key, &form_data)?;
+    let client = request::build_client()?;
+
+    let run_id = Uuid::new_v4().to_string();
+    let cancel = runs.register(&run_id).await;
+
+    let id = run_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = loadtest::run(client, prepared, &id, &config, cancel, &|event| {
+            let _ = on_event.send(event);
+        })
+        .await;
+        if let Err(error) = result {
+            let _ = on_event.send(LoadTestEvent::Failed { error });
+        }
+        app.state::<RunManager>().finish(&id, None).await;
+    });
+
+    Ok(run_id)
+}
@@ -0,0 +1,93 @@
+use crate::db::Database;
+use crate::history::snapshot::Redactions;
+use crate::mock::{
+    MockLogEntry, MockServerInfo, MockServerManager, ReplayConfig, ReplayMock, SpecMock,
+};
+use crate::network::session::SessionEvent;
+use crate::secrets::SecretVault;
+use crate::spec;
+use crate::templating::WorkspaceContext;
+use std::sync::Arc;
+use tauri::ipc::Channel;
+use uuid::Uuid;
+
+/// Relays a mock server's events to the channel the caller passed in
+fn forward_to_channel(
+    mut event_rx: tokio::sync::mpsc::Receiver<SessionEvent>,
+    on_event: Channel<SessionEvent>,
+) {
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = event_rx.recv().await {
+            let _ = on_event.send(event);
+        }
+    });
+}
+
+/// Starts a mock server for the workspace's active spec. Its lifecycle and request
+/// events are sent on `on_event`, which the caller creates before invoking so the
+/// `listening` event isn't missed.
+#[tauri::command]
+pub async fn start_mock_server(
+    db: tauri::State<'_, Database>,
+    mocks: tauri::State<'_, MockServerManager>,
+    workspace_id: String,
+    port: Option<u16>,
+    validate_requests: Option<bool>,
+    on_event: Channel<SessionEvent>,
+) -> Result<MockServerInfo, String> {
+    let spec = spec::load_active(db.pool(), &workspace_id).await?;
+    let handler = Arc::new(SpecMock::new(spec, validate_requests.unwrap_or(true)));
+
+    let id = Uuid::new_v4().to_string();
+    let (event_tx, event_rx) = tokio::sync::mpsc::channel::<SessionEvent>(256);
+    forward_to_channel(event_rx, on_event);
+
+    mocks.start(id, port, handler, event_tx).await
+}
+
+/// Starts a server that replays the workspace's recorded responses, optionally proxying
+/// and recording misses from a real upstream. Secrets are kept out of recorded requests
+/// as they were when the server started. Events are sent on `on_event` as for
+/// [`start_mock_server`].
+#[tauri::command]
+pub async fn start_replay_server(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    mocks: tauri::State<'_, MockServerManager>,
+    workspace_id: String,
+    config: ReplayConfig,
+    on_event: Channel<SessionEvent>,
+) -> Result<MockServerInfo, String> {
+    // Without a spec, recordings are matched by their literal path
+    let context = WorkspaceContext::load(db.pool(), &vault, &workspace_id).await?;
+    let port = config.port;
+    let handler = Arc::new(ReplayMock::new(
+        db.pool().clone(),
+        workspace_id,
+        context.spec.as_ref(),
+        Redactions::new(&context),
+        config,
+    )?);
+
+    let id = Uuid::new_v4().to_string();
+    let (event_tx, event_rx) = tokio::sync::mpsc::channel::<SessionEvent>(256);
+    forward_to_channel(event_rx, on_event);
+
+    mocks.start(id, port, handler, event_tx).await
+}
+
+#[tauri::command]
+pub async fn stop_mock_server(
+    mocks: tauri::State<'_, MockServerManager>,
+    server_id: String,
+) -> Result<(), String> {
+    mocks.stop(&server_id).await
+}
+
+#[tauri::command]
+pub async fn get_mock_server_log(
+    mocks: tauri::State<'_, MockServerManager>,
+    server_id: String,
+) -> Result<Vec<MockLogEntry>, String> {
+    mocks.log(&server_id).await
+}
@@ -1,2 +1,17 @@
+pub mod assertions;
+pub mod backup;
+pub mod bundle;
+pub mod diff;
+pub mod extraction;
+pub mod history;
 pub mod http;
+pub mod loadtest;
+pub mod mock;
+pub mod repository;
+pub mod runner;
+pub mod scripting;
 pub mod session;
+pub mod spec;
+pub mod sync;
+pub mod validation;
+pub mod vault;
@@ -0,0 +1,290 @@
+use crate::db::repository::{
+    self, DbOperationState, DbSpec, DbWorkspace, FullWorkspaceData, InitialData,
+};
+use crate::db::Database;
+use crate::secrets::SecretVault;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub async fn load_initial_data(db: tauri::State<'_, Database>) -> Result<InitialData, String> {
+    repository::load_initial_data(db.pool()).await
+}
+
+#[tauri::command]
+pub async fn create_workspace(
+    db: tauri::State<'_, Database>,
+    id: String,
+    name: String,
+    sort_order: i64,
+) -> Result<(), String> {
+    repository::create_workspace(db.pool(), &id, &name, sort_order).await
+}
+
+#[tauri::command]
+pub async fn update_workspace(
+    db: tauri::State<'_, Database>,
+    workspace: DbWorkspace,
+) -> Result<(), String> {
+    repository::update_workspace(db.pool(), &workspace).await
+}
+
+#[tauri::command]
+pub async fn delete_workspace(db: tauri::State<'_, Database>, id: String) -> Result<(), String> {
+    repository::delete_workspace(db.pool(), &id).await
+}
+
+/// Secret values come back masked; they are only ever decrypted in the backend
+#[tauri::command]
+pub async fn get_full_workspace_data(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+) -> Result<Option<FullWorkspaceData>, String> {
+    repository::get_full_workspace_data(db.pool(), &vault, &workspace_id).await
+}
+
+#[tauri::command]
+pub async fn save_spec(
+    db: tauri::State<'_, Database>,
+    id: String,
+    spec_content: String,
+) -> Result<(), String> {
+    repository::save_spec(db.pool(), &id, &spec_content).await
+}
+
+#[tauri::command]
+pub async fn get_spec(
+    db: tauri::State<'_, Database>,
+    id: String,
+) -> Result<Option<DbSpec>, String> {
+    repository::get_spec(db.pool(), &id).await
+}
+
+#[tauri::command]
+pub async fn delete_spec(db: tauri::State<'_, Database>, id: String) -> Result<(), String> {
+    repository::delete_spec(db.pool(), &id).await
+}
+
+#[tauri::command]
+pub async fn create_environment(
+    db: tauri::State<'_, Database>,
+    id: String,
+    workspace_id: String,
+    name: String,
+) -> Result<(), String> {
+    repository::create_environment(db.pool(), &id, &workspace_id, &name).await
+}
+
+#[tauri::command]
+pub async fn update_environment(
+    db: tauri::State<'_, Database>,
+    id: String,
+    name: String,
+) -> Result<(), String> {
+    repository::update_environment(db.pool(), &id, &name).await
+}
+
+#[tauri::command]
+pub async fn delete_environment(db: tauri::State<'_, Database>, id: String) -> Result<(), String> {
+    repository::delete_environment(db.pool(), &id).await
+}
+
+#[tauri::command]
+pub async fn add_variable_key(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    key_name: String,
+) -> Result<i64, String> {
+    repository::add_variable_key(db.pool(), &workspace_id, &key_name).await
+}
+
+#[tauri::command]
+pub async fn remove_variable_key(
+    db: tauri::State<'_, Database>,
+    key_id: i64,
+) -> Result<(), String> {
+    repository::remove_variable_key(db.pool(), key_id).await
+}
+
+#[tauri::command]
+pub async fn rename_variable_key(
+    db: tauri::State<'_, Database>,
+    key_id: i64,
+    key_name: String,
+) -> Result<(), String> {
+    repository::rename_variable_key(db.pool(), key_id, &key_name).await
+}
+
+#[tauri::command]
+pub async fn set_variable_value(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    environment_id: String,
+    variable_key_id: i64,
+    value: String,
+) -> Result<(), String> {
+    repository::set_variable_value(db.pool(), &vault, &environment_id, variable_key_id, &value)
+        .await
+}
+
+#[tauri::command]
+pub async fn delete_variable_value(
+    db: tauri::State<'_, Database>,
+    environment_id: String,
+    variable_key_id: i64,
+) -> Result<(), String> {
+    repository::delete_variable_value(db.pool(), &environment_id, variable_key_id).await
+}
+
+#[tauri::command]
+pub async fn set_global_header(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    key: String,
+    value: String,
+) -> Result<(), String> {
+    repository::set_global_header(db.pool(), &workspace_id, &key, &value).await
+}
+
+#[tauri::command]
+pub async fn delete_global_header(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    key: String,
+) -> Result<(), String> {
+    repository::delete_global_header(db.pool(), &workspace_id, &key).await
+}
+
+#[tauri::command]
+pub async fn set_all_global_headers(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    headers: HashMap<String, String>,
+) -> Result<(), String> {
+    repository::set_all_global_headers(db.pool(), &workspace_id, &headers).await
+}
+
+#[tauri::command]
+pub async fn set_auth_value(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    environment_id: Option<String>,
+    scheme_name: String,
+    value_json: String,
+) -> Result<(), String> {
+    repository::set_auth_value(
+        db.pool(),
+        &vault,
+        &workspace_id,
+        environment_id.as_deref(),
+        &scheme_name,
+        &value_json,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn delete_auth_value(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    environment_id: Option<String>,
+    scheme_name: String,
+) -> Result<(), String> {
+    repository::delete_auth_value(
+        db.pool(),
+        &workspace_id,
+        environment_id.as_deref(),
+        &scheme_name,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn save_operation_state(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+    form_data: String,
+    response: Option<String>,
+) -> Result<(), String> {
+    repository::save_operation_state(
+        db.pool(),
+        &workspace_id,
+        &operation_key,
+        &form_data,
+        response.as_deref(),
+        now_ms(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn get_operation_state(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+) -> Result<Option<DbOperationState>, String> {
+    repository::get_operation_state(db.pool(), &workspace_id, &operation_key).await
+}
+
+#[tauri::command]
+pub async fn delete_operation_state(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+) -> Result<(), String> {
+    repository::delete_operation_state(db.pool(), &workspace_id, &operation_key).await
+}
+
+/// Deletes operation states not saved within the last `max_age_ms`
+#[tauri::command]
+pub async fn cleanup_old_responses(
+    db: tauri::State<'_, Database>,
+    max_age_ms: i64,
+) -> Result<u64, String> {
+    repository::cleanup_old_responses(db.pool(), now_ms() - max_age_ms).await
+}
+
+#[tauri::command]
+pub async fn add_history_entry(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+) -> Result<(), String> {
+    repository::add_history_entry(db.pool(), &workspace_id, &operation_key, now_ms()).await
+}
+
+#[tauri::command]
+pub async fn prune_history(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    keep_count: i64,
+) -> Result<(), String> {
+    repository::prune_history(db.pool(), &workspace_id, keep_count).await
+}
+
+#[tauri::command]
+pub async fn clear_history(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+) -> Result<(), String> {
+    repository::clear_history(db.pool(), &workspace_id).await
+}
+
+#[tauri::command]
+pub async fn clear_response_history(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+) -> Result<(), String> {
+    repository::clear_response_history(db.pool(), &workspace_id, &operation_key).await
+}
@@ -0,0 +1,77 @@
+use crate::db::Database;
+use crate::runner::{self, RunConfig, RunEvent, RunManager};
+use crate::secrets::SecretVault;
+use crate::templating::WorkspaceContext;
+use tauri::ipc::Channel;
+use tauri::Manager;
+use uuid::Uuid;
+
+/// Starts a collection run in the background and returns its id. Progress, including
+/// the final summary, is sent on `on_event`, which the caller creates before invoking so
+/// no event is missed.
+#[tauri::command]
+pub async fn start_run(
+    app: tauri::AppHandle,
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    runs: tauri::State<'_, RunManager>,
+    config: RunConfig,
+    on_event: Channel<RunEvent>,
+) -> Result<String, String> {
+    let context = WorkspaceContext::load_for_environment(
+        db.pool(),
+        &vault,
+        &config.workspace_id,
+        config.environment_id.as_deref(),
+    )
+    .await?;
+
+    let run_id = Uuid::new_v4().to_string();
+    let cancel = runs.register(&run_id).await;
+    let pool = db.pool().clone();
+
+    let id = run_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let vault = app.state::<SecretVault>();
+        let result = runner::run(
+            &pool,
+            vault.inner(),
+            context,
+            &id,
+            &config,
+            &cancel,
+            &|event| {
+                let _ = on_event.send(event);
+            },
+        )
+        .await;
+        let summary = match result {
+            Ok(summary) => Some(summary),
+            Err(error) => {
+                let _ = on_event.send(RunEvent::Failed { error });
+                None
+            }
+        };
+        app.state::<RunManager>().finish(&id, summary).await;
+    });
+
+    Ok(run_id)
+}
+
+#[tauri::command]
+pub async fn cancel_run(runs: tauri::State<'_, RunManager>, run_id: String) -> Result<(), String> {
+    runs.cancel(&run_id).await
+}
+
+/// Writes a finished run's per-step results to a CSV file
+#[tauri::command]
+pub async fn export_run_results(
+    runs: tauri::State<'_, RunManager>,
+    run_id: String,
+    path: String,
+) -> Result<(), String> {
+    let summary = runs.report(&run_id).await?;
+    tauri::async_runtime::spawn_blocking(move || runner::data::export_csv(&summary, &path))
+        .await
+        .map_err(|e| e.to_string())?
+}
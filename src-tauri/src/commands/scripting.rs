@@ -0,0 +1,21 @@
+use crate::db::Database;
+use crate::scripting::{self, OperationScripts};
+
+#[tauri::command]
+pub async fn get_operation_scripts(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+) -> Result<OperationScripts, String> {
+    scripting::load(db.pool(), &workspace_id, &operation_key).await
+}
+
+#[tauri::command]
+pub async fn save_operation_scripts(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    operation_key: String,
+    scripts: OperationScripts,
+) -> Result<(), String> {
+    scripting::save(db.pool(), &workspace_id, &operation_key, &scripts).await
+}
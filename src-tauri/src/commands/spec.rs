@@ -0,0 +1,46 @@
+use crate::db::Database;
+use crate::spec::{self, LoadedSpec, OperationEntry, SpecSource};
+
+async fn cached_spec(db: &Database, spec_id: &str) -> Result<Option<LoadedSpec>, String> {
+    Ok(spec::load_stored(db.pool(), spec_id)
+        .await?
+        .map(|value| LoadedSpec::from_processed(spec_id.to_string(), value)))
+}
+
+/// Loads, dereferences and converts a spec, caching the result in the `specs` table.
+/// A cached spec is returned as-is unless `refresh` is set.
+#[tauri::command]
+pub async fn load_spec(
+    db: tauri::State<'_, Database>,
+    source: SpecSource,
+    refresh: Option<bool>,
+) -> Result<LoadedSpec, String> {
+    if !refresh.unwrap_or(false) {
+        if let Some(cached) = cached_spec(&db, source.id()).await? {
+            return Ok(cached);
+        }
+    }
+
+    let loaded = spec::load(&source).await?;
+    let content = serde_json::to_string(&loaded.spec).map_err(|e| e.to_string())?;
+    sqlx::query("INSERT OR REPLACE INTO specs (id, spec_content) VALUES (?, ?)")
+        .bind(&loaded.id)
+        .bind(content)
+        .execute(db.pool())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(loaded)
+}
+
+/// Returns the operation index of a cached spec
+#[tauri::command]
+pub async fn get_operation_index(
+    db: tauri::State<'_, Database>,
+    spec_id: String,
+) -> Result<Vec<OperationEntry>, String> {
+    cached_spec(&db, &spec_id)
+        .await?
+        .map(|loaded| loaded.operations)
+        .ok_or_else(|| format!("Spec not found: {}", spec_id))
+}
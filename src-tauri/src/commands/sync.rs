@@ -0,0 +1,40 @@
+use crate::db::Database;
+use crate::secrets::SecretVault;
+use crate::sync::{self, SyncLink, SyncReport, SyncSide};
+
+/// Mirrors a workspace to `directory`; call `sync_workspace_directory` to write the files
+#[tauri::command]
+pub async fn link_workspace_directory(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+    directory: String,
+) -> Result<(), String> {
+    sync::link(db.pool(), &workspace_id, &directory).await
+}
+
+#[tauri::command]
+pub async fn unlink_workspace_directory(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+) -> Result<(), String> {
+    sync::unlink(db.pool(), &workspace_id).await
+}
+
+#[tauri::command]
+pub async fn get_workspace_directory(
+    db: tauri::State<'_, Database>,
+    workspace_id: String,
+) -> Result<Option<SyncLink>, String> {
+    sync::get_link(db.pool(), &workspace_id).await
+}
+
+/// Reconciles a workspace with its directory; conflicts are reported unless `prefer` picks a side
+#[tauri::command]
+pub async fn sync_workspace_directory(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    prefer: Option<SyncSide>,
+) -> Result<SyncReport, String> {
+    sync::sync(db.pool(), &vault, &workspace_id, prefer).await
+}
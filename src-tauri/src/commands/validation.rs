@@ -0,0 +1,43 @@
+use crate::db::Database;
+use crate::network::request::BackendResponse;
+use crate::secrets::SecretVault;
+use crate::spec;
+use crate::templating::{self, FormData, WorkspaceContext};
+use crate::validation::{self, PreflightReport, ValidationReport};
+
+/// Checks a response against the operation's declared responses in a stored spec
+#[tauri::command]
+pub async fn validate_response(
+    db: tauri::State<'_, Database>,
+    spec_id: String,
+    operation_key: String,
+    response: BackendResponse,
+) -> Result<ValidationReport, String> {
+    let spec = spec::load_stored(db.pool(), &spec_id)
+        .await?
+        .ok_or_else(|| format!("Spec not found: {}", spec_id))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        validation::validate_response(&spec, &operation_key, &response)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Resolves an operation's form data and checks it against the spec without sending it
+#[tauri::command]
+pub async fn validate_request(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    operation_key: String,
+    form_data: FormData,
+) -> Result<PreflightReport, String> {
+    let context = WorkspaceContext::load(db.pool(), &vault, &workspace_id).await?;
+    let resolved = templating::resolve_form_data(&form_data, &context.variables);
+    let prepared = templating::build_resolved_request(&context, &operation_key, &resolved)?;
+    let spec = context
+        .spec
+        .as_ref()
+        .ok_or_else(|| "Workspace has no active spec".to_string())?;
+    validation::validate_request(spec, &operation_key, &resolved, &prepared)
+}
@@ -0,0 +1,93 @@
+use crate::db::Database;
+use crate::secrets::{store, SecretVault, VaultCredential, VaultStatus};
+
+#[tauri::command]
+pub async fn get_vault_status(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+) -> Result<VaultStatus, String> {
+    vault.status(db.pool()).await
+}
+
+#[tauri::command]
+pub async fn unlock_vault(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    credential: VaultCredential,
+) -> Result<VaultStatus, String> {
+    vault.unlock(db.pool(), &credential).await?;
+    vault.status(db.pool()).await
+}
+
+#[tauri::command]
+pub async fn lock_vault(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+) -> Result<VaultStatus, String> {
+    vault.lock();
+    vault.status(db.pool()).await
+}
+
+#[tauri::command]
+pub async fn set_variable_secret(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    variable_key_id: i64,
+    is_secret: bool,
+) -> Result<(), String> {
+    store::set_variable_secret(db.pool(), &vault, variable_key_id, is_secret).await
+}
+
+#[tauri::command]
+pub async fn set_secret_variable_value(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    environment_id: String,
+    variable_key_id: i64,
+    value: String,
+) -> Result<(), String> {
+    store::set_variable_value(db.pool(), &vault, &environment_id, variable_key_id, &value).await
+}
+
+#[tauri::command]
+pub async fn set_auth_value_secret(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    environment_id: Option<String>,
+    scheme_name: String,
+    is_secret: bool,
+) -> Result<(), String> {
+    store::set_auth_secret(
+        db.pool(),
+        &vault,
+        &workspace_id,
+        environment_id.as_deref(),
+        &scheme_name,
+        is_secret,
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn set_secret_auth_value(
+    db: tauri::State<'_, Database>,
+    vault: tauri::State<'_, SecretVault>,
+    workspace_id: String,
+    environment_id: Option<String>,
+    scheme_name: String,
+    value_json: String,
+    is_secret: bool,
+) -> Result<(), String> {
+    store::set_auth_value(
+        db.pool(),
+        &vault,
+        &workspace_id,
+        environment_id.as_deref(),
+        &scheme_name,
+        &value_json,
+        is_secret,
+    )
+    .await
+}
+
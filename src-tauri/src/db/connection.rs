@@ -0,0 +1,64 @@
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tauri::Manager;
+
+/// Connection string the SQL plugin preloads and the frontend loads
+pub const DB_URL: &str = "sqlite:cogeass.db";
+
+/// File name of the database inside the app config directory
+pub const DB_FILE_NAME: &str = "cogeass.db";
+
+/// Resolves the on-disk location of the database the same way the SQL plugin does
+pub fn database_path<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(DB_FILE_NAME))
+}
+
+/// Backend-side handle to the application database.
+///
+/// Commands read and write rows through this pool; the frontend only reaches the database
+/// through those commands.
+pub struct Database {
+    pool: SqlitePool,
+}
+
+impl Database {
+    pub async fn open(path: &Path) -> Result<Self, String> {
+        let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.display()))
+            .map_err(|e| e.to_string())?
+            .create_if_missing(true)
+            .foreign_keys(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self { pool })
+    }
+
+    /// Opens a private in-memory database with every migration applied
+    pub async fn open_in_memory() -> Result<Self, String> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")
+            .map_err(|e| e.to_string())?
+            .foreign_keys(true);
+
+        // Every connection to `:memory:` gets its own database, so keep exactly one alive
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .map_err(|e| e.to_string())?;
+        super::migrations::apply_all(&pool).await?;
+
+        Ok(Self { pool })
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+}
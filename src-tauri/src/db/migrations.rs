@@ -1,8 +1,13 @@
+use futures::future::BoxFuture;
+use sqlx::error::BoxDynError;
+use sqlx::migrate::{Migration as SqlxMigration, MigrationSource, MigrationType, Migrator};
+use sqlx::SqlitePool;
 use tauri_plugin_sql::{Migration, MigrationKind};
 
-/// Returns all database migrations for the application
+/// Returns all database migrations for the application. [`apply_pending`] only runs the
+/// `Up` ones; `Down` migrations are run by [`migrate`] when reverting.
 pub fn get_migrations() -> Vec<Migration> {
-    vec![
+    let mut migrations = vec![
         // Migration 1: Workspaces table
         Migration {
             version: 1,
@@ -154,5 +159,775 @@ pub fn get_migrations() -> Vec<Migration> {
             CREATE INDEX idx_response_history_lookup ON response_history(workspace_id, operation_key, timestamp DESC);",
             kind: MigrationKind::Up,
         },
+        // Migration 13: Secret flags and vault metadata
+        Migration {
+            version: 13,
+            description: "add secret flags and vault_meta table",
+            sql: "ALTER TABLE workspace_variable_keys ADD COLUMN is_secret INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE auth_values ADD COLUMN is_secret INTEGER NOT NULL DEFAULT 0;
+            CREATE TABLE vault_meta (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                kdf TEXT NOT NULL,
+                salt TEXT NOT NULL,
+                verifier TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );",
+            kind: MigrationKind::Up,
+        },
+        // Migration 14: Request fingerprints for recorded responses
+        Migration {
+            version: 14,
+            description: "add request_json to response_history",
+            sql: "ALTER TABLE response_history ADD COLUMN request_json TEXT;",
+            kind: MigrationKind::Up,
+        },
+        // Migration 15: Assertions attached to operations
+        Migration {
+            version: 15,
+            description: "create operation_assertions table",
+            sql: "CREATE TABLE operation_assertions (
+                workspace_id TEXT NOT NULL,
+                operation_key TEXT NOT NULL,
+                assertions_json TEXT NOT NULL,
+                last_modified INTEGER NOT NULL,
+                PRIMARY KEY(workspace_id, operation_key),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        // Migration 16: Variable extraction rules attached to operations
+        Migration {
+            version: 16,
+            description: "create operation_extractions table",
+            sql: "CREATE TABLE operation_extractions (
+                workspace_id TEXT NOT NULL,
+                operation_key TEXT NOT NULL,
+                rules_json TEXT NOT NULL,
+                last_modified INTEGER NOT NULL,
+                PRIMARY KEY(workspace_id, operation_key),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        // Migration 17: Scripts run before and after an operation's request
+        Migration {
+            version: 17,
+            description: "create operation_scripts table",
+            sql: "CREATE TABLE operation_scripts (
+                workspace_id TEXT NOT NULL,
+                operation_key TEXT NOT NULL,
+                pre_request TEXT,
+                post_response TEXT,
+                last_modified INTEGER NOT NULL,
+                PRIMARY KEY(workspace_id, operation_key),
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        // Migration 18: Full-text index over response history, kept in sync by triggers
+        Migration {
+            version: 18,
+            description: "create response_history_fts table",
+            sql: "CREATE VIRTUAL TABLE response_history_fts USING fts5(headers, body);
+            INSERT INTO response_history_fts (rowid, headers, body)
+                SELECT id,
+                    CASE WHEN json_valid(response_json) THEN COALESCE(json_extract(response_json, '$.headers'), '') ELSE '' END,
+                    CASE WHEN json_valid(response_json) THEN COALESCE(json_extract(response_json, '$.bodyText'), '') ELSE response_json END
+                FROM response_history;
+            CREATE TRIGGER response_history_fts_insert AFTER INSERT ON response_history BEGIN
+                INSERT INTO response_history_fts (rowid, headers, body) VALUES (
+                    new.id,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.headers'), '') ELSE '' END,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.bodyText'), '') ELSE new.response_json END
+                );
+            END;
+            CREATE TRIGGER response_history_fts_delete AFTER DELETE ON response_history BEGIN
+                DELETE FROM response_history_fts WHERE rowid = old.id;
+            END;
+            CREATE TRIGGER response_history_fts_update AFTER UPDATE OF response_json ON response_history BEGIN
+                DELETE FROM response_history_fts WHERE rowid = old.id;
+                INSERT INTO response_history_fts (rowid, headers, body) VALUES (
+                    new.id,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.headers'), '') ELSE '' END,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.bodyText'), '') ELSE new.response_json END
+                );
+            END;",
+            kind: MigrationKind::Up,
+        },
+        // Migration 19: History retention policies and compressed response bodies
+        Migration {
+            version: 19,
+            description: "add history_retention table and response_blob column",
+            sql: "CREATE TABLE history_retention (
+                workspace_id TEXT PRIMARY KEY,
+                max_entries_per_operation INTEGER,
+                max_age_days INTEGER,
+                max_total_bytes INTEGER,
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+            );
+            ALTER TABLE response_history ADD COLUMN response_blob BLOB;
+            DROP TRIGGER response_history_fts_update;
+            CREATE TRIGGER response_history_fts_update AFTER UPDATE OF response_json ON response_history
+            WHEN new.response_blob IS NULL BEGIN
+                DELETE FROM response_history_fts WHERE rowid = old.id;
+                INSERT INTO response_history_fts (rowid, headers, body) VALUES (
+                    new.id,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.headers'), '') ELSE '' END,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.bodyText'), '') ELSE new.response_json END
+                );
+            END;
+            CREATE TRIGGER response_history_fts_compress AFTER UPDATE OF response_json ON response_history
+            WHEN new.response_blob IS NOT NULL AND old.response_blob IS NULL BEGIN
+                DELETE FROM response_history_fts WHERE rowid = old.id;
+                INSERT INTO response_history_fts (rowid, headers, body) VALUES (
+                    old.id,
+                    CASE WHEN json_valid(old.response_json) THEN COALESCE(json_extract(old.response_json, '$.headers'), '') ELSE '' END,
+                    substr(CASE WHEN json_valid(old.response_json) THEN COALESCE(json_extract(old.response_json, '$.bodyText'), '') ELSE old.response_json END, 1, 65536)
+                );
+            END;",
+            kind: MigrationKind::Up,
+        },
+        // Migration 20: Redacted request snapshots for replaying history entries
+        Migration {
+            version: 20,
+            description: "add request_snapshot column to response_history",
+            sql: "ALTER TABLE response_history ADD COLUMN request_snapshot TEXT;",
+            kind: MigrationKind::Up,
+        },
+        // Migration 21: Directories workspaces are mirrored to, with hashes from the last sync
+        Migration {
+            version: 21,
+            description: "create workspace_sync table",
+            sql: "CREATE TABLE workspace_sync (
+                workspace_id TEXT PRIMARY KEY,
+                directory TEXT NOT NULL,
+                state_json TEXT NOT NULL DEFAULT '{}',
+                last_synced_at INTEGER,
+                FOREIGN KEY(workspace_id) REFERENCES workspaces(id) ON DELETE CASCADE
+            );",
+            kind: MigrationKind::Up,
+        },
+        // Migration 22: Response status in its own column, so compressed entries can be
+        // filtered by it. Compressed rows are filled in by `history::store::fill_status`.
+        Migration {
+            version: 22,
+            description: "add status column to response_history",
+            sql: "ALTER TABLE response_history ADD COLUMN status INTEGER;
+            UPDATE response_history SET status = json_extract(response_json, '$.status')
+                WHERE json_valid(response_json);
+            CREATE INDEX idx_response_history_status ON response_history(workspace_id, status);",
+            kind: MigrationKind::Up,
+        },
+    ];
+    migrations.extend(down_migrations());
+    migrations
+}
+
+/// Reverts each migration whose data survives the trip. Reverting 13 or 19 would leave
+/// encrypted or compressed values the older schema can't read, so those fail while any
+/// exist: the guard table's CHECK rejects a non-zero count.
+fn down_migrations() -> Vec<Migration> {
+    let down = |version, description, sql| Migration {
+        version,
+        description,
+        sql,
+        kind: MigrationKind::Down,
+    };
+    vec![
+        down(1, "drop workspaces table", "DROP TABLE workspaces;"),
+        down(2, "drop specs table", "DROP TABLE specs;"),
+        down(3, "drop environments table", "DROP TABLE environments;"),
+        down(
+            4,
+            "drop workspace_variable_keys table",
+            "DROP TABLE workspace_variable_keys;",
+        ),
+        down(
+            5,
+            "drop environment_variable_values table",
+            "DROP TABLE environment_variable_values;",
+        ),
+        down(6, "drop global_headers table", "DROP TABLE global_headers;"),
+        down(7, "drop auth_values table", "DROP TABLE auth_values;"),
+        down(8, "drop operation_states table", "DROP TABLE operation_states;"),
+        down(9, "drop history table", "DROP TABLE history;"),
+        down(
+            10,
+            "drop performance indexes",
+            "DROP INDEX idx_operations_last_modified;
+            DROP INDEX idx_history_timestamp;",
+        ),
+        down(
+            11,
+            "drop spec_url column from workspaces",
+            "ALTER TABLE workspaces DROP COLUMN spec_url;",
+        ),
+        down(
+            12,
+            "drop response_history table",
+            "DROP INDEX idx_response_history_lookup;
+            DROP TABLE response_history;",
+        ),
+        down(
+            13,
+            "drop secret flags and vault_meta table",
+            "CREATE TEMP TABLE down_guard (secret_values INTEGER CHECK (secret_values = 0));
+            INSERT INTO down_guard SELECT
+                (SELECT COUNT(*) FROM workspace_variable_keys WHERE is_secret = 1)
+                + (SELECT COUNT(*) FROM auth_values WHERE is_secret = 1);
+            DROP TABLE down_guard;
+            DROP TABLE vault_meta;
+            ALTER TABLE auth_values DROP COLUMN is_secret;
+            ALTER TABLE workspace_variable_keys DROP COLUMN is_secret;",
+        ),
+        down(
+            14,
+            "drop request_json from response_history",
+            "ALTER TABLE response_history DROP COLUMN request_json;",
+        ),
+        down(
+            15,
+            "drop operation_assertions table",
+            "DROP TABLE operation_assertions;",
+        ),
+        down(
+            16,
+            "drop operation_extractions table",
+            "DROP TABLE operation_extractions;",
+        ),
+        down(
+            17,
+            "drop operation_scripts table",
+            "DROP TABLE operation_scripts;",
+        ),
+        down(
+            18,
+            "drop response_history_fts table",
+            "DROP TRIGGER response_history_fts_insert;
+            DROP TRIGGER response_history_fts_delete;
+            DROP TRIGGER response_history_fts_update;
+            DROP TABLE response_history_fts;",
+        ),
+        down(
+            19,
+            "drop history_retention table and response_blob column",
+            // Restores the search trigger as migration 18 created it
+            "CREATE TEMP TABLE down_guard (compressed_responses INTEGER CHECK (compressed_responses = 0));
+            INSERT INTO down_guard SELECT COUNT(*) FROM response_history WHERE response_blob IS NOT NULL;
+            DROP TABLE down_guard;
+            DROP TRIGGER response_history_fts_compress;
+            DROP TRIGGER response_history_fts_update;
+            ALTER TABLE response_history DROP COLUMN response_blob;
+            DROP TABLE history_retention;
+            CREATE TRIGGER response_history_fts_update AFTER UPDATE OF response_json ON response_history BEGIN
+                DELETE FROM response_history_fts WHERE rowid = old.id;
+                INSERT INTO response_history_fts (rowid, headers, body) VALUES (
+                    new.id,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.headers'), '') ELSE '' END,
+                    CASE WHEN json_valid(new.response_json) THEN COALESCE(json_extract(new.response_json, '$.bodyText'), '') ELSE new.response_json END
+                );
+            END;",
+        ),
+        down(
+            20,
+            "drop request_snapshot from response_history",
+            "ALTER TABLE response_history DROP COLUMN request_snapshot;",
+        ),
+        down(21, "drop workspace_sync table", "DROP TABLE workspace_sync;"),
+        down(
+            22,
+            "drop status from response_history",
+            "DROP INDEX idx_response_history_status;
+            ALTER TABLE response_history DROP COLUMN status;",
+        ),
     ]
 }
+
+/// Version of the newest migration, i.e. the schema this build writes
+pub fn latest_version() -> i64 {
+    get_migrations()
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Moves a database's schema from version `from` to `to`, running `Up` migrations in
+/// ascending order or `Down` migrations in descending order. Each runs in its own
+/// transaction, so a failure leaves the database at the last version that succeeded.
+pub async fn migrate(pool: &SqlitePool, from: i64, to: i64) -> Result<(), String> {
+    let mut steps: Vec<Migration> = get_migrations()
+        .into_iter()
+        .filter(|m| {
+            if to >= from {
+                matches!(m.kind, MigrationKind::Up) && m.version > from && m.version <= to
+            } else {
+                matches!(m.kind, MigrationKind::Down) && m.version > to && m.version <= from
+            }
+        })
+        .collect();
+    steps.sort_by_key(|m| m.version);
+    if to < from {
+        steps.reverse();
+        if let Some(missing) = (to + 1..=from).find(|v| steps.iter().all(|m| m.version != *v)) {
+            return Err(format!("Migration {} can't be reverted", missing));
+        }
+    }
+
+    for migration in steps {
+        let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+        sqlx::raw_sql(migration.sql)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Migration {} failed: {}", migration.version, e))?;
+        tx.commit().await.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// The `Up` migrations through a version, recorded in `_sqlx_migrations` the way the SQL
+/// plugin recorded them before the app ran migrations itself
+#[derive(Debug)]
+struct UpMigrations(i64);
+
+impl MigrationSource<'static> for UpMigrations {
+    fn resolve(self) -> BoxFuture<'static, Result<Vec<SqlxMigration>, BoxDynError>> {
+        Box::pin(async move {
+            let mut migrations: Vec<SqlxMigration> = get_migrations()
+                .into_iter()
+                .filter(|m| matches!(m.kind, MigrationKind::Up) && m.version <= self.0)
+                .map(|m| {
+                    SqlxMigration::new(
+                        m.version,
+                        m.description.into(),
+                        MigrationType::ReversibleUp,
+                        m.sql.into(),
+                        false,
+                    )
+                })
+                .collect();
+            migrations.sort_by_key(|m| m.version);
+            Ok(migrations)
+        })
+    }
+}
+
+/// Applies the `Up` migrations through version `to` that `_sqlx_migrations` doesn't record
+/// yet. Each runs in its own transaction, so a failure leaves the last one that succeeded.
+pub async fn apply_pending(pool: &SqlitePool, to: i64) -> Result<(), String> {
+    let migrator = Migrator::new(UpMigrations(to))
+        .await
+        .map_err(|e| e.to_string())?;
+    migrator.run(pool).await.map_err(|e| e.to_string())
+}
+
+/// Runs every migration in order on a fresh database, without recording them
+pub async fn apply_all(pool: &SqlitePool) -> Result<(), String> {
+    migrate(pool, 0, latest_version()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+    use std::str::FromStr;
+
+    /// Rows inserted once the schema reaches the version they're listed with
+    const FIXTURES: &[(i64, &str)] = &[
+        (
+            1,
+            "INSERT INTO workspaces (id, name, active_spec_id, active_environment_id, base_url, selected_operation_key, sort_order)
+             VALUES ('w1', 'Pets', 's1', 'e1', 'http://pets', 'get:/pets', 0),
+                    ('w2', 'Other', NULL, NULL, NULL, NULL, 1);",
+        ),
+        (2, "INSERT INTO specs (id, spec_content) VALUES ('s1', '{}');"),
+        (
+            3,
+            "INSERT INTO environments (id, workspace_id, name) VALUES ('e1', 'w1', 'dev'), ('e2', 'w2', 'dev');",
+        ),
+        (
+            4,
+            "INSERT INTO workspace_variable_keys (id, workspace_id, key_name) VALUES (1, 'w1', 'host'), (2, 'w2', 'host');",
+        ),
+        (
+            5,
+            "INSERT INTO environment_variable_values (environment_id, variable_key_id, value)
+             VALUES ('e1', 1, 'http://dev'), ('e2', 2, 'http://other');",
+        ),
+        (
+            6,
+            "INSERT INTO global_headers (workspace_id, key, value) VALUES ('w1', 'x-app', 'pets'), ('w2', 'x-app', 'other');",
+        ),
+        (
+            7,
+            "INSERT INTO auth_values (workspace_id, environment_id, scheme_name, value_json)
+             VALUES ('w1', 'e1', 'bearer', '{\"token\":\"t\"}'), ('w1', NULL, 'basic', '{}'), ('w2', NULL, 'basic', '{}');",
+        ),
+        (
+            8,
+            "INSERT INTO operation_states (workspace_id, operation_key, form_data, response, last_modified)
+             VALUES ('w1', 'get:/pets', '{}', NULL, 1), ('w2', 'get:/other', '{}', NULL, 1);",
+        ),
+        (
+            9,
+            "INSERT INTO history (workspace_id, operation_key, timestamp) VALUES ('w1', 'get:/pets', 1), ('w2', 'get:/other', 1);",
+        ),
+        (
+            12,
+            "INSERT INTO response_history (workspace_id, operation_key, response_json, timestamp)
+             VALUES ('w1', 'get:/pets', '{\"status\":200,\"headers\":{},\"bodyText\":\"needle\"}', 1),
+                    ('w2', 'get:/other', '{\"status\":200,\"headers\":{},\"bodyText\":\"needle\"}', 1);",
+        ),
+        (
+            15,
+            "INSERT INTO operation_assertions (workspace_id, operation_key, assertions_json, last_modified)
+             VALUES ('w1', 'get:/pets', '[]', 1);",
+        ),
+        (
+            16,
+            "INSERT INTO operation_extractions (workspace_id, operation_key, rules_json, last_modified)
+             VALUES ('w1', 'get:/pets', '[]', 1);",
+        ),
+        (
+            17,
+            "INSERT INTO operation_scripts (workspace_id, operation_key, pre_request, post_response, last_modified)
+             VALUES ('w1', 'get:/pets', 'log(1)', NULL, 1);",
+        ),
+        (
+            19,
+            "INSERT INTO history_retention (workspace_id, max_entries_per_operation) VALUES ('w1', 10);",
+        ),
+        (
+            21,
+            "INSERT INTO workspace_sync (workspace_id, directory) VALUES ('w1', '/tmp/pets');",
+        ),
+    ];
+
+    async fn empty_pool() -> SqlitePool {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")
+            .unwrap()
+            .foreign_keys(true);
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await
+            .unwrap()
+    }
+
+    /// A database at `version` holding the fixtures that fit its schema
+    async fn database_at(version: i64) -> SqlitePool {
+        let pool = empty_pool().await;
+        migrate(&pool, 0, version).await.unwrap();
+        for (since, sql) in FIXTURES {
+            if *since <= version {
+                sqlx::raw_sql(sql).execute(&pool).await.unwrap();
+            }
+        }
+        pool
+    }
+
+    async fn count(pool: &SqlitePool, sql: &str) -> i64 {
+        let (count,): (i64,) = sqlx::query_as(sql).fetch_one(pool).await.unwrap();
+        count
+    }
+
+    async fn foreign_key_violations(pool: &SqlitePool) -> usize {
+        sqlx::query("PRAGMA foreign_key_check")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+            .len()
+    }
+
+    /// Tables, columns, foreign keys, indexes and triggers, in a form that doesn't depend on
+    /// how a table's SQL was last rewritten
+    async fn schema(pool: &SqlitePool) -> Vec<String> {
+        let objects: Vec<(String, String, Option<String>)> = sqlx::query_as(
+            "SELECT type, name, sql FROM sqlite_master WHERE name NOT LIKE 'sqlite_%' ORDER BY type, name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+
+        let mut schema = Vec::new();
+        for (kind, name, sql) in objects {
+            if kind != "table" {
+                let sql = sql.unwrap_or_default();
+                schema.push(format!(
+                    "{} {}: {}",
+                    kind,
+                    name,
+                    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+                ));
+                continue;
+            }
+            let columns: Vec<(String, String, i64, Option<String>, i64)> = sqlx::query_as(
+                "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?)",
+            )
+            .bind(&name)
+            .fetch_all(pool)
+            .await
+            .unwrap();
+            for column in columns {
+                schema.push(format!("table {}: {:?}", name, column));
+            }
+            let keys: Vec<(String, String, Option<String>, String)> = sqlx::query_as(
+                "SELECT \"table\", \"from\", \"to\", on_delete FROM pragma_foreign_key_list(?)",
+            )
+            .bind(&name)
+            .fetch_all(pool)
+            .await
+            .unwrap();
+            for key in keys {
+                schema.push(format!("foreign key {}: {:?}", name, key));
+            }
+        }
+        schema
+    }
+
+    /// Tables with a `workspace_id` column, which all cascade from `workspaces`
+    async fn workspace_tables(pool: &SqlitePool) -> Vec<String> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT m.name FROM sqlite_master m, pragma_table_info(m.name) c
+             WHERE m.type = 'table' AND c.name = 'workspace_id' ORDER BY m.name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        rows.into_iter().map(|(name,)| name).collect()
+    }
+
+    #[test]
+    fn versions_are_sequential_and_downs_pair_with_ups() {
+        let migrations = get_migrations();
+        let ups: Vec<i64> = migrations
+            .iter()
+            .filter(|m| matches!(m.kind, MigrationKind::Up))
+            .map(|m| m.version)
+            .collect();
+        assert_eq!(ups, (1..=latest_version()).collect::<Vec<_>>());
+
+        let mut downs: Vec<i64> = migrations
+            .iter()
+            .filter(|m| matches!(m.kind, MigrationKind::Down))
+            .map(|m| m.version)
+            .collect();
+        downs.sort();
+        downs.dedup();
+        assert!(downs.iter().all(|v| ups.contains(v)));
+        assert_eq!(
+            downs.len(),
+            migrations
+                .iter()
+                .filter(|m| matches!(m.kind, MigrationKind::Down))
+                .count()
+        );
+    }
+
+    #[tokio::test]
+    async fn every_version_upgrades_to_latest_with_its_data() {
+        let latest = latest_version();
+        let fresh = schema(&database_at(latest).await).await;
+
+        for version in 0..latest {
+            let pool = database_at(version).await;
+            migrate(&pool, version, latest)
+                .await
+                .unwrap_or_else(|e| panic!("upgrading from {}: {}", version, e));
+
+            assert_eq!(
+                schema(&pool).await,
+                fresh,
+                "schema after upgrading from {}",
+                version
+            );
+            assert_eq!(
+                foreign_key_violations(&pool).await,
+                0,
+                "upgrading from {}",
+                version
+            );
+
+            let expected = FIXTURES
+                .iter()
+                .filter(|(since, _)| *since <= version)
+                .count();
+            let mut present = 0;
+            for (since, sql) in FIXTURES {
+                let table = sql.split_whitespace().nth(2).unwrap();
+                if *since <= version
+                    && count(&pool, &format!("SELECT COUNT(*) FROM {}", table)).await > 0
+                {
+                    present += 1;
+                }
+            }
+            assert_eq!(
+                present, expected,
+                "fixture rows lost upgrading from {}",
+                version
+            );
+
+            // Responses stored before the search index existed are indexed when it's created
+            assert_eq!(
+                count(&pool, "SELECT COUNT(*) FROM response_history_fts WHERE response_history_fts MATCH 'needle'").await,
+                count(&pool, "SELECT COUNT(*) FROM response_history").await,
+                "search index after upgrading from {}",
+                version
+            );
+            assert_eq!(
+                count(
+                    &pool,
+                    "SELECT COUNT(*) FROM response_history WHERE status = 200"
+                )
+                .await,
+                count(&pool, "SELECT COUNT(*) FROM response_history").await,
+                "response status after upgrading from {}",
+                version
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn down_migrations_restore_each_previous_schema() {
+        let latest = latest_version();
+        let pool = database_at(latest).await;
+
+        for version in (0..latest).rev() {
+            migrate(&pool, version + 1, version)
+                .await
+                .unwrap_or_else(|e| panic!("reverting to {}: {}", version, e));
+            let expected = schema(&database_at(version).await).await;
+            assert_eq!(
+                schema(&pool).await,
+                expected,
+                "schema after reverting to {}",
+                version
+            );
+            assert_eq!(
+                foreign_key_violations(&pool).await,
+                0,
+                "reverting to {}",
+                version
+            );
+            if version >= 1 {
+                assert_eq!(count(&pool, "SELECT COUNT(*) FROM workspaces").await, 2);
+            }
+        }
+        assert!(schema(&pool).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn down_migrations_refuse_to_drop_unreadable_values() {
+        let latest = latest_version();
+
+        let pool = database_at(latest).await;
+        sqlx::query("UPDATE response_history SET response_blob = x'00', response_json = '' WHERE workspace_id = 'w1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let error = migrate(&pool, latest, 18).await.unwrap_err();
+        assert!(error.starts_with("Migration 19 failed"), "{}", error);
+        // Everything after 19 was reverted; 19 itself rolled back whole
+        assert_eq!(
+            count(&pool, "SELECT COUNT(*) FROM history_retention").await,
+            1
+        );
+        assert_eq!(schema(&pool).await, schema(&database_at(19).await).await);
+
+        let pool = database_at(latest).await;
+        sqlx::query("UPDATE workspace_variable_keys SET is_secret = 1 WHERE id = 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let error = migrate(&pool, latest, 12).await.unwrap_err();
+        assert!(error.starts_with("Migration 13 failed"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn deleting_a_workspace_cascades_at_every_version() {
+        for version in 3..=latest_version() {
+            let pool = database_at(version).await;
+            let tables = workspace_tables(&pool).await;
+            let before: Vec<i64> = {
+                let mut counts = Vec::new();
+                for table in &tables {
+                    counts.push(
+                        count(
+                            &pool,
+                            &format!("SELECT COUNT(*) FROM {} WHERE workspace_id = 'w2'", table),
+                        )
+                        .await,
+                    );
+                }
+                counts
+            };
+
+            sqlx::query("DELETE FROM workspaces WHERE id = 'w1'")
+                .execute(&pool)
+                .await
+                .unwrap();
+
+            for (table, before) in tables.iter().zip(before) {
+                let sql = format!("SELECT COUNT(*) FROM {} WHERE workspace_id = ?", table);
+                let (left,): (i64,) = sqlx::query_as(&sql)
+                    .bind("w1")
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    left, 0,
+                    "{} kept rows of a deleted workspace at version {}",
+                    table, version
+                );
+                let (kept,): (i64,) = sqlx::query_as(&sql)
+                    .bind("w2")
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    kept, before,
+                    "{} lost another workspace's rows at version {}",
+                    table, version
+                );
+            }
+            if version >= 5 {
+                // Values cascade through their environment and key, not a workspace column
+                assert_eq!(
+                    count(&pool, "SELECT COUNT(*) FROM environment_variable_values WHERE environment_id = 'e1'").await,
+                    0
+                );
+            }
+            if version >= 18 {
+                assert_eq!(
+                    count(&pool, "SELECT COUNT(*) FROM response_history_fts").await,
+                    count(&pool, "SELECT COUNT(*) FROM response_history").await
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn backend_connections_enforce_foreign_keys() {
+        let path =
+            std::env::temp_dir().join(format!("cogeass-migrations-{}.db", uuid::Uuid::new_v4()));
+        let file = Database::open(&path).await.unwrap();
+        let memory = Database::open_in_memory().await.unwrap();
+        apply_all(file.pool()).await.unwrap();
+
+        for db in [&file, &memory] {
+            assert_eq!(count(db.pool(), "PRAGMA foreign_keys").await, 1);
+            let orphan = sqlx::query(
+                "INSERT INTO environments (id, workspace_id, name) VALUES ('e', 'missing', 'dev')",
+            )
+            .execute(db.pool())
+            .await;
+            assert!(orphan.is_err());
+        }
+
+        file.pool().close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+}
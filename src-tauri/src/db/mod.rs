@@ -1,4 +1,7 @@
+pub mod connection;
 pub mod migrations;
+pub mod repository;
 
 // Re-export for convenience
+pub use connection::{database_path, Database, DB_URL};
 pub use migrations::get_migrations;
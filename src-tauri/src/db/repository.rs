@@ -0,0 +1,1054 @@
+//! Typed access to the workspace tables the frontend edits. Writes that touch more than
+//! one row run in a transaction, and foreign keys are enforced by the backend pool.
+
+use crate::secrets::{store, SecretVault};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, SqlitePool};
+use std::collections::HashMap;
+
+/// Operations kept in a workspace's recently used list
+pub const HISTORY_LIMIT: i64 = 50;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbWorkspace {
+    pub id: String,
+    pub name: String,
+    pub active_spec_id: Option<String>,
+    pub active_environment_id: Option<String>,
+    pub base_url: Option<String>,
+    pub selected_operation_key: Option<String>,
+    pub sort_order: i64,
+    pub spec_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbSpec {
+    pub id: String,
+    pub spec_content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbEnvironment {
+    pub id: String,
+    pub workspace_id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbVariableKey {
+    pub id: i64,
+    pub workspace_id: String,
+    pub key_name: String,
+    /// 1 when values are encrypted by the vault
+    pub is_secret: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbVariableValue {
+    pub environment_id: String,
+    pub variable_key_id: i64,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbGlobalHeader {
+    pub workspace_id: String,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbAuthValue {
+    pub workspace_id: String,
+    pub environment_id: Option<String>,
+    pub scheme_name: String,
+    pub value_json: String,
+    pub is_secret: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbOperationState {
+    pub workspace_id: String,
+    pub operation_key: String,
+    pub form_data: String,
+    pub response: Option<String>,
+    pub last_modified: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct DbHistoryEntry {
+    pub id: i64,
+    pub workspace_id: String,
+    pub operation_key: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InitialData {
+    pub workspaces: Vec<DbWorkspace>,
+}
+
+/// Everything loaded when switching to a workspace
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FullWorkspaceData {
+    pub workspace: DbWorkspace,
+    pub spec: Option<DbSpec>,
+    pub environments: Vec<DbEnvironment>,
+    pub variable_keys: Vec<DbVariableKey>,
+    pub variable_values: Vec<DbVariableValue>,
+    pub global_headers: Vec<DbGlobalHeader>,
+    pub auth_values: Vec<DbAuthValue>,
+    pub history: Vec<DbHistoryEntry>,
+}
+
+// ============================================================================
+// WORKSPACES
+// ============================================================================
+
+pub async fn load_initial_data(pool: &SqlitePool) -> Result<InitialData, String> {
+    let workspaces = sqlx::query_as("SELECT * FROM workspaces ORDER BY sort_order ASC")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(InitialData { workspaces })
+}
+
+pub async fn create_workspace(
+    pool: &SqlitePool,
+    id: &str,
+    name: &str,
+    sort_order: i64,
+) -> Result<(), String> {
+    sqlx::query("INSERT INTO workspaces (id, name, sort_order) VALUES (?, ?, ?)")
+        .bind(id)
+        .bind(name)
+        .bind(sort_order)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Saves a workspace's details. Its active environment must belong to it and its active
+/// spec must exist, since neither column has a foreign key.
+pub async fn update_workspace(pool: &SqlitePool, workspace: &DbWorkspace) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    if let Some(environment_id) = &workspace.active_environment_id {
+        let owner: Option<(String,)> =
+            sqlx::query_as("SELECT workspace_id FROM environments WHERE id = ?")
+                .bind(environment_id)
+                .fetch_optional(&mut *tx)
+                .await
+                .map_err(|e| e.to_string())?;
+        if owner.map(|(id,)| id).as_deref() != Some(workspace.id.as_str()) {
+            return Err(format!(
+                "Environment {} does not belong to workspace {}",
+                environment_id, workspace.id
+            ));
+        }
+    }
+    if let Some(spec_id) = &workspace.active_spec_id {
+        let spec: Option<(String,)> = sqlx::query_as("SELECT id FROM specs WHERE id = ?")
+            .bind(spec_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+        if spec.is_none() {
+            return Err(format!("Spec not found: {}", spec_id));
+        }
+    }
+
+    let updated = sqlx::query(
+        "UPDATE workspaces
+         SET name = ?, active_spec_id = ?, active_environment_id = ?, base_url = ?,
+             selected_operation_key = ?, sort_order = ?, spec_url = ?
+         WHERE id = ?",
+    )
+    .bind(&workspace.name)
+    .bind(&workspace.active_spec_id)
+    .bind(&workspace.active_environment_id)
+    .bind(&workspace.base_url)
+    .bind(&workspace.selected_operation_key)
+    .bind(workspace.sort_order)
+    .bind(&workspace.spec_url)
+    .bind(&workspace.id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    if updated.rows_affected() == 0 {
+        return Err(format!("Workspace not found: {}", workspace.id));
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Deletes a workspace with everything that cascades from it, plus its spec when no other
+/// workspace uses it. Specs don't cascade from workspaces, so an unshared spec used to be
+/// left behind with nothing pointing at it.
+pub async fn delete_workspace(pool: &SqlitePool, id: &str) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let spec: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT active_spec_id FROM workspaces WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM workspaces WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if let Some((Some(spec_id),)) = spec {
+        sqlx::query(
+            "DELETE FROM specs WHERE id = ?
+             AND NOT EXISTS (SELECT 1 FROM workspaces WHERE active_spec_id = ?)",
+        )
+        .bind(&spec_id)
+        .bind(&spec_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Loads a workspace and its related rows from one consistent read. Secret values are
+/// masked rather than sent as ciphertext.
+pub async fn get_full_workspace_data(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+) -> Result<Option<FullWorkspaceData>, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let workspace: Option<DbWorkspace> = sqlx::query_as("SELECT * FROM workspaces WHERE id = ?")
+        .bind(workspace_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let Some(workspace) = workspace else {
+        return Ok(None);
+    };
+
+    let spec = match &workspace.active_spec_id {
+        Some(spec_id) => sqlx::query_as("SELECT * FROM specs WHERE id = ?")
+            .bind(spec_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?,
+        None => None,
+    };
+    let environments =
+        sqlx::query_as("SELECT * FROM environments WHERE workspace_id = ? ORDER BY name ASC")
+            .bind(workspace_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    let variable_keys = sqlx::query_as(
+        "SELECT * FROM workspace_variable_keys WHERE workspace_id = ? ORDER BY key_name ASC",
+    )
+    .bind(workspace_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut variable_values: Vec<DbVariableValue> = sqlx::query_as(
+        "SELECT evv.* FROM environment_variable_values evv
+         INNER JOIN environments e ON evv.environment_id = e.id
+         WHERE e.workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    let global_headers = sqlx::query_as("SELECT * FROM global_headers WHERE workspace_id = ?")
+        .bind(workspace_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut auth_values: Vec<DbAuthValue> =
+        sqlx::query_as("SELECT * FROM auth_values WHERE workspace_id = ?")
+            .bind(workspace_id)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?;
+    let history = sqlx::query_as(
+        "SELECT * FROM history WHERE workspace_id = ? ORDER BY timestamp DESC LIMIT ?",
+    )
+    .bind(workspace_id)
+    .bind(HISTORY_LIMIT)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())?;
+
+    for value in &mut variable_values {
+        value.value = store::mask_variable_value(&value.value);
+    }
+    for value in &mut auth_values {
+        value.value_json = store::mask_auth_json(vault, &value.value_json);
+    }
+    Ok(Some(FullWorkspaceData {
+        workspace,
+        spec,
+        environments,
+        variable_keys,
+        variable_values,
+        global_headers,
+        auth_values,
+        history,
+    }))
+}
+
+// ============================================================================
+// SPECS
+// ============================================================================
+
+pub async fn save_spec(pool: &SqlitePool, id: &str, spec_content: &str) -> Result<(), String> {
+    sqlx::query("INSERT OR REPLACE INTO specs (id, spec_content) VALUES (?, ?)")
+        .bind(id)
+        .bind(spec_content)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn get_spec(pool: &SqlitePool, id: &str) -> Result<Option<DbSpec>, String> {
+    sqlx::query_as("SELECT * FROM specs WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Deletes a spec and unsets it on the workspaces that had it active
+pub async fn delete_spec(pool: &SqlitePool, id: &str) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("UPDATE workspaces SET active_spec_id = NULL WHERE active_spec_id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM specs WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// ENVIRONMENTS
+// ============================================================================
+
+pub async fn create_environment(
+    pool: &SqlitePool,
+    id: &str,
+    workspace_id: &str,
+    name: &str,
+) -> Result<(), String> {
+    sqlx::query("INSERT INTO environments (id, workspace_id, name) VALUES (?, ?, ?)")
+        .bind(id)
+        .bind(workspace_id)
+        .bind(name)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn update_environment(pool: &SqlitePool, id: &str, name: &str) -> Result<(), String> {
+    sqlx::query("UPDATE environments SET name = ? WHERE id = ?")
+        .bind(name)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Deletes an environment with its values, unsetting it where it was the active one
+pub async fn delete_environment(pool: &SqlitePool, id: &str) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query(
+        "UPDATE workspaces SET active_environment_id = NULL WHERE active_environment_id = ?",
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM environments WHERE id = ?")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// VARIABLES
+// ============================================================================
+
+/// Adds a variable key and returns its id
+pub async fn add_variable_key(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    key_name: &str,
+) -> Result<i64, String> {
+    let result =
+        sqlx::query("INSERT INTO workspace_variable_keys (workspace_id, key_name) VALUES (?, ?)")
+            .bind(workspace_id)
+            .bind(key_name)
+            .execute(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(result.last_insert_rowid())
+}
+
+pub async fn remove_variable_key(pool: &SqlitePool, key_id: i64) -> Result<(), String> {
+    sqlx::query("DELETE FROM workspace_variable_keys WHERE id = ?")
+        .bind(key_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn rename_variable_key(
+    pool: &SqlitePool,
+    key_id: i64,
+    key_name: &str,
+) -> Result<(), String> {
+    sqlx::query("UPDATE workspace_variable_keys SET key_name = ? WHERE id = ?")
+        .bind(key_name)
+        .bind(key_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Sets a variable's value in an environment, encrypting it when the key is secret. The
+/// environment and key must belong to the same workspace.
+pub async fn set_variable_value(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    environment_id: &str,
+    variable_key_id: i64,
+    value: &str,
+) -> Result<(), String> {
+    let same_workspace: Option<(i64,)> = sqlx::query_as(
+        "SELECT 1 FROM environments e
+         INNER JOIN workspace_variable_keys k ON k.workspace_id = e.workspace_id
+         WHERE e.id = ? AND k.id = ?",
+    )
+    .bind(environment_id)
+    .bind(variable_key_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    if same_workspace.is_none() {
+        return Err(format!(
+            "Variable key {} and environment {} are not in the same workspace",
+            variable_key_id, environment_id
+        ));
+    }
+    store::set_variable_value(pool, vault, environment_id, variable_key_id, value).await
+}
+
+pub async fn delete_variable_value(
+    pool: &SqlitePool,
+    environment_id: &str,
+    variable_key_id: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        "DELETE FROM environment_variable_values WHERE environment_id = ? AND variable_key_id = ?",
+    )
+    .bind(environment_id)
+    .bind(variable_key_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ============================================================================
+// GLOBAL HEADERS
+// ============================================================================
+
+pub async fn set_global_header(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO global_headers (workspace_id, key, value) VALUES (?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(key)
+    .bind(value)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn delete_global_header(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    key: &str,
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM global_headers WHERE workspace_id = ? AND key = ?")
+        .bind(workspace_id)
+        .bind(key)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Replaces all of a workspace's global headers at once
+pub async fn set_all_global_headers(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    headers: &HashMap<String, String>,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("DELETE FROM global_headers WHERE workspace_id = ?")
+        .bind(workspace_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    for (key, value) in headers {
+        sqlx::query(
+            "INSERT OR REPLACE INTO global_headers (workspace_id, key, value) VALUES (?, ?, ?)",
+        )
+        .bind(workspace_id)
+        .bind(key)
+        .bind(value)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+// ============================================================================
+// AUTH VALUES
+// ============================================================================
+
+/// Sets an auth value, keeping its secret flag so a secret is never rewritten in plaintext
+pub async fn set_auth_value(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    environment_id: Option<&str>,
+    scheme_name: &str,
+    value_json: &str,
+) -> Result<(), String> {
+    serde_json::from_str::<serde_json::Value>(value_json).map_err(|e| e.to_string())?;
+    let existing: Option<(bool,)> = sqlx::query_as(
+        "SELECT is_secret FROM auth_values WHERE workspace_id = ? AND environment_id IS ? AND scheme_name = ?",
+    )
+    .bind(workspace_id)
+    .bind(environment_id)
+    .bind(scheme_name)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let is_secret = existing.is_some_and(|(is_secret,)| is_secret);
+    store::set_auth_value(
+        pool,
+        vault,
+        workspace_id,
+        environment_id,
+        scheme_name,
+        value_json,
+        is_secret,
+    )
+    .await
+}
+
+pub async fn delete_auth_value(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    environment_id: Option<&str>,
+    scheme_name: &str,
+) -> Result<(), String> {
+    sqlx::query(
+        "DELETE FROM auth_values WHERE workspace_id = ? AND environment_id IS ? AND scheme_name = ?",
+    )
+    .bind(workspace_id)
+    .bind(environment_id)
+    .bind(scheme_name)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ============================================================================
+// OPERATION STATES
+// ============================================================================
+
+/// Saves an operation's form data and last response, both JSON
+pub async fn save_operation_state(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    form_data: &str,
+    response: Option<&str>,
+    last_modified: i64,
+) -> Result<(), String> {
+    serde_json::from_str::<serde_json::Value>(form_data)
+        .map_err(|e| format!("Invalid form data: {}", e))?;
+    sqlx::query(
+        "INSERT INTO operation_states (workspace_id, operation_key, form_data, response, last_modified)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(workspace_id, operation_key) DO UPDATE SET
+             form_data = excluded.form_data,
+             response = excluded.response,
+             last_modified = excluded.last_modified",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .bind(form_data)
+    .bind(response)
+    .bind(last_modified)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn get_operation_state(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+) -> Result<Option<DbOperationState>, String> {
+    sqlx::query_as("SELECT * FROM operation_states WHERE workspace_id = ? AND operation_key = ?")
+        .bind(workspace_id)
+        .bind(operation_key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub async fn delete_operation_state(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM operation_states WHERE workspace_id = ? AND operation_key = ?")
+        .bind(workspace_id)
+        .bind(operation_key)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Deletes operation states last saved before `cutoff`, returning how many were
+pub async fn cleanup_old_responses(pool: &SqlitePool, cutoff: i64) -> Result<u64, String> {
+    let result = sqlx::query("DELETE FROM operation_states WHERE last_modified < ?")
+        .bind(cutoff)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// HISTORY
+// ============================================================================
+
+/// Records a use of an operation and trims the list to the newest `HISTORY_LIMIT`
+pub async fn add_history_entry(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    timestamp: i64,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    sqlx::query("INSERT INTO history (workspace_id, operation_key, timestamp) VALUES (?, ?, ?)")
+        .bind(workspace_id)
+        .bind(operation_key)
+        .bind(timestamp)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    prune(&mut tx, workspace_id, HISTORY_LIMIT).await?;
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+async fn prune(
+    conn: &mut sqlx::SqliteConnection,
+    workspace_id: &str,
+    keep: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        "DELETE FROM history WHERE workspace_id = ? AND id NOT IN (
+             SELECT id FROM history WHERE workspace_id = ? ORDER BY timestamp DESC, id DESC LIMIT ?
+         )",
+    )
+    .bind(workspace_id)
+    .bind(workspace_id)
+    .bind(keep)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn prune_history(pool: &SqlitePool, workspace_id: &str, keep: i64) -> Result<(), String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    prune(&mut conn, workspace_id, keep).await
+}
+
+pub async fn clear_history(pool: &SqlitePool, workspace_id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM history WHERE workspace_id = ?")
+        .bind(workspace_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn clear_response_history(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM response_history WHERE workspace_id = ? AND operation_key = ?")
+        .bind(workspace_id)
+        .bind(operation_key)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+    use crate::secrets::VaultCredential;
+
+    async fn database_with_workspace() -> Database {
+        let db = Database::open_in_memory().await.unwrap();
+        create_workspace(db.pool(), "w1", "Pets", 0).await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn full_workspace_data_includes_related_rows() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        save_spec(pool, "s1", "{}").await.unwrap();
+        create_environment(pool, "e1", "w1", "dev").await.unwrap();
+        let key = add_variable_key(pool, "w1", "host").await.unwrap();
+        set_variable_value(pool, &vault, "e1", key, "http://dev")
+            .await
+            .unwrap();
+        set_global_header(pool, "w1", "x-app", "cogeass")
+            .await
+            .unwrap();
+        set_auth_value(pool, &vault, "w1", Some("e1"), "bearer", r#"{"token":"t"}"#)
+            .await
+            .unwrap();
+        add_history_entry(pool, "w1", "get:/pets", 1).await.unwrap();
+
+        let mut workspace = load_initial_data(pool).await.unwrap().workspaces.remove(0);
+        workspace.active_spec_id = Some("s1".to_string());
+        workspace.active_environment_id = Some("e1".to_string());
+        update_workspace(pool, &workspace).await.unwrap();
+
+        let data = get_full_workspace_data(pool, &SecretVault::new(), "w1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.workspace, workspace);
+        assert_eq!(data.spec.unwrap().spec_content, "{}");
+        assert_eq!(data.environments.len(), 1);
+        assert_eq!(data.variable_keys[0].key_name, "host");
+        assert_eq!(data.variable_values[0].value, "http://dev");
+        assert_eq!(data.global_headers[0].value, "cogeass");
+        assert_eq!(data.auth_values[0].scheme_name, "bearer");
+        assert_eq!(data.history[0].operation_key, "get:/pets");
+
+        assert!(
+            get_full_workspace_data(pool, &SecretVault::new(), "missing")
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_workspace_cascades_and_removes_unshared_spec() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        create_workspace(pool, "w2", "Other", 1).await.unwrap();
+        save_spec(pool, "s1", "{}").await.unwrap();
+        save_spec(pool, "shared", "{}").await.unwrap();
+        for (id, spec) in [("w1", "s1"), ("w2", "shared")] {
+            let mut workspace = get_full_workspace_data(pool, &SecretVault::new(), id)
+                .await
+                .unwrap()
+                .unwrap()
+                .workspace;
+            workspace.active_spec_id = Some(spec.to_string());
+            update_workspace(pool, &workspace).await.unwrap();
+        }
+        create_environment(pool, "e1", "w1", "dev").await.unwrap();
+        save_operation_state(pool, "w1", "get:/pets", "{}", None, 1)
+            .await
+            .unwrap();
+        add_history_entry(pool, "w1", "get:/pets", 1).await.unwrap();
+
+        delete_workspace(pool, "w1").await.unwrap();
+
+        for table in ["environments", "operation_states", "history"] {
+            let (count,): (i64,) = sqlx::query_as(&format!(
+                "SELECT COUNT(*) FROM {} WHERE workspace_id = 'w1'",
+                table
+            ))
+            .fetch_one(pool)
+            .await
+            .unwrap();
+            assert_eq!(count, 0, "{} rows left behind", table);
+        }
+        assert!(get_spec(pool, "s1").await.unwrap().is_none());
+        assert!(get_spec(pool, "shared").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn save_operation_state_upserts_and_rejects_bad_input() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+
+        save_operation_state(pool, "w1", "get:/pets", r#"{"a":1}"#, None, 1)
+            .await
+            .unwrap();
+        save_operation_state(pool, "w1", "get:/pets", r#"{"a":2}"#, Some("{}"), 2)
+            .await
+            .unwrap();
+        let state = get_operation_state(pool, "w1", "get:/pets")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.form_data, r#"{"a":2}"#);
+        assert_eq!(state.response.as_deref(), Some("{}"));
+        assert_eq!(state.last_modified, 2);
+
+        assert!(
+            save_operation_state(pool, "w1", "get:/pets", "not json", None, 3)
+                .await
+                .is_err()
+        );
+        assert!(
+            save_operation_state(pool, "missing", "get:/pets", "{}", None, 3)
+                .await
+                .is_err()
+        );
+
+        assert_eq!(cleanup_old_responses(pool, 3).await.unwrap(), 1);
+        assert!(get_operation_state(pool, "w1", "get:/pets")
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn update_workspace_rejects_foreign_environment() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        create_workspace(pool, "w2", "Other", 1).await.unwrap();
+        create_environment(pool, "e2", "w2", "dev").await.unwrap();
+
+        let mut workspace = get_full_workspace_data(pool, &SecretVault::new(), "w1")
+            .await
+            .unwrap()
+            .unwrap()
+            .workspace;
+        workspace.active_environment_id = Some("e2".to_string());
+        assert!(update_workspace(pool, &workspace).await.is_err());
+
+        workspace.active_environment_id = None;
+        workspace.active_spec_id = Some("missing".to_string());
+        assert!(update_workspace(pool, &workspace).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn deleting_environment_clears_it_as_active() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        create_environment(pool, "e1", "w1", "dev").await.unwrap();
+        let mut workspace = get_full_workspace_data(pool, &SecretVault::new(), "w1")
+            .await
+            .unwrap()
+            .unwrap()
+            .workspace;
+        workspace.active_environment_id = Some("e1".to_string());
+        update_workspace(pool, &workspace).await.unwrap();
+
+        delete_environment(pool, "e1").await.unwrap();
+
+        let workspace = get_full_workspace_data(pool, &SecretVault::new(), "w1")
+            .await
+            .unwrap()
+            .unwrap()
+            .workspace;
+        assert_eq!(workspace.active_environment_id, None);
+    }
+
+    #[tokio::test]
+    async fn secrets_are_masked_and_kept_when_the_mask_is_saved() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        vault
+            .unlock(pool, &VaultCredential::Passphrase("pw".to_string()))
+            .await
+            .unwrap();
+        create_environment(pool, "e1", "w1", "dev").await.unwrap();
+        let key = add_variable_key(pool, "w1", "token").await.unwrap();
+        set_variable_value(pool, &vault, "e1", key, "abc123")
+            .await
+            .unwrap();
+        store::set_variable_secret(pool, &vault, key, true)
+            .await
+            .unwrap();
+        set_auth_value(
+            pool,
+            &vault,
+            "w1",
+            None,
+            "basic",
+            r#"{"username":"me","password":""}"#,
+        )
+        .await
+        .unwrap();
+        store::set_auth_secret(pool, &vault, "w1", None, "basic", true)
+            .await
+            .unwrap();
+
+        let data = get_full_workspace_data(pool, &vault, "w1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.variable_values[0].value, store::MASKED_VALUE);
+        let auth: serde_json::Value =
+            serde_json::from_str(&data.auth_values[0].value_json).unwrap();
+        assert_eq!(
+            auth,
+            serde_json::json!({ "username": store::MASKED_VALUE, "password": "" })
+        );
+
+        // Saving what the frontend was shown changes only the fields that were edited
+        set_variable_value(pool, &vault, "e1", key, store::MASKED_VALUE)
+            .await
+            .unwrap();
+        let masked_user = format!(
+            r#"{{"username":"{}","password":"pw2"}}"#,
+            store::MASKED_VALUE
+        );
+        set_auth_value(pool, &vault, "w1", None, "basic", &masked_user)
+            .await
+            .unwrap();
+        let (value,): (String,) = sqlx::query_as("SELECT value FROM environment_variable_values")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(vault.reveal(&value).unwrap(), "abc123");
+        let (value_json,): (String,) = sqlx::query_as("SELECT value_json FROM auth_values")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        let auth: serde_json::Value =
+            serde_json::from_str(&vault.reveal(&value_json).unwrap()).unwrap();
+        assert_eq!(
+            auth,
+            serde_json::json!({ "username": "me", "password": "pw2" })
+        );
+
+        // A locked vault can't even say which fields exist
+        vault.lock();
+        let data = get_full_workspace_data(pool, &vault, "w1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.auth_values[0].value_json, "{}");
+        assert_eq!(data.variable_values[0].value, store::MASKED_VALUE);
+    }
+
+    #[tokio::test]
+    async fn variable_values_stay_within_their_workspace() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        create_workspace(pool, "w2", "Other", 1).await.unwrap();
+        create_environment(pool, "e2", "w2", "dev").await.unwrap();
+        let key = add_variable_key(pool, "w1", "host").await.unwrap();
+
+        assert!(set_variable_value(pool, &vault, "e2", key, "x")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn global_headers_are_replaced_together() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        set_global_header(pool, "w1", "old", "1").await.unwrap();
+
+        let headers = HashMap::from([
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ]);
+        set_all_global_headers(pool, "w1", &headers).await.unwrap();
+
+        let data = get_full_workspace_data(pool, &SecretVault::new(), "w1")
+            .await
+            .unwrap()
+            .unwrap();
+        let mut keys: Vec<&str> = data.global_headers.iter().map(|h| h.key.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn history_keeps_the_newest_entries() {
+        let db = database_with_workspace().await;
+        let pool = db.pool();
+        for timestamp in 0..HISTORY_LIMIT + 5 {
+            add_history_entry(pool, "w1", &format!("op{}", timestamp), timestamp)
+                .await
+                .unwrap();
+        }
+
+        let history = get_full_workspace_data(pool, &SecretVault::new(), "w1")
+            .await
+            .unwrap()
+            .unwrap()
+            .history;
+        assert_eq!(history.len() as i64, HISTORY_LIMIT);
+        assert_eq!(history[0].timestamp, HISTORY_LIMIT + 4);
+        assert_eq!(history.last().unwrap().timestamp, 5);
+
+        prune_history(pool, "w1", 10).await.unwrap();
+        let history = get_full_workspace_data(pool, &SecretVault::new(), "w1")
+            .await
+            .unwrap()
+            .unwrap()
+            .history;
+        assert_eq!(history.len(), 10);
+    }
+}
@@ -0,0 +1,403 @@
+use super::{ComparableResponse, DiffOptions, DiffSummary, ResponseDiff};
+use crate::network::request::{self, BackendResponse};
+use crate::secrets::SecretVault;
+use crate::templating::{self, FormData, WorkspaceContext};
+use futures::future;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+/// One environment's side of a comparison
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentResponse {
+    pub environment_id: String,
+    pub environment_name: String,
+    pub response: Option<BackendResponse>,
+    /// Why the request couldn't be built or sent
+    pub error: Option<String>,
+}
+
+/// How an environment's response differs from the baseline's
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentDiff {
+    pub environment_id: String,
+    pub summary: DiffSummary,
+    pub diff: ResponseDiff,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvironmentComparison {
+    /// The first environment requested; the others are diffed against it
+    pub baseline_environment_id: String,
+    pub responses: Vec<EnvironmentResponse>,
+    /// One per other environment, left out where either side has no response
+    pub diffs: Vec<EnvironmentDiff>,
+}
+
+async fn environment_name(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    environment_id: &str,
+) -> Result<String, String> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT name FROM environments WHERE id = ? AND workspace_id = ?")
+            .bind(environment_id)
+            .bind(workspace_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    row.map(|(name,)| name)
+        .ok_or_else(|| format!("Environment not found: {}", environment_id))
+}
+
+async fn send_in(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    client: &reqwest::Client,
+    workspace_id: &str,
+    environment_id: &str,
+    operation_key: &str,
+    form_data: &FormData,
+) -> Result<BackendResponse, String> {
+    let context =
+        WorkspaceContext::load_for_environment(pool, vault, workspace_id, Some(environment_id))
+            .await?;
+    let prepared = templating::build_request(&context, operation_key, form_data)?;
+    request::execute(client, prepared, None, &|_| {}).await
+}
+
+/// Sends an operation with its saved form data in each environment at once and diffs every
+/// response against the first environment's. Scripts and extraction rules don't run, so
+/// comparing leaves the environments unchanged.
+pub async fn compare(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    operation_key: &str,
+    environment_ids: &[String],
+    options: &DiffOptions,
+) -> Result<EnvironmentComparison, String> {
+    let baseline = environment_ids
+        .first()
+        .ok_or_else(|| "No environments to compare".to_string())?;
+    // Checked before anything is sent, so a typo doesn't cost a round of requests
+    let ignore_paths = options.ignore_patterns()?;
+    let mut names = Vec::with_capacity(environment_ids.len());
+    for environment_id in environment_ids {
+        names.push(environment_name(pool, workspace_id, environment_id).await?);
+    }
+    let form_data = templating::saved_form_data(pool, workspace_id, operation_key).await?;
+    let client = request::build_client()?;
+
+    let outcomes = future::join_all(environment_ids.iter().map(|environment_id| {
+        send_in(
+            pool,
+            vault,
+            &client,
+            workspace_id,
+            environment_id,
+            operation_key,
+            &form_data,
+        )
+    }))
+    .await;
+
+    let responses: Vec<EnvironmentResponse> = environment_ids
+        .iter()
+        .zip(names)
+        .zip(outcomes)
+        .map(
+            |((environment_id, environment_name), outcome)| EnvironmentResponse {
+                environment_id: environment_id.clone(),
+                environment_name,
+                error: outcome.as_ref().err().cloned(),
+                response: outcome.ok(),
+            },
+        )
+        .collect();
+
+    let diffs = match &responses[0].response {
+        Some(baseline_response) => {
+            let left = ComparableResponse::from(baseline_response);
+            responses[1..]
+                .iter()
+                .filter_map(|other| {
+                    let right = ComparableResponse::from(other.response.as_ref()?);
+                    let diff = super::diff_parsed(&left, &right, options, &ignore_paths);
+                    Some(EnvironmentDiff {
+                        environment_id: other.environment_id.clone(),
+                        summary: diff.summary(),
+                        diff,
+                    })
+                })
+                .collect()
+        }
+        None => Vec::new(),
+    };
+
+    Ok(EnvironmentComparison {
+        baseline_environment_id: baseline.clone(),
+        responses,
+        diffs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::mock::server::{IncomingRequest, MockHandler, MockReply};
+    use crate::mock::MockServerManager;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    /// Answers `/{environment}/items?tag=…` with the environment and tag it was reached with
+    struct Upstream;
+
+    #[async_trait::async_trait]
+    impl MockHandler for Upstream {
+        async fn respond(&self, request: &IncomingRequest) -> MockReply {
+            let environment = request.path.trim_start_matches('/').split('/').next();
+            let tag = request
+                .query
+                .iter()
+                .find(|(k, _)| k == "tag")
+                .map(|(_, v)| v);
+            MockReply::json(
+                200,
+                &json!({
+                    "environment": environment,
+                    "tag": tag,
+                    "count": if environment == Some("prod") { 2 } else { 1 },
+                    "updatedAt": request.path,
+                }),
+            )
+        }
+    }
+
+    struct Fixture {
+        db: Database,
+        vault: SecretVault,
+        _mocks: MockServerManager,
+    }
+
+    /// A workspace whose `get:/items` is sent to `{{baseUrl}}` with `?tag={{tag}}`, and whose
+    /// `broken` environment has no `baseUrl`
+    async fn setup() -> Fixture {
+        let mocks = MockServerManager::new();
+        let (event_tx, _) = tokio::sync::mpsc::channel(1);
+        let url = mocks
+            .start("upstream".to_string(), None, Arc::new(Upstream), event_tx)
+            .await
+            .unwrap()
+            .url;
+
+        let db = Database::open_in_memory().await.unwrap();
+        let vault = SecretVault::new();
+        let pool = db.pool();
+        let spec = json!({ "openapi": "3.0.3", "paths": { "/items": { "get": {} } } });
+        repository::create_workspace(pool, "w1", "Items", 0)
+            .await
+            .unwrap();
+        repository::save_spec(pool, "s1", &spec.to_string())
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE workspaces SET active_spec_id = 's1', base_url = '{{baseUrl}}' WHERE id = 'w1'",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        repository::save_operation_state(
+            pool,
+            "w1",
+            "get:/items",
+            r#"{"queryData": {"tag": "{{tag}}"}}"#,
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+
+        let base_url = repository::add_variable_key(pool, "w1", "baseUrl")
+            .await
+            .unwrap();
+        let tag = repository::add_variable_key(pool, "w1", "tag")
+            .await
+            .unwrap();
+        for (id, tag_value) in [
+            ("dev", "a"),
+            ("staging", "a"),
+            ("prod", "b"),
+            ("broken", "a"),
+        ] {
+            repository::create_environment(pool, id, "w1", id)
+                .await
+                .unwrap();
+            repository::set_variable_value(pool, &vault, id, tag, tag_value)
+                .await
+                .unwrap();
+            if id != "broken" {
+                let value = format!("{}/{}", url, id);
+                repository::set_variable_value(pool, &vault, id, base_url, &value)
+                    .await
+                    .unwrap();
+            }
+        }
+        Fixture {
+            db,
+            vault,
+            _mocks: mocks,
+        }
+    }
+
+    impl Fixture {
+        async fn compare(&self, environment_ids: &[&str]) -> EnvironmentComparison {
+            let ids: Vec<String> = environment_ids.iter().map(|id| id.to_string()).collect();
+            compare(
+                self.db.pool(),
+                &self.vault,
+                "w1",
+                "get:/items",
+                &ids,
+                &DiffOptions::default(),
+            )
+            .await
+            .unwrap()
+        }
+    }
+
+    fn body(response: &EnvironmentResponse) -> Value {
+        serde_json::from_str(&response.response.as_ref().unwrap().body_text).unwrap()
+    }
+
+    fn changed_paths(diff: &EnvironmentDiff) -> Vec<&str> {
+        let mut paths: Vec<&str> = diff.diff.body.iter().map(|c| c.path.as_str()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[tokio::test]
+    async fn sends_with_each_environments_own_variables() {
+        let fixture = setup().await;
+        let comparison = fixture.compare(&["dev", "staging", "prod"]).await;
+
+        let bodies: Vec<(String, Value, Value)> = comparison
+            .responses
+            .iter()
+            .map(|r| {
+                let body = body(r);
+                (
+                    r.environment_name.clone(),
+                    body["environment"].clone(),
+                    body["tag"].clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            bodies,
+            vec![
+                ("dev".to_string(), json!("dev"), json!("a")),
+                ("staging".to_string(), json!("staging"), json!("a")),
+                ("prod".to_string(), json!("prod"), json!("b")),
+            ]
+        );
+        assert!(comparison.responses.iter().all(|r| r.error.is_none()));
+    }
+
+    #[tokio::test]
+    async fn diffs_every_environment_against_the_first() {
+        let fixture = setup().await;
+        let comparison = fixture.compare(&["staging", "dev", "prod"]).await;
+        assert_eq!(comparison.baseline_environment_id, "staging");
+
+        let diffs: Vec<&str> = comparison
+            .diffs
+            .iter()
+            .map(|d| d.environment_id.as_str())
+            .collect();
+        assert_eq!(diffs, vec!["dev", "prod"]);
+
+        // `updatedAt` differs too but is ignored by default
+        assert_eq!(changed_paths(&comparison.diffs[0]), vec!["$.environment"]);
+        assert_eq!(
+            changed_paths(&comparison.diffs[1]),
+            vec!["$.count", "$.environment", "$.tag"]
+        );
+        let environment = &comparison.diffs[0].diff.body[0];
+        assert_eq!(environment.left, Some(json!("staging")));
+        assert_eq!(environment.right, Some(json!("dev")));
+        assert_eq!(comparison.diffs[1].summary.changed, 3);
+    }
+
+    #[tokio::test]
+    async fn a_failing_environment_leaves_the_others_compared() {
+        let fixture = setup().await;
+        let comparison = fixture.compare(&["dev", "broken", "prod"]).await;
+
+        let broken = &comparison.responses[1];
+        assert!(broken.response.is_none());
+        assert!(broken.error.is_some());
+        assert_eq!(body(&comparison.responses[2])["environment"], json!("prod"));
+        let diffs: Vec<&str> = comparison
+            .diffs
+            .iter()
+            .map(|d| d.environment_id.as_str())
+            .collect();
+        assert_eq!(diffs, vec!["prod"]);
+
+        // Without a baseline response there is nothing to diff, but the others still send
+        let comparison = fixture.compare(&["broken", "dev", "prod"]).await;
+        assert!(comparison.responses[0].error.is_some());
+        assert!(comparison.responses[1..]
+            .iter()
+            .all(|r| r.response.is_some()));
+        assert!(comparison.diffs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_environments_and_bad_ignore_paths_before_sending() {
+        let fixture = setup().await;
+        let ids = ["dev".to_string(), "qa".to_string()];
+        let err = compare(
+            fixture.db.pool(),
+            &fixture.vault,
+            "w1",
+            "get:/items",
+            &ids,
+            &DiffOptions::default(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err, "Environment not found: qa");
+
+        let options = DiffOptions {
+            ignore_paths: vec!["items".to_string()],
+            ..Default::default()
+        };
+        let err = compare(
+            fixture.db.pool(),
+            &fixture.vault,
+            "w1",
+            "get:/items",
+            &ids,
+            &options,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.starts_with("Invalid JSONPath"), "{}", err);
+        let err = compare(
+            fixture.db.pool(),
+            &fixture.vault,
+            "w1",
+            "get:/items",
+            &[],
+            &options,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err, "No environments to compare");
+    }
+}
@@ -0,0 +1,504 @@
+pub mod environments;
+
+use crate::assertions::jsonpath::{self, PathStep, Pattern};
+use crate::network::request::BackendResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+
+/// Headers that differ on every response regardless of what the API did
+const VOLATILE_HEADERS: &[&str] = &[
+    "date",
+    "age",
+    "expires",
+    "last-modified",
+    "etag",
+    "x-request-id",
+    "x-correlation-id",
+    "x-trace-id",
+    "traceparent",
+];
+/// Body fields that usually hold per-request values
+const VOLATILE_FIELDS: &[&str] = &[
+    "timestamp",
+    "createdAt",
+    "updatedAt",
+    "requestId",
+    "traceId",
+];
+
+/// One side of a comparison: a stored history entry or a response from `make_request`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum DiffSource {
+    History { id: i64 },
+    Live { response: Box<BackendResponse> },
+}
+
+/// What gets left out of a comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DiffOptions {
+    /// Object keys skipped wherever they appear in the body
+    pub ignore_fields: Vec<String>,
+    /// JSONPath locations skipped in the body, e.g. `$.items[*].id`
+    pub ignore_paths: Vec<String>,
+    /// Header names skipped, compared case-insensitively
+    pub ignore_headers: Vec<String>,
+}
+
+impl DiffOptions {
+    /// Parses `ignore_paths`, failing on the first that isn't a valid path
+    pub fn ignore_patterns(&self) -> Result<Vec<Pattern>, String> {
+        self.ignore_paths
+            .iter()
+            .map(|path| Pattern::parse(path))
+            .collect()
+    }
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        Self {
+            ignore_fields: VOLATILE_FIELDS.iter().map(|f| f.to_string()).collect(),
+            ignore_paths: Vec::new(),
+            ignore_headers: VOLATILE_HEADERS.iter().map(|h| h.to_string()).collect(),
+        }
+    }
+}
+
+/// The parts of a response a diff looks at
+#[derive(Debug, Clone)]
+pub struct ComparableResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body_text: String,
+}
+
+impl From<&BackendResponse> for ComparableResponse {
+    fn from(response: &BackendResponse) -> Self {
+        Self {
+            status: response.status,
+            headers: response.headers.clone(),
+            body_text: response.body_text.clone(),
+        }
+    }
+}
+
+impl ComparableResponse {
+    /// Reads a response as the frontend stores it in `response_history.response_json`
+    pub fn from_history_json(json: &str) -> Result<Self, String> {
+        let value: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let headers = value
+            .get("headers")
+            .and_then(Value::as_object)
+            .map(|headers| {
+                headers
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        };
+                        (name.clone(), value)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(Self {
+            status: value
+                .get("status")
+                .and_then(Value::as_u64)
+                .and_then(|s| u16::try_from(s).ok())
+                .ok_or_else(|| "Stored response has no status".to_string())?,
+            headers,
+            body_text: value
+                .get("bodyText")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A difference at one body path or header; `left`/`right` are absent where the value is
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    /// JSONPath of a body value, or the lowercased header name
+    pub path: String,
+    pub kind: ChangeKind,
+    pub left: Option<Value>,
+    pub right: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusDiff {
+    pub left: u16,
+    pub right: u16,
+    pub changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseDiff {
+    pub status: StatusDiff,
+    pub headers: Vec<Change>,
+    /// Whether both bodies parsed as JSON; otherwise they're compared as text under `$`
+    pub structural: bool,
+    pub body: Vec<Change>,
+    pub identical: bool,
+}
+
+/// Counts of a diff's changes, for listing many comparisons at once
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffSummary {
+    pub status_changed: bool,
+    pub headers_changed: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub identical: bool,
+}
+
+impl ResponseDiff {
+    pub fn summary(&self) -> DiffSummary {
+        let count = |kind| self.body.iter().filter(|c| c.kind == kind).count();
+        DiffSummary {
+            status_changed: self.status.changed,
+            headers_changed: self.headers.len(),
+            added: count(ChangeKind::Added),
+            removed: count(ChangeKind::Removed),
+            changed: count(ChangeKind::Changed),
+            identical: self.identical,
+        }
+    }
+}
+
+/// What a body diff leaves out, with `ignore_paths` parsed once for the whole walk
+struct BodyIgnores<'a> {
+    fields: &'a [String],
+    paths: &'a [Pattern],
+}
+
+impl BodyIgnores<'_> {
+    fn skips(&self, location: &[PathStep]) -> bool {
+        if let Some(PathStep::Key(key)) = location.last() {
+            if self.fields.iter().any(|f| f == key) {
+                return true;
+            }
+        }
+        self.paths.iter().any(|path| path.matches(location))
+    }
+}
+
+fn diff_values(
+    left: &Value,
+    right: &Value,
+    location: &mut Vec<PathStep>,
+    ignores: &BodyIgnores,
+    changes: &mut Vec<Change>,
+) {
+    let mut child =
+        |step: PathStep, left: Option<&Value>, right: Option<&Value>, changes: &mut Vec<Change>| {
+            location.push(step);
+            if !ignores.skips(location) {
+                match (left, right) {
+                    (Some(l), Some(r)) => diff_values(l, r, location, ignores, changes),
+                    (l, r) => changes.push(Change {
+                        path: jsonpath::format(location),
+                        kind: if l.is_some() {
+                            ChangeKind::Removed
+                        } else {
+                            ChangeKind::Added
+                        },
+                        left: l.cloned(),
+                        right: r.cloned(),
+                    }),
+                }
+            }
+            location.pop();
+        };
+
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            let keys: BTreeSet<&String> = l.keys().chain(r.keys()).collect();
+            for key in keys {
+                child(PathStep::Key(key.clone()), l.get(key), r.get(key), changes);
+            }
+        }
+        (Value::Array(l), Value::Array(r)) => {
+            for i in 0..l.len().max(r.len()) {
+                child(PathStep::Index(i), l.get(i), r.get(i), changes);
+            }
+        }
+        (l, r) if l != r => changes.push(Change {
+            path: jsonpath::format(location),
+            kind: ChangeKind::Changed,
+            left: Some(l.clone()),
+            right: Some(r.clone()),
+        }),
+        _ => {}
+    }
+}
+
+fn diff_headers(
+    left: &HashMap<String, String>,
+    right: &HashMap<String, String>,
+    options: &DiffOptions,
+) -> Vec<Change> {
+    let lower = |headers: &HashMap<String, String>| -> HashMap<String, String> {
+        headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.clone()))
+            .collect()
+    };
+    let (left, right) = (lower(left), lower(right));
+    let names: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+
+    names
+        .into_iter()
+        .filter(|name| {
+            !options
+                .ignore_headers
+                .iter()
+                .any(|ignored| ignored.eq_ignore_ascii_case(name))
+        })
+        .filter_map(|name| {
+            let (l, r) = (left.get(name), right.get(name));
+            let kind = match (l, r) {
+                (Some(l), Some(r)) if l == r => return None,
+                (Some(_), Some(_)) => ChangeKind::Changed,
+                (Some(_), None) => ChangeKind::Removed,
+                _ => ChangeKind::Added,
+            };
+            Some(Change {
+                path: name.clone(),
+                kind,
+                left: l.map(|v| Value::String(v.clone())),
+                right: r.map(|v| Value::String(v.clone())),
+            })
+        })
+        .collect()
+}
+
+/// Compares two responses' status, headers and body, failing when an ignore path is invalid
+pub fn diff(
+    left: &ComparableResponse,
+    right: &ComparableResponse,
+    options: &DiffOptions,
+) -> Result<ResponseDiff, String> {
+    let ignore_paths = options.ignore_patterns()?;
+    Ok(diff_parsed(left, right, options, &ignore_paths))
+}
+
+/// [`diff`] with `options.ignore_paths` already parsed into `ignore_paths`
+fn diff_parsed(
+    left: &ComparableResponse,
+    right: &ComparableResponse,
+    options: &DiffOptions,
+    ignore_paths: &[Pattern],
+) -> ResponseDiff {
+    let ignores = BodyIgnores {
+        fields: &options.ignore_fields,
+        paths: ignore_paths,
+    };
+    let parsed = (
+        serde_json::from_str::<Value>(&left.body_text),
+        serde_json::from_str::<Value>(&right.body_text),
+    );
+    let (structural, body) = match parsed {
+        (Ok(l), Ok(r)) => {
+            let mut changes = Vec::new();
+            diff_values(&l, &r, &mut Vec::new(), &ignores, &mut changes);
+            (true, changes)
+        }
+        _ if left.body_text == right.body_text => (false, Vec::new()),
+        _ => (
+            false,
+            vec![Change {
+                path: "$".to_string(),
+                kind: ChangeKind::Changed,
+                left: Some(Value::String(left.body_text.clone())),
+                right: Some(Value::String(right.body_text.clone())),
+            }],
+        ),
+    };
+
+    let status = StatusDiff {
+        left: left.status,
+        right: right.status,
+        changed: left.status != right.status,
+    };
+    let headers = diff_headers(&left.headers, &right.headers, options);
+    let identical = !status.changed && headers.is_empty() && body.is_empty();
+    ResponseDiff {
+        status,
+        headers,
+        structural,
+        body,
+        identical,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: Value) -> ComparableResponse {
+        ComparableResponse {
+            status,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body_text: body.to_string(),
+        }
+    }
+
+    fn paths(changes: &[Change]) -> Vec<(&str, ChangeKind)> {
+        changes.iter().map(|c| (c.path.as_str(), c.kind)).collect()
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_body_values() {
+        let left = response(
+            200,
+            &[],
+            json!({ "id": 1, "name": "Rex", "tags": ["a", "b"], "owner": { "id": 7 } }),
+        );
+        let right = response(
+            201,
+            &[],
+            json!({ "id": 2, "name": "Max", "tags": ["a"], "owner": { "id": 7, "since": 2020 } }),
+        );
+        let diff = diff(&left, &right, &DiffOptions::default()).unwrap();
+
+        assert!(diff.status.changed);
+        assert!(diff.structural);
+        assert!(!diff.identical);
+        assert_eq!(
+            paths(&diff.body),
+            vec![
+                ("$.id", ChangeKind::Changed),
+                ("$.name", ChangeKind::Changed),
+                ("$.owner.since", ChangeKind::Added),
+                ("$.tags[1]", ChangeKind::Removed),
+            ]
+        );
+        let summary = diff.summary();
+        assert_eq!((summary.added, summary.removed, summary.changed), (1, 1, 2));
+    }
+
+    #[test]
+    fn compares_non_json_bodies_as_text() {
+        let left = ComparableResponse {
+            status: 200,
+            headers: HashMap::new(),
+            body_text: "plain".to_string(),
+        };
+        let same = diff(&left, &left, &DiffOptions::default()).unwrap();
+        assert!(same.identical && !same.structural);
+
+        let right = ComparableResponse {
+            body_text: "other".to_string(),
+            ..left.clone()
+        };
+        let changed = diff(&left, &right, &DiffOptions::default()).unwrap();
+        assert_eq!(paths(&changed.body), vec![("$", ChangeKind::Changed)]);
+    }
+
+    #[test]
+    fn compares_headers_case_insensitively_and_skips_volatile_ones() {
+        let left = response(
+            200,
+            &[
+                ("Content-Type", "application/json"),
+                ("Date", "Mon"),
+                ("x-old", "1"),
+            ],
+            json!({}),
+        );
+        let right = response(
+            200,
+            &[
+                ("content-type", "text/plain"),
+                ("date", "Tue"),
+                ("X-New", "1"),
+            ],
+            json!({}),
+        );
+        let diff = diff(&left, &right, &DiffOptions::default()).unwrap();
+        assert_eq!(
+            paths(&diff.headers),
+            vec![
+                ("content-type", ChangeKind::Changed),
+                ("x-new", ChangeKind::Added),
+                ("x-old", ChangeKind::Removed),
+            ]
+        );
+
+        let options = DiffOptions {
+            ignore_headers: vec![
+                "CONTENT-TYPE".to_string(),
+                "x-new".to_string(),
+                "x-old".to_string(),
+            ],
+            ..Default::default()
+        };
+        let diff = super::diff(&left, &right, &options).unwrap();
+        // Date is only skipped by the defaults this replaces
+        assert_eq!(paths(&diff.headers), vec![("date", ChangeKind::Changed)]);
+    }
+
+    #[test]
+    fn skips_ignored_fields_and_paths() {
+        let left = response(
+            200,
+            &[],
+            json!({ "requestId": "a", "items": [{ "id": 1, "name": "x" }], "meta": { "page": 1 } }),
+        );
+        let right = response(
+            200,
+            &[],
+            json!({ "requestId": "b", "items": [{ "id": 2, "name": "x" }], "meta": { "page": 2 } }),
+        );
+
+        let defaults = diff(&left, &right, &DiffOptions::default()).unwrap();
+        assert_eq!(
+            paths(&defaults.body),
+            vec![
+                ("$.items[0].id", ChangeKind::Changed),
+                ("$.meta.page", ChangeKind::Changed)
+            ]
+        );
+
+        let options = DiffOptions {
+            ignore_paths: vec!["$.items[*].id".to_string(), "$['meta']".to_string()],
+            ..Default::default()
+        };
+        assert!(diff(&left, &right, &options).unwrap().identical);
+    }
+
+    #[test]
+    fn rejects_invalid_ignore_paths() {
+        let left = response(200, &[], json!({}));
+        let options = DiffOptions {
+            ignore_paths: vec!["$.ok".to_string(), "items[0]".to_string()],
+            ..Default::default()
+        };
+        let err = diff(&left, &left, &options).unwrap_err();
+        assert!(err.contains("items[0]"), "{}", err);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::assertions::jsonpath;
+use crate::network::request::BackendResponse;
+use crate::secrets::{store, SecretVault};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where in a response a value is taken from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "camelCase")]
+pub enum ExtractSource {
+    /// First value selected from the JSON body; non-strings are stored as JSON text
+    JsonPath {
+        path: String,
+    },
+    /// XPath 1.0 expression evaluated against an XML body
+    XPath {
+        expression: String,
+    },
+    /// Regex applied to the raw body. Defaults to the first capture group, or the whole
+    /// match when the pattern has none.
+    Regex {
+        pattern: String,
+        group: Option<usize>,
+    },
+    Header {
+        name: String,
+    },
+}
+
+/// Copies a value from a response into an environment variable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionRule {
+    pub variable: String,
+    #[serde(flatten)]
+    pub source: ExtractSource,
+}
+
+/// A value a rule pulled out of a response
+#[derive(Debug, Clone)]
+pub struct Extracted {
+    pub variable: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionFailure {
+    pub variable: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractionReport {
+    pub environment_id: Option<String>,
+    /// Variables whose value differs from what was stored before
+    pub changed: Vec<String>,
+    pub failed: Vec<ExtractionFailure>,
+}
+
+/// Reads the extraction rules saved for an operation; none when nothing was saved
+pub async fn load(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+) -> Result<Vec<ExtractionRule>, String> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT rules_json FROM operation_extractions WHERE workspace_id = ? AND operation_key = ?",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match row {
+        Some((json,)) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Replaces the extraction rules saved for an operation
+pub async fn save(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    rules: &[ExtractionRule],
+) -> Result<(), String> {
+    if rules.is_empty() {
+        sqlx::query(
+            "DELETE FROM operation_extractions WHERE workspace_id = ? AND operation_key = ?",
+        )
+        .bind(workspace_id)
+        .bind(operation_key)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        return Ok(());
+    }
+
+    let json = serde_json::to_string(rules).map_err(|e| e.to_string())?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    sqlx::query(
+        "INSERT OR REPLACE INTO operation_extractions (workspace_id, operation_key, rules_json, last_modified)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .bind(json)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Runs every rule against a response, splitting the values found from the rules that failed
+pub fn extract(
+    rules: &[ExtractionRule],
+    response: &BackendResponse,
+) -> (Vec<Extracted>, Vec<ExtractionFailure>) {
+    let mut extracted = Vec::new();
+    let mut failed = Vec::new();
+    for rule in rules {
+        match extract_one(&rule.source, response) {
+            Ok(Some(value)) => extracted.push(Extracted {
+                variable: rule.variable.clone(),
+                value,
+            }),
+            Ok(None) => failed.push(ExtractionFailure {
+                variable: rule.variable.clone(),
+                message: "No value found in the response".to_string(),
+            }),
+            Err(message) => failed.push(ExtractionFailure {
+                variable: rule.variable.clone(),
+                message,
+            }),
+        }
+    }
+    (extracted, failed)
+}
+
+fn extract_one(
+    source: &ExtractSource,
+    response: &BackendResponse,
+) -> Result<Option<String>, String> {
+    match source {
+        ExtractSource::JsonPath { path } => {
+            let body: Value = serde_json::from_str(&response.body_text)
+                .map_err(|_| "Response body is not JSON".to_string())?;
+            Ok(jsonpath::select(&body, path)?
+                .first()
+                .map(|value| match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                }))
+        }
+        ExtractSource::XPath { expression } => {
+            let package = sxd_document::parser::parse(&response.body_text)
+                .map_err(|e| format!("Response body is not XML: {}", e))?;
+            let document = package.as_document();
+            let value =
+                sxd_xpath::evaluate_xpath(&document, expression).map_err(|e| e.to_string())?;
+            Ok(match value {
+                sxd_xpath::Value::Nodeset(nodes) if nodes.size() == 0 => None,
+                value => Some(value.string()),
+            })
+        }
+        ExtractSource::Regex { pattern, group } => {
+            let re = Regex::new(pattern).map_err(|e| e.to_string())?;
+            let group = group.unwrap_or(if re.captures_len() > 1 { 1 } else { 0 });
+            Ok(re
+                .captures(&response.body_text)
+                .and_then(|captures| captures.get(group))
+                .map(|m| m.as_str().to_string()))
+        }
+        ExtractSource::Header { name } => Ok(response
+            .headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())),
+    }
+}
+
+/// Writes extracted values into an environment, creating variable keys that don't exist
+/// yet, and returns the names whose value changed. Secret variables stay encrypted.
+/// All values are written in one transaction, so a failure leaves none of them stored.
+pub async fn store(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    environment_id: &str,
+    extracted: &[Extracted],
+) -> Result<Vec<String>, String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let mut changed = Vec::new();
+    for Extracted { variable, value } in extracted {
+        let key: Option<(i64,)> = sqlx::query_as(
+            "SELECT id FROM workspace_variable_keys WHERE workspace_id = ? AND key_name = ?",
+        )
+        .bind(workspace_id)
+        .bind(variable)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let key_id = match key {
+            Some((id,)) => id,
+            None => sqlx::query(
+                "INSERT INTO workspace_variable_keys (workspace_id, key_name) VALUES (?, ?)",
+            )
+            .bind(workspace_id)
+            .bind(variable)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| e.to_string())?
+            .last_insert_rowid(),
+        };
+
+        let current: Option<(String,)> = sqlx::query_as(
+            "SELECT value FROM environment_variable_values WHERE environment_id = ? AND variable_key_id = ?",
+        )
+        .bind(environment_id)
+        .bind(key_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+        if let Some((stored,)) = current {
+            if vault.reveal(&stored)? == *value {
+                continue;
+            }
+        }
+
+        store::write_variable_value(&mut tx, vault, environment_id, key_id, value).await?;
+        changed.push(variable.clone());
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(changed)
+}
+
+/// Extracts values from a response and stores them in `environment_id`
+pub async fn apply(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    environment_id: Option<&str>,
+    rules: &[ExtractionRule],
+    response: &BackendResponse,
+) -> Result<ExtractionReport, String> {
+    let (extracted, failed) = extract(rules, response);
+    let changed = match environment_id {
+        Some(env_id) => store(pool, vault, workspace_id, env_id, &extracted).await?,
+        None if extracted.is_empty() => Vec::new(),
+        None => return Err("Workspace has no active environment to store variables in".to_string()),
+    };
+    Ok(ExtractionReport {
+        environment_id: environment_id.map(str::to_string),
+        changed,
+        failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::secrets::VaultCredential;
+
+    fn extracted(variable: &str, value: &str) -> Extracted {
+        Extracted {
+            variable: variable.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    async fn stored(pool: &SqlitePool) -> Vec<(String, String)> {
+        sqlx::query_as(
+            "SELECT k.key_name, v.value FROM environment_variable_values v
+             INNER JOIN workspace_variable_keys k ON v.variable_key_id = k.id
+             ORDER BY k.key_name",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn store_writes_all_values_or_none() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        repository::create_environment(pool, "e1", "w1", "dev")
+            .await
+            .unwrap();
+
+        let vault = SecretVault::new();
+        let changed = store(pool, &vault, "w1", "e1", &[extracted("id", "7")])
+            .await
+            .unwrap();
+        assert_eq!(changed, ["id"]);
+        let unchanged = store(pool, &vault, "w1", "e1", &[extracted("id", "7")])
+            .await
+            .unwrap();
+        assert!(unchanged.is_empty());
+
+        vault
+            .unlock(pool, &VaultCredential::Passphrase("pw".to_string()))
+            .await
+            .unwrap();
+        let token = repository::add_variable_key(pool, "w1", "token")
+            .await
+            .unwrap();
+        store::set_variable_secret(pool, &vault, token, true)
+            .await
+            .unwrap();
+        vault.lock();
+
+        // Sealing the secret fails while the vault is locked, which rolls back `id` too
+        let err = store(
+            pool,
+            &vault,
+            "w1",
+            "e1",
+            &[extracted("id", "8"), extracted("token", "t0ken")],
+        )
+        .await
+        .unwrap_err();
+        assert!(!err.is_empty());
+        assert_eq!(stored(pool).await, [("id".to_string(), "7".to_string())]);
+    }
+}
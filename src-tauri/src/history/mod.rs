@@ -0,0 +1,229 @@
+pub mod retention;
+pub mod snapshot;
+pub mod store;
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+/// Marks the start and end of a match in snippets returned by SQLite
+const MATCH_START: char = '\u{1}';
+const MATCH_END: char = '\u{2}';
+const DEFAULT_SEARCH_LIMIT: u32 = 50;
+/// Tokens of context `snippet()` keeps around a match
+const SNIPPET_TOKENS: u32 = 16;
+
+/// A full-text query over stored responses, narrowed by the optional filters
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySearch {
+    /// Words to look for in response headers and bodies; a trailing `*` matches a prefix
+    pub query: String,
+    pub workspace_id: Option<String>,
+    pub operation_key: Option<String>,
+    pub status_min: Option<u16>,
+    pub status_max: Option<u16>,
+    /// Inclusive bounds on the entry timestamp, in milliseconds since the epoch
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// A run of snippet text, highlighted when it is part of a match
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetPart {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryMatch {
+    /// `response_history.id`
+    pub id: i64,
+    pub workspace_id: String,
+    pub operation_key: String,
+    pub status: Option<i64>,
+    pub timestamp: i64,
+    pub snippet: Vec<SnippetPart>,
+}
+
+/// Turns user input into an FTS5 query matching every word, so punctuation such as the
+/// `-` in `order-12345` is searched for rather than read as query syntax
+fn fts_query(input: &str) -> String {
+    input
+        .split_whitespace()
+        .map(|term| {
+            let (term, prefix) = match term.strip_suffix('*') {
+                Some(stem) if !stem.is_empty() => (stem, "*"),
+                _ => (term, ""),
+            };
+            format!("\"{}\"{}", term.replace('"', "\"\""), prefix)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Splits a snippet on the match markers into plain and highlighted parts
+fn snippet_parts(snippet: &str) -> Vec<SnippetPart> {
+    let mut parts = Vec::new();
+    let mut highlighted = false;
+    for piece in snippet.split([MATCH_START, MATCH_END]) {
+        if !piece.is_empty() {
+            parts.push(SnippetPart {
+                text: piece.to_string(),
+                highlighted,
+            });
+        }
+        highlighted = !highlighted;
+    }
+    parts
+}
+
+/// Searches stored response headers and bodies, best matches first
+pub async fn search(
+    pool: &SqlitePool,
+    search: &HistorySearch,
+) -> Result<Vec<HistoryMatch>, String> {
+    let query = fts_query(&search.query);
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let rows: Vec<(i64, String, String, Option<i64>, i64, String)> = sqlx::query_as(
+        "SELECT h.id, h.workspace_id, h.operation_key, h.status, h.timestamp,
+                snippet(response_history_fts, -1, ?1, ?2, '…', ?3)
+         FROM response_history_fts
+         JOIN response_history h ON h.id = response_history_fts.rowid
+         WHERE response_history_fts MATCH ?4
+           AND (?5 IS NULL OR h.workspace_id = ?5)
+           AND (?6 IS NULL OR h.operation_key = ?6)
+           AND (?7 IS NULL OR h.status >= ?7)
+           AND (?8 IS NULL OR h.status <= ?8)
+           AND (?9 IS NULL OR h.timestamp >= ?9)
+           AND (?10 IS NULL OR h.timestamp <= ?10)
+         ORDER BY rank
+         LIMIT ?11",
+    )
+    .bind(MATCH_START.to_string())
+    .bind(MATCH_END.to_string())
+    .bind(SNIPPET_TOKENS)
+    .bind(query)
+    .bind(&search.workspace_id)
+    .bind(&search.operation_key)
+    .bind(search.status_min)
+    .bind(search.status_max)
+    .bind(search.from)
+    .bind(search.to)
+    .bind(search.limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(id, workspace_id, operation_key, status, timestamp, snippet)| HistoryMatch {
+                id,
+                workspace_id,
+                operation_key,
+                status,
+                timestamp,
+                snippet: snippet_parts(&snippet),
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use serde_json::json;
+
+    fn search_for(query: &str) -> HistorySearch {
+        HistorySearch {
+            query: query.to_string(),
+            ..Default::default()
+        }
+    }
+
+    async fn ids(pool: &SqlitePool, search: &HistorySearch) -> Vec<i64> {
+        let mut ids: Vec<i64> = super::search(pool, search)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn the_index_follows_inserts_updates_compression_and_deletes() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+
+        let small = json!({ "status": 200, "headers": { "x-trace": "abc" }, "bodyText": "order-12345 shipped" });
+        let large = json!({
+            "status": 500,
+            "headers": {},
+            "bodyText": format!("order-12345 failed {}", "pad ".repeat(store::COMPRESS_THRESHOLD)),
+        });
+        let small_id = store::insert(pool, "w1", "get:/orders", &small.to_string(), None, None, 1)
+            .await
+            .unwrap();
+        let large_id = store::insert(pool, "w1", "get:/orders", &large.to_string(), None, None, 2)
+            .await
+            .unwrap();
+
+        // The compressed entry stays searchable and keeps its status
+        assert_eq!(
+            ids(pool, &search_for("order-12345")).await,
+            vec![small_id, large_id]
+        );
+        assert_eq!(ids(pool, &search_for("abc")).await, vec![small_id]);
+        let errors = HistorySearch {
+            status_min: Some(500),
+            ..search_for("order-12345")
+        };
+        let matches = super::search(pool, &errors).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!((matches[0].id, matches[0].status), (large_id, Some(500)));
+        assert!(matches[0]
+            .snippet
+            .iter()
+            .any(|part| part.highlighted && part.text.contains("order")));
+
+        sqlx::query("UPDATE response_history SET response_json = ? WHERE id = ?")
+            .bind(json!({ "status": 200, "headers": {}, "bodyText": "delivered" }).to_string())
+            .bind(small_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(ids(pool, &search_for("shipped")).await, Vec::<i64>::new());
+        assert_eq!(ids(pool, &search_for("deliv*")).await, vec![small_id]);
+
+        sqlx::query("DELETE FROM response_history WHERE id = ?")
+            .bind(large_id)
+            .execute(pool)
+            .await
+            .unwrap();
+        assert_eq!(ids(pool, &search_for("failed")).await, Vec::<i64>::new());
+        let (indexed,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM response_history_fts")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(indexed, 1);
+    }
+
+    #[test]
+    fn user_input_is_quoted_into_an_fts_query() {
+        assert_eq!(fts_query("order-1 ship*"), "\"order-1\" \"ship\"*");
+        assert_eq!(fts_query("say \"hi\""), "\"say\" \"\"\"hi\"\"\"");
+        assert_eq!(fts_query("  "), "");
+    }
+}
@@ -0,0 +1,394 @@
+use super::store;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Kept per operation when a workspace has no policy, matching what the app always kept
+pub const DEFAULT_MAX_ENTRIES_PER_OPERATION: u32 = 25;
+/// How often the background task enforces retention
+const PRUNE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Delay before the first pass so pruning doesn't compete with startup
+const PRUNE_STARTUP_DELAY: Duration = Duration::from_secs(60);
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Limits on a workspace's response history; `None` means unlimited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    pub max_entries_per_operation: Option<u32>,
+    pub max_age_days: Option<u32>,
+    /// Budget for the stored size of all the workspace's entries; the oldest go first
+    pub max_total_bytes: Option<i64>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_entries_per_operation: Some(DEFAULT_MAX_ENTRIES_PER_OPERATION),
+            max_age_days: None,
+            max_total_bytes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceUsage {
+    pub workspace_id: String,
+    pub name: String,
+    pub entries: i64,
+    pub compressed_entries: i64,
+    /// Size of the stored responses and requests, compressed where they are
+    pub stored_bytes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageStats {
+    pub database_bytes: i64,
+    /// Space in the file that `VACUUM` would give back
+    pub free_bytes: i64,
+    pub workspaces: Vec<WorkspaceUsage>,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+pub async fn load(pool: &SqlitePool, workspace_id: &str) -> Result<RetentionPolicy, String> {
+    let row: Option<(Option<u32>, Option<u32>, Option<i64>)> = sqlx::query_as(
+        "SELECT max_entries_per_operation, max_age_days, max_total_bytes FROM history_retention WHERE workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(row
+        .map(
+            |(max_entries_per_operation, max_age_days, max_total_bytes)| RetentionPolicy {
+                max_entries_per_operation,
+                max_age_days,
+                max_total_bytes,
+            },
+        )
+        .unwrap_or_default())
+}
+
+pub async fn save(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    policy: &RetentionPolicy,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO history_retention (workspace_id, max_entries_per_operation, max_age_days, max_total_bytes)
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(policy.max_entries_per_operation)
+    .bind(policy.max_age_days)
+    .bind(policy.max_total_bytes)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Deletes an operation's entries beyond the policy's per-operation limit
+pub async fn prune_operation(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    policy: &RetentionPolicy,
+) -> Result<u64, String> {
+    let Some(max_entries) = policy.max_entries_per_operation else {
+        return Ok(0);
+    };
+    let result = sqlx::query(
+        "DELETE FROM response_history
+         WHERE workspace_id = ? AND operation_key = ?
+         AND id NOT IN (
+             SELECT id FROM response_history
+             WHERE workspace_id = ? AND operation_key = ?
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?
+         )",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .bind(workspace_id)
+    .bind(operation_key)
+    .bind(max_entries)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(result.rows_affected())
+}
+
+/// Applies a workspace's policy to all its entries and returns how many were deleted
+pub async fn prune_workspace(pool: &SqlitePool, workspace_id: &str) -> Result<u64, String> {
+    let policy = load(pool, workspace_id).await?;
+    let mut deleted = 0;
+
+    if let Some(days) = policy.max_age_days {
+        deleted +=
+            sqlx::query("DELETE FROM response_history WHERE workspace_id = ? AND timestamp < ?")
+                .bind(workspace_id)
+                .bind(now_ms() - i64::from(days) * DAY_MS)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?
+                .rows_affected();
+    }
+
+    if let Some(max_entries) = policy.max_entries_per_operation {
+        deleted += sqlx::query(
+            "DELETE FROM response_history WHERE id IN (
+                 SELECT id FROM (
+                     SELECT id, ROW_NUMBER() OVER (
+                         PARTITION BY operation_key ORDER BY timestamp DESC, id DESC
+                     ) AS position
+                     FROM response_history WHERE workspace_id = ?
+                 ) WHERE position > ?
+             )",
+        )
+        .bind(workspace_id)
+        .bind(max_entries)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    }
+
+    if let Some(max_bytes) = policy.max_total_bytes {
+        deleted += sqlx::query(
+            "DELETE FROM response_history WHERE id IN (
+                 SELECT id FROM (
+                     SELECT id, SUM(
+                         length(CAST(response_json AS BLOB))
+                         + COALESCE(length(response_blob), 0)
+                         + COALESCE(length(CAST(request_json AS BLOB)), 0)
+                         + COALESCE(length(CAST(request_snapshot AS BLOB)), 0)
+                     ) OVER (ORDER BY timestamp DESC, id DESC) AS running_bytes
+                     FROM response_history WHERE workspace_id = ?
+                 ) WHERE running_bytes > ?
+             )",
+        )
+        .bind(workspace_id)
+        .bind(max_bytes)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .rows_affected();
+    }
+
+    Ok(deleted)
+}
+
+/// Applies every workspace's policy, compresses large entries and fills in missing statuses
+pub async fn enforce(pool: &SqlitePool) -> Result<u64, String> {
+    let workspaces: Vec<(String,)> = sqlx::query_as("SELECT id FROM workspaces")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut deleted = 0;
+    for (workspace_id,) in workspaces {
+        deleted += prune_workspace(pool, &workspace_id).await?;
+    }
+    store::compress_large(pool).await?;
+    store::fill_status(pool).await?;
+    Ok(deleted)
+}
+
+/// Enforces retention in the background for as long as the app runs
+pub async fn run_periodically(pool: SqlitePool) {
+    tokio::time::sleep(PRUNE_STARTUP_DELAY).await;
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        // A failed pass is retried on the next tick
+        let _ = enforce(&pool).await;
+    }
+}
+
+/// Reports the database size and how much of it each workspace's history uses
+pub async fn stats(pool: &SqlitePool) -> Result<StorageStats, String> {
+    let (page_count,): (i64,) = sqlx::query_as("PRAGMA page_count")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (page_size,): (i64,) = sqlx::query_as("PRAGMA page_size")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (free_pages,): (i64,) = sqlx::query_as("PRAGMA freelist_count")
+        .fetch_one(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let rows: Vec<(String, String, i64, i64, i64)> = sqlx::query_as(
+        "SELECT w.id, w.name, COUNT(h.id), COUNT(h.response_blob),
+             COALESCE(SUM(
+                 length(CAST(h.response_json AS BLOB))
+                 + COALESCE(length(h.response_blob), 0)
+                 + COALESCE(length(CAST(h.request_json AS BLOB)), 0)
+                 + COALESCE(length(CAST(h.request_snapshot AS BLOB)), 0)
+             ), 0)
+         FROM workspaces w
+         LEFT JOIN response_history h ON h.workspace_id = w.id
+         GROUP BY w.id
+         ORDER BY w.sort_order",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(StorageStats {
+        database_bytes: page_count * page_size,
+        free_bytes: free_pages * page_size,
+        workspaces: rows
+            .into_iter()
+            .map(
+                |(workspace_id, name, entries, compressed_entries, stored_bytes)| WorkspaceUsage {
+                    workspace_id,
+                    name,
+                    entries,
+                    compressed_entries,
+                    stored_bytes,
+                },
+            )
+            .collect(),
+    })
+}
+
+/// Enforces retention, then rebuilds the database file to give freed space back
+pub async fn vacuum(pool: &SqlitePool) -> Result<StorageStats, String> {
+    enforce(pool).await?;
+    sqlx::query("VACUUM")
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    stats(pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+
+    async fn timestamps(pool: &SqlitePool, operation_key: &str) -> Vec<i64> {
+        let rows: Vec<(i64,)> = sqlx::query_as(
+            "SELECT timestamp FROM response_history WHERE operation_key = ? ORDER BY timestamp",
+        )
+        .bind(operation_key)
+        .fetch_all(pool)
+        .await
+        .unwrap();
+        rows.into_iter().map(|(timestamp,)| timestamp).collect()
+    }
+
+    async fn workspace_with_history(db: &Database) -> &SqlitePool {
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        let now = now_ms();
+        for (operation_key, age_days) in [
+            ("get:/pets", 10),
+            ("get:/pets", 3),
+            ("get:/pets", 2),
+            ("get:/pets", 1),
+            ("get:/owners", 5),
+        ] {
+            store::insert(
+                pool,
+                "w1",
+                operation_key,
+                r#"{"status":200,"bodyText":"ok"}"#,
+                None,
+                None,
+                now - age_days * DAY_MS,
+            )
+            .await
+            .unwrap();
+        }
+        pool
+    }
+
+    #[tokio::test]
+    async fn workspaces_without_a_policy_get_the_default() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = workspace_with_history(&db).await;
+        let policy = load(pool, "w1").await.unwrap();
+        assert_eq!(
+            policy.max_entries_per_operation,
+            Some(DEFAULT_MAX_ENTRIES_PER_OPERATION)
+        );
+        assert_eq!(prune_workspace(pool, "w1").await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn prunes_each_operation_to_its_newest_entries() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = workspace_with_history(&db).await;
+        let before = timestamps(pool, "get:/pets").await;
+        let policy = RetentionPolicy {
+            max_entries_per_operation: Some(2),
+            max_age_days: None,
+            max_total_bytes: None,
+        };
+
+        assert_eq!(
+            prune_operation(pool, "w1", "get:/pets", &policy)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(timestamps(pool, "get:/pets").await, before[2..]);
+        assert_eq!(timestamps(pool, "get:/owners").await.len(), 1);
+
+        let unlimited = RetentionPolicy {
+            max_entries_per_operation: None,
+            ..policy
+        };
+        assert_eq!(
+            prune_operation(pool, "w1", "get:/pets", &unlimited)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn prunes_a_workspace_by_age_count_and_size() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = workspace_with_history(&db).await;
+        let before = timestamps(pool, "get:/pets").await;
+
+        let policy = RetentionPolicy {
+            max_entries_per_operation: Some(2),
+            max_age_days: Some(7),
+            max_total_bytes: None,
+        };
+        save(pool, "w1", &policy).await.unwrap();
+        // The 10-day-old entry goes for its age, then one more to keep two per operation
+        assert_eq!(prune_workspace(pool, "w1").await.unwrap(), 2);
+        assert_eq!(timestamps(pool, "get:/pets").await, before[2..]);
+        assert_eq!(timestamps(pool, "get:/owners").await.len(), 1);
+
+        // Room for one entry keeps only the newest in the whole workspace
+        let entry_bytes = r#"{"status":200,"bodyText":"ok"}"#.len() as i64;
+        let policy = RetentionPolicy {
+            max_entries_per_operation: None,
+            max_age_days: None,
+            max_total_bytes: Some(entry_bytes),
+        };
+        save(pool, "w1", &policy).await.unwrap();
+        assert_eq!(prune_workspace(pool, "w1").await.unwrap(), 2);
+        assert_eq!(timestamps(pool, "get:/pets").await, before[3..]);
+        assert!(timestamps(pool, "get:/owners").await.is_empty());
+    }
+}
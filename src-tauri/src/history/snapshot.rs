@@ -0,0 +1,300 @@
+use crate::network::request::PreparedRequest;
+use crate::templating::resolve;
+use crate::templating::{resolve_string, WorkspaceContext};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Secret values shorter than this are left in place, since replacing them would mangle
+/// unrelated text that happens to contain the same characters
+const MIN_SECRET_LENGTH: usize = 4;
+
+/// Headers that carry credentials, so their values are never stored: replay recordings
+/// leave them out and request snapshots keep them blank
+pub const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+    "api-key",
+    "x-auth-token",
+    "x-access-token",
+    "x-csrf-token",
+    "x-amz-security-token",
+];
+
+pub fn is_sensitive_header(name: &str) -> bool {
+    SENSITIVE_HEADERS
+        .iter()
+        .any(|sensitive| sensitive.eq_ignore_ascii_case(name))
+}
+
+/// The request behind a history entry, with secrets swapped for `{{name}}` placeholders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestSnapshot {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+    pub environment_id: Option<String>,
+    /// Headers set by the operation's auth scheme, stored empty and recomputed on replay
+    #[serde(default)]
+    pub auth_headers: Vec<String>,
+}
+
+/// Maps placeholder names to the secret values they stand for: secret environment
+/// variables by name, and auth values as `auth:<scheme>.<field>`
+fn secret_values(context: &WorkspaceContext) -> HashMap<String, String> {
+    let mut secrets = HashMap::new();
+    for name in &context.secret_variables {
+        if let Some(value) = context.variables.get(name) {
+            secrets.insert(name.clone(), value.clone());
+        }
+    }
+    for (scheme, fields) in &context.auth_values {
+        for (field, value) in fields {
+            secrets.insert(format!("auth:{}.{}", scheme, field), value.clone());
+        }
+    }
+    secrets
+}
+
+/// Header names compare case-insensitively, since the frontend lowercases them
+fn auth_header<'a>(auth: &'a HashMap<String, String>, name: &str) -> Option<&'a String> {
+    auth.iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)
+}
+
+/// Secret values paired with the placeholder names that replace them, longest value first
+/// so a secret containing another is replaced whole
+pub struct Redactions(Vec<(String, String)>);
+
+impl Redactions {
+    pub fn new(context: &WorkspaceContext) -> Self {
+        let mut ordered: Vec<(String, String)> = secret_values(context)
+            .into_iter()
+            .filter(|(_, value)| value.len() >= MIN_SECRET_LENGTH)
+            .collect();
+        ordered.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+        Self(ordered)
+    }
+
+    /// Swaps every secret value in `text` for its `{{name}}` placeholder
+    pub fn apply(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+        for (name, value) in &self.0 {
+            redacted = redacted.replace(value.as_str(), &format!("{{{{{}}}}}", name));
+        }
+        redacted
+    }
+}
+
+/// A sent request with every secret and auth value swapped for its placeholder, safe to
+/// hand to the frontend. A snapshot captured from it still replays, since [`restore`]
+/// fills the placeholders back in.
+pub fn redact_request(request: &PreparedRequest, context: &WorkspaceContext) -> PreparedRequest {
+    let redactions = Redactions::new(context);
+    PreparedRequest {
+        method: request.method.clone(),
+        url: redactions.apply(&request.url),
+        headers: request
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), redactions.apply(value)))
+            .collect(),
+        body: request.body.as_deref().map(|body| redactions.apply(body)),
+    }
+}
+
+/// Records a sent request with its auth headers, other [`SENSITIVE_HEADERS`] and secret
+/// values redacted. The request may already have been through [`redact_request`], as the
+/// one the frontend hands back after `resolve_and_send` has; auth headers are recognised
+/// in either form.
+pub fn capture(
+    request: &PreparedRequest,
+    context: &WorkspaceContext,
+    operation_key: &str,
+) -> RequestSnapshot {
+    let redactions = Redactions::new(context);
+
+    let auth = resolve::auth_headers(context, operation_key).unwrap_or_default();
+    let mut auth_headers = Vec::new();
+    let headers = request
+        .headers
+        .iter()
+        .map(|(name, value)| {
+            let from_auth = auth_header(&auth, name)
+                .is_some_and(|auth| auth == value || redactions.apply(auth) == *value);
+            if from_auth {
+                auth_headers.push(name.clone());
+                (name.clone(), String::new())
+            } else if is_sensitive_header(name) {
+                (name.clone(), String::new())
+            } else {
+                (name.clone(), redactions.apply(value))
+            }
+        })
+        .collect();
+    auth_headers.sort();
+
+    RequestSnapshot {
+        method: request.method.clone(),
+        url: redactions.apply(&request.url),
+        headers,
+        body: request.body.as_deref().map(|body| redactions.apply(body)),
+        environment_id: context.environment_id.clone(),
+        auth_headers,
+    }
+}
+
+/// Rebuilds the request a snapshot was taken of, filling secrets and auth headers from the
+/// context. Other `{{...}}` text is left as it was sent, and sensitive headers that didn't
+/// come from auth are left out since their values weren't kept.
+pub fn restore(
+    snapshot: &RequestSnapshot,
+    context: &WorkspaceContext,
+    operation_key: &str,
+) -> Result<PreparedRequest, String> {
+    let secrets = secret_values(context);
+    let auth = if snapshot.auth_headers.is_empty() {
+        HashMap::new()
+    } else {
+        resolve::auth_headers(context, operation_key)?
+    };
+
+    let mut headers = HashMap::new();
+    for (name, value) in &snapshot.headers {
+        let value = if snapshot.auth_headers.contains(name) {
+            auth_header(&auth, name)
+                .cloned()
+                .ok_or_else(|| format!("No auth value for header: {}", name))?
+        } else if is_sensitive_header(name) && value.is_empty() {
+            continue;
+        } else {
+            resolve_string(value, &secrets)
+        };
+        headers.insert(name.clone(), value);
+    }
+
+    let request = PreparedRequest {
+        method: snapshot.method.clone(),
+        url: resolve_string(&snapshot.url, &secrets),
+        headers,
+        body: snapshot
+            .body
+            .as_deref()
+            .map(|body| resolve_string(body, &secrets)),
+    };
+    context.ensure_unlocked(
+        std::iter::once(request.url.as_str())
+            .chain(request.headers.values().map(String::as_str))
+            .chain(request.body.as_deref()),
+    )?;
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> WorkspaceContext {
+        let spec = json!({
+            "openapi": "3.0.0",
+            "paths": { "/me": { "get": { "security": [{ "bearer": [] }] } } },
+            "components": {
+                "securitySchemes": { "bearer": { "type": "http", "scheme": "bearer" } }
+            }
+        });
+        WorkspaceContext {
+            base_url: "https://api.test".to_string(),
+            environment_id: Some("e1".to_string()),
+            spec: Some(spec),
+            variables: HashMap::from([("apiKey".to_string(), "k3y-value".to_string())]),
+            secret_variables: ["apiKey".to_string()].into(),
+            global_headers: HashMap::new(),
+            auth_values: HashMap::from([(
+                "bearer".to_string(),
+                HashMap::from([("token".to_string(), "t0ken-value".to_string())]),
+            )]),
+            locked_variables: Default::default(),
+            locked_auth: Default::default(),
+        }
+    }
+
+    #[test]
+    fn sensitive_headers_are_blanked_and_left_out_on_replay() {
+        let context = context();
+        let request = PreparedRequest {
+            method: "GET".to_string(),
+            url: "https://api.test/me?key=k3y-value".to_string(),
+            headers: HashMap::from([
+                (
+                    "authorization".to_string(),
+                    "Bearer t0ken-value".to_string(),
+                ),
+                ("Cookie".to_string(), "session=abc".to_string()),
+                ("X-Api-Key".to_string(), "typed-by-hand".to_string()),
+                ("x-trace".to_string(), "trace-k3y-value".to_string()),
+            ]),
+            body: None,
+        };
+
+        let snapshot = capture(&request, &context, "get:/me");
+        assert_eq!(snapshot.auth_headers, vec!["authorization"]);
+        assert_eq!(snapshot.url, "https://api.test/me?key={{apiKey}}");
+        assert_eq!(snapshot.headers["authorization"], "");
+        assert_eq!(snapshot.headers["Cookie"], "");
+        assert_eq!(snapshot.headers["X-Api-Key"], "");
+        assert_eq!(snapshot.headers["x-trace"], "trace-{{apiKey}}");
+
+        let restored = restore(&snapshot, &context, "get:/me").unwrap();
+        assert_eq!(restored.url, request.url);
+        assert_eq!(restored.headers["authorization"], "Bearer t0ken-value");
+        assert_eq!(restored.headers["x-trace"], "trace-k3y-value");
+        assert!(!restored.headers.contains_key("Cookie"));
+        assert!(!restored.headers.contains_key("X-Api-Key"));
+    }
+
+    #[test]
+    fn sensitive_header_names_match_in_any_case() {
+        assert!(is_sensitive_header("Proxy-Authorization"));
+        assert!(is_sensitive_header("set-cookie"));
+        assert!(!is_sensitive_header("content-type"));
+    }
+
+    #[test]
+    fn redacted_requests_keep_their_auth_on_replay() {
+        let context = context();
+        let sent = PreparedRequest {
+            method: "GET".to_string(),
+            url: "https://api.test/me?key=k3y-value".to_string(),
+            headers: HashMap::from([
+                (
+                    "authorization".to_string(),
+                    "Bearer t0ken-value".to_string(),
+                ),
+                ("x-trace".to_string(), "trace-k3y-value".to_string()),
+            ]),
+            body: Some(r#"{"key":"k3y-value"}"#.to_string()),
+        };
+
+        let redacted = redact_request(&sent, &context);
+        assert_eq!(
+            redacted.headers["authorization"],
+            "Bearer {{auth:bearer.token}}"
+        );
+        let snapshot = capture(&redacted, &context, "get:/me");
+        assert_eq!(snapshot.auth_headers, vec!["authorization"]);
+        assert_eq!(snapshot.headers["authorization"], "");
+        assert_eq!(snapshot.url, "https://api.test/me?key={{apiKey}}");
+        assert_eq!(snapshot.body.as_deref(), Some(r#"{"key":"{{apiKey}}"}"#));
+
+        let restored = restore(&snapshot, &context, "get:/me").unwrap();
+        assert_eq!(restored.url, sent.url);
+        assert_eq!(restored.headers, sent.headers);
+        assert_eq!(restored.body, sent.body);
+    }
+}
@@ -0,0 +1,366 @@
+use super::snapshot::RequestSnapshot;
+use crate::network::request::decode_body;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::io::Write;
+
+/// Responses larger than this are stored gzip-compressed in `response_blob`, leaving
+/// `response_json` empty. Search keeps indexing the first 64 KB of their body.
+pub const COMPRESS_THRESHOLD: usize = 16 * 1024;
+/// Rows compressed per query when catching up on rows stored before compression
+const COMPRESS_BATCH: i64 = 100;
+
+/// `id, response_json, response_blob, request_json, request_snapshot, timestamp`
+type HistoryRow = (
+    i64,
+    String,
+    Option<Vec<u8>>,
+    Option<String>,
+    Option<String>,
+    i64,
+);
+
+/// A `response_history` row with its response decompressed
+#[derive(Debug, Clone, Serialize)]
+pub struct ResponseHistoryEntry {
+    pub id: i64,
+    pub workspace_id: String,
+    pub operation_key: String,
+    pub response_json: String,
+    pub request_json: Option<String>,
+    pub request_snapshot: Option<RequestSnapshot>,
+    pub timestamp: i64,
+}
+
+fn compress(text: &str) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(text.as_bytes())
+        .map_err(|e| e.to_string())?;
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+/// The status code recorded in a response's JSON, if it has one
+pub fn response_status(response_json: &str) -> Option<i64> {
+    serde_json::from_str::<serde_json::Value>(response_json)
+        .ok()?
+        .get("status")?
+        .as_i64()
+}
+
+/// Returns a row's response JSON, decompressing it when it was stored compressed
+pub fn response_json(
+    response_json: String,
+    response_blob: Option<Vec<u8>>,
+) -> Result<String, String> {
+    match response_blob {
+        Some(blob) => {
+            let bytes = decode_body(Some("gzip"), &blob)?;
+            String::from_utf8(bytes).map_err(|e| e.to_string())
+        }
+        None => Ok(response_json),
+    }
+}
+
+/// Stores a row's compressed response. `response_json` changes in the same statement so
+/// the search triggers index the text before it is cleared.
+async fn store_blob(
+    executor: impl sqlx::Executor<'_, Database = sqlx::Sqlite>,
+    id: i64,
+    blob: Vec<u8>,
+) -> Result<(), String> {
+    sqlx::query("UPDATE response_history SET response_blob = ?, response_json = '' WHERE id = ?")
+        .bind(blob)
+        .bind(id)
+        .execute(executor)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Stores a response, compressing it when it is large, and returns the new row id
+pub async fn insert(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    response_json: &str,
+    request_json: Option<&str>,
+    request_snapshot: Option<&RequestSnapshot>,
+    timestamp: i64,
+) -> Result<i64, String> {
+    let request_snapshot = request_snapshot
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    // Compressed before anything is written, so only the database can fail mid-insert
+    let blob = if response_json.len() > COMPRESS_THRESHOLD {
+        Some(compress(response_json)?)
+    } else {
+        None
+    };
+
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let id = sqlx::query(
+        "INSERT INTO response_history (workspace_id, operation_key, response_json, status, request_json, request_snapshot, timestamp)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .bind(response_json)
+    .bind(response_status(response_json))
+    .bind(request_json)
+    .bind(request_snapshot)
+    .bind(timestamp)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+
+    if let Some(blob) = blob {
+        store_blob(&mut *tx, id, blob).await?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    Ok(id)
+}
+
+/// Lists an operation's stored responses, newest first. Without a limit every entry the
+/// retention policy kept is listed.
+pub async fn list(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    limit: Option<u32>,
+) -> Result<Vec<ResponseHistoryEntry>, String> {
+    let rows: Vec<HistoryRow> = sqlx::query_as(
+        "SELECT id, response_json, response_blob, request_json, request_snapshot, timestamp FROM response_history
+         WHERE workspace_id = ? AND operation_key = ?
+         ORDER BY timestamp DESC LIMIT ?",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    // A negative limit means none to SQLite
+    .bind(limit.map_or(-1, i64::from))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    rows.into_iter()
+        .map(|(id, json, blob, request_json, snapshot, timestamp)| {
+            Ok(ResponseHistoryEntry {
+                id,
+                workspace_id: workspace_id.to_string(),
+                operation_key: operation_key.to_string(),
+                response_json: response_json(json, blob)?,
+                request_json,
+                request_snapshot: parse_snapshot(snapshot),
+                timestamp,
+            })
+        })
+        .collect()
+}
+
+/// Loads one entry's response JSON, decompressed
+pub async fn get_response(pool: &SqlitePool, id: i64) -> Result<String, String> {
+    let row: Option<(String, Option<Vec<u8>>)> =
+        sqlx::query_as("SELECT response_json, response_blob FROM response_history WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (json, blob) = row.ok_or_else(|| format!("History entry not found: {}", id))?;
+    response_json(json, blob)
+}
+
+/// Snapshots that no longer parse are treated as missing rather than failing the listing
+fn parse_snapshot(snapshot: Option<String>) -> Option<RequestSnapshot> {
+    snapshot.and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// Loads an entry's workspace, operation and request snapshot
+pub async fn get_snapshot(
+    pool: &SqlitePool,
+    id: i64,
+) -> Result<(String, String, Option<RequestSnapshot>), String> {
+    let row: Option<(String, String, Option<String>)> = sqlx::query_as(
+        "SELECT workspace_id, operation_key, request_snapshot FROM response_history WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let (workspace_id, operation_key, snapshot) =
+        row.ok_or_else(|| format!("History entry not found: {}", id))?;
+    Ok((workspace_id, operation_key, parse_snapshot(snapshot)))
+}
+
+/// Fills in the status of rows compressed before it had a column of its own, returning
+/// how many were
+pub async fn fill_status(pool: &SqlitePool) -> Result<u64, String> {
+    let mut filled = 0;
+    let mut after = 0;
+    loop {
+        let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(
+            "SELECT id, response_blob FROM response_history
+             WHERE status IS NULL AND response_blob IS NOT NULL AND id > ?
+             ORDER BY id LIMIT ?",
+        )
+        .bind(after)
+        .bind(COMPRESS_BATCH)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let Some((last, _)) = rows.last() else {
+            return Ok(filled);
+        };
+        after = *last;
+        for (id, blob) in rows {
+            // Rows without a status are skipped rather than failing the pass
+            let Some(status) = response_json(String::new(), Some(blob))
+                .ok()
+                .and_then(|json| response_status(&json))
+            else {
+                continue;
+            };
+            sqlx::query("UPDATE response_history SET status = ? WHERE id = ?")
+                .bind(status)
+                .bind(id)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+            filled += 1;
+        }
+    }
+}
+
+/// Compresses large rows stored before compression existed, returning how many were
+pub async fn compress_large(pool: &SqlitePool) -> Result<u64, String> {
+    let mut compressed = 0;
+    loop {
+        let rows: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT id, response_json FROM response_history
+             WHERE response_blob IS NULL AND length(response_json) > ? LIMIT ?",
+        )
+        .bind(COMPRESS_THRESHOLD as i64)
+        .bind(COMPRESS_BATCH)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        if rows.is_empty() {
+            return Ok(compressed);
+        }
+        for (id, text) in rows {
+            store_blob(pool, id, compress(&text)?).await?;
+            compressed += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use serde_json::json;
+
+    fn response(status: u16, body: &str) -> String {
+        json!({ "status": status, "headers": {}, "bodyText": body }).to_string()
+    }
+
+    #[tokio::test]
+    async fn large_responses_are_compressed_and_read_back() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+
+        let large = response(200, &"x".repeat(COMPRESS_THRESHOLD));
+        let small = response(404, "missing");
+        let large_id = insert(pool, "w1", "get:/pets", &large, None, None, 1)
+            .await
+            .unwrap();
+        insert(pool, "w1", "get:/pets", &small, None, None, 2)
+            .await
+            .unwrap();
+
+        let (stored, compressed, status): (String, Option<Vec<u8>>, Option<i64>) = sqlx::query_as(
+            "SELECT response_json, response_blob, status FROM response_history WHERE id = ?",
+        )
+        .bind(large_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert!(stored.is_empty());
+        assert!(compressed.unwrap().len() < large.len());
+        assert_eq!(status, Some(200));
+        assert_eq!(get_response(pool, large_id).await.unwrap(), large);
+
+        let entries = list(pool, "w1", "get:/pets", None).await.unwrap();
+        let bodies: Vec<&str> = entries.iter().map(|e| e.response_json.as_str()).collect();
+        assert_eq!(bodies, vec![small.as_str(), large.as_str()]);
+        assert_eq!(
+            list(pool, "w1", "get:/pets", Some(1)).await.unwrap().len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn a_failed_compression_leaves_no_row_behind() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        sqlx::query(
+            "CREATE TRIGGER fail_compression BEFORE UPDATE OF response_blob ON response_history
+             BEGIN SELECT RAISE(ABORT, 'disk full'); END",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let large = response(200, &"x".repeat(COMPRESS_THRESHOLD));
+        let err = insert(pool, "w1", "get:/pets", &large, None, None, 1)
+            .await
+            .unwrap_err();
+        assert!(err.contains("disk full"), "{}", err);
+        assert!(list(pool, "w1", "get:/pets", None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn catches_up_on_rows_stored_before_compression_and_status() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+
+        let large = response(201, &"y".repeat(COMPRESS_THRESHOLD));
+        sqlx::query(
+            "INSERT INTO response_history (workspace_id, operation_key, response_json, timestamp)
+             VALUES ('w1', 'post:/pets', ?, 1), ('w1', 'post:/pets', 'not json', 2)",
+        )
+        .bind(&large)
+        .execute(pool)
+        .await
+        .unwrap();
+
+        assert_eq!(compress_large(pool).await.unwrap(), 1);
+        assert_eq!(compress_large(pool).await.unwrap(), 0);
+        assert_eq!(fill_status(pool).await.unwrap(), 1);
+        assert_eq!(fill_status(pool).await.unwrap(), 0);
+
+        let statuses: Vec<(Option<i64>,)> =
+            sqlx::query_as("SELECT status FROM response_history ORDER BY timestamp")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        assert_eq!(statuses, vec![(Some(201),), (None,)]);
+        let entries = list(pool, "w1", "post:/pets", None).await.unwrap();
+        assert_eq!(entries[1].response_json, large);
+    }
+}
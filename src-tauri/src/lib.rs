@@ -0,0 +1,131 @@
+//! Backend shared by the desktop app and the `cogeass-cli` binary
+
+pub mod assertions;
+pub mod backup;
+pub mod bundle;
+pub mod commands;
+pub mod db;
+pub mod diff;
+pub mod extraction;
+pub mod history;
+pub mod loadtest;
+pub mod mock;
+pub mod network;
+pub mod runner;
+pub mod scripting;
+pub mod secrets;
+pub mod spec;
+pub mod sync;
+pub mod templating;
+pub mod validation;
+
+use tauri::Manager;
+
+/// Builds and runs the desktop app
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_shell::init())
+        // Must come before the SQL plugin, which opens the database during its setup
+        .plugin(backup::plugin())
+        .plugin(tauri_plugin_sql::Builder::default().build())
+        .setup(|app| {
+            // Migrations have already run in the backup plugin's setup; open our own pool on the same file
+            let db_path = db::database_path(app.handle())?;
+            let database = tauri::async_runtime::block_on(db::Database::open(&db_path))?;
+            tauri::async_runtime::spawn(history::retention::run_periodically(
+                database.pool().clone(),
+            ));
+            app.manage(database);
+            Ok(())
+        })
+        .manage(network::session::SessionManager::new())
+        .manage(secrets::SecretVault::new())
+        .manage(mock::MockServerManager::new())
+        .manage(runner::RunManager::new())
+        .invoke_handler(tauri::generate_handler![
+            commands::repository::load_initial_data,
+            commands::repository::create_workspace,
+            commands::repository::update_workspace,
+            commands::repository::delete_workspace,
+            commands::repository::get_full_workspace_data,
+            commands::repository::save_spec,
+            commands::repository::get_spec,
+            commands::repository::delete_spec,
+            commands::repository::create_environment,
+            commands::repository::update_environment,
+            commands::repository::delete_environment,
+            commands::repository::add_variable_key,
+            commands::repository::remove_variable_key,
+            commands::repository::rename_variable_key,
+            commands::repository::set_variable_value,
+            commands::repository::delete_variable_value,
+            commands::repository::set_global_header,
+            commands::repository::delete_global_header,
+            commands::repository::set_all_global_headers,
+            commands::repository::set_auth_value,
+            commands::repository::delete_auth_value,
+            commands::repository::save_operation_state,
+            commands::repository::get_operation_state,
+            commands::repository::delete_operation_state,
+            commands::repository::cleanup_old_responses,
+            commands::repository::add_history_entry,
+            commands::repository::prune_history,
+            commands::repository::clear_history,
+            commands::repository::clear_response_history,
+            commands::http::make_request,
+            commands::http::resolve_request,
+            commands::http::resolve_and_send,
+            commands::session::open_session,
+            commands::session::close_session,
+            commands::session::send_message,
+            commands::spec::load_spec,
+            commands::spec::get_operation_index,
+            commands::validation::validate_response,
+            commands::validation::validate_request,
+            commands::mock::start_mock_server,
+            commands::mock::start_replay_server,
+            commands::mock::stop_mock_server,
+            commands::mock::get_mock_server_log,
+            commands::runner::start_run,
+            commands::runner::cancel_run,
+            commands::runner::export_run_results,
+            commands::loadtest::start_load_test,
+            commands::backup::get_database_health,
+            commands::backup::list_backups,
+            commands::backup::create_backup,
+            commands::backup::restore_backup,
+            commands::bundle::export_workspace,
+            commands::bundle::import_workspace,
+            commands::sync::link_workspace_directory,
+            commands::sync::unlink_workspace_directory,
+            commands::sync::get_workspace_directory,
+            commands::sync::sync_workspace_directory,
+            commands::diff::diff_responses,
+            commands::diff::compare_environments,
+            commands::history::search_history,
+            commands::history::add_response_history,
+            commands::history::get_response_history,
+            commands::history::replay_history_entry,
+            commands::history::get_retention_policy,
+            commands::history::save_retention_policy,
+            commands::history::get_storage_stats,
+            commands::history::vacuum_database,
+            commands::assertions::get_assertions,
+            commands::assertions::save_assertions,
+            commands::assertions::evaluate_assertions,
+            commands::extraction::get_extraction_rules,
+            commands::extraction::save_extraction_rules,
+            commands::extraction::apply_extractions,
+            commands::scripting::get_operation_scripts,
+            commands::scripting::save_operation_scripts,
+            commands::vault::get_vault_status,
+            commands::vault::unlock_vault,
+            commands::vault::lock_vault,
+            commands::vault::set_variable_secret,
+            commands::vault::set_secret_variable_value,
+            commands::vault::set_auth_value_secret,
+            commands::vault::set_secret_auth_value,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
@@ -0,0 +1,473 @@
+pub mod stats;
+
+use crate::network::request::{self, PreparedRequest};
+use serde::{Deserialize, Serialize};
+use stats::{HistogramBucket, LatencyStats, Recorder};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::MissedTickBehavior;
+
+/// How often progress is reported while a load test runs
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+/// Highest `rate_per_second` accepted; faster rates round the pacing interval to zero
+const MAX_RATE_PER_SECOND: f64 = 1_000_000.0;
+
+/// One operation fired repeatedly. The test stops at whichever of `duration_ms` and
+/// `requests` is reached first; at least one must be set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestConfig {
+    pub workspace_id: String,
+    /// Defaults to the workspace's active environment
+    pub environment_id: Option<String>,
+    pub operation_key: String,
+    /// Workers sending requests back to back; defaults to 1
+    pub concurrency: Option<usize>,
+    pub duration_ms: Option<u64>,
+    pub requests: Option<u64>,
+    /// Caps the combined request rate of all workers, up to `MAX_RATE_PER_SECOND`
+    pub rate_per_second: Option<f64>,
+}
+
+impl LoadTestConfig {
+    /// Checks the settings `run` can't start without
+    pub fn validate(&self) -> Result<(), String> {
+        if self.duration_ms.is_none() && self.requests.is_none() {
+            return Err("Set a duration or a request count for the load test".to_string());
+        }
+        if let Some(rate) = self.rate_per_second {
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(format!("Invalid request rate: {}", rate));
+            }
+            if rate > MAX_RATE_PER_SECOND {
+                return Err(format!(
+                    "Request rate {} is above the limit of {} per second",
+                    rate, MAX_RATE_PER_SECOND
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestProgress {
+    pub elapsed_ms: f64,
+    pub completed: u64,
+    /// Requests without a response or with a status of 400 or above
+    pub errors: u64,
+    /// Throughput since the previous progress event
+    pub requests_per_second: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestSummary {
+    pub run_id: String,
+    pub completed: u64,
+    pub errors: u64,
+    pub cancelled: bool,
+    pub duration_ms: f64,
+    pub requests_per_second: f64,
+    /// Measured over requests that got a response
+    pub latency: LatencyStats,
+    pub histogram: Vec<HistogramBucket>,
+    pub status_codes: BTreeMap<u16, u64>,
+    /// Requests that got no response, counted by error message
+    pub transport_errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum LoadTestEvent {
+    Progress(LoadTestProgress),
+    Finished(LoadTestSummary),
+    /// The test stopped on an error before it could finish
+    Failed {
+        error: String,
+    },
+}
+
+/// State shared by the workers of one load test
+struct Shared {
+    recorder: Mutex<Recorder>,
+    /// Requests claimed by a worker, used to stop at the configured count
+    issued: AtomicU64,
+    completed: AtomicU64,
+    errors: AtomicU64,
+    cancel: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+    max_requests: Option<u64>,
+    limiter: Option<tokio::sync::Mutex<tokio::time::Interval>>,
+}
+
+impl Shared {
+    fn should_stop(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst) || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Waits for the rate limiter, then claims the next request; `false` when the test is over
+    async fn claim(&self) -> bool {
+        if self.should_stop() {
+            return false;
+        }
+        if let Some(limiter) = &self.limiter {
+            limiter.lock().await.tick().await;
+            if self.should_stop() {
+                return false;
+            }
+        }
+        let issued = self.issued.fetch_add(1, Ordering::SeqCst);
+        self.max_requests.map_or(true, |max| issued < max)
+    }
+
+    fn record(&self, outcome: Result<u16, String>, latency_ms: f64) {
+        let mut recorder = self.recorder.lock().unwrap_or_else(|e| e.into_inner());
+        let failed = match outcome {
+            Ok(status) => {
+                recorder.record_response(status, latency_ms);
+                status >= 400
+            }
+            Err(message) => {
+                recorder.record_error(message);
+                true
+            }
+        };
+        if failed {
+            self.errors.fetch_add(1, Ordering::SeqCst);
+        }
+        self.completed.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+async fn worker(shared: Arc<Shared>, client: reqwest::Client, request: PreparedRequest) {
+    while shared.claim().await {
+        let started = Instant::now();
+        let outcome = request::execute(&client, request.clone(), None, &|_| {})
+            .await
+            .map(|response| response.status);
+        shared.record(outcome, started.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+/// Sends `request` from `concurrency` workers sharing one client and its connection pool,
+/// reporting progress to `on_event` until the duration or request count is reached.
+///
+/// Setting `cancel` stops workers before their next request; requests in flight finish
+/// and are counted.
+pub async fn run(
+    client: reqwest::Client,
+    request: PreparedRequest,
+    run_id: &str,
+    config: &LoadTestConfig,
+    cancel: Arc<AtomicBool>,
+    on_event: &(dyn Fn(LoadTestEvent) + Send + Sync),
+) -> Result<LoadTestSummary, String> {
+    config.validate()?;
+    let limiter = config.rate_per_second.map(|rate| {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+        // Workers that fall behind must not catch up in a burst above the rate
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        tokio::sync::Mutex::new(interval)
+    });
+
+    let started = Instant::now();
+    let shared = Arc::new(Shared {
+        recorder: Mutex::new(Recorder::default()),
+        issued: AtomicU64::new(0),
+        completed: AtomicU64::new(0),
+        errors: AtomicU64::new(0),
+        cancel,
+        deadline: config
+            .duration_ms
+            .map(|ms| started + Duration::from_millis(ms)),
+        max_requests: config.requests,
+        limiter,
+    });
+
+    let workers: Vec<_> = (0..config.concurrency.unwrap_or(1).max(1))
+        .map(|_| tokio::spawn(worker(shared.clone(), client.clone(), request.clone())))
+        .collect();
+    let mut all_done = Box::pin(futures::future::join_all(workers));
+
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    ticker.tick().await;
+    let mut last_completed = 0;
+    let mut last_tick = started;
+    loop {
+        tokio::select! {
+            results = &mut all_done => {
+                for result in results {
+                    result.map_err(|e| e.to_string())?;
+                }
+                break;
+            }
+            _ = ticker.tick() => {
+                let completed = shared.completed.load(Ordering::SeqCst);
+                let now = Instant::now();
+                on_event(LoadTestEvent::Progress(LoadTestProgress {
+                    elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+                    completed,
+                    errors: shared.errors.load(Ordering::SeqCst),
+                    requests_per_second: (completed - last_completed) as f64
+                        / (now - last_tick).as_secs_f64(),
+                }));
+                last_completed = completed;
+                last_tick = now;
+            }
+        }
+    }
+
+    let elapsed = started.elapsed();
+    let recorder = shared.recorder.lock().unwrap_or_else(|e| e.into_inner());
+    let completed = shared.completed.load(Ordering::SeqCst);
+    let summary = LoadTestSummary {
+        run_id: run_id.to_string(),
+        completed,
+        errors: shared.errors.load(Ordering::SeqCst),
+        cancelled: shared.cancel.load(Ordering::SeqCst),
+        duration_ms: elapsed.as_secs_f64() * 1000.0,
+        requests_per_second: completed as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        latency: recorder.latency(),
+        histogram: recorder.histogram(),
+        status_codes: recorder.status_codes.clone(),
+        transport_errors: recorder.transport_errors.clone(),
+    };
+
+    on_event(LoadTestEvent::Finished(summary.clone()));
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Response, Server};
+    use std::collections::HashMap;
+    use std::convert::Infallible;
+    use std::net::SocketAddr;
+
+    /// Serves 200 for three requests in four and 500 for the fourth, returning the
+    /// address and the number of requests received
+    async fn serve() -> (SocketAddr, Arc<AtomicU64>) {
+        let hits = Arc::new(AtomicU64::new(0));
+        let counter = hits.clone();
+        let make_service = make_service_fn(move |_| {
+            let counter = counter.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_| {
+                    let hit = counter.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        let status = if hit % 4 == 3 { 500 } else { 200 };
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::from("ok"))
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        (addr, hits)
+    }
+
+    fn get(addr: SocketAddr) -> PreparedRequest {
+        PreparedRequest {
+            method: "GET".to_string(),
+            url: format!("http://{}/ping", addr),
+            headers: HashMap::new(),
+            body: None,
+        }
+    }
+
+    fn config(
+        concurrency: usize,
+        duration_ms: Option<u64>,
+        requests: Option<u64>,
+        rate_per_second: Option<f64>,
+    ) -> LoadTestConfig {
+        LoadTestConfig {
+            workspace_id: "ws".to_string(),
+            environment_id: None,
+            operation_key: "get:/ping".to_string(),
+            concurrency: Some(concurrency),
+            duration_ms,
+            requests,
+            rate_per_second,
+        }
+    }
+
+    async fn run_test(
+        request: PreparedRequest,
+        config: &LoadTestConfig,
+        cancel: Arc<AtomicBool>,
+    ) -> Result<LoadTestSummary, String> {
+        let client = request::build_client()?;
+        run(client, request, "test", config, cancel, &|_| {}).await
+    }
+
+    #[tokio::test]
+    async fn stops_at_the_request_count() {
+        let (addr, hits) = serve().await;
+        let summary = run_test(
+            get(addr),
+            &config(4, None, Some(40), None),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.completed, 40);
+        assert_eq!(hits.load(Ordering::SeqCst), 40);
+        assert_eq!(summary.status_codes[&200], 30);
+        assert_eq!(summary.status_codes[&500], 10);
+        assert_eq!(summary.errors, 10);
+        assert!(!summary.cancelled);
+        assert_eq!(summary.histogram.iter().map(|b| b.count).sum::<u64>(), 40);
+        let latency = &summary.latency;
+        assert!(latency.min_ms <= latency.p50_ms);
+        assert!(latency.p50_ms <= latency.p90_ms);
+        assert!(latency.p90_ms <= latency.p99_ms);
+        assert!(latency.p99_ms <= latency.max_ms);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_caps_requests_over_a_duration() {
+        let (addr, hits) = serve().await;
+        let summary = run_test(
+            get(addr),
+            &config(4, Some(500), None, Some(20.0)),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        // 20 per second for half a second, plus the tick at the start
+        assert!(
+            (5..=12).contains(&summary.completed),
+            "completed {}",
+            summary.completed
+        );
+        assert_eq!(hits.load(Ordering::SeqCst), summary.completed);
+        assert!(summary.duration_ms >= 500.0);
+    }
+
+    #[tokio::test]
+    async fn streams_progress_and_a_final_summary() {
+        let (addr, _) = serve().await;
+        let events = Mutex::new(Vec::new());
+        let client = request::build_client().unwrap();
+        run(
+            client,
+            get(addr),
+            "progress",
+            &config(2, Some(1200), None, Some(50.0)),
+            Default::default(),
+            &|event| events.lock().unwrap().push(event),
+        )
+        .await
+        .unwrap();
+
+        let events = events.into_inner().unwrap();
+        let progress: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                LoadTestEvent::Progress(p) => Some(p),
+                _ => None,
+            })
+            .collect();
+        assert!(progress.len() >= 2);
+        assert!(progress
+            .windows(2)
+            .all(|w| w[0].completed <= w[1].completed));
+        match events.last() {
+            Some(LoadTestEvent::Finished(summary)) => assert_eq!(summary.run_id, "progress"),
+            other => panic!("expected a final summary, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_test_sends_nothing_more() {
+        let (addr, hits) = serve().await;
+        let cancel = Arc::new(AtomicBool::new(true));
+        let summary = run_test(get(addr), &config(4, None, Some(100), None), cancel)
+            .await
+            .unwrap();
+
+        assert_eq!(summary.completed, 0);
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+        assert!(summary.cancelled);
+    }
+
+    #[tokio::test]
+    async fn counts_transport_errors() {
+        // Bind and drop a listener to get a port nothing listens on
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let summary = run_test(
+            get(addr),
+            &config(2, None, Some(6), None),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.completed, 6);
+        assert_eq!(summary.errors, 6);
+        assert!(summary.status_codes.is_empty());
+        assert_eq!(summary.transport_errors.values().sum::<u64>(), 6);
+        assert_eq!(summary.latency.max_ms, 0.0);
+    }
+
+    #[tokio::test]
+    async fn requires_a_stopping_condition() {
+        let result = run_test(
+            get(([127, 0, 0, 1], 9).into()),
+            &config(1, None, None, None),
+            Default::default(),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_rates_too_fast_to_pace() {
+        // 1 / 1e12 seconds rounds to a zero interval, which tokio refuses
+        let result = run_test(
+            get(([127, 0, 0, 1], 9).into()),
+            &config(1, None, Some(1), Some(1e12)),
+            Default::default(),
+        )
+        .await;
+        assert!(result.unwrap_err().contains("above the limit"));
+    }
+
+    #[test]
+    fn validates_the_config() {
+        assert!(config(1, Some(100), None, None).validate().is_ok());
+        assert!(config(1, None, Some(5), Some(2.5)).validate().is_ok());
+        assert_eq!(
+            config(1, None, None, None).validate().unwrap_err(),
+            "Set a duration or a request count for the load test"
+        );
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(config(1, None, Some(5), Some(rate)).validate().is_err());
+        }
+        assert!(config(1, None, Some(5), Some(MAX_RATE_PER_SECOND))
+            .validate()
+            .is_ok());
+        assert_eq!(
+            config(1, None, Some(5), Some(2e9)).validate().unwrap_err(),
+            "Request rate 2000000000 is above the limit of 1000000 per second"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Upper bounds of the latency histogram buckets; slower responses land in a final open bucket
+const BUCKET_BOUNDS_MS: &[f64] = &[
+    1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0,
+];
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramBucket {
+    /// Inclusive upper bound; `None` for the bucket holding everything slower
+    pub le_ms: Option<f64>,
+    pub count: u64,
+}
+
+/// Collects the outcome of every request in a load test
+#[derive(Debug, Default)]
+pub struct Recorder {
+    latencies_ms: Vec<f64>,
+    pub status_codes: BTreeMap<u16, u64>,
+    /// Requests that got no response, counted by error message
+    pub transport_errors: BTreeMap<String, u64>,
+}
+
+impl Recorder {
+    pub fn record_response(&mut self, status: u16, latency_ms: f64) {
+        self.latencies_ms.push(latency_ms);
+        *self.status_codes.entry(status).or_default() += 1;
+    }
+
+    pub fn record_error(&mut self, message: String) {
+        *self.transport_errors.entry(message).or_default() += 1;
+    }
+
+    pub fn latency(&self) -> LatencyStats {
+        let mut sorted = self.latencies_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+        let (Some(min), Some(max)) = (sorted.first(), sorted.last()) else {
+            return LatencyStats::default();
+        };
+        LatencyStats {
+            min_ms: *min,
+            mean_ms: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            p50_ms: percentile(&sorted, 50.0),
+            p90_ms: percentile(&sorted, 90.0),
+            p99_ms: percentile(&sorted, 99.0),
+            max_ms: *max,
+        }
+    }
+
+    pub fn histogram(&self) -> Vec<HistogramBucket> {
+        let mut counts = vec![0u64; BUCKET_BOUNDS_MS.len() + 1];
+        for latency in &self.latencies_ms {
+            let bucket = BUCKET_BOUNDS_MS
+                .iter()
+                .position(|bound| latency <= bound)
+                .unwrap_or(BUCKET_BOUNDS_MS.len());
+            counts[bucket] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(i, count)| HistogramBucket {
+                le_ms: BUCKET_BOUNDS_MS.get(i).copied(),
+                count,
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let mut recorder = Recorder::default();
+        for latency in (1..=100).rev() {
+            recorder.record_response(200, latency as f64);
+        }
+        let stats = recorder.latency();
+        assert_eq!(stats.min_ms, 1.0);
+        assert_eq!(stats.p50_ms, 50.0);
+        assert_eq!(stats.p90_ms, 90.0);
+        assert_eq!(stats.p99_ms, 99.0);
+        assert_eq!(stats.max_ms, 100.0);
+        assert_eq!(stats.mean_ms, 50.5);
+    }
+
+    #[test]
+    fn single_sample_is_every_percentile() {
+        let mut recorder = Recorder::default();
+        recorder.record_response(200, 7.5);
+        let stats = recorder.latency();
+        assert_eq!(stats.p50_ms, 7.5);
+        assert_eq!(stats.p99_ms, 7.5);
+    }
+
+    #[test]
+    fn empty_recorder_has_zeroed_stats() {
+        let recorder = Recorder::default();
+        assert_eq!(recorder.latency().max_ms, 0.0);
+        assert!(recorder.histogram().iter().all(|b| b.count == 0));
+    }
+
+    #[test]
+    fn histogram_buckets_are_inclusive_and_open_ended() {
+        let mut recorder = Recorder::default();
+        for latency in [0.5, 1.0, 1.5, 250.0, 60_000.0] {
+            recorder.record_response(200, latency);
+        }
+        let histogram = recorder.histogram();
+        assert_eq!(histogram[0].le_ms, Some(1.0));
+        assert_eq!(histogram[0].count, 2);
+        assert_eq!(histogram[1].count, 1);
+        assert_eq!(histogram[8].le_ms, Some(500.0));
+        assert_eq!(histogram[8].count, 1);
+        let last = histogram.last().unwrap();
+        assert_eq!(last.le_ms, None);
+        assert_eq!(last.count, 1);
+    }
+
+    #[test]
+    fn counts_status_codes_and_transport_errors() {
+        let mut recorder = Recorder::default();
+        recorder.record_response(200, 1.0);
+        recorder.record_response(200, 1.0);
+        recorder.record_response(503, 1.0);
+        recorder.record_error("connection refused".to_string());
+        assert_eq!(recorder.status_codes[&200], 2);
+        assert_eq!(recorder.status_codes[&503], 1);
+        assert_eq!(recorder.transport_errors["connection refused"], 1);
+    }
+}
@@ -0,0 +1,467 @@
+use crate::spec::index::follow_ref;
+use rand::Rng;
+use serde_json::{json, Map, Value};
+
+/// How deep generation follows nested and recursive schemas before giving up
+const MAX_DEPTH: usize = 8;
+
+/// Generates a plausible value for `schema`, preferring any example or default it declares
+pub fn generate(spec: &Value, schema: &Value) -> Value {
+    generate_at(spec, schema, 0)
+}
+
+fn generate_at(spec: &Value, schema: &Value, depth: usize) -> Value {
+    if depth > MAX_DEPTH {
+        return Value::Null;
+    }
+    let schema = follow_ref(spec, schema);
+    let Some(map) = schema.as_object() else {
+        return Value::Null;
+    };
+
+    for key in ["example", "default", "const"] {
+        if let Some(value) = map.get(key) {
+            return value.clone();
+        }
+    }
+    if let Some(first) = map
+        .get("examples")
+        .and_then(Value::as_array)
+        .and_then(|e| e.first())
+    {
+        return first.clone();
+    }
+    if let Some(first) = map
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|e| e.iter().find(|v| !v.is_null()))
+    {
+        return first.clone();
+    }
+
+    if let Some(parts) = map.get("allOf").and_then(Value::as_array) {
+        let mut merged = Map::new();
+        for part in parts {
+            if let Value::Object(object) = generate_at(spec, part, depth + 1) {
+                merged.extend(object);
+            }
+        }
+        if let Value::Object(own) = generate_object(spec, map, depth) {
+            merged.extend(own);
+        }
+        return Value::Object(merged);
+    }
+    for key in ["oneOf", "anyOf"] {
+        if let Some(first) = map.get(key).and_then(Value::as_array).and_then(|v| {
+            v.iter()
+                .find(|s| follow_ref(spec, s).get("type") != Some(&json!("null")))
+        }) {
+            return generate_at(spec, first, depth + 1);
+        }
+    }
+
+    let schema_type = match map.get("type") {
+        Some(Value::String(t)) => t.as_str(),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|t| *t != "null")
+            .unwrap_or("null"),
+        _ if map.contains_key("properties") => "object",
+        _ if map.contains_key("items") => "array",
+        _ => "string",
+    };
+
+    match schema_type {
+        "object" => generate_object(spec, map, depth),
+        "array" => generate_array(spec, map, depth),
+        "integer" => generate_integer(map),
+        "number" => generate_number(map),
+        "boolean" => Value::Bool(rand::thread_rng().gen()),
+        "null" => Value::Null,
+        _ => generate_string(map),
+    }
+}
+
+fn generate_object(spec: &Value, map: &Map<String, Value>, depth: usize) -> Value {
+    let mut object = Map::new();
+    for (name, property) in map
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        // Responses never carry write-only properties
+        if follow_ref(spec, property).get("writeOnly") == Some(&Value::Bool(true)) {
+            continue;
+        }
+        object.insert(name.clone(), generate_at(spec, property, depth + 1));
+    }
+    Value::Object(object)
+}
+
+fn generate_array(spec: &Value, map: &Map<String, Value>, depth: usize) -> Value {
+    let min = map.get("minItems").and_then(Value::as_u64).unwrap_or(1);
+    let max = map
+        .get("maxItems")
+        .and_then(Value::as_u64)
+        .unwrap_or(min.max(1));
+    let count = min.max(1).min(max) as usize;
+
+    match map.get("items") {
+        // Tuple-style items
+        Some(Value::Array(items)) => Value::Array(
+            items
+                .iter()
+                .map(|item| generate_at(spec, item, depth + 1))
+                .collect(),
+        ),
+        Some(item) => Value::Array(
+            (0..count)
+                .map(|_| generate_at(spec, item, depth + 1))
+                .collect(),
+        ),
+        None => Value::Array(Vec::new()),
+    }
+}
+
+/// Where numbers are generated when a schema leaves a side open
+const DEFAULT_LOW: f64 = 0.0;
+const DEFAULT_HIGH: f64 = 1000.0;
+/// How many multiples of `multipleOf` are tried before settling for one the validator's float
+/// check rejects, e.g. `0.3` for `0.1`
+const MULTIPLE_ATTEMPTS: usize = 16;
+
+/// A limit on a number and whether it's exclusive, from `minimum`/`maximum` and either the
+/// OAS 3.0 boolean or the 3.1 numeric form of `exclusiveMinimum`/`exclusiveMaximum`
+fn limit(
+    map: &Map<String, Value>,
+    key: &str,
+    exclusive_key: &str,
+    tighter: fn(f64, f64) -> bool,
+) -> Option<(f64, bool)> {
+    let inclusive = map.get(key).and_then(Value::as_f64);
+    let exclusive = match map.get(exclusive_key) {
+        Some(Value::Bool(true)) => inclusive,
+        other => other.and_then(Value::as_f64),
+    };
+    match (inclusive, exclusive) {
+        (Some(inclusive), Some(exclusive)) if tighter(inclusive, exclusive) => {
+            Some((inclusive, false))
+        }
+        (_, Some(exclusive)) => Some((exclusive, true)),
+        (inclusive, None) => inclusive.map(|value| (value, false)),
+    }
+}
+
+/// The numbers a schema allows. An open side is filled in from `DEFAULT_LOW..=DEFAULT_HIGH`,
+/// or a window that wide next to the other bound when that bound lies outside it.
+struct NumberRange {
+    low: f64,
+    exclusive_low: bool,
+    high: f64,
+    exclusive_high: bool,
+}
+
+impl NumberRange {
+    fn of(map: &Map<String, Value>) -> Self {
+        let lower = limit(map, "minimum", "exclusiveMinimum", |i, e| i > e);
+        let upper = limit(map, "maximum", "exclusiveMaximum", |i, e| i < e);
+        let (low, exclusive_low) = match (lower, upper) {
+            (Some(lower), _) => lower,
+            (None, Some((high, _))) if high <= DEFAULT_LOW => (high - DEFAULT_HIGH, false),
+            (None, _) => (DEFAULT_LOW, false),
+        };
+        let (high, exclusive_high) = match upper {
+            Some(upper) => upper,
+            None if low >= DEFAULT_HIGH => (low + DEFAULT_HIGH, false),
+            None => (DEFAULT_HIGH, false),
+        };
+        Self {
+            low,
+            exclusive_low,
+            high,
+            exclusive_high,
+        }
+    }
+
+    fn contains(&self, value: f64) -> bool {
+        let above = if self.exclusive_low {
+            value > self.low
+        } else {
+            value >= self.low
+        };
+        let below = if self.exclusive_high {
+            value < self.high
+        } else {
+            value <= self.high
+        };
+        above && below
+    }
+}
+
+fn multiple_of(map: &Map<String, Value>) -> Option<f64> {
+    map.get("multipleOf")
+        .and_then(Value::as_f64)
+        .filter(|m| m.is_finite() && *m > 0.0)
+}
+
+/// Whether `value` passes the validator's float `multipleOf` check
+fn is_multiple(value: f64, multiple: f64) -> bool {
+    (value / multiple) % 1.0 < f64::EPSILON
+}
+
+fn generate_integer(map: &Map<String, Value>) -> Value {
+    let range = NumberRange::of(map);
+    // Fractional multiples are ignored; every integer is a multiple of e.g. 0.5
+    let step = multiple_of(map)
+        .filter(|m| *m >= 1.0 && m.fract() == 0.0)
+        .unwrap_or(1.0);
+    // Bounds beyond i64 saturate when cast
+    let low = if range.exclusive_low {
+        (range.low / step).floor() + 1.0
+    } else {
+        (range.low / step).ceil()
+    } as i64;
+    let high = if range.exclusive_high {
+        (range.high / step).ceil() - 1.0
+    } else {
+        (range.high / step).floor()
+    } as i64;
+    // Contradictory bounds have no valid value; settle for the lowest one
+    let k = if low <= high {
+        rand::thread_rng().gen_range(low..=high)
+    } else {
+        low
+    };
+    let value = (k as i128 * step as i128).clamp(i64::MIN as i128, i64::MAX as i128);
+    Value::from(value as i64)
+}
+
+fn generate_number(map: &Map<String, Value>) -> Value {
+    let range = NumberRange::of(map);
+    let mut rng = rand::thread_rng();
+
+    if let Some(step) = multiple_of(map) {
+        let low = (range.low / step).ceil();
+        let high = (range.high / step).floor();
+        if low <= high {
+            let mut value = range.low;
+            for _ in 0..MULTIPLE_ATTEMPTS {
+                let k = rng.gen_range(low as i64..=high as i64);
+                value = k as f64 * step;
+                if range.contains(value) && is_multiple(value, step) {
+                    break;
+                }
+            }
+            return json!(value);
+        }
+    }
+
+    // Sampling needs a span well within f64; one this wide contains the default window
+    let (low, high) = if range.high - range.low <= f64::MAX / 2.0 {
+        (range.low, range.high)
+    } else {
+        (range.low.max(-DEFAULT_HIGH), range.high.min(DEFAULT_HIGH))
+    };
+    if low >= high {
+        return json!(low);
+    }
+    let value = rng.gen_range(low..=high);
+    let rounded = (value * 100.0).round() / 100.0;
+    json!([rounded, value, low + (high - low) / 2.0]
+        .into_iter()
+        .find(|v| range.contains(*v))
+        .unwrap_or(value))
+}
+
+fn generate_string(map: &Map<String, Value>) -> Value {
+    let format = map
+        .get("format")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let value = match format {
+        "date-time" => "2024-01-01T12:00:00Z".to_string(),
+        "date" => "2024-01-01".to_string(),
+        "time" => "12:00:00".to_string(),
+        "email" => "user@example.com".to_string(),
+        "uuid" => uuid::Uuid::new_v4().to_string(),
+        "uri" | "url" => "https://example.com".to_string(),
+        "hostname" => "example.com".to_string(),
+        "ipv4" => "192.0.2.1".to_string(),
+        "ipv6" => "2001:db8::1".to_string(),
+        "byte" => "ZXhhbXBsZQ==".to_string(),
+        "password" => "********".to_string(),
+        _ => "string".to_string(),
+    };
+
+    let min_length = map.get("minLength").and_then(Value::as_u64).unwrap_or(0) as usize;
+    let max_length = map
+        .get("maxLength")
+        .and_then(Value::as_u64)
+        .map(|m| m as usize);
+    let mut value = value;
+    while value.chars().count() < min_length {
+        value.push('x');
+    }
+    if let Some(max_length) = max_length {
+        value = value.chars().take(max_length).collect();
+    }
+    Value::String(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Value {
+        json!({
+            "components": {
+                "schemas": {
+                    "Node": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string" },
+                            "child": { "$ref": "#/components/schemas/Node" }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    /// Generates from `schema` repeatedly and checks every value against it
+    fn assert_valid_numbers(schema: Value) {
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        for _ in 0..200 {
+            let value = generate(&spec(), &schema);
+            assert!(value.is_number(), "{}", value);
+            assert!(validator.is_valid(&value), "{} against {}", value, schema);
+        }
+    }
+
+    #[test]
+    fn numbers_stay_within_their_bounds() {
+        assert_valid_numbers(json!({ "type": "integer", "minimum": 5, "maximum": 7 }));
+        assert_valid_numbers(json!({ "type": "number", "minimum": -1.5, "maximum": -1.0 }));
+        assert_valid_numbers(json!({ "type": "number", "minimum": 0.001, "maximum": 0.002 }));
+        assert_valid_numbers(json!({ "type": "integer", "maximum": -50 }));
+        assert_valid_numbers(json!({ "type": "number", "minimum": -1e308, "maximum": 1e308 }));
+        assert_valid_numbers(json!({ "type": "number", "minimum": -f64::MAX }));
+        assert_valid_numbers(json!({ "type": "integer", "minimum": -1e30, "maximum": 1e30 }));
+
+        assert_valid_numbers(json!({ "type": "integer", "minimum": 2000 }));
+        let pinned = json!({ "type": "integer", "minimum": 2000, "maximum": 2000 });
+        assert_eq!(generate(&spec(), &pinned), json!(2000));
+    }
+
+    #[test]
+    fn honours_exclusive_bounds() {
+        // The 3.1 numeric form
+        let between = json!({ "type": "integer", "exclusiveMinimum": 4, "exclusiveMaximum": 6 });
+        assert_valid_numbers(between.clone());
+        assert_eq!(generate(&spec(), &between), json!(5));
+        assert_valid_numbers(
+            json!({ "type": "number", "exclusiveMinimum": 0, "exclusiveMaximum": 0.01 }),
+        );
+        assert_valid_numbers(json!({ "type": "integer", "exclusiveMinimum": 2000 }));
+        assert_valid_numbers(json!({ "type": "integer", "exclusiveMaximum": 0 }));
+
+        // The OAS 3.0 boolean form, which the validator doesn't read
+        let schema = json!({
+            "type": "integer",
+            "minimum": 4,
+            "exclusiveMinimum": true,
+            "maximum": 6,
+            "exclusiveMaximum": true
+        });
+        assert_eq!(generate(&spec(), &schema), json!(5));
+        let schema = json!({
+            "type": "number",
+            "minimum": 0,
+            "exclusiveMinimum": true,
+            "maximum": 0.01
+        });
+        for _ in 0..200 {
+            let value = generate(&spec(), &schema).as_f64().unwrap();
+            assert!(value > 0.0 && value <= 0.01, "{}", value);
+        }
+    }
+
+    #[test]
+    fn honours_multiple_of() {
+        assert_valid_numbers(
+            json!({ "type": "integer", "multipleOf": 5, "minimum": 1, "maximum": 99 }),
+        );
+        assert_valid_numbers(json!({ "type": "integer", "multipleOf": 7, "exclusiveMaximum": 0 }));
+        assert_valid_numbers(
+            json!({ "type": "integer", "multipleOf": 0.5, "minimum": 1, "maximum": 3 }),
+        );
+        assert_valid_numbers(
+            json!({ "type": "number", "multipleOf": 0.25, "minimum": 1, "maximum": 2 }),
+        );
+        assert_valid_numbers(json!({ "type": "number", "multipleOf": 0.01, "maximum": 5 }));
+        assert_valid_numbers(
+            json!({ "type": "number", "multipleOf": 3, "exclusiveMinimum": 3, "maximum": 9 }),
+        );
+    }
+
+    #[test]
+    fn strings_and_arrays_respect_their_lengths() {
+        let short = json!({ "type": "string", "maxLength": 3 });
+        let long = json!({ "type": "string", "format": "email", "minLength": 20 });
+        let array = json!({
+            "type": "array",
+            "minItems": 3,
+            "maxItems": 5,
+            "items": { "type": "boolean" }
+        });
+        let capped = json!({ "type": "array", "maxItems": 0, "items": { "type": "string" } });
+
+        assert_eq!(generate(&spec(), &short), json!("str"));
+        let email = generate(&spec(), &long);
+        let email = email.as_str().unwrap();
+        assert!(email.starts_with("user@example.com"));
+        assert_eq!(email.len(), 20);
+        assert_eq!(generate(&spec(), &array).as_array().unwrap().len(), 3);
+        assert_eq!(generate(&spec(), &capped), json!([]));
+    }
+
+    #[test]
+    fn prefers_declared_values() {
+        let example = json!({ "type": "integer", "example": 42, "default": 1 });
+        let enumerated = json!({ "type": "string", "enum": [null, "cat", "dog"] });
+        let nullable = json!({ "oneOf": [{ "type": "null" }, { "type": "string", "const": "x" }] });
+
+        assert_eq!(generate(&spec(), &example), json!(42));
+        assert_eq!(generate(&spec(), &enumerated), json!("cat"));
+        assert_eq!(generate(&spec(), &nullable), json!("x"));
+    }
+
+    #[test]
+    fn skips_write_only_properties_and_stops_recursing() {
+        let schema = json!({
+            "allOf": [
+                { "$ref": "#/components/schemas/Node" },
+                {
+                    "type": "object",
+                    "properties": {
+                        "password": { "type": "string", "writeOnly": true },
+                        "id": { "type": "integer", "minimum": 1, "maximum": 1 }
+                    }
+                }
+            ]
+        });
+        let value = generate(&spec(), &schema);
+
+        assert_eq!(value["id"], json!(1));
+        assert!(value.get("password").is_none());
+        let mut depth = 0;
+        let mut node = &value;
+        while let Some(child) = node.get("child").filter(|c| !c.is_null()) {
+            node = child;
+            depth += 1;
+        }
+        assert!(depth <= MAX_DEPTH, "{}", depth);
+    }
+}
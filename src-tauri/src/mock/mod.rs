@@ -0,0 +1,9 @@
+pub mod generator;
+pub mod replay;
+pub mod router;
+pub mod server;
+pub mod spec_mock;
+
+pub use replay::{ReplayConfig, ReplayMock};
+pub use server::{MockLogEntry, MockServerInfo, MockServerManager};
+pub use spec_mock::SpecMock;
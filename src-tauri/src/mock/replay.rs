@@ -0,0 +1,710 @@
+use super::router::{RouteResult, Router};
+use super::server::{IncomingRequest, MockHandler, MockReply};
+use crate::history::snapshot::{self, Redactions, RequestSnapshot};
+use crate::history::store;
+use crate::network::request;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Headers that describe the wire encoding of the original response rather than its body
+const HOP_HEADERS: &[&str] = &[
+    "content-encoding",
+    "content-length",
+    "transfer-encoding",
+    "connection",
+];
+
+/// How many recordings per operation are considered when matching
+const CANDIDATE_LIMIT: i64 = 200;
+
+/// `id, response_json, response_blob, request_json, request_snapshot`
+type RecordingRow = (i64, String, Option<Vec<u8>>, Option<String>, Option<String>);
+
+fn body_hash(body: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(body))
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayConfig {
+    pub port: Option<u16>,
+    /// Compare query strings when matching; on by default
+    pub match_query: Option<bool>,
+    /// Request headers whose values must match; headers are ignored when empty
+    #[serde(default)]
+    pub match_headers: Vec<String>,
+    /// Compare a SHA-256 hash of the request body when matching
+    #[serde(default)]
+    pub match_body: bool,
+    /// Delay replies by the recorded total time; on by default
+    pub simulate_latency: Option<bool>,
+    /// Real API to proxy to when nothing matches
+    pub upstream_url: Option<String>,
+    /// Store proxied responses in `response_history` so they replay next time; on by default
+    pub record: Option<bool>,
+}
+
+/// What is stored in `response_history.request_json` to match requests on replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    headers: HashMap<String, String>,
+    body_hash: Option<String>,
+}
+
+impl RecordedRequest {
+    /// Secret values in query parameters and headers are swapped for their placeholders,
+    /// on recording and on lookup alike, so matching still compares like with like
+    fn from_incoming(request: &IncomingRequest, redactions: &Redactions) -> Self {
+        let mut query: Vec<(String, String)> = request
+            .query
+            .iter()
+            .map(|(k, v)| (k.clone(), redactions.apply(v)))
+            .collect();
+        query.sort();
+        Self {
+            method: request.method.clone(),
+            path: request.path.clone(),
+            query,
+            headers: request
+                .headers
+                .iter()
+                .filter(|(k, _)| !snapshot::is_sensitive_header(k))
+                .map(|(k, v)| (k.clone(), redactions.apply(v)))
+                .collect(),
+            body_hash: (!request.body.is_empty()).then(|| body_hash(&request.body)),
+        }
+    }
+}
+
+/// A response from the upstream, with its body kept as the bytes that were sent
+struct Proxied {
+    status: u16,
+    status_text: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+    wire_size_bytes: usize,
+    ttfb_ms: f64,
+    download_ms: f64,
+    total_ms: f64,
+}
+
+/// Serves responses recorded in `response_history`, falling through to an upstream on misses
+pub struct ReplayMock {
+    pool: SqlitePool,
+    workspace_id: String,
+    router: Option<Router>,
+    redactions: Redactions,
+    config: ReplayConfig,
+    client: reqwest::Client,
+}
+
+impl ReplayMock {
+    /// `spec`, when available, maps concrete paths to the templated operation keys the
+    /// frontend records history under. `redactions` holds the workspace's secrets, which
+    /// are kept out of recorded requests.
+    pub fn new(
+        pool: SqlitePool,
+        workspace_id: String,
+        spec: Option<&Value>,
+        redactions: Redactions,
+        config: ReplayConfig,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            pool,
+            workspace_id,
+            router: spec.map(Router::new),
+            redactions,
+            config,
+            client: request::build_client()?,
+        })
+    }
+
+    fn operation_key(&self, request: &IncomingRequest) -> (String, bool) {
+        if let Some(router) = &self.router {
+            if let RouteResult::Matched(matched) = router.find(&request.method, &request.path) {
+                return (matched.operation_key, true);
+            }
+        }
+        (
+            format!("{}:{}", request.method.to_lowercase(), request.path),
+            false,
+        )
+    }
+
+    fn matches(&self, recorded: &RecordedRequest, incoming: &RecordedRequest) -> bool {
+        if recorded.method != incoming.method || recorded.path != incoming.path {
+            return false;
+        }
+        if self.config.match_query.unwrap_or(true) && recorded.query != incoming.query {
+            return false;
+        }
+        if self.config.match_body && recorded.body_hash != incoming.body_hash {
+            return false;
+        }
+        self.config.match_headers.iter().all(|name| {
+            let name = name.to_lowercase();
+            recorded.headers.get(&name) == incoming.headers.get(&name)
+        })
+    }
+
+    /// Matches a request builder snapshot, which keeps the full URL that was sent. The
+    /// incoming path only has to end that URL's path, so a base path on the recorded
+    /// server still matches.
+    fn matches_snapshot(&self, snapshot: &RequestSnapshot, incoming: &RecordedRequest) -> bool {
+        let Ok(url) = Url::parse(&snapshot.url) else {
+            return false;
+        };
+        if !snapshot.method.eq_ignore_ascii_case(&incoming.method)
+            || !url.path().ends_with(&incoming.path)
+        {
+            return false;
+        }
+        if self.config.match_query.unwrap_or(true) {
+            let mut query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+            query.sort();
+            if query != incoming.query {
+                return false;
+            }
+        }
+        // Snapshot bodies are redacted, so a body holding a secret never matches
+        let recorded_hash = snapshot
+            .body
+            .as_deref()
+            .filter(|body| !body.is_empty())
+            .map(|body| body_hash(body.as_bytes()));
+        if self.config.match_body && recorded_hash != incoming.body_hash {
+            return false;
+        }
+        self.config.match_headers.iter().all(|name| {
+            let recorded = snapshot
+                .headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value);
+            recorded == incoming.headers.get(&name.to_lowercase())
+        })
+    }
+
+    /// Whether a row answered `incoming`, or `None` when the row kept nothing to compare
+    fn row_matches(
+        &self,
+        request_json: Option<&str>,
+        request_snapshot: Option<&str>,
+        incoming: &RecordedRequest,
+    ) -> Result<Option<bool>, String> {
+        let matched = match (request_json, request_snapshot) {
+            (Some(json), _) => {
+                let recorded: RecordedRequest =
+                    serde_json::from_str(json).map_err(|e| e.to_string())?;
+                self.matches(&recorded, incoming)
+            }
+            (None, Some(json)) => {
+                let snapshot: RequestSnapshot =
+                    serde_json::from_str(json).map_err(|e| e.to_string())?;
+                self.matches_snapshot(&snapshot, incoming)
+            }
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(matched))
+    }
+
+    /// Finds the newest matching recording. Rows that kept neither a fingerprint nor a
+    /// snapshot match on the operation alone, after exact matches. Rows that can't be read
+    /// are skipped rather than failing every lookup.
+    async fn find_recording(
+        &self,
+        operation_key: &str,
+        templated: bool,
+        incoming: &RecordedRequest,
+    ) -> Result<Option<Value>, String> {
+        let rows: Vec<RecordingRow> = sqlx::query_as(
+            "SELECT id, response_json, response_blob, request_json, request_snapshot
+             FROM response_history
+             WHERE workspace_id = ? AND operation_key = ?
+             ORDER BY timestamp DESC LIMIT ?",
+        )
+        .bind(&self.workspace_id)
+        .bind(operation_key)
+        .bind(CANDIDATE_LIMIT)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        let mut fallback = None;
+        for (id, response_json, response_blob, request_json, request_snapshot) in rows {
+            let matched = match self.row_matches(
+                request_json.as_deref(),
+                request_snapshot.as_deref(),
+                incoming,
+            ) {
+                Ok(matched) => matched,
+                Err(e) => {
+                    eprintln!("Skipping unreadable recording {}: {}", id, e);
+                    continue;
+                }
+            };
+            let wanted = match matched {
+                Some(matched) => matched,
+                None => templated && fallback.is_none(),
+            };
+            if !wanted {
+                continue;
+            }
+            let response = store::response_json(response_json, response_blob)
+                .and_then(|json| serde_json::from_str::<Value>(&json).map_err(|e| e.to_string()));
+            match (response, matched) {
+                (Ok(response), Some(true)) => return Ok(Some(response)),
+                (Ok(response), _) => fallback = Some(response),
+                (Err(e), _) => eprintln!("Skipping unreadable recording {}: {}", id, e),
+            }
+        }
+        Ok(fallback)
+    }
+
+    fn replay(&self, recorded: &Value) -> MockReply {
+        let status = recorded
+            .get("status")
+            .and_then(Value::as_u64)
+            .unwrap_or(200) as u16;
+        let headers = recorded
+            .get("headers")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .filter(|(name, _)| !HOP_HEADERS.contains(&name.to_lowercase().as_str()))
+            .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+            .collect();
+        // Bodies that aren't valid UTF-8 are recorded as base64 next to a lossy `bodyText`
+        let body = match recorded.get("bodyBase64").and_then(Value::as_str) {
+            Some(encoded) => BASE64.decode(encoded).unwrap_or_default(),
+            None => recorded
+                .get("bodyText")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .as_bytes()
+                .to_vec(),
+        };
+
+        // The request builder records camelCase timings
+        let total_ms = recorded
+            .pointer("/timings/totalMs")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0);
+        let delay = (self.config.simulate_latency.unwrap_or(true) && total_ms > 0.0)
+            .then(|| Duration::from_secs_f64(total_ms / 1000.0));
+
+        MockReply {
+            status,
+            headers,
+            body,
+            operation_key: None,
+            warnings: Vec::new(),
+            delay,
+        }
+    }
+
+    /// Forwards a request to the upstream byte for byte
+    async fn proxy(&self, upstream: &str, request: &IncomingRequest) -> Result<Proxied, String> {
+        let started = Instant::now();
+        let mut url = format!("{}{}", upstream.trim_end_matches('/'), request.path);
+        if !request.query.is_empty() {
+            let query = url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&request.query)
+                .finish();
+            url.push('?');
+            url.push_str(&query);
+        }
+        let method =
+            reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| e.to_string())?;
+
+        let mut builder = self.client.request(method, &url);
+        for (name, value) in &request.headers {
+            if name != "host" && !HOP_HEADERS.contains(&name.as_str()) {
+                builder = builder.header(name, value);
+            }
+        }
+        if !request.body.is_empty() {
+            builder = builder.body(request.body.clone());
+        }
+
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach {}: {}", upstream, e))?;
+        let ttfb_ms = started.elapsed().as_secs_f64() * 1000.0;
+        let status = response.status();
+        let headers = request::collect_headers(&response);
+
+        let downloading = Instant::now();
+        let wire = response.bytes().await.map_err(|e| e.to_string())?;
+        let download_ms = downloading.elapsed().as_secs_f64() * 1000.0;
+        let body =
+            request::decode_body(headers.get("content-encoding").map(String::as_str), &wire)?;
+
+        Ok(Proxied {
+            status: status.as_u16(),
+            status_text: status.canonical_reason().unwrap_or("").to_string(),
+            headers,
+            body,
+            wire_size_bytes: wire.len(),
+            ttfb_ms,
+            download_ms,
+            total_ms: started.elapsed().as_secs_f64() * 1000.0,
+        })
+    }
+
+    /// Stores a proxied response in the same shape the request builder records
+    async fn record(
+        &self,
+        operation_key: &str,
+        incoming: &RecordedRequest,
+        response: &Proxied,
+    ) -> Result<(), String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default();
+        let body_text = String::from_utf8_lossy(&response.body);
+        let mut response_json = json!({
+            "status": response.status,
+            "statusText": response.status_text,
+            "headers": response.headers,
+            "bodyText": body_text,
+            "bodyJson": serde_json::from_str::<Value>(&body_text).ok(),
+            "timestamp": timestamp,
+            "timings": {
+                "prepareMs": 0.0,
+                "dnsLookupMs": 0.0,
+                "tcpConnectMs": 0.0,
+                "tlsHandshakeMs": 0.0,
+                "ttfbMs": response.ttfb_ms,
+                "downloadMs": response.download_ms,
+                "processMs": 0.0,
+                "totalMs": response.total_ms,
+            },
+            "wireSizeBytes": response.wire_size_bytes,
+            "bodySizeBytes": response.body.len(),
+        });
+        if std::str::from_utf8(&response.body).is_err() {
+            response_json["bodyBase64"] = json!(BASE64.encode(&response.body));
+        }
+        let request_json = serde_json::to_string(incoming).map_err(|e| e.to_string())?;
+
+        store::insert(
+            &self.pool,
+            &self.workspace_id,
+            operation_key,
+            &response_json.to_string(),
+            Some(&request_json),
+            None,
+            timestamp,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl MockHandler for ReplayMock {
+    async fn respond(&self, request: &IncomingRequest) -> MockReply {
+        let (operation_key, templated) = self.operation_key(request);
+        let fingerprint = RecordedRequest::from_incoming(request, &self.redactions);
+
+        let recording = match self
+            .find_recording(&operation_key, templated, &fingerprint)
+            .await
+        {
+            Ok(recording) => recording,
+            Err(e) => return MockReply::json(500, &json!({ "message": e })),
+        };
+
+        let mut reply = match (recording, &self.config.upstream_url) {
+            (Some(recorded), _) => self.replay(&recorded),
+            (None, Some(upstream)) => match self.proxy(upstream, request).await {
+                Ok(response) => {
+                    if self.config.record.unwrap_or(true) {
+                        if let Err(e) = self.record(&operation_key, &fingerprint, &response).await {
+                            return MockReply::json(500, &json!({ "message": e }));
+                        }
+                    }
+                    MockReply {
+                        status: response.status,
+                        headers: response
+                            .headers
+                            .into_iter()
+                            .filter(|(k, _)| !HOP_HEADERS.contains(&k.as_str()))
+                            .collect(),
+                        body: response.body,
+                        operation_key: None,
+                        warnings: Vec::new(),
+                        delay: None,
+                    }
+                }
+                Err(e) => MockReply::json(502, &json!({ "message": e })),
+            },
+            (None, None) => MockReply::json(
+                404,
+                &json!({
+                    "message": format!("No recorded response matches {} {}", request.method, request.path)
+                }),
+            ),
+        };
+        reply.operation_key = Some(operation_key);
+        reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::templating::WorkspaceContext;
+
+    fn redactions() -> Redactions {
+        let context = WorkspaceContext {
+            base_url: String::new(),
+            environment_id: None,
+            spec: None,
+            variables: HashMap::from([("token".to_string(), "s3cr3t-token".to_string())]),
+            secret_variables: ["token".to_string()].into(),
+            global_headers: HashMap::new(),
+            auth_values: HashMap::from([(
+                "apiKey".to_string(),
+                HashMap::from([("value".to_string(), "k3y-value".to_string())]),
+            )]),
+            locked_variables: Default::default(),
+            locked_auth: Default::default(),
+        };
+        Redactions::new(&context)
+    }
+
+    #[test]
+    fn recorded_requests_keep_credentials_out() {
+        let incoming = IncomingRequest {
+            method: "GET".to_string(),
+            path: "/pets".to_string(),
+            query: vec![("key".to_string(), "k3y-value".to_string())],
+            headers: HashMap::from([
+                (
+                    "authorization".to_string(),
+                    "Bearer s3cr3t-token".to_string(),
+                ),
+                ("x-api-key".to_string(), "k3y-value".to_string()),
+                ("x-trace".to_string(), "from s3cr3t-token".to_string()),
+                ("accept".to_string(), "application/json".to_string()),
+            ]),
+            body: Vec::new(),
+        };
+
+        let recorded = RecordedRequest::from_incoming(&incoming, &redactions());
+        assert_eq!(
+            recorded.query,
+            vec![("key".to_string(), "{{auth:apiKey.value}}".to_string())]
+        );
+        assert_eq!(
+            recorded.headers,
+            HashMap::from([
+                ("x-trace".to_string(), "from {{token}}".to_string()),
+                ("accept".to_string(), "application/json".to_string()),
+            ])
+        );
+        assert_eq!(recorded.body_hash, None);
+    }
+
+    #[tokio::test]
+    async fn binary_bodies_replay_byte_for_byte() {
+        let db = Database::open_in_memory().await.unwrap();
+        repository::create_workspace(db.pool(), "w1", "Pets", 0)
+            .await
+            .unwrap();
+        let config: ReplayConfig = serde_json::from_value(json!({})).unwrap();
+        let mock = ReplayMock::new(
+            db.pool().clone(),
+            "w1".to_string(),
+            None,
+            redactions(),
+            config,
+        )
+        .unwrap();
+
+        let body = vec![0x89, b'P', b'N', b'G', 0xff, 0x00];
+        let proxied = Proxied {
+            status: 200,
+            status_text: "OK".to_string(),
+            headers: HashMap::from([("content-type".to_string(), "image/png".to_string())]),
+            body: body.clone(),
+            wire_size_bytes: body.len(),
+            ttfb_ms: 0.0,
+            download_ms: 0.0,
+            total_ms: 0.0,
+        };
+        let incoming = IncomingRequest {
+            method: "GET".to_string(),
+            path: "/logo".to_string(),
+            query: Vec::new(),
+            headers: HashMap::new(),
+            body: Vec::new(),
+        };
+        let fingerprint = RecordedRequest::from_incoming(&incoming, &mock.redactions);
+        mock.record("get:/logo", &fingerprint, &proxied)
+            .await
+            .unwrap();
+
+        let recorded = mock
+            .find_recording("get:/logo", false, &fingerprint)
+            .await
+            .unwrap()
+            .unwrap();
+        let reply = mock.replay(&recorded);
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.body, body);
+    }
+
+    fn get(path: &str, query: &[(&str, &str)]) -> IncomingRequest {
+        IncomingRequest {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: query
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            headers: HashMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn snapshot(url: &str) -> RequestSnapshot {
+        RequestSnapshot {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: HashMap::new(),
+            body: None,
+            environment_id: None,
+            auth_headers: Vec::new(),
+        }
+    }
+
+    /// A replay mock over a spec with a templated route, and a helper to look up what it
+    /// would replay for a request
+    struct Fixture {
+        db: Database,
+        mock: ReplayMock,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let db = Database::open_in_memory().await.unwrap();
+            repository::create_workspace(db.pool(), "w1", "Pets", 0)
+                .await
+                .unwrap();
+            let spec = json!({
+                "openapi": "3.0.3",
+                "paths": { "/pets/{petId}": { "get": {} } }
+            });
+            let config: ReplayConfig = serde_json::from_value(json!({})).unwrap();
+            let mock = ReplayMock::new(
+                db.pool().clone(),
+                "w1".to_string(),
+                Some(&spec),
+                redactions(),
+                config,
+            )
+            .unwrap();
+            Self { db, mock }
+        }
+
+        async fn store(
+            &self,
+            body: &str,
+            request_json: Option<&str>,
+            snapshot: Option<&RequestSnapshot>,
+            timestamp: i64,
+        ) {
+            let response = json!({ "status": 200, "headers": {}, "bodyText": body });
+            store::insert(
+                self.db.pool(),
+                "w1",
+                "get:/pets/{petId}",
+                &response.to_string(),
+                request_json,
+                snapshot,
+                timestamp,
+            )
+            .await
+            .unwrap();
+        }
+
+        async fn replayed(&self, request: IncomingRequest) -> Option<String> {
+            let (operation_key, templated) = self.mock.operation_key(&request);
+            let fingerprint = RecordedRequest::from_incoming(&request, &self.mock.redactions);
+            self.mock
+                .find_recording(&operation_key, templated, &fingerprint)
+                .await
+                .unwrap()
+                .map(|recorded| recorded["bodyText"].as_str().unwrap().to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn builder_recordings_match_on_method_and_url() {
+        let fixture = Fixture::new().await;
+        let one = snapshot("https://api.test/v1/pets/1?verbose=true&key={{auth:apiKey.value}}");
+        fixture.store("one", None, Some(&one), 1).await;
+        fixture
+            .store(
+                "two",
+                None,
+                Some(&snapshot("https://api.test/v1/pets/2")),
+                2,
+            )
+            .await;
+
+        assert_eq!(
+            fixture.replayed(get("/pets/2", &[])).await.as_deref(),
+            Some("two")
+        );
+        let query = [("key", "k3y-value"), ("verbose", "true")];
+        assert_eq!(
+            fixture.replayed(get("/pets/1", &query)).await.as_deref(),
+            Some("one")
+        );
+        assert_eq!(fixture.replayed(get("/pets/1", &[])).await, None);
+        assert_eq!(fixture.replayed(get("/pets/3", &[])).await, None);
+    }
+
+    #[tokio::test]
+    async fn unreadable_recordings_are_skipped() {
+        let fixture = Fixture::new().await;
+        let fingerprint = RecordedRequest::from_incoming(&get("/pets/1", &[]), &redactions());
+        let fingerprint = serde_json::to_string(&fingerprint).unwrap();
+        fixture.store("good", Some(&fingerprint), None, 1).await;
+        fixture.store("legacy", None, None, 2).await;
+        fixture.store("bad json", Some("{not json"), None, 3).await;
+        fixture.store("bad snapshot", None, None, 4).await;
+        sqlx::query("UPDATE response_history SET request_snapshot = '[]' WHERE response_json LIKE '%bad snapshot%'")
+            .execute(fixture.db.pool())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixture.replayed(get("/pets/1", &[])).await.as_deref(),
+            Some("good")
+        );
+        // Without an exact match, the newest row that kept nothing to compare is replayed
+        assert_eq!(
+            fixture.replayed(get("/pets/9", &[])).await.as_deref(),
+            Some("legacy")
+        );
+    }
+}
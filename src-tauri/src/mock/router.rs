@@ -0,0 +1,194 @@
+use crate::spec::index::{follow_ref, HTTP_METHODS};
+use percent_encoding::percent_decode_str;
+use serde_json::{Map, Value};
+
+/// One templated path from the spec, split into segments
+struct Route {
+    path: String,
+    segments: Vec<String>,
+    methods: Vec<String>,
+    /// Literal segments count, used to prefer `/pets/mine` over `/pets/{id}`
+    literal_count: usize,
+}
+
+/// The operation an incoming request resolved to
+pub struct RouteMatch {
+    pub operation_key: String,
+    pub path_params: Map<String, Value>,
+}
+
+pub enum RouteResult {
+    Matched(RouteMatch),
+    /// The path exists but not for this method
+    MethodNotAllowed(Vec<String>),
+    NotFound,
+}
+
+/// Matches incoming request paths against the spec's path templates
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new(spec: &Value) -> Self {
+        let mut routes: Vec<Route> = spec
+            .get("paths")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+            .map(|(path, item)| {
+                let item = follow_ref(spec, item);
+                let segments: Vec<String> = split_path(path).map(str::to_string).collect();
+                Route {
+                    path: path.clone(),
+                    literal_count: segments.iter().filter(|s| !s.contains('{')).count(),
+                    segments,
+                    methods: HTTP_METHODS
+                        .iter()
+                        .filter(|m| item.get(**m).is_some())
+                        .map(|m| m.to_string())
+                        .collect(),
+                }
+            })
+            .collect();
+        routes.sort_by_key(|route| std::cmp::Reverse(route.literal_count));
+        Self { routes }
+    }
+
+    /// Resolves a request path, stripped of any query string, to an operation
+    pub fn find(&self, method: &str, path: &str) -> RouteResult {
+        let method = method.to_lowercase();
+        let segments: Vec<&str> = split_path(path).collect();
+        let mut allowed: Option<&Vec<String>> = None;
+
+        for route in &self.routes {
+            let Some(path_params) = match_segments(&route.segments, &segments) else {
+                continue;
+            };
+            if route.methods.contains(&method) {
+                return RouteResult::Matched(RouteMatch {
+                    operation_key: format!("{}:{}", method, route.path),
+                    path_params,
+                });
+            }
+            allowed.get_or_insert(&route.methods);
+        }
+
+        match allowed {
+            Some(methods) => RouteResult::MethodNotAllowed(methods.clone()),
+            None => RouteResult::NotFound,
+        }
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+fn match_segments(template: &[String], actual: &[&str]) -> Option<Map<String, Value>> {
+    if template.len() != actual.len() {
+        return None;
+    }
+    let mut params = Map::new();
+    for (pattern, value) in template.iter().zip(actual) {
+        let value = percent_decode_str(value).decode_utf8_lossy();
+        match (pattern.find('{'), pattern.rfind('}')) {
+            (Some(start), Some(end)) if start < end => {
+                // Templates may carry a literal prefix or suffix, e.g. `{id}.json`
+                let prefix = &pattern[..start];
+                let suffix = &pattern[end + 1..];
+                let inner = value.strip_prefix(prefix)?.strip_suffix(suffix)?;
+                if inner.is_empty() {
+                    return None;
+                }
+                params.insert(
+                    pattern[start + 1..end].to_string(),
+                    Value::String(inner.to_string()),
+                );
+            }
+            _ if *pattern == value => {}
+            _ => return None,
+        }
+    }
+    Some(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn router() -> Router {
+        // Templates come first so precedence can't depend on document order
+        Router::new(&json!({
+            "paths": {
+                "/pets/{petId}": { "get": {}, "delete": {} },
+                "/pets/mine": { "get": {} },
+                "/files/{name}.json": { "get": {} },
+                "/pets": { "get": {}, "post": {} }
+            }
+        }))
+    }
+
+    fn matched(result: RouteResult) -> RouteMatch {
+        match result {
+            RouteResult::Matched(matched) => matched,
+            RouteResult::MethodNotAllowed(methods) => panic!("method not allowed: {:?}", methods),
+            RouteResult::NotFound => panic!("not found"),
+        }
+    }
+
+    #[test]
+    fn matches_path_templates_and_decodes_params() {
+        let router = router();
+
+        let found = matched(router.find("GET", "/pets/42"));
+        assert_eq!(found.operation_key, "get:/pets/{petId}");
+        assert_eq!(found.path_params["petId"], json!("42"));
+
+        let found = matched(router.find("delete", "/pets/big%20dog/"));
+        assert_eq!(found.operation_key, "delete:/pets/{petId}");
+        assert_eq!(found.path_params["petId"], json!("big dog"));
+
+        let found = matched(router.find("GET", "/files/report.json"));
+        assert_eq!(found.operation_key, "get:/files/{name}.json");
+        assert_eq!(found.path_params["name"], json!("report"));
+
+        assert!(matches!(
+            router.find("GET", "/files/.json"),
+            RouteResult::NotFound
+        ));
+        assert!(matches!(
+            router.find("GET", "/pets/1/toys"),
+            RouteResult::NotFound
+        ));
+    }
+
+    #[test]
+    fn prefers_literal_segments_over_templates() {
+        let router = router();
+
+        let found = matched(router.find("GET", "/pets/mine"));
+        assert_eq!(found.operation_key, "get:/pets/mine");
+        assert!(found.path_params.is_empty());
+
+        // Only the template handles DELETE, so it still catches `/pets/mine`
+        let found = matched(router.find("DELETE", "/pets/mine"));
+        assert_eq!(found.operation_key, "delete:/pets/{petId}");
+        assert_eq!(found.path_params["petId"], json!("mine"));
+    }
+
+    #[test]
+    fn reports_the_allowed_methods_of_a_known_path() {
+        let router = router();
+
+        match router.find("PUT", "/pets") {
+            RouteResult::MethodNotAllowed(methods) => assert_eq!(methods, ["get", "post"]),
+            _ => panic!("expected method not allowed"),
+        }
+        match router.find("POST", "/pets/7") {
+            RouteResult::MethodNotAllowed(methods) => assert_eq!(methods, ["get", "delete"]),
+            _ => panic!("expected method not allowed"),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use crate::network::session::SessionEvent;
+use crate::validation::Violation;
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+/// How many requests each server keeps in its log
+const LOG_CAPACITY: usize = 500;
+
+/// A request received by a mock server
+pub struct IncomingRequest {
+    /// Uppercase HTTP method
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    /// Header names are lowercase
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl IncomingRequest {
+    pub fn body_text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+/// What a mock handler answers with
+pub struct MockReply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// The operation the request was matched to, if any
+    pub operation_key: Option<String>,
+    pub warnings: Vec<Violation>,
+    /// Simulated latency before the reply is sent
+    pub delay: Option<Duration>,
+}
+
+impl MockReply {
+    pub fn json(status: u16, body: &serde_json::Value) -> Self {
+        Self {
+            status,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: body.to_string().into_bytes(),
+            operation_key: None,
+            warnings: Vec::new(),
+            delay: None,
+        }
+    }
+}
+
+/// Decides how a mock server answers each request
+#[async_trait::async_trait]
+pub trait MockHandler: Send + Sync {
+    async fn respond(&self, request: &IncomingRequest) -> MockReply;
+}
+
+/// One request served by a mock server, as shown in its log
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockLogEntry {
+    pub timestamp: u64,
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    pub status: u16,
+    pub operation_key: Option<String>,
+    pub warnings: Vec<Violation>,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServerInfo {
+    pub id: String,
+    pub port: u16,
+    pub url: String,
+}
+
+struct RunningServer {
+    shutdown: oneshot::Sender<()>,
+    log: Arc<std::sync::Mutex<VecDeque<MockLogEntry>>>,
+}
+
+pub struct MockServerManager {
+    servers: Mutex<HashMap<String, RunningServer>>,
+}
+
+impl Default for MockServerManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockServerManager {
+    pub fn new() -> Self {
+        Self {
+            servers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Binds `127.0.0.1:port` (any free port when `None`) and serves requests with
+    /// `handler`, reporting lifecycle and request events on `event_tx`
+    pub async fn start(
+        &self,
+        id: String,
+        port: Option<u16>,
+        handler: Arc<dyn MockHandler>,
+        event_tx: mpsc::Sender<SessionEvent>,
+    ) -> Result<MockServerInfo, String> {
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", port.unwrap_or(0)))
+            .await
+            .map_err(|e| format!("Failed to bind mock server: {}", e))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        let incoming = AddrIncoming::from_listener(listener).map_err(|e| e.to_string())?;
+
+        let log = Arc::new(std::sync::Mutex::new(VecDeque::new()));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+
+        let service_log = log.clone();
+        let service_events = event_tx.clone();
+        let make_service = make_service_fn(move |_| {
+            let handler = handler.clone();
+            let log = service_log.clone();
+            let events = service_events.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    serve_one(request, handler.clone(), log.clone(), events.clone())
+                }))
+            }
+        });
+
+        let server = Server::builder(incoming)
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            });
+
+        let _ = event_tx
+            .send(SessionEvent::Lifecycle {
+                status: "listening".to_string(),
+            })
+            .await;
+        tokio::spawn(async move {
+            let status = match server.await {
+                Ok(()) => "stopped".to_string(),
+                Err(e) => format!("error: {}", e),
+            };
+            let _ = event_tx.send(SessionEvent::Lifecycle { status }).await;
+        });
+
+        self.servers.lock().await.insert(
+            id.clone(),
+            RunningServer {
+                shutdown: shutdown_tx,
+                log,
+            },
+        );
+
+        Ok(MockServerInfo {
+            id,
+            port: address.port(),
+            url: format!("http://{}", address),
+        })
+    }
+
+    pub async fn stop(&self, id: &str) -> Result<(), String> {
+        let server = self
+            .servers
+            .lock()
+            .await
+            .remove(id)
+            .ok_or_else(|| format!("Mock server not found: {}", id))?;
+        let _ = server.shutdown.send(());
+        Ok(())
+    }
+
+    pub async fn log(&self, id: &str) -> Result<Vec<MockLogEntry>, String> {
+        let servers = self.servers.lock().await;
+        let server = servers
+            .get(id)
+            .ok_or_else(|| format!("Mock server not found: {}", id))?;
+        let log = server.log.lock().map_err(|e| e.to_string())?;
+        Ok(log.iter().cloned().collect())
+    }
+}
+
+async fn serve_one(
+    request: Request<Body>,
+    handler: Arc<dyn MockHandler>,
+    log: Arc<std::sync::Mutex<VecDeque<MockLogEntry>>>,
+    events: mpsc::Sender<SessionEvent>,
+) -> Result<Response<Body>, Infallible> {
+    let started = Instant::now();
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body).await.unwrap_or_default();
+
+    let raw_query = parts.uri.query().unwrap_or_default().to_string();
+    let incoming = IncomingRequest {
+        method: parts.method.as_str().to_uppercase(),
+        path: parts.uri.path().to_string(),
+        query: url::form_urlencoded::parse(raw_query.as_bytes())
+            .into_owned()
+            .collect(),
+        headers: parts
+            .headers
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.as_str().to_lowercase(),
+                    String::from_utf8_lossy(v.as_bytes()).to_string(),
+                )
+            })
+            .collect(),
+        body: body.to_vec(),
+    };
+
+    let reply = handler.respond(&incoming).await;
+    if let Some(delay) = reply.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let entry = MockLogEntry {
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default(),
+        method: incoming.method,
+        path: incoming.path,
+        query: raw_query,
+        status: reply.status,
+        operation_key: reply.operation_key,
+        warnings: reply.warnings,
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+    };
+    if let Ok(mut log) = log.lock() {
+        if log.len() >= LOG_CAPACITY {
+            log.pop_front();
+        }
+        log.push_back(entry.clone());
+    }
+    let _ = events.send(SessionEvent::MockRequest(entry)).await;
+
+    let mut response = Response::builder().status(reply.status);
+    for (name, value) in &reply.headers {
+        response = response.header(name, value);
+    }
+    Ok(response.body(Body::from(reply.body)).unwrap_or_else(|e| {
+        let mut fallback = Response::new(Body::from(e.to_string()));
+        *fallback.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
+        fallback
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl MockHandler for Echo {
+        async fn respond(&self, request: &IncomingRequest) -> MockReply {
+            let mut reply = MockReply::json(201, &json!({ "path": request.path }));
+            reply.operation_key = Some("post:/echo".to_string());
+            reply
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_logs_and_stops() {
+        let manager = MockServerManager::new();
+        let (event_tx, mut event_rx) = mpsc::channel(16);
+        let info = manager
+            .start("m1".to_string(), None, Arc::new(Echo), event_tx)
+            .await
+            .unwrap();
+        assert!(matches!(
+            event_rx.recv().await,
+            Some(SessionEvent::Lifecycle { status }) if status == "listening"
+        ));
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/echo?x=1", info.url))
+            .body("hi")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 201);
+        assert_eq!(response.text().await.unwrap(), r#"{"path":"/echo"}"#);
+
+        let Some(SessionEvent::MockRequest(entry)) = event_rx.recv().await else {
+            panic!("expected a request event");
+        };
+        assert_eq!(entry.method, "POST");
+        assert_eq!(entry.query, "x=1");
+        let log = manager.log("m1").await.unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].operation_key.as_deref(), Some("post:/echo"));
+
+        manager.stop("m1").await.unwrap();
+        assert!(matches!(
+            event_rx.recv().await,
+            Some(SessionEvent::Lifecycle { status }) if status == "stopped"
+        ));
+        assert!(manager.stop("m1").await.is_err());
+        assert!(manager.log("m1").await.is_err());
+    }
+}
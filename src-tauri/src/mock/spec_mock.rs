@@ -0,0 +1,448 @@
+use super::generator::generate;
+use super::router::{RouteResult, Router};
+use super::server::{IncomingRequest, MockHandler, MockReply};
+use crate::network::request::PreparedRequest;
+use crate::spec::index::{find_operation, follow_ref, split_operation_key};
+use crate::templating::FormData;
+use crate::validation::{self, is_json_media_type};
+use serde_json::{json, Map, Value};
+
+/// Preferences a client can send in a `Prefer` header, e.g. `Prefer: code=404, example=missing`
+#[derive(Default)]
+struct Preferences {
+    code: Option<String>,
+    example: Option<String>,
+    /// `dynamic=true` skips declared examples and always generates data from the schema
+    dynamic: bool,
+}
+
+fn parse_prefer(header: Option<&String>) -> Preferences {
+    let mut preferences = Preferences::default();
+    for token in header
+        .into_iter()
+        .flat_map(|h| h.split([',', ';']))
+        .map(str::trim)
+    {
+        let Some((key, value)) = token.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        match key.trim() {
+            "code" => preferences.code = Some(value),
+            "example" => preferences.example = Some(value),
+            "dynamic" => preferences.dynamic = value == "true",
+            _ => {}
+        }
+    }
+    preferences
+}
+
+/// Serves every operation of a spec with declared examples or generated data
+pub struct SpecMock {
+    spec: Value,
+    router: Router,
+    validate_requests: bool,
+}
+
+impl SpecMock {
+    pub fn new(spec: Value, validate_requests: bool) -> Self {
+        Self {
+            router: Router::new(&spec),
+            spec,
+            validate_requests,
+        }
+    }
+
+    fn check_request(
+        &self,
+        operation_key: &str,
+        path_params: Map<String, Value>,
+        request: &IncomingRequest,
+    ) -> Result<validation::PreflightReport, String> {
+        let mut query_data = Map::new();
+        for (key, value) in &request.query {
+            let value = Value::String(value.clone());
+            match query_data.get_mut(key) {
+                Some(Value::Array(items)) => items.push(value),
+                Some(existing) => *existing = Value::Array(vec![existing.clone(), value]),
+                None => {
+                    query_data.insert(key.clone(), value);
+                }
+            }
+        }
+
+        let body = (!request.body.is_empty()).then(|| request.body_text());
+        // Whatever the client sent counts as filled in, even a literal `{}`
+        let form_data = FormData {
+            path_data: Some(path_params),
+            query_data: Some(query_data),
+            body_data: body.clone().map(Value::String),
+            ..FormData::default()
+        };
+        let prepared = PreparedRequest {
+            method: request.method.clone(),
+            url: request.path.clone(),
+            headers: request.headers.clone(),
+            body,
+        };
+        validation::validate_request(&self.spec, operation_key, &form_data, &prepared)
+    }
+
+    fn build_reply(&self, operation_key: &str, preferences: &Preferences) -> MockReply {
+        let Some(op) = split_operation_key(operation_key)
+            .ok()
+            .and_then(|(method, path)| find_operation(&self.spec, &method, &path))
+        else {
+            return MockReply::json(404, &json!({ "message": "Operation not found" }));
+        };
+        let responses = op
+            .get("responses")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+
+        let chosen = match &preferences.code {
+            Some(code) => responses.get_key_value(code.as_str()),
+            None => responses
+                .iter()
+                .find(|(code, _)| code.starts_with('2'))
+                .or_else(|| responses.iter().next()),
+        };
+        let Some((code, response)) = chosen else {
+            return MockReply::json(
+                501,
+                &json!({ "message": "No matching response is declared for this operation" }),
+            );
+        };
+        let response = follow_ref(&self.spec, response);
+
+        // Ranges like `2XX` and `default` need a concrete status
+        let status = code
+            .parse::<u16>()
+            .ok()
+            .or_else(|| code.chars().next()?.to_digit(10).map(|d| d as u16 * 100))
+            .unwrap_or(200);
+
+        let mut headers = Vec::new();
+        for (name, header) in response
+            .get("headers")
+            .and_then(Value::as_object)
+            .into_iter()
+            .flatten()
+        {
+            let header = follow_ref(&self.spec, header);
+            let value = match header.get("example") {
+                Some(example) => example.clone(),
+                None => header
+                    .get("schema")
+                    .map(|s| generate(&self.spec, s))
+                    .unwrap_or(Value::Null),
+            };
+            let value = match value {
+                Value::String(s) => s,
+                Value::Null => continue,
+                other => other.to_string(),
+            };
+            headers.push((name.to_lowercase(), value));
+        }
+
+        let content = response.get("content").and_then(Value::as_object);
+        let media = content.and_then(|c| {
+            c.iter()
+                .find(|(media_type, _)| is_json_media_type(media_type))
+                .or_else(|| c.iter().next())
+        });
+
+        let body = match media {
+            Some((media_type, media)) => {
+                let content_type = if media_type.contains('*') {
+                    "application/json".to_string()
+                } else {
+                    media_type.clone()
+                };
+                let value = self.pick_example(media, preferences);
+                headers.retain(|(name, _)| name != "content-type");
+                headers.push(("content-type".to_string(), content_type.clone()));
+                match value {
+                    Value::String(s) if !is_json_media_type(&content_type) => s.into_bytes(),
+                    other => other.to_string().into_bytes(),
+                }
+            }
+            None => Vec::new(),
+        };
+
+        MockReply {
+            status,
+            headers,
+            body,
+            operation_key: Some(operation_key.to_string()),
+            warnings: Vec::new(),
+            delay: None,
+        }
+    }
+
+    /// Picks the named example, then the first declared example, then generated data
+    fn pick_example(&self, media: &Value, preferences: &Preferences) -> Value {
+        let examples = media.get("examples").and_then(Value::as_object);
+        let example_value = |example: &Value| -> Option<Value> {
+            follow_ref(&self.spec, example).get("value").cloned()
+        };
+
+        if !preferences.dynamic {
+            if let Some(name) = &preferences.example {
+                if let Some(value) = examples.and_then(|e| e.get(name)).and_then(example_value) {
+                    return value;
+                }
+            }
+            if let Some(example) = media.get("example") {
+                return example.clone();
+            }
+            if let Some(value) = examples
+                .and_then(|e| e.values().next())
+                .and_then(example_value)
+            {
+                return value;
+            }
+        }
+
+        match media.get("schema") {
+            Some(schema) if preferences.dynamic => generate(&self.spec, &without_examples(schema)),
+            Some(schema) => generate(&self.spec, schema),
+            None => Value::Null,
+        }
+    }
+}
+
+/// Drops the top-level example so `dynamic=true` really generates fresh data
+fn without_examples(schema: &Value) -> Value {
+    let mut schema = schema.clone();
+    if let Some(map) = schema.as_object_mut() {
+        map.remove("example");
+        map.remove("examples");
+    }
+    schema
+}
+
+#[async_trait::async_trait]
+impl MockHandler for SpecMock {
+    async fn respond(&self, request: &IncomingRequest) -> MockReply {
+        let matched = match self.router.find(&request.method, &request.path) {
+            RouteResult::Matched(matched) => matched,
+            RouteResult::MethodNotAllowed(methods) => {
+                let mut reply = MockReply::json(
+                    405,
+                    &json!({ "message": format!("{} is not allowed here", request.method) }),
+                );
+                let allow: Vec<String> = methods.iter().map(|m| m.to_uppercase()).collect();
+                reply.headers.push(("allow".to_string(), allow.join(", ")));
+                return reply;
+            }
+            RouteResult::NotFound => {
+                return MockReply::json(
+                    404,
+                    &json!({ "message": format!("No operation matches {}", request.path) }),
+                );
+            }
+        };
+
+        let preferences = parse_prefer(request.headers.get("prefer"));
+
+        let mut warnings = Vec::new();
+        if self.validate_requests {
+            match self.check_request(&matched.operation_key, matched.path_params, request) {
+                Ok(report) if !report.valid && preferences.code.is_none() => {
+                    let mut reply = MockReply::json(
+                        400,
+                        &json!({
+                            "message": "Request does not match the spec",
+                            "violations": report.warnings,
+                        }),
+                    );
+                    reply.operation_key = Some(matched.operation_key);
+                    reply.warnings = report.warnings;
+                    return reply;
+                }
+                Ok(report) => warnings = report.warnings,
+                Err(e) => {
+                    return MockReply::json(500, &json!({ "message": e }));
+                }
+            }
+        }
+
+        let mut reply = self.build_reply(&matched.operation_key, &preferences);
+        reply.warnings = warnings;
+        reply
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn mock() -> SpecMock {
+        let spec = json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "OK",
+                                "headers": { "X-Total": { "schema": { "type": "integer", "example": 2 } } },
+                                "content": {
+                                    "application/json": {
+                                        "examples": {
+                                            "one": { "value": [{ "name": "Rex" }] },
+                                            "two": { "value": [{ "name": "Rex" }, { "name": "Tom" }] }
+                                        }
+                                    }
+                                }
+                            },
+                            "404": {
+                                "description": "Missing",
+                                "content": {
+                                    "application/json": { "example": { "message": "none" } }
+                                }
+                            },
+                            "5XX": { "description": "Broken" }
+                        }
+                    },
+                    "post": {
+                        "requestBody": {
+                            "required": true,
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "required": ["name"],
+                                        "properties": { "name": { "type": "string" } }
+                                    }
+                                }
+                            }
+                        },
+                        "responses": {
+                            "201": {
+                                "description": "Created",
+                                "content": {
+                                    "application/json": {
+                                        "schema": {
+                                            "type": "object",
+                                            "properties": { "id": { "type": "integer", "minimum": 9, "maximum": 9 } }
+                                        }
+                                    }
+                                }
+                            },
+                            "400": { "description": "Bad request" }
+                        }
+                    }
+                }
+            }
+        });
+        SpecMock::new(spec, true)
+    }
+
+    fn request(method: &str, path: &str, headers: &[(&str, &str)], body: &str) -> IncomingRequest {
+        IncomingRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: Vec::new(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<HashMap<_, _>>(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    fn header<'a>(reply: &'a MockReply, name: &str) -> Option<&'a str> {
+        reply
+            .headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn body(reply: &MockReply) -> Value {
+        serde_json::from_slice(&reply.body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn serves_the_first_success_and_its_first_example() {
+        let reply = mock().respond(&request("GET", "/pets", &[], "")).await;
+
+        assert_eq!(reply.status, 200);
+        assert_eq!(reply.operation_key.as_deref(), Some("get:/pets"));
+        assert_eq!(header(&reply, "content-type"), Some("application/json"));
+        assert_eq!(header(&reply, "x-total"), Some("2"));
+        assert_eq!(body(&reply), json!([{ "name": "Rex" }]));
+    }
+
+    #[tokio::test]
+    async fn prefer_selects_the_status_and_example() {
+        let mock = mock();
+
+        let reply = mock
+            .respond(&request("GET", "/pets", &[("prefer", "example=two")], ""))
+            .await;
+        assert_eq!(reply.status, 200);
+        assert_eq!(body(&reply).as_array().unwrap().len(), 2);
+
+        let reply = mock
+            .respond(&request("GET", "/pets", &[("prefer", "code=404")], ""))
+            .await;
+        assert_eq!(reply.status, 404);
+        assert_eq!(body(&reply), json!({ "message": "none" }));
+
+        let reply = mock
+            .respond(&request("GET", "/pets", &[("prefer", "code=5XX")], ""))
+            .await;
+        assert_eq!(reply.status, 500);
+        assert!(reply.body.is_empty());
+
+        let reply = mock
+            .respond(&request("GET", "/pets", &[("prefer", "code=418")], ""))
+            .await;
+        assert_eq!(reply.status, 501);
+    }
+
+    #[tokio::test]
+    async fn rejects_other_methods_with_an_allow_header() {
+        let reply = mock().respond(&request("DELETE", "/pets", &[], "")).await;
+
+        assert_eq!(reply.status, 405);
+        assert_eq!(header(&reply, "allow"), Some("GET, POST"));
+
+        let reply = mock().respond(&request("GET", "/owners", &[], "")).await;
+        assert_eq!(reply.status, 404);
+    }
+
+    #[tokio::test]
+    async fn validates_requests_unless_a_status_is_preferred() {
+        let mock = mock();
+        let json = ("content-type", "application/json");
+
+        let reply = mock
+            .respond(&request("POST", "/pets", &[json], r#"{"name": "Rex"}"#))
+            .await;
+        assert_eq!(reply.status, 201);
+        assert_eq!(body(&reply), json!({ "id": 9 }));
+
+        let reply = mock
+            .respond(&request("POST", "/pets", &[json], r#"{"name": 1}"#))
+            .await;
+        assert_eq!(reply.status, 400);
+        assert!(!reply.warnings.is_empty());
+
+        let reply = mock
+            .respond(&request(
+                "POST",
+                "/pets",
+                &[json, ("prefer", "code=201")],
+                r#"{"name": 1}"#,
+            ))
+            .await;
+        assert_eq!(reply.status, 201);
+        assert!(!reply.warnings.is_empty());
+    }
+}
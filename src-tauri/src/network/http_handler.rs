@@ -6,6 +6,12 @@ pub struct HttpHandler {
     client: Option<reqwest::Client>,
 }
 
+impl Default for HttpHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpHandler {
     pub fn new() -> Self {
         Self { client: None }
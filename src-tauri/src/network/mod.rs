@@ -1,3 +1,4 @@
 pub mod http_handler;
+pub mod request;
 pub mod session;
 pub mod sse_handler;
@@ -0,0 +1,371 @@
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResponseTimings {
+    pub prepare_ms: f64,
+    pub dns_lookup_ms: f64,
+    pub tcp_connect_ms: f64,
+    pub tls_handshake_ms: f64,
+    pub ttfb_ms: f64,
+    pub download_ms: f64,
+    pub process_ms: f64,
+    pub total_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackendResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub headers: HashMap<String, String>,
+    pub body_text: String,
+    pub timings: ResponseTimings,
+    pub wire_size_bytes: usize,
+    pub body_size_bytes: usize,
+    pub session_id: Option<String>,
+    /// Environment variables updated by the operation's extraction rules
+    #[serde(default)]
+    pub changed_variables: Vec<String>,
+    /// Output of the operation's scripts, when it has any
+    #[serde(default)]
+    pub script: Option<crate::scripting::ScriptReport>,
+    /// The request `resolve_and_send` sent, with secrets swapped for `{{name}}` placeholders
+    #[serde(default)]
+    pub request: Option<PreparedRequest>,
+    /// How the response compares with the operation's declared responses, when sent from a spec
+    #[serde(default)]
+    pub validation: Option<crate::validation::ValidationReport>,
+    /// Results of the operation's saved assertions, when it has any
+    #[serde(default)]
+    pub assertions: Vec<crate::assertions::AssertionResult>,
+    /// What failed after the response arrived, e.g. storing extracted variables
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+impl BackendResponse {
+    /// Stands in for a request that was never sent, with status 0 and the reason in `errors`
+    pub fn not_sent(reason: String) -> Self {
+        Self {
+            status: 0,
+            status_text: "Not sent".to_string(),
+            headers: HashMap::new(),
+            body_text: String::new(),
+            timings: ResponseTimings::default(),
+            wire_size_bytes: 0,
+            body_size_bytes: 0,
+            session_id: None,
+            changed_variables: Vec::new(),
+            script: None,
+            request: None,
+            validation: None,
+            assertions: Vec::new(),
+            errors: vec![reason],
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct SseEvent {
+    pub session_id: String,
+    pub event_id: u32,
+    pub event_type: String,
+    pub data: String,
+    pub timestamp: u64,
+    pub elapsed_ms: f64,
+}
+
+/// A fully resolved request, ready to go on the wire
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreparedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+}
+
+pub fn build_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(600))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+fn parse_sse_frame(frame: &str) -> (String, String) {
+    let mut event_type = String::from("message");
+    let mut data_lines: Vec<&str> = Vec::new();
+
+    for line in frame.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event_type = value.trim().to_string();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data_lines.push(value.strip_prefix(' ').unwrap_or(value));
+        } else if line.starts_with("id:") {
+            // SSE id field — ignored for now
+        } else if line.starts_with("retry:") {
+            // SSE retry field — ignored
+        }
+    }
+
+    let data = data_lines.join("\n");
+    (event_type, data)
+}
+
+pub fn collect_headers(response: &reqwest::Response) -> HashMap<String, String> {
+    let mut response_headers = HashMap::new();
+    for (key, value) in response.headers().iter() {
+        let value_str = String::from_utf8_lossy(value.as_bytes()).to_string();
+        response_headers.insert(key.as_str().to_string(), value_str);
+    }
+    response_headers
+}
+
+/// Decompresses a body according to its `content-encoding` header
+pub fn decode_body(encoding: Option<&str>, body_bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let Some(encoding) = encoding else {
+        return Ok(body_bytes.to_vec());
+    };
+
+    let mut decompressed = Vec::new();
+    match encoding.to_lowercase().as_str() {
+        "gzip" => {
+            let mut decoder = flate2::read::GzDecoder::new(body_bytes);
+            decoder
+                .read_to_end(&mut decompressed)
+                .map_err(|e| e.to_string())?;
+        }
+        "deflate" => {
+            let mut decoder = flate2::read::DeflateDecoder::new(body_bytes);
+            decoder
+                .read_to_end(&mut decompressed)
+                .map_err(|e| e.to_string())?;
+        }
+        "br" => {
+            let mut decoder = brotli::Decompressor::new(body_bytes, 4096);
+            decoder
+                .read_to_end(&mut decompressed)
+                .map_err(|e| e.to_string())?;
+        }
+        _ => decompressed = body_bytes.to_vec(),
+    }
+    Ok(decompressed)
+}
+
+async fn handle_sse_stream(
+    session_id: String,
+    response: reqwest::Response,
+    start_time: std::time::Instant,
+    prepare_ms: f64,
+    ttfb_ms: f64,
+    on_event: &(dyn Fn(&SseEvent) + Send + Sync),
+) -> Result<BackendResponse, String> {
+    let status = response.status().as_u16();
+    let status_text = response
+        .status()
+        .canonical_reason()
+        .unwrap_or("")
+        .to_string();
+
+    let response_headers = collect_headers(&response);
+
+    let mut stream = response.bytes_stream();
+    let mut buffer = String::new();
+    let mut accumulated_body = String::new();
+    let mut event_id: u32 = 0;
+    let mut wire_size_bytes: usize = 0;
+
+    let stream_start = std::time::Instant::now();
+
+    while let Some(chunk_result) = stream.next().await {
+        let chunk = chunk_result.map_err(|e| e.to_string())?;
+        wire_size_bytes += chunk.len();
+        let chunk_str = String::from_utf8_lossy(&chunk);
+        buffer.push_str(&chunk_str);
+
+        // Process complete SSE frames (delimited by \n\n)
+        while let Some(pos) = buffer.find("\n\n") {
+            let frame = buffer[..pos].to_string();
+            buffer = buffer[pos + 2..].to_string();
+
+            // Skip empty frames
+            if frame.trim().is_empty() {
+                continue;
+            }
+
+            let (event_type, data) = parse_sse_frame(&frame);
+
+            // Skip frames with no data
+            if data.is_empty() {
+                continue;
+            }
+
+            event_id += 1;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            let elapsed = stream_start.elapsed().as_secs_f64() * 1000.0;
+
+            let sse_event = SseEvent {
+                session_id: session_id.clone(),
+                event_id,
+                event_type,
+                data: data.clone(),
+                timestamp: now,
+                elapsed_ms: elapsed,
+            };
+
+            on_event(&sse_event);
+
+            accumulated_body.push_str(&data);
+            accumulated_body.push('\n');
+        }
+    }
+
+    let download_ms = stream_start.elapsed().as_secs_f64() * 1000.0;
+    let total_ms = start_time.elapsed().as_secs_f64() * 1000.0;
+    let body_size_bytes = accumulated_body.len();
+
+    let timings = ResponseTimings {
+        prepare_ms,
+        dns_lookup_ms: 0.0,
+        tcp_connect_ms: 0.0,
+        tls_handshake_ms: 0.0,
+        ttfb_ms,
+        download_ms,
+        process_ms: 0.0,
+        total_ms,
+    };
+
+    Ok(BackendResponse {
+        status,
+        status_text,
+        headers: response_headers,
+        body_text: accumulated_body,
+        timings,
+        wire_size_bytes,
+        body_size_bytes,
+        session_id: Some(session_id),
+        changed_variables: Vec::new(),
+        script: None,
+        request: None,
+        validation: None,
+        assertions: Vec::new(),
+        errors: Vec::new(),
+    })
+}
+
+/// Sends a prepared request and collects the response.
+///
+/// Server-sent event streams are read to completion, with each frame handed to `on_event`
+/// as it arrives.
+pub async fn execute(
+    client: &reqwest::Client,
+    request: PreparedRequest,
+    session_id: Option<String>,
+    on_event: &(dyn Fn(&SseEvent) + Send + Sync),
+) -> Result<BackendResponse, String> {
+    let t0 = std::time::Instant::now();
+
+    let method = request.method.to_uppercase();
+
+    // Build the request based on the method
+    let mut request_builder = match method.as_str() {
+        "GET" => client.get(&request.url),
+        "POST" => client.post(&request.url),
+        "PUT" => client.put(&request.url),
+        "DELETE" => client.delete(&request.url),
+        "PATCH" => client.patch(&request.url),
+        "HEAD" => client.head(&request.url),
+        _ => return Err(format!("Unsupported HTTP method: {}", method)),
+    };
+
+    // Add all headers to the request including Accept-Encoding
+    for (key, value) in request.headers {
+        request_builder = request_builder.header(&key, value);
+    }
+
+    // Add a body if one was provided
+    if let Some(body_content) = request.body {
+        request_builder = request_builder.body(body_content);
+    }
+
+    let prepare_ms = t0.elapsed().as_secs_f64() * 1000.0;
+
+    // Send the request and await the response (TTFB includes DNS+TCP+TLS+server)
+    let t1 = std::time::Instant::now();
+    let response = request_builder.send().await.map_err(|e| e.to_string())?;
+    let ttfb_ms = t1.elapsed().as_secs_f64() * 1000.0;
+
+    // Check if this is an SSE stream
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_lowercase();
+
+    if content_type.contains("text/event-stream") {
+        let sid = session_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+        return handle_sse_stream(sid, response, t0, prepare_ms, ttfb_ms, on_event).await;
+    }
+
+    // Extract status code and text
+    let status = response.status().as_u16();
+    let status_text = response
+        .status()
+        .canonical_reason()
+        .unwrap_or("")
+        .to_string();
+
+    // Extract headers from the response
+    let response_headers = collect_headers(&response);
+
+    // Download body bytes
+    let t2 = std::time::Instant::now();
+    let body_bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    let download_ms = t2.elapsed().as_secs_f64() * 1000.0;
+
+    // Wire size = raw bytes received (before decompression)
+    let wire_size_bytes = body_bytes.len();
+
+    // Decompress if necessary, measuring process time
+    let t3 = std::time::Instant::now();
+    let content_encoding = response_headers.get("content-encoding").map(String::as_str);
+    let decompressed_bytes = decode_body(content_encoding, &body_bytes)?;
+    let body_text = String::from_utf8_lossy(&decompressed_bytes).to_string();
+    let process_ms = t3.elapsed().as_secs_f64() * 1000.0;
+
+    let body_size_bytes = body_text.len();
+    let total_ms = t0.elapsed().as_secs_f64() * 1000.0;
+
+    let timings = ResponseTimings {
+        prepare_ms,
+        dns_lookup_ms: 0.0,
+        tcp_connect_ms: 0.0,
+        tls_handshake_ms: 0.0,
+        ttfb_ms,
+        download_ms,
+        process_ms,
+        total_ms,
+    };
+
+    Ok(BackendResponse {
+        status,
+        status_text,
+        headers: response_headers,
+        body_text,
+        timings,
+        wire_size_bytes,
+        body_size_bytes,
+        session_id: None,
+        changed_variables: Vec::new(),
+        script: None,
+        request: None,
+        validation: None,
+        assertions: Vec::new(),
+        errors: Vec::new(),
+    })
+}
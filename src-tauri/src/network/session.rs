@@ -38,6 +38,7 @@ pub enum SessionEvent {
         data: String,
         id: Option<String>,
     },
+    MockRequest(crate::mock::MockLogEntry),
 }
 
 fn serialize_bytes_as_array<S>(bytes: &Vec<u8>, serializer: S) -> Result<S::Ok, S::Error>
@@ -69,6 +70,12 @@ pub struct SessionManager {
     sessions: Mutex<HashMap<String, Box<dyn SessionHandler>>>,
 }
 
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SessionManager {
     pub fn new() -> Self {
         Self {
@@ -6,6 +6,12 @@ pub struct SseHandler {
     abort_tx: Option<tokio::sync::oneshot::Sender<()>>,
 }
 
+impl Default for SseHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SseHandler {
     pub fn new() -> Self {
         Self { abort_tx: None }
@@ -0,0 +1,254 @@
+use super::{report, RunSummary};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One row of a data file: variable names to the values that override the environment's
+pub type DataRow = HashMap<String, String>;
+
+/// Reads a data file into rows. `.json` files hold an array of objects; anything else is
+/// read as CSV with a header row naming the variables.
+pub fn load_rows(path: &str) -> Result<Vec<DataRow>, String> {
+    let is_json = Path::new(path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        load_json(path)
+    } else {
+        load_csv(path)
+    }
+}
+
+fn load_csv(path: &str) -> Result<Vec<DataRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_path(path)
+        .map_err(|e| e.to_string())?;
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        rows.push(
+            headers
+                .iter()
+                .zip(record.iter())
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+fn load_json(path: &str) -> Result<Vec<DataRow>, String> {
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let value: Value =
+        serde_json::from_reader(std::io::BufReader::new(file)).map_err(|e| e.to_string())?;
+    let Value::Array(items) = value else {
+        return Err("Data file must contain a JSON array of objects".to_string());
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let Value::Object(fields) = item else {
+                return Err(format!(
+                    "Row {} of the data file is not an object",
+                    index + 1
+                ));
+            };
+            Ok(fields
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| {
+                    let value = match value {
+                        Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    (name, value)
+                })
+                .collect())
+        })
+        .collect()
+}
+
+/// Writes one CSV line per step of a finished run
+pub fn export_csv(summary: &RunSummary, path: &str) -> Result<(), String> {
+    let mut writer = csv::Writer::from_path(path).map_err(|e| e.to_string())?;
+    writer
+        .write_record([
+            "iteration",
+            "index",
+            "operation_key",
+            "method",
+            "url",
+            "status",
+            "passed",
+            "duration_ms",
+            "failures",
+        ])
+        .map_err(|e| e.to_string())?;
+
+    for result in &summary.results {
+        writer
+            .write_record([
+                result.iteration.to_string(),
+                result.index.to_string(),
+                result.operation_key.clone(),
+                result.method.clone().unwrap_or_default(),
+                result.url.clone().unwrap_or_default(),
+                result.status.map(|s| s.to_string()).unwrap_or_default(),
+                result.passed.to_string(),
+                format!("{:.1}", result.duration_ms),
+                report::failure_messages(result).join("; "),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::StepResult;
+    use std::path::PathBuf;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("cogeass-data-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn write(&self, name: &str, content: &str) -> String {
+            let path = self.0.join(name);
+            std::fs::write(&path, content).unwrap();
+            path.display().to_string()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn row(fields: &[(&str, &str)]) -> DataRow {
+        fields
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn csv_rows_are_keyed_by_the_header_row() {
+        let dir = TempDir::new();
+        let path = dir.write(
+            "users.csv",
+            " user , age\nalice,30\n\"bob, jr\",\"4\"\"2\"\n",
+        );
+
+        assert_eq!(
+            load_rows(&path).unwrap(),
+            [
+                row(&[("user", "alice"), ("age", "30")]),
+                row(&[("user", "bob, jr"), ("age", "4\"2")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn ragged_csv_rows_are_rejected() {
+        let dir = TempDir::new();
+        let short = dir.write("short.csv", "user,age\nalice\n");
+        let long = dir.write("long.csv", "user,age\nalice,30,extra\n");
+
+        assert!(load_rows(&short).is_err());
+        assert!(load_rows(&long).is_err());
+    }
+
+    #[test]
+    fn json_rows_must_be_objects() {
+        let dir = TempDir::new();
+        let path = dir.write(
+            "users.JSON",
+            r#"[{"user": "alice", "age": 30, "admin": true, "note": null, "tags": ["a"]}]"#,
+        );
+        assert_eq!(
+            load_rows(&path).unwrap(),
+            [row(&[
+                ("user", "alice"),
+                ("age", "30"),
+                ("admin", "true"),
+                ("tags", r#"["a"]"#),
+            ])]
+        );
+
+        let path = dir.write("mixed.json", r#"[{"user": "alice"}, "bob"]"#);
+        assert_eq!(
+            load_rows(&path).unwrap_err(),
+            "Row 2 of the data file is not an object"
+        );
+        let path = dir.write("object.json", r#"{"user": "alice"}"#);
+        assert_eq!(
+            load_rows(&path).unwrap_err(),
+            "Data file must contain a JSON array of objects"
+        );
+    }
+
+    #[test]
+    fn exported_cells_are_escaped() {
+        let dir = TempDir::new();
+        let path = dir.0.join("results.csv").display().to_string();
+        let summary = RunSummary {
+            run_id: "r1".to_string(),
+            total: 1,
+            passed: 0,
+            failed: 1,
+            skipped: 0,
+            cancelled: false,
+            duration_ms: 12.0,
+            results: vec![StepResult {
+                iteration: 0,
+                index: 0,
+                operation_key: "get:/pets".to_string(),
+                method: Some("GET".to_string()),
+                url: Some("https://api.test/pets?tags=a,b".to_string()),
+                status: None,
+                passed: false,
+                assertions: Vec::new(),
+                extraction: None,
+                script: None,
+                validation: None,
+                duration_ms: 12.34,
+                error: Some("said \"no\"\nthen hung up".to_string()),
+            }],
+        };
+
+        export_csv(&summary, &path).unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains(r#""https://api.test/pets?tags=a,b""#));
+        assert!(written.contains("\"said \"\"no\"\"\nthen hung up\""));
+
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].iter().collect::<Vec<_>>(),
+            [
+                "0",
+                "0",
+                "get:/pets",
+                "GET",
+                "https://api.test/pets?tags=a,b",
+                "",
+                "false",
+                "12.3",
+                "said \"no\"\nthen hung up",
+            ]
+        );
+    }
+}
@@ -0,0 +1,694 @@
+use super::data::{self, DataRow};
+use super::{RunConfig, RunEvent, RunSummary, StepResult};
+use crate::assertions::{self, Assertion};
+use crate::extraction::{self, Extracted, ExtractionReport, ExtractionRule};
+use crate::network::request::{self, BackendResponse};
+use crate::scripting::{self, OperationScripts, ScriptReport};
+use crate::secrets::SecretVault;
+use crate::templating::{self, FormData, WorkspaceContext};
+use crate::validation;
+use futures::{stream, StreamExt};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// What each operation runs with: its saved form data, assertions, extraction rules, and scripts
+struct SavedOperation {
+    form_data: FormData,
+    assertions: Vec<Assertion>,
+    rules: Vec<ExtractionRule>,
+    scripts: OperationScripts,
+}
+
+/// Reads each operation's saved state; operations never opened in the UI run with empty form data
+async fn load_operations(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_keys: &[String],
+) -> Result<HashMap<String, SavedOperation>, String> {
+    let mut operations = HashMap::new();
+    for key in operation_keys {
+        if operations.contains_key(key) {
+            continue;
+        }
+        let form_data = templating::saved_form_data(pool, workspace_id, key).await?;
+        let assertions = assertions::load(pool, workspace_id, key).await?;
+        let rules = extraction::load(pool, workspace_id, key).await?;
+        let scripts = scripting::load(pool, workspace_id, key).await?;
+        operations.insert(
+            key.clone(),
+            SavedOperation {
+                form_data,
+                assertions,
+                rules,
+                scripts,
+            },
+        );
+    }
+    Ok(operations)
+}
+
+/// Spaces the requests of a run `delay` apart, whichever iteration sends them
+struct Pacer {
+    delay: Duration,
+    next: tokio::sync::Mutex<Option<Instant>>,
+}
+
+impl Pacer {
+    fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            next: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// Waits until the next request may be sent
+    async fn wait(&self) {
+        if self.delay.is_zero() {
+            return;
+        }
+        let mut next = self.next.lock().await;
+        if let Some(at) = *next {
+            tokio::time::sleep_until(tokio::time::Instant::from_std(at)).await;
+        }
+        *next = Some(Instant::now() + self.delay);
+    }
+}
+
+/// Shared by every step of a run
+struct RunScope<'a> {
+    pool: &'a SqlitePool,
+    vault: &'a SecretVault,
+    workspace_id: &'a str,
+    client: reqwest::Client,
+    /// What each iteration starts from before extracted values and its data row are applied
+    context: WorkspaceContext,
+    /// Values extracted so far, carried into iterations that start later
+    extracted: Mutex<HashMap<String, String>>,
+    pacer: Pacer,
+}
+
+impl RunScope<'_> {
+    /// The context for a new iteration: the run's variables, overridden by everything
+    /// extracted so far and then by the iteration's data row
+    fn iteration_context(&self, row: Option<&DataRow>) -> WorkspaceContext {
+        let mut context = self.context.clone();
+        context.variables.extend(
+            self.extracted
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        );
+        if let Some(row) = row {
+            context.variables.extend(row.clone());
+        }
+        context
+    }
+
+    /// Stores values in the run's environment and makes them visible to later steps,
+    /// returning the names whose value changed
+    async fn set_variables(
+        &self,
+        context: &mut WorkspaceContext,
+        values: Vec<Extracted>,
+    ) -> Result<Vec<String>, String> {
+        let changed = match &context.environment_id {
+            Some(env_id) => {
+                extraction::store(self.pool, self.vault, self.workspace_id, env_id, &values).await?
+            }
+            None => values
+                .iter()
+                .filter(|e| context.variables.get(&e.variable) != Some(&e.value))
+                .map(|e| e.variable.clone())
+                .collect(),
+        };
+
+        let mut kept = self.extracted.lock().unwrap_or_else(|e| e.into_inner());
+        for Extracted { variable, value } in values {
+            context.variables.insert(variable.clone(), value.clone());
+            kept.insert(variable, value);
+        }
+        Ok(changed)
+    }
+
+    async fn extract(
+        &self,
+        context: &mut WorkspaceContext,
+        operation: &SavedOperation,
+        response: &BackendResponse,
+    ) -> Result<ExtractionReport, String> {
+        let (extracted, failed) = extraction::extract(&operation.rules, response);
+        let changed = self.set_variables(context, extracted).await?;
+        Ok(ExtractionReport {
+            environment_id: context.environment_id.clone(),
+            changed,
+            failed,
+        })
+    }
+
+    /// Applies what a script did to `env`
+    async fn script_variables(
+        &self,
+        context: &mut WorkspaceContext,
+        before: &HashMap<String, String>,
+        after: HashMap<String, String>,
+    ) -> Result<(), String> {
+        let values = after
+            .into_iter()
+            .filter(|(name, value)| before.get(name) != Some(value))
+            .map(|(variable, value)| Extracted { variable, value })
+            .collect();
+        self.set_variables(context, values).await.map(|_| ())
+    }
+}
+
+/// Ends a step that could not complete
+fn failed(mut result: StepResult, started: Instant, error: String) -> StepResult {
+    result.passed = false;
+    result.error = Some(error);
+    result.duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    result
+}
+
+async fn execute_step(
+    scope: &RunScope<'_>,
+    context: &mut WorkspaceContext,
+    iteration: u32,
+    index: usize,
+    operation_key: &str,
+    operation: &SavedOperation,
+) -> StepResult {
+    let started = Instant::now();
+    let mut result = StepResult {
+        iteration,
+        index,
+        operation_key: operation_key.to_string(),
+        method: None,
+        url: None,
+        status: None,
+        passed: false,
+        assertions: Vec::new(),
+        extraction: None,
+        script: None,
+        validation: None,
+        duration_ms: 0.0,
+        error: None,
+    };
+
+    let built = templating::build_request(context, operation_key, &operation.form_data);
+    let mut prepared = match built {
+        Ok(prepared) => prepared,
+        Err(e) => return failed(result, started, e),
+    };
+    let mut script_report = ScriptReport::default();
+
+    if let Some(source) = operation.scripts.pre_request() {
+        let before = context.variables.clone();
+        let (run, rewritten, after) =
+            match scripting::pre_request(source, prepared.clone(), before.clone()).await {
+                Ok(outcome) => outcome,
+                Err(e) => return failed(result, started, e),
+            };
+        let script_error = run.error.clone();
+        script_report.pre_request = Some(run);
+        if let Some(e) = script_error {
+            result.script = Some(script_report);
+            return failed(result, started, format!("Pre-request script failed: {}", e));
+        }
+        prepared = rewritten;
+        if let Err(e) = scope.script_variables(context, &before, after).await {
+            return failed(result, started, e);
+        }
+    }
+
+    result.method = Some(prepared.method.clone());
+    result.url = Some(prepared.url.clone());
+    let response = match request::execute(&scope.client, prepared.clone(), None, &|_| {}).await {
+        Ok(response) => response,
+        Err(e) => return failed(result, started, e),
+    };
+
+    result.status = Some(response.status);
+    result.validation = context
+        .spec
+        .as_ref()
+        .and_then(|spec| validation::validate_response(spec, operation_key, &response).ok());
+    result.assertions = assertions::evaluate(
+        &operation.assertions,
+        &response,
+        context.spec.as_ref(),
+        operation_key,
+    );
+    result.passed = if operation.assertions.is_empty() {
+        response.status < 400
+    } else {
+        result.assertions.iter().all(|a| a.passed)
+    };
+
+    if let Some(source) = operation.scripts.post_response() {
+        let before = context.variables.clone();
+        match scripting::post_response(source, prepared, response.clone(), before.clone()).await {
+            Ok((run, after)) => {
+                script_report.post_response = Some(run);
+                if let Err(e) = scope.script_variables(context, &before, after).await {
+                    return failed(result, started, e);
+                }
+            }
+            Err(e) => return failed(result, started, e),
+        }
+    }
+    if script_report.pre_request.is_some() || script_report.post_response.is_some() {
+        result.passed &= script_report.passed();
+        result.script = Some(script_report);
+    }
+
+    if !operation.rules.is_empty() {
+        match scope.extract(context, operation, &response).await {
+            Ok(report) => {
+                // A later step may depend on the value that wasn't found
+                result.passed &= report.failed.is_empty();
+                result.extraction = Some(report);
+            }
+            Err(e) => return failed(result, started, e),
+        }
+    }
+
+    result.duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    result
+}
+
+/// Runs the configured operations against `context`, streaming progress to `on_event`.
+/// The steps of an iteration always run in order; `concurrency` lets that many iterations
+/// run at once. With a data file, each row's values override the context's variables for
+/// one iteration. Values extracted by a step are available to every later step of its
+/// iteration and to iterations that start after it.
+///
+/// Setting `cancel` stops the run before its next request; requests already in flight
+/// finish and are reported.
+pub async fn run(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    context: WorkspaceContext,
+    run_id: &str,
+    config: &RunConfig,
+    cancel: &AtomicBool,
+    on_event: &(dyn Fn(RunEvent) + Send + Sync),
+) -> Result<RunSummary, String> {
+    let operations = load_operations(pool, &config.workspace_id, &config.operation_keys).await?;
+    let rows = match config.data_file.clone() {
+        Some(path) => Some(
+            tokio::task::spawn_blocking(move || data::load_rows(&path))
+                .await
+                .map_err(|e| e.to_string())??,
+        ),
+        None => None,
+    };
+    let scope = RunScope {
+        pool,
+        vault,
+        workspace_id: &config.workspace_id,
+        client: request::build_client()?,
+        context,
+        extracted: Mutex::new(HashMap::new()),
+        pacer: Pacer::new(Duration::from_millis(config.delay_ms.unwrap_or(0))),
+    };
+
+    let iterations = match &rows {
+        Some(rows) => rows.len() as u32,
+        None => config.iterations.unwrap_or(1).max(1),
+    };
+    let concurrency = config.concurrency.unwrap_or(1).max(1);
+    let total = iterations as usize * config.operation_keys.len();
+
+    on_event(RunEvent::Started {
+        run_id: run_id.to_string(),
+        total,
+    });
+
+    let started = Instant::now();
+    let stopped = AtomicBool::new(false);
+    let halted = || cancel.load(Ordering::SeqCst) || stopped.load(Ordering::SeqCst);
+
+    let scope = &scope;
+    let operations = &operations;
+    let stopped = &stopped;
+    let halted = &halted;
+    // Collected up front so the future returned by `run` stays `Send` when spawned
+    let iteration_runs: Vec<_> = (0..iterations)
+        .map(|iteration| {
+            let row = rows.as_ref().map(|rows| &rows[iteration as usize]);
+            async move {
+                let mut results = Vec::new();
+                if halted() {
+                    return results;
+                }
+                let mut context = scope.iteration_context(row);
+                for (index, key) in config.operation_keys.iter().enumerate() {
+                    scope.pacer.wait().await;
+                    if halted() {
+                        break;
+                    }
+
+                    on_event(RunEvent::StepStarted {
+                        iteration,
+                        index,
+                        operation_key: key.clone(),
+                    });
+                    let result =
+                        execute_step(scope, &mut context, iteration, index, key, &operations[key])
+                            .await;
+                    if !result.passed && config.stop_on_failure {
+                        stopped.store(true, Ordering::SeqCst);
+                    }
+                    on_event(RunEvent::StepFinished(Box::new(result.clone())));
+                    results.push(result);
+                }
+                results
+            }
+        })
+        .collect();
+
+    let iteration_results: Vec<Vec<StepResult>> = stream::iter(iteration_runs)
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    let mut results: Vec<StepResult> = iteration_results.into_iter().flatten().collect();
+
+    results.sort_by_key(|r| (r.iteration, r.index));
+    let passed = results.iter().filter(|r| r.passed).count();
+    let summary = RunSummary {
+        run_id: run_id.to_string(),
+        total,
+        passed,
+        failed: results.len() - passed,
+        skipped: total - results.len(),
+        cancelled: cancel.load(Ordering::SeqCst),
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+        results,
+    };
+
+    on_event(RunEvent::Finished(summary.clone()));
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::extraction::ExtractSource;
+    use crate::mock::server::{IncomingRequest, MockHandler, MockReply};
+    use crate::mock::MockServerManager;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    /// Hands out a fresh token on `/login` after a short delay and only accepts tokens it
+    /// has already handed out on `/me/{token}`
+    #[derive(Default)]
+    struct Upstream {
+        issued: Mutex<Vec<String>>,
+        arrivals: Mutex<Vec<(String, Instant)>>,
+        counter: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl MockHandler for Upstream {
+        async fn respond(&self, request: &IncomingRequest) -> MockReply {
+            self.arrivals
+                .lock()
+                .unwrap()
+                .push((request.path.clone(), Instant::now()));
+            if request.path == "/login" {
+                let token = format!("t{}", self.counter.fetch_add(1, Ordering::SeqCst));
+                self.issued.lock().unwrap().push(token.clone());
+                let mut reply = MockReply::json(200, &json!({ "token": token }));
+                reply.delay = Some(Duration::from_millis(30));
+                return reply;
+            }
+            if let Some(token) = request.path.strip_prefix("/me/") {
+                let known = self.issued.lock().unwrap().iter().any(|t| t == token);
+                return MockReply::json(if known { 200 } else { 401 }, &json!({}));
+            }
+            if request.path == "/fail" {
+                return MockReply::json(500, &json!({}));
+            }
+            MockReply::json(200, &json!({}))
+        }
+    }
+
+    struct Fixture {
+        db: Database,
+        vault: SecretVault,
+        upstream: Arc<Upstream>,
+        base_url: String,
+        _mocks: MockServerManager,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let db = Database::open_in_memory().await.unwrap();
+            let pool = db.pool();
+            repository::create_workspace(pool, "w1", "Runs", 0)
+                .await
+                .unwrap();
+            repository::save_operation_state(
+                pool,
+                "w1",
+                "get:/me/{token}",
+                r#"{"pathData": {"token": "{{token}}"}}"#,
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+            extraction::save(
+                pool,
+                "w1",
+                "post:/login",
+                &[ExtractionRule {
+                    variable: "token".to_string(),
+                    source: ExtractSource::JsonPath {
+                        path: "$.token".to_string(),
+                    },
+                }],
+            )
+            .await
+            .unwrap();
+
+            let upstream = Arc::new(Upstream::default());
+            let mocks = MockServerManager::new();
+            let (event_tx, _) = tokio::sync::mpsc::channel(1);
+            let info = mocks
+                .start("upstream".to_string(), None, upstream.clone(), event_tx)
+                .await
+                .unwrap();
+            Self {
+                db,
+                vault: SecretVault::new(),
+                upstream,
+                base_url: info.url,
+                _mocks: mocks,
+            }
+        }
+
+        fn context(&self) -> WorkspaceContext {
+            WorkspaceContext {
+                base_url: self.base_url.clone(),
+                environment_id: None,
+                spec: Some(json!({
+                    "openapi": "3.0.3",
+                    "paths": {
+                        "/login": { "post": {} },
+                        "/me/{token}": { "get": {} },
+                        "/ping": { "get": {} },
+                        "/fail": { "get": {} }
+                    }
+                })),
+                variables: HashMap::new(),
+                secret_variables: Default::default(),
+                global_headers: HashMap::new(),
+                auth_values: HashMap::new(),
+                locked_variables: Default::default(),
+                locked_auth: Default::default(),
+            }
+        }
+
+        async fn run(
+            &self,
+            config: serde_json::Value,
+            cancel: &AtomicBool,
+            on_event: &(dyn Fn(RunEvent) + Send + Sync),
+        ) -> RunSummary {
+            let mut config = config;
+            config["workspaceId"] = json!("w1");
+            let config: RunConfig = serde_json::from_value(config).unwrap();
+            run(
+                self.db.pool(),
+                &self.vault,
+                self.context(),
+                "r1",
+                &config,
+                cancel,
+                on_event,
+            )
+            .await
+            .unwrap()
+        }
+
+        fn arrivals(&self) -> Vec<(String, Instant)> {
+            self.upstream.arrivals.lock().unwrap().clone()
+        }
+    }
+
+    fn steps(summary: &RunSummary) -> Vec<(u32, usize, bool)> {
+        summary
+            .results
+            .iter()
+            .map(|r| (r.iteration, r.index, r.passed))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn steps_of_concurrent_iterations_run_in_order() {
+        let fixture = Fixture::new().await;
+        let summary = fixture
+            .run(
+                json!({
+                    "operationKeys": ["post:/login", "get:/me/{token}"],
+                    "iterations": 3,
+                    "concurrency": 3
+                }),
+                &AtomicBool::new(false),
+                &|_| {},
+            )
+            .await;
+
+        // Each `/me` call needs the token its own iteration's login extracted
+        assert_eq!(
+            steps(&summary),
+            [
+                (0, 0, true),
+                (0, 1, true),
+                (1, 0, true),
+                (1, 1, true),
+                (2, 0, true),
+                (2, 1, true)
+            ]
+        );
+        assert_eq!((summary.total, summary.passed, summary.skipped), (6, 6, 0));
+        assert_eq!(fixture.upstream.issued.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn delay_spaces_requests_across_iterations() {
+        let fixture = Fixture::new().await;
+        let summary = fixture
+            .run(
+                json!({
+                    "operationKeys": ["get:/ping"],
+                    "iterations": 3,
+                    "concurrency": 3,
+                    "delayMs": 40
+                }),
+                &AtomicBool::new(false),
+                &|_| {},
+            )
+            .await;
+
+        assert_eq!(summary.passed, 3);
+        let mut arrivals: Vec<Instant> = fixture.arrivals().into_iter().map(|(_, at)| at).collect();
+        arrivals.sort();
+        for pair in arrivals.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap >= Duration::from_millis(35), "{:?}", gap);
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_on_failure_skips_the_remaining_steps() {
+        let fixture = Fixture::new().await;
+        let config = json!({
+            "operationKeys": ["get:/fail", "get:/ping"],
+            "iterations": 2,
+            "stopOnFailure": true
+        });
+        let summary = fixture
+            .run(config.clone(), &AtomicBool::new(false), &|_| {})
+            .await;
+
+        assert_eq!(steps(&summary), [(0, 0, false)]);
+        assert_eq!((summary.total, summary.failed, summary.skipped), (4, 1, 3));
+        assert!(!summary.cancelled);
+        assert_eq!(fixture.arrivals().len(), 1);
+
+        let mut config = config;
+        config["stopOnFailure"] = json!(false);
+        let summary = fixture.run(config, &AtomicBool::new(false), &|_| {}).await;
+        assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 2, 0));
+    }
+
+    #[tokio::test]
+    async fn cancelling_stops_before_the_next_request() {
+        let fixture = Fixture::new().await;
+        let cancel = AtomicBool::new(false);
+        let events = Mutex::new(Vec::new());
+        let summary = fixture
+            .run(
+                json!({ "operationKeys": ["get:/ping", "get:/ping"], "iterations": 3 }),
+                &cancel,
+                &|event| {
+                    if let RunEvent::StepFinished(_) = event {
+                        cancel.store(true, Ordering::SeqCst);
+                    }
+                    events.lock().unwrap().push(event);
+                },
+            )
+            .await;
+
+        assert_eq!(steps(&summary), [(0, 0, true)]);
+        assert!(summary.cancelled);
+        assert_eq!(summary.skipped, 5);
+        let kinds: Vec<&str> = events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|event| match event {
+                RunEvent::Started { .. } => "started",
+                RunEvent::StepStarted { .. } => "step started",
+                RunEvent::StepFinished(_) => "step finished",
+                RunEvent::Finished(_) => "finished",
+                RunEvent::Failed { .. } => "failed",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            ["started", "step started", "step finished", "finished"]
+        );
+    }
+
+    #[tokio::test]
+    async fn runs_the_configured_number_of_iterations() {
+        let fixture = Fixture::new().await;
+        let summary = fixture
+            .run(
+                json!({ "operationKeys": ["get:/ping", "get:/fail"] }),
+                &AtomicBool::new(false),
+                &|_| {},
+            )
+            .await;
+        assert_eq!(steps(&summary), [(0, 0, true), (0, 1, false)]);
+
+        let summary = fixture
+            .run(
+                json!({ "operationKeys": ["get:/ping"], "iterations": 4, "concurrency": 2 }),
+                &AtomicBool::new(false),
+                &|_| {},
+            )
+            .await;
+        let iterations: Vec<u32> = summary.results.iter().map(|r| r.iteration).collect();
+        assert_eq!(iterations, [0, 1, 2, 3]);
+        assert_eq!(summary.total, 4);
+    }
+}
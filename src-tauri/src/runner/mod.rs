@@ -0,0 +1,208 @@
+pub mod data;
+pub mod engine;
+pub mod report;
+
+pub use engine::run;
+
+use crate::assertions::AssertionResult;
+use crate::extraction::ExtractionReport;
+use crate::scripting::ScriptReport;
+use crate::validation::ValidationReport;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// What to run: operations in order, with their saved form data
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunConfig {
+    pub workspace_id: String,
+    /// Defaults to the workspace's active environment
+    pub environment_id: Option<String>,
+    pub operation_keys: Vec<String>,
+    /// How many iterations may run at once; 1 (the default) runs them one after another.
+    /// The steps of an iteration always run in order.
+    pub concurrency: Option<usize>,
+    /// Gap between the start of one request and the next, across all iterations
+    pub delay_ms: Option<u64>,
+    /// How many times to run the whole list; defaults to 1
+    pub iterations: Option<u32>,
+    /// CSV or JSON file with one row per iteration, each overriding environment variables.
+    /// When set, the row count replaces `iterations`.
+    pub data_file: Option<String>,
+    #[serde(default)]
+    pub stop_on_failure: bool,
+}
+
+/// The outcome of one request in a run
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepResult {
+    /// Also the data file row the step ran with, counting from 0
+    pub iteration: u32,
+    pub index: usize,
+    pub operation_key: String,
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub status: Option<u16>,
+    /// All saved assertions held, or the status was below 400 when there are none,
+    /// and every extraction rule found a value and every script test passed
+    pub passed: bool,
+    pub assertions: Vec<AssertionResult>,
+    pub extraction: Option<ExtractionReport>,
+    pub script: Option<ScriptReport>,
+    /// How the response compares with the operation's declared responses
+    pub validation: Option<ValidationReport>,
+    pub duration_ms: f64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub run_id: String,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    /// Steps never started because the run stopped early
+    pub skipped: usize,
+    pub cancelled: bool,
+    pub duration_ms: f64,
+    pub results: Vec<StepResult>,
+}
+
+/// Progress of a run, streamed while it executes
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RunEvent {
+    #[serde(rename_all = "camelCase")]
+    Started {
+        run_id: String,
+        total: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    StepStarted {
+        iteration: u32,
+        index: usize,
+        operation_key: String,
+    },
+    StepFinished(Box<StepResult>),
+    Finished(RunSummary),
+    /// The run could not start or stopped on an error outside any step
+    Failed {
+        error: String,
+    },
+}
+
+/// How many finished reports are kept for export; the oldest is dropped first
+const KEPT_REPORTS: usize = 10;
+
+/// Tracks runs in progress so they can be cancelled, and keeps the latest finished
+/// reports for export
+pub struct RunManager {
+    runs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    reports: Mutex<VecDeque<RunSummary>>,
+}
+
+impl Default for RunManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunManager {
+    pub fn new() -> Self {
+        Self {
+            runs: Mutex::new(HashMap::new()),
+            reports: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Registers a run and returns the flag that cancels it
+    pub async fn register(&self, run_id: &str) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(false));
+        self.runs
+            .lock()
+            .await
+            .insert(run_id.to_string(), flag.clone());
+        flag
+    }
+
+    pub async fn finish(&self, run_id: &str, summary: Option<RunSummary>) {
+        self.runs.lock().await.remove(run_id);
+        if let Some(summary) = summary {
+            let mut reports = self.reports.lock().await;
+            if reports.len() >= KEPT_REPORTS {
+                reports.pop_front();
+            }
+            reports.push_back(summary);
+        }
+    }
+
+    pub async fn report(&self, run_id: &str) -> Result<RunSummary, String> {
+        self.reports
+            .lock()
+            .await
+            .iter()
+            .find(|summary| summary.run_id == run_id)
+            .cloned()
+            .ok_or_else(|| format!("No finished run with id: {}", run_id))
+    }
+
+    pub async fn cancel(&self, run_id: &str) -> Result<(), String> {
+        let runs = self.runs.lock().await;
+        let flag = runs
+            .get(run_id)
+            .ok_or_else(|| format!("Run not found: {}", run_id))?;
+        flag.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(run_id: &str) -> RunSummary {
+        RunSummary {
+            run_id: run_id.to_string(),
+            total: 0,
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            cancelled: false,
+            duration_ms: 0.0,
+            results: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_only_the_latest_reports() {
+        let manager = RunManager::new();
+        for i in 0..KEPT_REPORTS + 2 {
+            let run_id = format!("r{}", i);
+            manager.register(&run_id).await;
+            manager.finish(&run_id, Some(summary(&run_id))).await;
+        }
+
+        assert!(manager.report("r0").await.is_err());
+        assert!(manager.report("r1").await.is_err());
+        assert_eq!(manager.report("r2").await.unwrap().run_id, "r2");
+        let last = format!("r{}", KEPT_REPORTS + 1);
+        assert_eq!(manager.report(&last).await.unwrap().run_id, last);
+    }
+
+    #[tokio::test]
+    async fn cancels_only_runs_in_progress() {
+        let manager = RunManager::new();
+        let flag = manager.register("r1").await;
+        manager.cancel("r1").await.unwrap();
+        assert!(flag.load(Ordering::SeqCst));
+
+        manager.finish("r1", None).await;
+        assert!(manager.cancel("r1").await.is_err());
+        assert!(manager.report("r1").await.is_err());
+    }
+}
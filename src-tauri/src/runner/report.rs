@@ -0,0 +1,183 @@
+use super::{RunSummary, StepResult};
+
+/// Why a step failed, one line per reason
+pub fn failure_messages(step: &StepResult) -> Vec<String> {
+    let mut messages = Vec::new();
+    if let Some(error) = &step.error {
+        messages.push(error.clone());
+    }
+    for assertion in step.assertions.iter().filter(|a| !a.passed) {
+        messages.extend(assertion.message.clone());
+    }
+    if let Some(script) = &step.script {
+        for run in [&script.pre_request, &script.post_response]
+            .into_iter()
+            .flatten()
+        {
+            messages.extend(run.error.clone());
+            for test in run.tests.iter().filter(|t| !t.passed) {
+                messages.push(match &test.message {
+                    Some(message) => format!("Test failed: {} ({})", test.name, message),
+                    None => format!("Test failed: {}", test.name),
+                });
+            }
+        }
+    }
+    if let Some(extraction) = &step.extraction {
+        for failure in &extraction.failed {
+            messages.push(format!("{}: {}", failure.variable, failure.message));
+        }
+    }
+    if messages.is_empty() && !step.passed {
+        if let Some(status) = step.status {
+            messages.push(format!("HTTP {}", status));
+        }
+    }
+    messages
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if c.is_control() && !matches!(c, '\n' | '\t' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Renders a run as a JUnit XML report, one test case per step. Steps that never got a
+/// response are reported as errors, the rest of the failing steps as failures.
+pub fn junit(summary: &RunSummary, suite_name: &str) -> String {
+    let errors = summary
+        .results
+        .iter()
+        .filter(|r| r.status.is_none() && !r.passed)
+        .count();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+        escape_xml(suite_name),
+        summary.results.len(),
+        summary.failed - errors,
+        errors,
+        summary.skipped,
+        summary.duration_ms / 1000.0,
+    ));
+
+    for step in &summary.results {
+        let name = match (&step.method, &step.url) {
+            (Some(method), Some(url)) => {
+                format!("#{} {} {}", step.iteration + 1, method, url)
+            }
+            _ => format!("#{} {}", step.iteration + 1, step.operation_key),
+        };
+        xml.push_str(&format!(
+            "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            escape_xml(&step.operation_key),
+            escape_xml(&name),
+            step.duration_ms / 1000.0,
+        ));
+        if step.passed {
+            xml.push_str("/>\n");
+            continue;
+        }
+
+        let messages = failure_messages(step);
+        let tag = if step.status.is_none() {
+            "error"
+        } else {
+            "failure"
+        };
+        xml.push_str(&format!(
+            ">\n    <{} message=\"{}\">{}</{}>\n  </testcase>\n",
+            tag,
+            escape_xml(messages.first().map(String::as_str).unwrap_or("Failed")),
+            escape_xml(&messages.join("\n")),
+            tag,
+        ));
+    }
+
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assertions::{Assertion, AssertionResult};
+
+    fn step(index: usize, status: Option<u16>, passed: bool) -> StepResult {
+        StepResult {
+            iteration: 0,
+            index,
+            operation_key: "get:/pets".to_string(),
+            method: status.map(|_| "GET".to_string()),
+            url: status.map(|_| "https://api.test/pets?a=1&b=2".to_string()),
+            status,
+            passed,
+            assertions: Vec::new(),
+            extraction: None,
+            script: None,
+            validation: None,
+            duration_ms: 1500.0,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn lists_why_a_step_failed() {
+        let mut failing = step(0, Some(200), false);
+        failing.assertions = vec![
+            AssertionResult {
+                assertion: Assertion::Status { equals: 200 },
+                passed: true,
+                actual: None,
+                message: None,
+            },
+            AssertionResult {
+                assertion: Assertion::Status { equals: 201 },
+                passed: false,
+                actual: None,
+                message: Some("expected 201, got 200".to_string()),
+            },
+        ];
+        assert_eq!(failure_messages(&failing), ["expected 201, got 200"]);
+
+        assert_eq!(failure_messages(&step(1, Some(503), false)), ["HTTP 503"]);
+        assert!(failure_messages(&step(2, Some(200), true)).is_empty());
+    }
+
+    #[test]
+    fn renders_failures_errors_and_escaped_names() {
+        let mut unsent = step(2, None, false);
+        unsent.error = Some("connection refused <eof>".to_string());
+        let summary = RunSummary {
+            run_id: "r1".to_string(),
+            total: 4,
+            passed: 1,
+            failed: 2,
+            skipped: 1,
+            cancelled: false,
+            duration_ms: 4500.0,
+            results: vec![step(0, Some(200), true), step(1, Some(500), false), unsent],
+        };
+
+        let xml = junit(&summary, "Smoke & sanity");
+        assert!(xml.contains(
+            r#"<testsuite name="Smoke &amp; sanity" tests="3" failures="1" errors="1" skipped="1" time="4.500">"#
+        ));
+        assert!(xml.contains(r##"name="#1 GET https://api.test/pets?a=1&amp;b=2" time="1.500"/>"##));
+        assert!(xml.contains(r#"<failure message="HTTP 500">HTTP 500</failure>"#));
+        assert!(xml.contains(
+            r#"<error message="connection refused &lt;eof&gt;">connection refused &lt;eof&gt;</error>"#
+        ));
+        assert!(xml.ends_with("</testsuite>\n"));
+    }
+}
@@ -0,0 +1,255 @@
+pub mod sandbox;
+
+use crate::extraction::{self, Extracted};
+use crate::network::request::{BackendResponse, PreparedRequest};
+use crate::secrets::SecretVault;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Rhai scripts attached to an operation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationScripts {
+    /// Runs after the request is resolved and before it is sent
+    pub pre_request: Option<String>,
+    pub post_response: Option<String>,
+}
+
+impl OperationScripts {
+    fn source(script: &Option<String>) -> Option<String> {
+        script.clone().filter(|s| !s.trim().is_empty())
+    }
+
+    pub fn pre_request(&self) -> Option<String> {
+        Self::source(&self.pre_request)
+    }
+
+    pub fn post_response(&self) -> Option<String> {
+        Self::source(&self.post_response)
+    }
+}
+
+/// A result recorded by a script with `test(name, passed)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptTest {
+    pub name: String,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+/// What one script run produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptRun {
+    /// Lines written with `print` and `debug`
+    pub console: Vec<String>,
+    pub tests: Vec<ScriptTest>,
+    pub error: Option<String>,
+    pub duration_ms: f64,
+}
+
+impl ScriptRun {
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.tests.iter().all(|t| t.passed)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptReport {
+    pub pre_request: Option<ScriptRun>,
+    pub post_response: Option<ScriptRun>,
+}
+
+impl ScriptReport {
+    pub fn passed(&self) -> bool {
+        [&self.pre_request, &self.post_response]
+            .into_iter()
+            .flatten()
+            .all(ScriptRun::passed)
+    }
+}
+
+pub async fn load(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+) -> Result<OperationScripts, String> {
+    let row: Option<(Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT pre_request, post_response FROM operation_scripts WHERE workspace_id = ? AND operation_key = ?",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(row
+        .map(|(pre_request, post_response)| OperationScripts {
+            pre_request,
+            post_response,
+        })
+        .unwrap_or_default())
+}
+
+pub async fn save(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+    scripts: &OperationScripts,
+) -> Result<(), String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    sqlx::query(
+        "INSERT OR REPLACE INTO operation_scripts (workspace_id, operation_key, pre_request, post_response, last_modified)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .bind(&scripts.pre_request)
+    .bind(&scripts.post_response)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Runs a pre-request script off the async runtime, returning the rewritten request
+pub async fn pre_request(
+    source: String,
+    mut request: PreparedRequest,
+    mut variables: HashMap<String, String>,
+) -> Result<(ScriptRun, PreparedRequest, HashMap<String, String>), String> {
+    tokio::task::spawn_blocking(move || {
+        let run = sandbox::run_pre_request(&source, &mut request, &mut variables);
+        (run, request, variables)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Runs a post-response script off the async runtime
+pub async fn post_response(
+    source: String,
+    request: PreparedRequest,
+    response: BackendResponse,
+    mut variables: HashMap<String, String>,
+) -> Result<(ScriptRun, HashMap<String, String>), String> {
+    tokio::task::spawn_blocking(move || {
+        let run = sandbox::run_post_response(&source, &request, &response, &mut variables);
+        (run, variables)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Stores the variables a script set or changed. Without an environment nothing is stored.
+pub async fn persist_variables(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    environment_id: Option<&str>,
+    before: &HashMap<String, String>,
+    after: &HashMap<String, String>,
+) -> Result<Vec<String>, String> {
+    let Some(environment_id) = environment_id else {
+        return Ok(Vec::new());
+    };
+    let updated: Vec<Extracted> = after
+        .iter()
+        .filter(|(name, value)| before.get(*name) != Some(*value))
+        .map(|(name, value)| Extracted {
+            variable: name.clone(),
+            value: value.clone(),
+        })
+        .collect();
+    extraction::store(pool, vault, workspace_id, environment_id, &updated).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+
+    #[tokio::test]
+    async fn saved_scripts_round_trip_and_blank_ones_are_skipped() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+
+        assert!(load(pool, "w1", "get:/pets")
+            .await
+            .unwrap()
+            .pre_request()
+            .is_none());
+        let scripts = OperationScripts {
+            pre_request: Some("  \n".to_string()),
+            post_response: Some(r#"test("ok", true);"#.to_string()),
+        };
+        save(pool, "w1", "get:/pets", &scripts).await.unwrap();
+
+        let loaded = load(pool, "w1", "get:/pets").await.unwrap();
+        assert_eq!(loaded.pre_request(), None);
+        assert_eq!(
+            loaded.post_response().as_deref(),
+            Some(r#"test("ok", true);"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn script_variables_are_stored_in_the_environment() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        repository::create_environment(pool, "e1", "w1", "dev")
+            .await
+            .unwrap();
+
+        let request = PreparedRequest {
+            method: "GET".to_string(),
+            url: "https://api.test/pets".to_string(),
+            headers: HashMap::new(),
+            body: None,
+        };
+        let before = HashMap::from([("host".to_string(), "api.test".to_string())]);
+        let (run, rewritten, after) = pre_request(
+            r#"env.token = "t0ken"; request.url += "?page=2";"#.to_string(),
+            request,
+            before.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(run.passed());
+        assert_eq!(rewritten.url, "https://api.test/pets?page=2");
+
+        // Without an environment there is nowhere to keep them
+        assert!(persist_variables(pool, &vault, "w1", None, &before, &after)
+            .await
+            .unwrap()
+            .is_empty());
+        let changed = persist_variables(pool, &vault, "w1", Some("e1"), &before, &after)
+            .await
+            .unwrap();
+        assert_eq!(changed, ["token"]);
+        let (value,): (String,) = sqlx::query_as(
+            "SELECT v.value FROM environment_variable_values v
+             INNER JOIN workspace_variable_keys k ON k.id = v.variable_key_id
+             WHERE v.environment_id = 'e1' AND k.key_name = 'token'",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        assert_eq!(value, "t0ken");
+    }
+}
@@ -0,0 +1,437 @@
+use super::{ScriptRun, ScriptTest};
+use crate::network::request::{BackendResponse, PreparedRequest};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Wall-clock limit for one script
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_OPERATIONS: u64 = 50_000_000;
+/// Size limits keep a runaway script from exhausting memory
+const MAX_STRING_SIZE: usize = 4 * 1024 * 1024;
+const MAX_ARRAY_SIZE: usize = 100_000;
+const MAX_MAP_SIZE: usize = 100_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_CONSOLE_LINES: usize = 1000;
+
+/// Console output and test results collected while a script runs
+#[derive(Default)]
+struct Capture {
+    console: Vec<String>,
+    tests: Vec<ScriptTest>,
+}
+
+impl Capture {
+    fn log(&mut self, line: String) {
+        if self.console.len() < MAX_CONSOLE_LINES {
+            self.console.push(line);
+        }
+    }
+}
+
+fn script_error(message: impl Into<String>) -> Box<EvalAltResult> {
+    message.into().into()
+}
+
+fn hmac_sha256(key: &str, message: &str) -> Vec<u8> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Locks a script's capture, recovering it if a callback panicked while holding the lock
+fn lock(capture: &Mutex<Capture>) -> MutexGuard<'_, Capture> {
+    capture.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Builds an engine with no file, module, or `eval` access and the helpers scripts rely on
+fn build_engine(capture: &Arc<Mutex<Capture>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_ARRAY_SIZE);
+    engine.set_max_map_size(MAX_MAP_SIZE);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+
+    let started = Instant::now();
+    engine.on_progress(move |_| (started.elapsed() > TIMEOUT).then_some(Dynamic::UNIT));
+
+    let sink = capture.clone();
+    engine.on_print(move |text| lock(&sink).log(text.to_string()));
+    let sink = capture.clone();
+    engine.on_debug(move |text, _, _| lock(&sink).log(text.to_string()));
+
+    let sink = capture.clone();
+    engine.register_fn("test", move |name: &str, passed: bool| {
+        lock(&sink).tests.push(ScriptTest {
+            name: name.to_string(),
+            passed,
+            message: None,
+        });
+    });
+    let sink = capture.clone();
+    engine.register_fn("test", move |name: &str, passed: bool, message: &str| {
+        lock(&sink).tests.push(ScriptTest {
+            name: name.to_string(),
+            passed,
+            message: Some(message.to_string()),
+        });
+    });
+
+    engine.register_fn("uuid", || uuid::Uuid::new_v4().to_string());
+    engine.register_fn("now_ms", || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default()
+    });
+    engine.register_fn("random_hex", |bytes: i64| {
+        let bytes: Vec<u8> = (0..bytes.clamp(0, 1024))
+            .map(|_| rand::thread_rng().gen())
+            .collect();
+        hex(&bytes)
+    });
+    engine.register_fn("sha256_hex", |text: &str| {
+        hex(&Sha256::digest(text.as_bytes()))
+    });
+    engine.register_fn("hmac_sha256_hex", |key: &str, message: &str| {
+        hex(&hmac_sha256(key, message))
+    });
+    engine.register_fn("hmac_sha256_base64", |key: &str, message: &str| {
+        BASE64.encode(hmac_sha256(key, message))
+    });
+    engine.register_fn("base64_encode", |text: &str| BASE64.encode(text));
+    engine.register_fn(
+        "base64_decode",
+        |text: &str| -> Result<String, Box<EvalAltResult>> {
+            let bytes = BASE64
+                .decode(text)
+                .map_err(|e| script_error(e.to_string()))?;
+            String::from_utf8(bytes).map_err(|e| script_error(e.to_string()))
+        },
+    );
+    engine.register_fn(
+        "parse_json",
+        |text: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+            let value: Value =
+                serde_json::from_str(text).map_err(|e| script_error(e.to_string()))?;
+            rhai::serde::to_dynamic(value)
+        },
+    );
+    engine.register_fn(
+        "to_json",
+        |value: Dynamic| -> Result<String, Box<EvalAltResult>> {
+            let value: Value = rhai::serde::from_dynamic(&value)?;
+            Ok(value.to_string())
+        },
+    );
+
+    engine
+}
+
+fn variables_map(variables: &HashMap<String, String>) -> Map {
+    variables
+        .iter()
+        .map(|(k, v)| (k.as_str().into(), Dynamic::from(v.clone())))
+        .collect()
+}
+
+/// Reads `env` back after a script; non-string values are stored as their text
+fn read_variables(scope: &Scope, variables: &mut HashMap<String, String>) {
+    let Some(env) = scope.get_value::<Map>("env") else {
+        return;
+    };
+    for (key, value) in env {
+        if value.is_unit() {
+            continue;
+        }
+        let value = match value.clone().into_string() {
+            Ok(s) => s,
+            Err(_) => value.to_string(),
+        };
+        variables.insert(key.to_string(), value);
+    }
+}
+
+fn run(
+    source: &str,
+    scope: &mut Scope,
+    capture: &Arc<Mutex<Capture>>,
+) -> (Option<String>, Duration) {
+    let engine = build_engine(capture);
+    let started = Instant::now();
+    // The engine is third-party code running untrusted input; a panic in it fails the script
+    let result =
+        std::panic::catch_unwind(AssertUnwindSafe(|| engine.run_with_scope(scope, source)));
+    let error = match result {
+        Err(_) => Some("The script engine failed unexpectedly".to_string()),
+        Ok(Ok(())) => None,
+        Ok(Err(e)) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => Some(format!(
+            "Script exceeded the {} second time limit",
+            TIMEOUT.as_secs()
+        )),
+        Ok(Err(e)) => Some(e.to_string()),
+    };
+    (error, started.elapsed())
+}
+
+fn finish(capture: Arc<Mutex<Capture>>, error: Option<String>, elapsed: Duration) -> ScriptRun {
+    let capture = std::mem::take(&mut *lock(&capture));
+    ScriptRun {
+        console: capture.console,
+        tests: capture.tests,
+        error,
+        duration_ms: elapsed.as_secs_f64() * 1000.0,
+    }
+}
+
+/// Runs a pre-request script, which may rewrite `request` and `variables`. Neither is
+/// changed when the script fails.
+pub fn run_pre_request(
+    source: &str,
+    request: &mut PreparedRequest,
+    variables: &mut HashMap<String, String>,
+) -> ScriptRun {
+    let capture = Arc::new(Mutex::new(Capture::default()));
+    let mut scope = Scope::new();
+    match rhai::serde::to_dynamic(&*request) {
+        Ok(value) => scope.push("request", value),
+        Err(e) => return finish(capture, Some(e.to_string()), Duration::ZERO),
+    };
+    scope.push("env", variables_map(variables));
+
+    let (mut error, elapsed) = run(source, &mut scope, &capture);
+    if error.is_none() {
+        let updated = scope
+            .get_value::<Dynamic>("request")
+            .ok_or_else(|| "The script removed `request`".to_string())
+            .and_then(|value| {
+                rhai::serde::from_dynamic::<PreparedRequest>(&value)
+                    .map_err(|e| format!("Invalid request after script: {}", e))
+            });
+        match updated {
+            Ok(updated) => {
+                *request = updated;
+                read_variables(&scope, variables);
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+    finish(capture, error, elapsed)
+}
+
+/// Runs a post-response script with the request and response, whose changes are
+/// discarded. It may update `variables` and record tests.
+pub fn run_post_response(
+    source: &str,
+    request: &PreparedRequest,
+    response: &BackendResponse,
+    variables: &mut HashMap<String, String>,
+) -> ScriptRun {
+    let capture = Arc::new(Mutex::new(Capture::default()));
+    let response_value = json!({
+        "status": response.status,
+        "statusText": response.status_text,
+        "headers": response.headers,
+        "body": response.body_text,
+        "json": serde_json::from_str::<Value>(&response.body_text).ok(),
+        "timeMs": response.timings.total_ms,
+    });
+
+    let mut scope = Scope::new();
+    for (name, value) in [
+        ("request", rhai::serde::to_dynamic(request)),
+        ("response", rhai::serde::to_dynamic(&response_value)),
+    ] {
+        match value {
+            Ok(value) => scope.push(name, value),
+            Err(e) => return finish(capture, Some(e.to_string()), Duration::ZERO),
+        };
+    }
+    scope.push("env", variables_map(variables));
+
+    let (error, elapsed) = run(source, &mut scope, &capture);
+    if error.is_none() {
+        read_variables(&scope, variables);
+    }
+    finish(capture, error, elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> PreparedRequest {
+        PreparedRequest {
+            method: "POST".to_string(),
+            url: "https://api.test/pets".to_string(),
+            headers: HashMap::from([("accept".to_string(), "application/json".to_string())]),
+            body: Some(r#"{"name":"Rex"}"#.to_string()),
+        }
+    }
+
+    fn variables() -> HashMap<String, String> {
+        HashMap::from([("token".to_string(), "old".to_string())])
+    }
+
+    /// Runs a pre-request script that must fail, returning its error
+    fn pre_request_error(source: &str) -> String {
+        let mut request = request();
+        let mut variables = variables();
+        let run = run_pre_request(source, &mut request, &mut variables);
+        assert_eq!(request.url, "https://api.test/pets");
+        assert_eq!(variables, self::variables());
+        run.error.expect("the script should fail")
+    }
+
+    #[test]
+    fn pre_request_scripts_rewrite_the_request_and_env() {
+        let mut request = request();
+        let mut variables = variables();
+        let run = run_pre_request(
+            r#"
+                request.url += "?signed=1";
+                request.headers["x-signature"] = sha256_hex(request.body);
+                request.body = to_json(#{ name: parse_json(request.body).name, tag: "dog" });
+                env.token = "new";
+                env.count = 3;
+                print("signed " + request.url);
+            "#,
+            &mut request,
+            &mut variables,
+        );
+
+        assert_eq!(run.error, None);
+        assert_eq!(run.console, ["signed https://api.test/pets?signed=1"]);
+        assert_eq!(request.url, "https://api.test/pets?signed=1");
+        assert_eq!(
+            request.headers["x-signature"],
+            hex(&Sha256::digest(br#"{"name":"Rex"}"#))
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(request.body.as_deref().unwrap()).unwrap(),
+            json!({ "name": "Rex", "tag": "dog" })
+        );
+        assert_eq!(variables["token"], "new");
+        assert_eq!(variables["count"], "3");
+    }
+
+    #[test]
+    fn failed_scripts_change_nothing() {
+        let error = pre_request_error(
+            r#"request.url = "https://evil.test"; env.token = "x"; throw "stop";"#,
+        );
+        assert!(error.contains("stop"), "{}", error);
+
+        let error = pre_request_error("request = 42;");
+        assert!(
+            error.starts_with("Invalid request after script"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn post_response_scripts_record_tests_and_write_env() {
+        let mut response = BackendResponse::not_sent(String::new());
+        response.status = 201;
+        response.body_text = r#"{"id": 7}"#.to_string();
+        let mut variables = variables();
+
+        let run = run_post_response(
+            r#"
+                test("created", response.status == 201);
+                test("has name", response.json.name != (), "no name in body");
+                env.pet_id = response.json.id;
+                env.token = ();
+                request.url = "ignored";
+            "#,
+            &request(),
+            &response,
+            &mut variables,
+        );
+
+        assert_eq!(run.error, None);
+        assert!(!run.passed());
+        let tests: Vec<(&str, bool, Option<&str>)> = run
+            .tests
+            .iter()
+            .map(|t| (t.name.as_str(), t.passed, t.message.as_deref()))
+            .collect();
+        assert_eq!(
+            tests,
+            [
+                ("created", true, None),
+                ("has name", false, Some("no name in body"))
+            ]
+        );
+        assert_eq!(variables["pet_id"], "7");
+        // Unit values are skipped rather than stored
+        assert_eq!(variables["token"], "old");
+    }
+
+    #[test]
+    fn runaway_scripts_are_stopped() {
+        let started = Instant::now();
+        let error = pre_request_error("let n = 0; loop { n += 1; }");
+        assert!(started.elapsed() < TIMEOUT + Duration::from_secs(5));
+        assert!(
+            error.contains("time limit") || error.contains("operations"),
+            "{}",
+            error
+        );
+
+        let error = pre_request_error(r#"let s = "x"; loop { s += s; }"#);
+        assert!(
+            error.to_lowercase().contains("length of string"),
+            "{}",
+            error
+        );
+
+        let error = pre_request_error("let a = [0]; loop { a += a; }");
+        assert!(error.to_lowercase().contains("size of array"), "{}", error);
+
+        let error = pre_request_error("fn f(n) { f(n + 1) } f(0);");
+        assert!(error.to_lowercase().contains("stack overflow"), "{}", error);
+    }
+
+    #[test]
+    fn eval_and_imports_are_blocked() {
+        pre_request_error(r#"eval("env.token = \"x\"");"#);
+        pre_request_error(r#"import "std" as std;"#);
+        pre_request_error(r#"import "/etc/passwd" as secrets;"#);
+    }
+
+    #[test]
+    fn a_poisoned_capture_is_recovered() {
+        let capture = Arc::new(Mutex::new(Capture::default()));
+        lock(&capture).log("before".to_string());
+        let poisoner = capture.clone();
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.lock().unwrap();
+            panic!("callback panicked");
+        })
+        .join();
+        assert!(capture.is_poisoned());
+
+        lock(&capture).log("after".to_string());
+        let run = finish(capture, None, Duration::ZERO);
+        assert_eq!(run.console, ["before", "after"]);
+    }
+}
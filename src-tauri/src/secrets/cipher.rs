@@ -0,0 +1,118 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rand::RngCore;
+
+/// Marker prepended to every encrypted value stored in the database
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+pub const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+pub type SecretKey = [u8; KEY_LEN];
+
+/// Generates a random base64-encoded salt for key derivation
+pub fn generate_salt() -> String {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    BASE64.encode(salt)
+}
+
+/// Derives a 256-bit key from a passphrase (or key file contents) with Argon2id
+pub fn derive_key(secret: &[u8], salt: &str) -> Result<SecretKey, String> {
+    let salt_bytes = BASE64.decode(salt).map_err(|e| e.to_string())?;
+    let mut key = [0u8; KEY_LEN];
+    argon2::Argon2::default()
+        .hash_password_into(secret, &salt_bytes, &mut key)
+        .map_err(|e| e.to_string())?;
+    Ok(key)
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Encrypts a value with AES-256-GCM, returning `enc:v1:<base64(nonce || ciphertext)>`
+pub fn encrypt(key: &SecretKey, plaintext: &str) -> Result<String, String> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce_bytes);
+
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+        .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+    let mut payload = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    payload.extend_from_slice(&nonce_bytes);
+    payload.extend_from_slice(&ciphertext);
+
+    Ok(format!("{}{}", ENCRYPTED_PREFIX, BASE64.encode(payload)))
+}
+
+/// Decrypts a value produced by [`encrypt`]
+pub fn decrypt(key: &SecretKey, value: &str) -> Result<String, String> {
+    let encoded = value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| "Value is not encrypted".to_string())?;
+    let payload = BASE64.decode(encoded).map_err(|e| e.to_string())?;
+
+    if payload.len() < NONCE_LEN {
+        return Err("Encrypted value is truncated".to_string());
+    }
+    let (nonce_bytes, ciphertext) = payload.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| "Failed to decrypt secret — wrong key or corrupted value".to_string())?;
+
+    String::from_utf8(plaintext).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(passphrase: &str, salt: &str) -> SecretKey {
+        derive_key(passphrase.as_bytes(), salt).unwrap()
+    }
+
+    #[test]
+    fn encrypt_round_trips_with_a_fresh_nonce() {
+        let key = key("correct horse", &generate_salt());
+        let first = encrypt(&key, "s3cret").unwrap();
+        let second = encrypt(&key, "s3cret").unwrap();
+
+        assert!(is_encrypted(&first));
+        assert_ne!(first, second);
+        assert_eq!(decrypt(&key, &first).unwrap(), "s3cret");
+        assert_eq!(decrypt(&key, &second).unwrap(), "s3cret");
+    }
+
+    #[test]
+    fn derived_keys_depend_on_passphrase_and_salt() {
+        let salt = generate_salt();
+        assert_eq!(key("a", &salt), key("a", &salt));
+        assert_ne!(key("a", &salt), key("b", &salt));
+        assert_ne!(key("a", &salt), key("a", &generate_salt()));
+    }
+
+    #[test]
+    fn decrypt_rejects_wrong_keys_and_damaged_values() {
+        let salt = generate_salt();
+        let sealed = encrypt(&key("right", &salt), "s3cret").unwrap();
+
+        assert!(decrypt(&key("wrong", &salt), &sealed).is_err());
+        assert!(decrypt(&key("right", &salt), "s3cret").is_err());
+        assert!(decrypt(&key("right", &salt), "enc:v1:AAAA").is_err());
+
+        let mut tampered = sealed.into_bytes();
+        let i = ENCRYPTED_PREFIX.len() + 20;
+        tampered[i] = if tampered[i] == b'A' { b'B' } else { b'A' };
+        let tampered = String::from_utf8(tampered).unwrap();
+        assert!(decrypt(&key("right", &salt), &tampered).is_err());
+    }
+}
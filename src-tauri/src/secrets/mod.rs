@@ -0,0 +1,5 @@
+pub mod cipher;
+pub mod store;
+pub mod vault;
+
+pub use vault::{SecretVault, VaultCredential, VaultStatus};
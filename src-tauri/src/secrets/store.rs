@@ -0,0 +1,392 @@
+use super::cipher;
+use super::vault::SecretVault;
+use serde_json::{Map, Value};
+use sqlx::{SqliteConnection, SqlitePool};
+
+/// Shown to the frontend in place of a secret; writing it back leaves the secret unchanged
+pub const MASKED_VALUE: &str = "••••••••";
+
+/// What the frontend sees for a stored variable value
+pub fn mask_variable_value(stored: &str) -> String {
+    if cipher::is_encrypted(stored) {
+        MASKED_VALUE.to_string()
+    } else {
+        stored.to_string()
+    }
+}
+
+/// What the frontend sees for a stored auth value: each non-empty field of a secret value
+/// is masked. While the vault is locked the fields can't be read, so none are shown.
+pub fn mask_auth_json(vault: &SecretVault, stored: &str) -> String {
+    if !cipher::is_encrypted(stored) {
+        return stored.to_string();
+    }
+    let fields = vault
+        .reveal(stored)
+        .ok()
+        .and_then(|plaintext| serde_json::from_str::<Map<String, Value>>(&plaintext).ok())
+        .unwrap_or_default();
+    let masked: Map<String, Value> = fields
+        .into_iter()
+        .map(|(field, value)| {
+            let empty = value.as_str().is_some_and(str::is_empty);
+            let value = if empty {
+                value
+            } else {
+                Value::String(MASKED_VALUE.to_string())
+            };
+            (field, value)
+        })
+        .collect();
+    Value::Object(masked).to_string()
+}
+
+/// Puts back the stored fields of an auth value wherever `value_json` still holds the mask
+fn unmask_auth_json(vault: &SecretVault, stored: &str, value_json: &str) -> Result<String, String> {
+    if !value_json.contains(MASKED_VALUE) {
+        return Ok(value_json.to_string());
+    }
+    let stored: Map<String, Value> =
+        serde_json::from_str(&vault.reveal(stored)?).map_err(|e| e.to_string())?;
+    let mut fields: Map<String, Value> =
+        serde_json::from_str(value_json).map_err(|e| e.to_string())?;
+    for (field, value) in fields.iter_mut() {
+        if value.as_str() == Some(MASKED_VALUE) {
+            *value = stored.get(field).cloned().unwrap_or_default();
+        }
+    }
+    Ok(Value::Object(fields).to_string())
+}
+
+/// Flags (or unflags) a variable key as secret and re-encodes all of its values
+pub async fn set_variable_secret(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    variable_key_id: i64,
+    is_secret: bool,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+
+    let updated = sqlx::query("UPDATE workspace_variable_keys SET is_secret = ? WHERE id = ?")
+        .bind(is_secret)
+        .bind(variable_key_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    if updated.rows_affected() == 0 {
+        return Err(format!("Variable key not found: {}", variable_key_id));
+    }
+
+    let values: Vec<(String, String)> = sqlx::query_as(
+        "SELECT environment_id, value FROM environment_variable_values WHERE variable_key_id = ?",
+    )
+    .bind(variable_key_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for (environment_id, value) in values {
+        let encoded = encode_value(vault, &value, is_secret)?;
+        if encoded == value {
+            continue;
+        }
+        sqlx::query(
+            "UPDATE environment_variable_values SET value = ? WHERE environment_id = ? AND variable_key_id = ?",
+        )
+        .bind(&encoded)
+        .bind(&environment_id)
+        .bind(variable_key_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+/// Writes a variable value, encrypting it when its key is flagged secret. The mask shown
+/// for a secret keeps the stored value.
+pub async fn set_variable_value(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    environment_id: &str,
+    variable_key_id: i64,
+    value: &str,
+) -> Result<(), String> {
+    let mut conn = pool.acquire().await.map_err(|e| e.to_string())?;
+    write_variable_value(&mut conn, vault, environment_id, variable_key_id, value).await
+}
+
+/// [`set_variable_value`] on a given connection, so callers can batch writes in a transaction
+pub async fn write_variable_value(
+    conn: &mut SqliteConnection,
+    vault: &SecretVault,
+    environment_id: &str,
+    variable_key_id: i64,
+    value: &str,
+) -> Result<(), String> {
+    let is_secret: Option<(bool,)> =
+        sqlx::query_as("SELECT is_secret FROM workspace_variable_keys WHERE id = ?")
+            .bind(variable_key_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (is_secret,) =
+        is_secret.ok_or_else(|| format!("Variable key not found: {}", variable_key_id))?;
+    if is_secret && value == MASKED_VALUE {
+        return Ok(());
+    }
+
+    let encoded = encode_value(vault, value, is_secret)?;
+    sqlx::query(
+        "INSERT OR REPLACE INTO environment_variable_values (environment_id, variable_key_id, value) VALUES (?, ?, ?)",
+    )
+    .bind(environment_id)
+    .bind(variable_key_id)
+    .bind(&encoded)
+    .execute(conn)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Flags (or unflags) an auth value as secret and re-encodes its stored JSON
+pub async fn set_auth_secret(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    environment_id: Option<&str>,
+    scheme_name: &str,
+    is_secret: bool,
+) -> Result<(), String> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT value_json FROM auth_values WHERE workspace_id = ? AND environment_id IS ? AND scheme_name = ?",
+    )
+    .bind(workspace_id)
+    .bind(environment_id)
+    .bind(scheme_name)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let (value_json,) = row.ok_or_else(|| format!("Auth value not found: {}", scheme_name))?;
+
+    let encoded = encode_value(vault, &value_json, is_secret)?;
+    sqlx::query(
+        "UPDATE auth_values SET value_json = ?, is_secret = ?
+         WHERE workspace_id = ? AND environment_id IS ? AND scheme_name = ?",
+    )
+    .bind(&encoded)
+    .bind(is_secret)
+    .bind(workspace_id)
+    .bind(environment_id)
+    .bind(scheme_name)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Writes an auth value, encrypting it when `is_secret` is set. Fields still holding the
+/// mask keep their stored values.
+pub async fn set_auth_value(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    environment_id: Option<&str>,
+    scheme_name: &str,
+    value_json: &str,
+    is_secret: bool,
+) -> Result<(), String> {
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let existing: Option<(String,)> = sqlx::query_as(
+        "SELECT value_json FROM auth_values WHERE workspace_id = ? AND environment_id IS ? AND scheme_name = ?",
+    )
+    .bind(workspace_id)
+    .bind(environment_id)
+    .bind(scheme_name)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    let value_json = match existing {
+        Some((stored,)) if cipher::is_encrypted(&stored) => {
+            unmask_auth_json(vault, &stored, value_json)?
+        }
+        _ => value_json.to_string(),
+    };
+    let encoded = encode_value(vault, &value_json, is_secret)?;
+
+    // A NULL environment never conflicts in the primary key, so `INSERT OR REPLACE` would
+    // pile up copies of a global value; replace the row explicitly instead
+    sqlx::query(
+        "DELETE FROM auth_values WHERE workspace_id = ? AND environment_id IS ? AND scheme_name = ?",
+    )
+    .bind(workspace_id)
+    .bind(environment_id)
+    .bind(scheme_name)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+    sqlx::query(
+        "INSERT INTO auth_values (workspace_id, environment_id, scheme_name, value_json, is_secret)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(environment_id)
+    .bind(scheme_name)
+    .bind(&encoded)
+    .bind(is_secret)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().await.map_err(|e| e.to_string())
+}
+
+fn encode_value(vault: &SecretVault, value: &str, is_secret: bool) -> Result<String, String> {
+    match (is_secret, cipher::is_encrypted(value)) {
+        (true, false) => vault.seal(value),
+        (false, true) => vault.reveal(value),
+        _ => Ok(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::secrets::VaultCredential;
+
+    /// A workspace with one environment and one variable key, and a vault that isn't unlocked yet
+    async fn setup() -> (Database, SecretVault, i64) {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        repository::create_environment(pool, "e1", "w1", "dev")
+            .await
+            .unwrap();
+        let key = repository::add_variable_key(pool, "w1", "token")
+            .await
+            .unwrap();
+        (db, SecretVault::new(), key)
+    }
+
+    async fn unlock(db: &Database, vault: &SecretVault) {
+        vault
+            .unlock(db.pool(), &VaultCredential::Passphrase("pw".to_string()))
+            .await
+            .unwrap();
+    }
+
+    async fn stored_variable(pool: &SqlitePool) -> String {
+        let (value,): (String,) = sqlx::query_as("SELECT value FROM environment_variable_values")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        value
+    }
+
+    async fn stored_auth(pool: &SqlitePool) -> String {
+        let (value_json,): (String,) = sqlx::query_as("SELECT value_json FROM auth_values")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        value_json
+    }
+
+    #[tokio::test]
+    async fn secret_variables_are_encrypted_on_write_and_masked_on_read() {
+        let (db, vault, key) = setup().await;
+        let pool = db.pool();
+        unlock(&db, &vault).await;
+        set_variable_secret(pool, &vault, key, true).await.unwrap();
+        set_variable_value(pool, &vault, "e1", key, "t0ken")
+            .await
+            .unwrap();
+
+        let stored = stored_variable(pool).await;
+        assert!(cipher::is_encrypted(&stored));
+        assert!(!stored.contains("t0ken"));
+        assert_eq!(mask_variable_value(&stored), MASKED_VALUE);
+        assert_eq!(vault.reveal(&stored).unwrap(), "t0ken");
+
+        // Locked, the value is still masked but can't be read or replaced
+        vault.lock();
+        assert_eq!(mask_variable_value(&stored), MASKED_VALUE);
+        assert!(vault.reveal(&stored).is_err());
+        assert!(set_variable_value(pool, &vault, "e1", key, "new")
+            .await
+            .is_err());
+        set_variable_value(pool, &vault, "e1", key, MASKED_VALUE)
+            .await
+            .unwrap();
+        assert_eq!(stored_variable(pool).await, stored);
+
+        // Unflagging the key stores the plaintext again
+        unlock(&db, &vault).await;
+        set_variable_secret(pool, &vault, key, false).await.unwrap();
+        assert_eq!(stored_variable(pool).await, "t0ken");
+        assert_eq!(mask_variable_value("t0ken"), "t0ken");
+    }
+
+    #[tokio::test]
+    async fn secret_auth_values_are_masked_per_field_and_hidden_while_locked() {
+        let (db, vault, _) = setup().await;
+        let pool = db.pool();
+        unlock(&db, &vault).await;
+        set_auth_value(
+            pool,
+            &vault,
+            "w1",
+            None,
+            "basic",
+            r#"{"username":"me","password":""}"#,
+            true,
+        )
+        .await
+        .unwrap();
+
+        let stored = stored_auth(pool).await;
+        assert!(cipher::is_encrypted(&stored));
+        let masked: Value = serde_json::from_str(&mask_auth_json(&vault, &stored)).unwrap();
+        assert_eq!(
+            masked,
+            serde_json::json!({ "username": MASKED_VALUE, "password": "" })
+        );
+
+        vault.lock();
+        assert_eq!(mask_auth_json(&vault, &stored), "{}");
+        let plain = r#"{"username":"me"}"#;
+        assert_eq!(mask_auth_json(&vault, plain), plain);
+    }
+
+    #[tokio::test]
+    async fn already_encrypted_values_are_not_encrypted_again() {
+        let (db, vault, key) = setup().await;
+        let pool = db.pool();
+        unlock(&db, &vault).await;
+        set_variable_secret(pool, &vault, key, true).await.unwrap();
+
+        let sealed = vault.seal("t0ken").unwrap();
+        set_variable_value(pool, &vault, "e1", key, &sealed)
+            .await
+            .unwrap();
+        assert_eq!(stored_variable(pool).await, sealed);
+        set_variable_secret(pool, &vault, key, true).await.unwrap();
+        assert_eq!(stored_variable(pool).await, sealed);
+        assert_eq!(vault.reveal(&sealed).unwrap(), "t0ken");
+
+        let sealed_auth = vault.seal(r#"{"token":"abc"}"#).unwrap();
+        set_auth_value(pool, &vault, "w1", None, "bearer", &sealed_auth, true)
+            .await
+            .unwrap();
+        assert_eq!(stored_auth(pool).await, sealed_auth);
+
+        // Locked, a value that came in already encrypted still reads back masked
+        vault.lock();
+        assert_eq!(mask_variable_value(&sealed), MASKED_VALUE);
+    }
+}
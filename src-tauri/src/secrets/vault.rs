@@ -0,0 +1,257 @@
+use super::cipher::{self, SecretKey};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::sync::RwLock;
+
+const KDF_NAME: &str = "argon2id";
+const VERIFIER_PLAINTEXT: &str = "cogeass-vault";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum VaultCredential {
+    Passphrase(String),
+    KeyFile(String),
+}
+
+impl VaultCredential {
+    fn secret_bytes(&self) -> Result<Vec<u8>, String> {
+        match self {
+            VaultCredential::Passphrase(passphrase) => {
+                if passphrase.is_empty() {
+                    return Err("Passphrase must not be empty".to_string());
+                }
+                Ok(passphrase.as_bytes().to_vec())
+            }
+            VaultCredential::KeyFile(path) => {
+                let bytes = std::fs::read(path)
+                    .map_err(|e| format!("Failed to read key file {}: {}", path, e))?;
+                if bytes.is_empty() {
+                    return Err(format!("Key file is empty: {}", path));
+                }
+                Ok(bytes)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VaultStatus {
+    pub initialized: bool,
+    pub unlocked: bool,
+}
+
+/// Holds the derived encryption key while the vault is unlocked.
+///
+/// The key only ever lives in memory; the database stores the salt and an
+/// encrypted verifier so a wrong passphrase is rejected before any value is touched.
+pub struct SecretVault {
+    key: RwLock<Option<SecretKey>>,
+}
+
+impl Default for SecretVault {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecretVault {
+    pub fn new() -> Self {
+        Self {
+            key: RwLock::new(None),
+        }
+    }
+
+    pub async fn status(&self, pool: &SqlitePool) -> Result<VaultStatus, String> {
+        let initialized: Option<(i64,)> = sqlx::query_as("SELECT id FROM vault_meta WHERE id = 1")
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(VaultStatus {
+            initialized: initialized.is_some(),
+            unlocked: self.is_unlocked(),
+        })
+    }
+
+    /// Unlocks the vault, initializing it with the given credential on first use
+    pub async fn unlock(
+        &self,
+        pool: &SqlitePool,
+        credential: &VaultCredential,
+    ) -> Result<(), String> {
+        let secret = credential.secret_bytes()?;
+
+        let meta: Option<(String, String)> =
+            sqlx::query_as("SELECT salt, verifier FROM vault_meta WHERE id = 1")
+                .fetch_optional(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+        let key = match meta {
+            Some((salt, verifier)) => {
+                let key = cipher::derive_key(&secret, &salt)?;
+                match cipher::decrypt(&key, &verifier) {
+                    Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => key,
+                    _ => return Err("Invalid vault passphrase or key file".to_string()),
+                }
+            }
+            None => {
+                let salt = cipher::generate_salt();
+                let key = cipher::derive_key(&secret, &salt)?;
+                let verifier = cipher::encrypt(&key, VERIFIER_PLAINTEXT)?;
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as i64;
+
+                sqlx::query(
+                    "INSERT INTO vault_meta (id, kdf, salt, verifier, created_at) VALUES (1, ?, ?, ?, ?)",
+                )
+                .bind(KDF_NAME)
+                .bind(&salt)
+                .bind(&verifier)
+                .bind(now)
+                .execute(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+
+                key
+            }
+        };
+
+        *self.key.write().map_err(|e| e.to_string())? = Some(key);
+        Ok(())
+    }
+
+    pub fn lock(&self) {
+        if let Ok(mut guard) = self.key.write() {
+            if let Some(key) = guard.as_mut() {
+                key.fill(0);
+            }
+            *guard = None;
+        }
+    }
+
+    pub fn is_unlocked(&self) -> bool {
+        self.key.read().map(|k| k.is_some()).unwrap_or(false)
+    }
+
+    fn key(&self) -> Result<SecretKey, String> {
+        self.key
+            .read()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Vault is locked — unlock it to access secrets".to_string())
+    }
+
+    /// Encrypts a plaintext value for storage
+    pub fn seal(&self, plaintext: &str) -> Result<String, String> {
+        cipher::encrypt(&self.key()?, plaintext)
+    }
+
+    /// Returns the plaintext for a stored value, decrypting it if it is encrypted
+    pub fn reveal(&self, stored: &str) -> Result<String, String> {
+        if !cipher::is_encrypted(stored) {
+            return Ok(stored.to_string());
+        }
+        cipher::decrypt(&self.key()?, stored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Database;
+
+    fn passphrase(value: &str) -> VaultCredential {
+        VaultCredential::Passphrase(value.to_string())
+    }
+
+    #[tokio::test]
+    async fn seal_and_reveal_round_trip_while_unlocked() {
+        let db = Database::open_in_memory().await.unwrap();
+        let vault = SecretVault::new();
+        assert!(vault.seal("token").is_err());
+
+        vault
+            .unlock(db.pool(), &passphrase("hunter2"))
+            .await
+            .unwrap();
+        let status = vault.status(db.pool()).await.unwrap();
+        assert!(status.initialized && status.unlocked);
+
+        let sealed = vault.seal("token").unwrap();
+        assert!(cipher::is_encrypted(&sealed));
+        assert_eq!(vault.reveal(&sealed).unwrap(), "token");
+        assert_eq!(vault.reveal("plain").unwrap(), "plain");
+
+        vault.lock();
+        assert!(!vault.is_unlocked());
+        assert!(vault.reveal(&sealed).is_err());
+        assert_eq!(vault.reveal("plain").unwrap(), "plain");
+
+        // The same passphrase derives the same key from the stored salt
+        vault
+            .unlock(db.pool(), &passphrase("hunter2"))
+            .await
+            .unwrap();
+        assert_eq!(vault.reveal(&sealed).unwrap(), "token");
+    }
+
+    #[tokio::test]
+    async fn wrong_passphrase_is_rejected() {
+        let db = Database::open_in_memory().await.unwrap();
+        let vault = SecretVault::new();
+        vault
+            .unlock(db.pool(), &passphrase("hunter2"))
+            .await
+            .unwrap();
+        let sealed = vault.seal("token").unwrap();
+        vault.lock();
+
+        let err = vault
+            .unlock(db.pool(), &passphrase("hunter3"))
+            .await
+            .unwrap_err();
+        assert!(err.contains("Invalid"), "{}", err);
+        assert!(!vault.is_unlocked());
+        assert!(vault.reveal(&sealed).is_err());
+        assert!(vault.unlock(db.pool(), &passphrase("")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn key_file_unlocks_the_vault() {
+        let dir = std::env::temp_dir().join(format!("cogeass-vault-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key_file = dir.join("vault.key");
+        let other_file = dir.join("other.key");
+        std::fs::write(&key_file, [7u8; 64]).unwrap();
+        std::fs::write(&other_file, [8u8; 64]).unwrap();
+        let credential =
+            |path: &std::path::Path| VaultCredential::KeyFile(path.display().to_string());
+
+        let db = Database::open_in_memory().await.unwrap();
+        let vault = SecretVault::new();
+        vault
+            .unlock(db.pool(), &credential(&key_file))
+            .await
+            .unwrap();
+        let sealed = vault.seal("token").unwrap();
+        vault.lock();
+
+        assert!(vault
+            .unlock(db.pool(), &credential(&other_file))
+            .await
+            .is_err());
+        assert!(vault
+            .unlock(db.pool(), &credential(&dir.join("missing.key")))
+            .await
+            .is_err());
+        vault
+            .unlock(db.pool(), &credential(&key_file))
+            .await
+            .unwrap();
+        assert_eq!(vault.reveal(&sealed).unwrap(), "token");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
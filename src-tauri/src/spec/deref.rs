@@ -0,0 +1,291 @@
+use super::loader::document_key;
+use percent_encoding::percent_decode_str;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use url::Url;
+
+/// Inlines every `$ref` in a spec, across documents.
+///
+/// References that would recurse into themselves are left as local `$ref`s so the
+/// output stays a finite tree. Cyclic targets that live in external documents are
+/// hoisted into the root document under `hoist_pointer` so those refs stay resolvable.
+pub struct Dereferencer<'a> {
+    documents: &'a HashMap<String, Value>,
+    root_key: String,
+    hoist_pointer: &'static str,
+    stack: Vec<String>,
+    resolved: HashMap<String, Value>,
+    hoisted_names: BTreeMap<String, String>,
+    hoisted_values: BTreeMap<String, Value>,
+}
+
+impl<'a> Dereferencer<'a> {
+    pub fn new(
+        documents: &'a HashMap<String, Value>,
+        root_url: &Url,
+        hoist_pointer: &'static str,
+    ) -> Self {
+        Self {
+            documents,
+            root_key: document_key(root_url),
+            hoist_pointer,
+            stack: Vec::new(),
+            resolved: HashMap::new(),
+            hoisted_names: BTreeMap::new(),
+            hoisted_values: BTreeMap::new(),
+        }
+    }
+
+    /// Dereferences the root document
+    pub fn run(mut self, root_url: &Url) -> Result<Value, String> {
+        let root = self
+            .documents
+            .get(&self.root_key)
+            .ok_or_else(|| "Root document was not loaded".to_string())?;
+        let mut output = self.resolve(root, root_url)?;
+
+        for (name, value) in std::mem::take(&mut self.hoisted_values) {
+            insert_at_pointer(&mut output, self.hoist_pointer, &name, value)?;
+        }
+
+        Ok(output)
+    }
+
+    fn resolve(&mut self, value: &Value, base: &Url) -> Result<Value, String> {
+        match value {
+            Value::Object(map) => {
+                if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                    return self.resolve_ref(reference, map, base);
+                }
+                let mut out = Map::with_capacity(map.len());
+                for (key, child) in map {
+                    out.insert(key.clone(), self.resolve(child, base)?);
+                }
+                Ok(Value::Object(out))
+            }
+            Value::Array(items) => items
+                .iter()
+                .map(|item| self.resolve(item, base))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            other => Ok(other.clone()),
+        }
+    }
+
+    fn resolve_ref(
+        &mut self,
+        reference: &str,
+        map: &Map<String, Value>,
+        base: &Url,
+    ) -> Result<Value, String> {
+        let target_url = base
+            .join(reference)
+            .map_err(|e| format!("Invalid $ref {}: {}", reference, e))?;
+        let doc_key = document_key(&target_url);
+        let pointer = percent_decode_str(target_url.fragment().unwrap_or(""))
+            .decode_utf8_lossy()
+            .to_string();
+        let ref_key = format!("{}#{}", doc_key, pointer);
+
+        let resolved = if self.stack.contains(&ref_key) {
+            self.cycle_placeholder(&doc_key, &pointer, &ref_key)
+        } else if let Some(cached) = self.resolved.get(&ref_key) {
+            cached.clone()
+        } else {
+            let document = self
+                .documents
+                .get(&doc_key)
+                .ok_or_else(|| format!("Referenced document was not loaded: {}", doc_key))?;
+            let target = document
+                .pointer(&pointer)
+                .ok_or_else(|| format!("Unresolvable $ref: {}", reference))?;
+
+            self.stack.push(ref_key.clone());
+            let result = self.resolve(target, &target_url);
+            self.stack.pop();
+            let result = result?;
+
+            if let Some(name) = self.hoisted_names.get(&ref_key) {
+                self.hoisted_values.insert(name.clone(), result.clone());
+            }
+            self.resolved.insert(ref_key, result.clone());
+            result
+        };
+
+        // Sibling keys next to a $ref (allowed in OAS 3.1) override the target's
+        let siblings: Vec<(&String, &Value)> = map.iter().filter(|(k, _)| *k != "$ref").collect();
+        match resolved {
+            Value::Object(mut object) if !siblings.is_empty() => {
+                for (key, value) in siblings {
+                    object.insert(key.clone(), self.resolve(value, base)?);
+                }
+                Ok(Value::Object(object))
+            }
+            other => Ok(other),
+        }
+    }
+
+    fn cycle_placeholder(&mut self, doc_key: &str, pointer: &str, ref_key: &str) -> Value {
+        if doc_key == self.root_key {
+            return serde_json::json!({ "$ref": format!("#{}", pointer) });
+        }
+
+        let name = match self.hoisted_names.get(ref_key) {
+            Some(name) => name.clone(),
+            None => {
+                let name = self.unique_hoist_name(doc_key, pointer);
+                self.hoisted_names.insert(ref_key.to_string(), name.clone());
+                name
+            }
+        };
+        serde_json::json!({ "$ref": format!("#{}/{}", self.hoist_pointer, name) })
+    }
+
+    fn unique_hoist_name(&self, doc_key: &str, pointer: &str) -> String {
+        let base_name = pointer
+            .rsplit('/')
+            .find(|s| !s.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| {
+                doc_key
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or("External")
+                    .split('.')
+                    .next()
+                    .unwrap_or("External")
+                    .to_string()
+            });
+        let base_name: String = base_name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        let mut candidate = base_name.clone();
+        let mut suffix = 1;
+        while self.hoisted_names.values().any(|n| *n == candidate) {
+            suffix += 1;
+            candidate = format!("{}_{}", base_name, suffix);
+        }
+        candidate
+    }
+}
+
+/// Inserts `value` under `name` in the object at `pointer`, creating objects along the way
+fn insert_at_pointer(
+    root: &mut Value,
+    pointer: &str,
+    name: &str,
+    value: Value,
+) -> Result<(), String> {
+    let mut current = root;
+    for segment in pointer.split('/').filter(|s| !s.is_empty()) {
+        let object = current
+            .as_object_mut()
+            .ok_or_else(|| format!("Cannot hoist into non-object at {}", pointer))?;
+        current = object
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+    let object = current
+        .as_object_mut()
+        .ok_or_else(|| format!("Cannot hoist into non-object at {}", pointer))?;
+    object.entry(name.to_string()).or_insert(value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn documents(entries: &[(&Url, &str)]) -> HashMap<String, Value> {
+        entries
+            .iter()
+            .map(|(url, text)| (document_key(url), serde_json::from_str(text).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn inlines_local_refs_and_keeps_cycles_as_refs() {
+        let root = Url::parse("https://example.com/openapi.json").unwrap();
+        let doc = json!({
+            "paths": { "/a": { "schema": { "$ref": "#/components/schemas/A" } } },
+            "components": {
+                "schemas": {
+                    "A": {
+                        "type": "object",
+                        "properties": {
+                            "b": { "$ref": "#/components/schemas/B", "description": "The B" },
+                            "self": { "$ref": "#/components/schemas/A" }
+                        }
+                    },
+                    "B": { "type": "string", "description": "Plain" }
+                }
+            }
+        })
+        .to_string();
+        let documents = documents(&[(&root, &doc)]);
+
+        let out = Dereferencer::new(&documents, &root, "/components/schemas")
+            .run(&root)
+            .unwrap();
+        let schema = &out["paths"]["/a"]["schema"];
+        assert_eq!(
+            schema["properties"]["b"],
+            json!({ "type": "string", "description": "The B" })
+        );
+        assert_eq!(
+            schema["properties"]["self"],
+            json!({ "$ref": "#/components/schemas/A" })
+        );
+    }
+
+    #[test]
+    fn hoists_cyclic_targets_from_external_documents() {
+        let root = Url::parse("https://example.com/api/openapi.json").unwrap();
+        let common = Url::parse("https://example.com/api/common.json").unwrap();
+        let documents = documents(&[
+            (&root, include_str!("fixtures/split/openapi.json")),
+            (&common, include_str!("fixtures/split/common.json")),
+        ]);
+
+        let out = Dereferencer::new(&documents, &root, "/components/schemas")
+            .run(&root)
+            .unwrap();
+        let get = &out["paths"]["/categories"]["get"];
+        assert_eq!(
+            get["parameters"][0],
+            json!({ "name": "page", "in": "query", "schema": { "type": "integer" } })
+        );
+        let schema = &get["responses"]["200"]["content"]["application/json"]["schema"];
+        assert_eq!(schema["properties"]["name"], json!({ "type": "string" }));
+        assert_eq!(
+            schema["properties"]["parent"],
+            json!({ "$ref": "#/components/schemas/Category" })
+        );
+        assert_eq!(out["components"]["schemas"]["Category"], *schema);
+    }
+
+    #[test]
+    fn reports_missing_targets() {
+        let root = Url::parse("https://example.com/openapi.json").unwrap();
+        let documents = documents(&[(&root, r##"{ "a": { "$ref": "#/missing" } }"##)]);
+        let err = Dereferencer::new(&documents, &root, "/components/schemas")
+            .run(&root)
+            .unwrap_err();
+        assert!(err.contains("Unresolvable $ref: #/missing"), "{}", err);
+
+        let documents = self::documents(&[(&root, r#"{ "a": { "$ref": "other.json" } }"#)]);
+        let err = Dereferencer::new(&documents, &root, "/components/schemas")
+            .run(&root)
+            .unwrap_err();
+        assert!(err.contains("was not loaded"), "{}", err);
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+pub const HTTP_METHODS: &[&str] = &[
+    "get", "post", "put", "patch", "delete", "head", "options", "trace",
+];
+
+/// Lightweight summary of an operation, enough to render the operation explorer
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationEntry {
+    /// `method:path`, the key used by `operation_states` and `response_history`
+    pub key: String,
+    pub method: String,
+    pub path: String,
+    pub operation_id: Option<String>,
+    pub summary: Option<String>,
+    pub tag: String,
+    pub tags: Vec<String>,
+    pub deprecated: bool,
+    /// Set for OpenAPI 3.1 webhooks, whose `path` is the webhook name
+    pub webhook: bool,
+}
+
+/// Splits an operation key of the form `method:path`
+pub fn split_operation_key(operation_key: &str) -> Result<(String, String), String> {
+    let (method, path) = operation_key
+        .split_once(':')
+        .ok_or_else(|| format!("Invalid operation key: {}", operation_key))?;
+    Ok((method.to_lowercase(), path.to_string()))
+}
+
+/// Follows a local `#/...` reference, returning the value itself when it isn't a `$ref`
+pub fn follow_ref<'a>(spec: &'a Value, value: &'a Value) -> &'a Value {
+    let mut current = value;
+    // Bounded to guard against reference cycles
+    for _ in 0..32 {
+        let Some(reference) = current.get("$ref").and_then(Value::as_str) else {
+            return current;
+        };
+        let Some(pointer) = reference.strip_prefix('#') else {
+            return current;
+        };
+        match spec.pointer(pointer) {
+            Some(target) => current = target,
+            None => return current,
+        }
+    }
+    current
+}
+
+/// Returns true when an operation key's path names a webhook rather than a URL path.
+/// Paths always start with `/`, so the two can't collide.
+pub fn is_webhook_path(path: &str) -> bool {
+    !path.starts_with('/')
+}
+
+/// Looks up an operation object in the spec by method and path, or by webhook name
+pub fn find_operation<'a>(spec: &'a Value, method: &str, path: &str) -> Option<&'a Value> {
+    let section = if is_webhook_path(path) {
+        "webhooks"
+    } else {
+        "paths"
+    };
+    let path_item = follow_ref(spec, spec.get(section)?.get(path)?);
+    path_item.get(method.to_lowercase())
+}
+
+/// Lists every operation in the spec, paths in document order and each path's methods in
+/// `HTTP_METHODS` order, followed by any webhooks
+pub fn list_operations(spec: &Value) -> Vec<OperationEntry> {
+    let mut entries = Vec::new();
+    for (section, webhook) in [("paths", false), ("webhooks", true)] {
+        if let Some(items) = spec.get(section).and_then(Value::as_object) {
+            collect_operations(spec, items, webhook, &mut entries);
+        }
+    }
+    entries
+}
+
+fn collect_operations(
+    spec: &Value,
+    items: &Map<String, Value>,
+    webhook: bool,
+    entries: &mut Vec<OperationEntry>,
+) {
+    for (path, item) in items {
+        let item = follow_ref(spec, item);
+        for method in HTTP_METHODS {
+            let Some(op) = item.get(*method) else {
+                continue;
+            };
+            let tags: Vec<String> = op
+                .get("tags")
+                .and_then(Value::as_array)
+                .map(|t| {
+                    t.iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            entries.push(OperationEntry {
+                key: format!("{}:{}", method, path),
+                method: method.to_string(),
+                path: path.clone(),
+                operation_id: op
+                    .get("operationId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                summary: op
+                    .get("summary")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                tag: tags
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| "default".to_string()),
+                tags,
+                deprecated: op
+                    .get("deprecated")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false),
+                webhook,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lists_webhooks_as_operations_after_paths() {
+        let spec: Value =
+            serde_json::from_str(include_str!("fixtures/tree-openapi31.json")).unwrap();
+        let entries = list_operations(&spec);
+
+        let keys: Vec<&str> = entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, ["post:/nodes", "post:nodeChanged"]);
+        assert!(!entries[0].webhook);
+        let webhook = &entries[1];
+        assert!(webhook.webhook);
+        assert_eq!(webhook.method, "post");
+        assert_eq!(webhook.path, "nodeChanged");
+        assert_eq!(webhook.tag, "default");
+
+        let (method, path) = split_operation_key(&webhook.key).unwrap();
+        assert!(is_webhook_path(&path));
+        let operation = find_operation(&spec, &method, &path).unwrap();
+        assert!(operation.get("requestBody").is_some());
+        assert!(find_operation(&spec, "post", "/nodeChanged").is_none());
+    }
+
+    #[test]
+    fn follows_path_item_refs_for_webhooks() {
+        let spec = json!({
+            "openapi": "3.1.0",
+            "webhooks": {
+                "petAdded": { "$ref": "#/components/pathItems/PetEvent" }
+            },
+            "components": {
+                "pathItems": {
+                    "PetEvent": { "post": { "operationId": "petAdded", "tags": ["pets"] } }
+                }
+            }
+        });
+        let entries = list_operations(&spec);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "post:petAdded");
+        assert_eq!(entries[0].operation_id.as_deref(), Some("petAdded"));
+        assert_eq!(entries[0].tag, "pets");
+        assert!(entries[0].webhook);
+        assert!(find_operation(&spec, "POST", "petAdded").is_some());
+    }
+
+    #[test]
+    fn keeps_the_documents_path_order() {
+        let spec: Value = serde_json::from_str(
+            r#"{
+                "openapi": "3.0.3",
+                "paths": {
+                    "/zebras": { "post": {}, "get": {} },
+                    "/ants": { "delete": {} },
+                    "/moles": { "get": {} }
+                }
+            }"#,
+        )
+        .unwrap();
+        let keys: Vec<String> = list_operations(&spec).into_iter().map(|e| e.key).collect();
+        assert_eq!(
+            keys,
+            ["get:/zebras", "post:/zebras", "delete:/ants", "get:/moles"]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use url::Url;
+
+/// Parses a spec document, trying JSON first and falling back to YAML
+pub fn parse_document(text: &str) -> Result<Value, String> {
+    match serde_json::from_str(text) {
+        Ok(value) => Ok(value),
+        Err(_) => serde_yaml::from_str(text).map_err(|e| format!("Invalid JSON or YAML: {}", e)),
+    }
+}
+
+/// Turns a URL or file path into an absolute URL usable as a base for relative refs
+pub fn location_url(location: &str) -> Result<Url, String> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return Url::parse(location).map_err(|e| e.to_string());
+    }
+    if location.starts_with("file://") {
+        return Url::parse(location).map_err(|e| e.to_string());
+    }
+
+    let path = std::fs::canonicalize(location)
+        .map_err(|e| format!("Failed to resolve spec path {}: {}", location, e))?;
+    Url::from_file_path(&path).map_err(|_| format!("Invalid spec path: {}", path.display()))
+}
+
+/// Returns the document part of a URL, i.e. without its fragment
+pub fn document_key(url: &Url) -> String {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    url.to_string()
+}
+
+async fn fetch_text(client: &reqwest::Client, url: &Url) -> Result<String, String> {
+    match url.scheme() {
+        "http" | "https" => {
+            let response = client
+                .get(url.as_str())
+                .send()
+                .await
+                .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+            if !response.status().is_success() {
+                return Err(format!(
+                    "Failed to fetch {}: HTTP {}",
+                    url,
+                    response.status()
+                ));
+            }
+            response.text().await.map_err(|e| e.to_string())
+        }
+        "file" => {
+            let path = url
+                .to_file_path()
+                .map_err(|_| format!("Invalid file URL: {}", url))?;
+            tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+        }
+        scheme => Err(format!("Unsupported $ref scheme: {}", scheme)),
+    }
+}
+
+/// Collects the document URLs referenced by `$ref`s anywhere inside `value`
+fn collect_external_refs(value: &Value, base: &Url, out: &mut Vec<Url>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(reference) = map.get("$ref").and_then(Value::as_str) {
+                if !reference.starts_with('#') {
+                    if let Ok(target) = base.join(reference) {
+                        out.push(target);
+                    }
+                }
+            }
+            for child in map.values() {
+                collect_external_refs(child, base, out);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_external_refs(item, base, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Loads the root document plus every document it transitively references.
+///
+/// Documents are keyed by their URL without fragment, so the dereferencer can look
+/// any `$ref` target up without further I/O. Only a root document read from disk may
+/// reference `file:` URLs, so a remote or uploaded spec can't pull in local files.
+pub async fn load_documents(
+    root_url: &Url,
+    root_text: Option<String>,
+) -> Result<HashMap<String, Value>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(600))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut documents = HashMap::new();
+    let mut pending = vec![root_url.clone()];
+    let mut root_text = root_text;
+    let local_files_allowed = root_url.scheme() == "file";
+
+    while let Some(url) = pending.pop() {
+        let key = document_key(&url);
+        if documents.contains_key(&key) {
+            continue;
+        }
+        if url.scheme() == "file" && !local_files_allowed {
+            return Err(format!(
+                "Refusing to read local file {} referenced from {}",
+                url, root_url
+            ));
+        }
+
+        let text = match root_text.take() {
+            Some(text) => text,
+            None => fetch_text(&client, &url).await?,
+        };
+        let document = parse_document(&text)?;
+
+        let mut refs = Vec::new();
+        collect_external_refs(&document, &url, &mut refs);
+        pending.extend(
+            refs.into_iter()
+                .filter(|r| !documents.contains_key(&document_key(r))),
+        );
+
+        documents.insert(key, document);
+    }
+
+    Ok(documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn loads_referenced_files_next_to_a_spec_on_disk() {
+        let dir = std::env::temp_dir().join(format!("cogeass-spec-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("openapi.json"),
+            include_str!("fixtures/split/openapi.json"),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("common.json"),
+            include_str!("fixtures/split/common.json"),
+        )
+        .unwrap();
+
+        let root = location_url(dir.join("openapi.json").to_str().unwrap()).unwrap();
+        let documents = load_documents(&root, None).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let documents = documents.unwrap();
+        assert_eq!(documents.len(), 2);
+        assert!(documents.contains_key(&document_key(&root.join("common.json").unwrap())));
+    }
+
+    #[tokio::test]
+    async fn remote_and_uploaded_specs_cannot_reference_local_files() {
+        let text = r#"{ "openapi": "3.0.3", "x-leak": { "$ref": "file:///etc/passwd" } }"#;
+        for root in ["https://example.com/openapi.json", "memory:///spec"] {
+            let root = Url::parse(root).unwrap();
+            let err = load_documents(&root, Some(text.to_string()))
+                .await
+                .unwrap_err();
+            assert!(
+                err.starts_with("Refusing to read local file file:///etc/passwd"),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn parses_json_and_yaml() {
+        assert_eq!(
+            parse_document(r#"{ "openapi": "3.0.3" }"#).unwrap()["openapi"],
+            "3.0.3"
+        );
+        assert_eq!(
+            parse_document("openapi: 3.1.0\ninfo:\n  title: T\n").unwrap()["info"]["title"],
+            "T"
+        );
+        assert!(parse_document("{ not: [valid").is_err());
+    }
+}
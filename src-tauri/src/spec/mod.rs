@@ -0,0 +1,195 @@
+pub mod deref;
+pub mod index;
+pub mod loader;
+pub mod openapi31;
+pub mod swagger2;
+
+pub use index::{list_operations, OperationEntry};
+
+use deref::Dereferencer;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use url::Url;
+
+/// Base URL used for specs supplied as raw text, so relative refs still have a base
+const MEMORY_BASE_URL: &str = "memory:///spec";
+
+/// Where a spec comes from
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum SpecSource {
+    /// An http(s) URL
+    Url(String),
+    /// A path on disk
+    File(String),
+    /// Raw JSON or YAML text, e.g. from an uploaded file
+    Content { id: String, content: String },
+}
+
+impl SpecSource {
+    /// The id the spec is cached under in the `specs` table
+    pub fn id(&self) -> &str {
+        match self {
+            SpecSource::Url(url) => url,
+            SpecSource::File(path) => path,
+            SpecSource::Content { id, .. } => id,
+        }
+    }
+}
+
+/// A fully dereferenced OpenAPI 3.x spec plus its operation index
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadedSpec {
+    pub id: String,
+    pub spec: Value,
+    pub operations: Vec<OperationEntry>,
+    pub openapi_version: String,
+    /// Set to the original version when the spec was converted, e.g. `"2.0"`
+    pub converted_from: Option<String>,
+}
+
+impl LoadedSpec {
+    /// Wraps an already processed spec, e.g. one read back from the cache
+    pub fn from_processed(id: String, spec: Value) -> Self {
+        let openapi_version = spec
+            .get("openapi")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        let operations = list_operations(&spec);
+        Self {
+            id,
+            spec,
+            operations,
+            openapi_version,
+            converted_from: None,
+        }
+    }
+}
+
+/// Reads a processed spec back from the `specs` table
+pub async fn load_stored(pool: &SqlitePool, spec_id: &str) -> Result<Option<Value>, String> {
+    let row: Option<(String,)> = sqlx::query_as("SELECT spec_content FROM specs WHERE id = ?")
+        .bind(spec_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    row.map(|(content,)| serde_json::from_str(&content).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+/// Reads the active spec of a workspace from the `specs` table
+pub async fn load_active(pool: &SqlitePool, workspace_id: &str) -> Result<Value, String> {
+    let row: Option<(Option<String>,)> =
+        sqlx::query_as("SELECT active_spec_id FROM workspaces WHERE id = ?")
+            .bind(workspace_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let spec_id = row
+        .ok_or_else(|| format!("Workspace not found: {}", workspace_id))?
+        .0
+        .ok_or_else(|| "Workspace has no active spec".to_string())?;
+
+    load_stored(pool, &spec_id)
+        .await?
+        .ok_or_else(|| format!("Spec not found: {}", spec_id))
+}
+
+/// Fetches, dereferences and, for Swagger 2.0, converts a spec to OpenAPI 3.x
+pub async fn load(source: &SpecSource) -> Result<LoadedSpec, String> {
+    let (root_url, root_text) = match source {
+        SpecSource::Url(url) => (loader::location_url(url)?, None),
+        SpecSource::File(path) => (loader::location_url(path)?, None),
+        SpecSource::Content { content, .. } => (
+            Url::parse(MEMORY_BASE_URL).map_err(|e| e.to_string())?,
+            Some(content.clone()),
+        ),
+    };
+
+    let documents = loader::load_documents(&root_url, root_text).await?;
+    let id = source.id().to_string();
+
+    // Dereferencing and conversion are CPU-bound on large specs
+    tokio::task::spawn_blocking(move || {
+        let root = documents
+            .get(&loader::document_key(&root_url))
+            .ok_or_else(|| "Root document was not loaded".to_string())?;
+        let is_swagger2 = swagger2::is_swagger2(root);
+        let hoist_pointer = if is_swagger2 {
+            "/definitions"
+        } else {
+            "/components/schemas"
+        };
+
+        let dereferenced =
+            Dereferencer::new(&documents, &root_url, hoist_pointer).run(&root_url)?;
+
+        if is_swagger2 {
+            let mut loaded = LoadedSpec::from_processed(id, swagger2::convert(&dereferenced));
+            loaded.converted_from = Some("2.0".to_string());
+            return Ok(loaded);
+        }
+
+        if dereferenced
+            .get("openapi")
+            .and_then(Value::as_str)
+            .is_none()
+        {
+            return Err("Not an OpenAPI or Swagger document".to_string());
+        }
+
+        let mut spec = dereferenced;
+        if openapi31::is_openapi31(&spec) {
+            openapi31::normalize(&mut spec);
+        }
+        Ok(LoadedSpec::from_processed(id, spec))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn loads_and_converts_a_swagger2_spec() {
+        let source = SpecSource::Content {
+            id: "petstore".to_string(),
+            content: include_str!("fixtures/petstore-swagger2.json").to_string(),
+        };
+        let loaded = load(&source).await.unwrap();
+
+        assert_eq!(loaded.id, "petstore");
+        assert_eq!(loaded.openapi_version, "3.0.3");
+        assert_eq!(loaded.converted_from.as_deref(), Some("2.0"));
+        assert_eq!(loaded.operations.len(), 4);
+
+        let create = &loaded.spec["paths"]["/pets"]["post"];
+        assert!(create.get("parameters").is_none());
+        assert_eq!(create["requestBody"]["x-body-name"], "pet");
+        assert_eq!(
+            create["requestBody"]["content"]["application/json"]["schema"]["properties"]["tag"],
+            json!({ "type": "string", "nullable": true })
+        );
+        assert_eq!(
+            loaded.spec["paths"]["/pets/{petId}"]["parameters"][0]["schema"],
+            json!({ "type": "integer", "format": "int64" })
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_documents_that_are_not_openapi() {
+        let source = SpecSource::Content {
+            id: "other".to_string(),
+            content: "name: not a spec\n".to_string(),
+        };
+        let err = load(&source).await.unwrap_err();
+        assert_eq!(err, "Not an OpenAPI or Swagger document");
+    }
+}
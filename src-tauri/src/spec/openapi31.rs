@@ -0,0 +1,310 @@
+use serde_json::{json, Map, Value};
+
+/// Returns true for OpenAPI 3.1.x documents
+pub fn is_openapi31(doc: &Value) -> bool {
+    doc.get("openapi")
+        .and_then(Value::as_str)
+        .is_some_and(|v| v.starts_with("3.1"))
+}
+
+/// Rewrites JSON Schema 2020-12 constructs in a dereferenced 3.1 spec into the
+/// draft-07 subset the request forms understand.
+///
+/// `$defs` are hoisted into `components.schemas` so cyclic refs into them still
+/// resolve, `type` arrays become a single type plus `nullable` (or an `anyOf`),
+/// `const` gains an equivalent single-value `enum`, and `prefixItems` become
+/// tuple-style `items`.
+pub fn normalize(spec: &mut Value) {
+    hoist_defs(spec);
+
+    if let Some(schemas) = spec
+        .pointer_mut("/components/schemas")
+        .and_then(Value::as_object_mut)
+    {
+        for schema in schemas.values_mut() {
+            normalize_schema(schema);
+        }
+    }
+    for section in ["paths", "webhooks", "components"] {
+        if let Some(value) = spec.get_mut(section) {
+            normalize_nested_schemas(value);
+        }
+    }
+}
+
+/// Moves root-level and component-level `$defs` into `components.schemas`,
+/// rewriting the refs that point at them
+fn hoist_defs(spec: &mut Value) {
+    let mut moves: Vec<(String, String, Value)> = Vec::new();
+
+    if let Some(defs) = spec
+        .as_object_mut()
+        .and_then(|o| o.remove("$defs"))
+        .and_then(|d| d.as_object().cloned())
+    {
+        for (name, value) in defs {
+            moves.push((format!("/$defs/{}", name), name, value));
+        }
+    }
+
+    if let Some(schemas) = spec
+        .pointer_mut("/components/schemas")
+        .and_then(Value::as_object_mut)
+    {
+        for (schema_name, schema) in schemas.iter_mut() {
+            let Some(defs) = schema.as_object_mut().and_then(|o| o.remove("$defs")) else {
+                continue;
+            };
+            for (name, value) in defs.as_object().into_iter().flatten() {
+                moves.push((
+                    format!("/components/schemas/{}/$defs/{}", schema_name, name),
+                    name.clone(),
+                    value.clone(),
+                ));
+            }
+        }
+    }
+
+    if moves.is_empty() {
+        return;
+    }
+
+    let Some(root) = spec.as_object_mut() else {
+        return;
+    };
+    let schemas = root
+        .entry("components")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .map(|c| {
+            c.entry("schemas")
+                .or_insert_with(|| Value::Object(Map::new()))
+        });
+    let Some(Value::Object(schemas)) = schemas else {
+        return;
+    };
+
+    let mut renames = Vec::with_capacity(moves.len());
+    for (old_pointer, name, value) in moves {
+        let mut candidate = name.clone();
+        let mut suffix = 1;
+        while schemas.contains_key(&candidate) {
+            suffix += 1;
+            candidate = format!("{}_{}", name, suffix);
+        }
+        schemas.insert(candidate.clone(), value);
+        renames.push((old_pointer, format!("/components/schemas/{}", candidate)));
+    }
+
+    rewrite_refs(spec, &renames);
+}
+
+fn rewrite_refs(value: &mut Value, renames: &[(String, String)]) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(reference)) = map.get_mut("$ref") {
+                if let Some(pointer) = reference.strip_prefix('#') {
+                    for (old, new) in renames {
+                        if let Some(rest) = pointer.strip_prefix(old.as_str()) {
+                            if rest.is_empty() || rest.starts_with('/') {
+                                *reference = format!("#{}{}", new, rest);
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            for child in map.values_mut() {
+                rewrite_refs(child, renames);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                rewrite_refs(item, renames);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Finds `schema` values in parameters, media types and headers and normalizes them
+fn normalize_nested_schemas(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                if key == "schema" && child.is_object() {
+                    normalize_schema(child);
+                } else if key != "schemas" {
+                    normalize_nested_schemas(child);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                normalize_nested_schemas(item);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Normalizes a single schema and all of its subschemas
+fn normalize_schema(schema: &mut Value) {
+    let Some(map) = schema.as_object_mut() else {
+        return;
+    };
+
+    // Anything still in $defs has already been inlined by dereferencing
+    map.remove("$defs");
+
+    if let Some(Value::Array(types)) = map.get("type").cloned() {
+        let nullable = types.iter().any(|t| t == "null");
+        let non_null: Vec<Value> = types.into_iter().filter(|t| t != "null").collect();
+        match non_null.len() {
+            0 => {
+                map.insert("type".to_string(), json!("null"));
+            }
+            1 => {
+                map.insert("type".to_string(), non_null[0].clone());
+            }
+            _ => {
+                map.remove("type");
+                let variants = non_null.into_iter().map(|t| json!({ "type": t })).collect();
+                map.entry("anyOf").or_insert_with(|| Value::Array(variants));
+            }
+        }
+        if nullable {
+            map.insert("nullable".to_string(), Value::Bool(true));
+        }
+    }
+
+    if let Some(constant) = map.get("const").cloned() {
+        map.entry("enum")
+            .or_insert_with(|| Value::Array(vec![constant]));
+    }
+
+    if let Some(prefix_items) = map.remove("prefixItems") {
+        // In 2020-12 `items` next to `prefixItems` describes the remaining items
+        if let Some(rest) = map.remove("items") {
+            map.insert("additionalItems".to_string(), rest);
+        }
+        map.insert("items".to_string(), prefix_items);
+    }
+
+    for key in ["properties", "patternProperties", "dependentSchemas"] {
+        if let Some(Value::Object(children)) = map.get_mut(key) {
+            for child in children.values_mut() {
+                normalize_schema(child);
+            }
+        }
+    }
+    for key in ["allOf", "anyOf", "oneOf"] {
+        if let Some(Value::Array(children)) = map.get_mut(key) {
+            for child in children.iter_mut() {
+                normalize_schema(child);
+            }
+        }
+    }
+    for key in [
+        "items",
+        "additionalItems",
+        "additionalProperties",
+        "not",
+        "if",
+        "then",
+        "else",
+        "contains",
+        "propertyNames",
+        "unevaluatedItems",
+        "unevaluatedProperties",
+    ] {
+        match map.get_mut(key) {
+            Some(Value::Array(children)) => {
+                for child in children.iter_mut() {
+                    normalize_schema(child);
+                }
+            }
+            Some(child @ Value::Object(_)) => normalize_schema(child),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::deref::Dereferencer;
+    use crate::spec::loader::document_key;
+    use std::collections::HashMap;
+    use url::Url;
+
+    fn normalized_fixture() -> Value {
+        let root = Url::parse("https://example.com/openapi.json").unwrap();
+        let documents = HashMap::from([(
+            document_key(&root),
+            serde_json::from_str(include_str!("fixtures/tree-openapi31.json")).unwrap(),
+        )]);
+        let mut spec = Dereferencer::new(&documents, &root, "/components/schemas")
+            .run(&root)
+            .unwrap();
+        assert!(is_openapi31(&spec));
+        normalize(&mut spec);
+        spec
+    }
+
+    #[test]
+    fn hoists_defs_into_components() {
+        let spec = normalized_fixture();
+        let schemas = &spec["components"]["schemas"];
+
+        assert!(schemas["Node"].get("$defs").is_none());
+        assert_eq!(
+            schemas["Meta"]["properties"]["note"],
+            json!({ "type": "string", "nullable": true })
+        );
+    }
+
+    #[test]
+    fn rewrites_2020_12_keywords() {
+        let spec = normalized_fixture();
+        let node = &spec["paths"]["/nodes"]["post"]["requestBody"]["content"]["application/json"]
+            ["schema"];
+
+        assert!(node.get("$defs").is_none());
+        let properties = &node["properties"];
+        assert_eq!(
+            properties["label"],
+            json!({ "type": "string", "nullable": true })
+        );
+        assert_eq!(
+            properties["weight"],
+            json!({ "anyOf": [{ "type": "integer" }, { "type": "string" }] })
+        );
+        assert_eq!(
+            properties["point"],
+            json!({
+                "type": "array",
+                "items": [{ "type": "number" }, { "type": "number" }],
+                "additionalItems": { "type": "string" }
+            })
+        );
+        assert_eq!(properties["meta"]["properties"]["note"]["nullable"], true);
+        assert_eq!(
+            properties["children"]["items"],
+            json!({ "$ref": "#/components/schemas/Node" })
+        );
+
+        let webhook = &spec["webhooks"]["nodeChanged"]["post"]["requestBody"]["content"]
+            ["application/json"]["schema"];
+        assert_eq!(
+            webhook["properties"]["version"],
+            json!({ "const": 1, "enum": [1] })
+        );
+    }
+
+    #[test]
+    fn leaves_other_versions_alone() {
+        assert!(!is_openapi31(&json!({ "openapi": "3.0.3" })));
+        assert!(is_openapi31(&json!({ "openapi": "3.1.1" })));
+    }
+}
@@ -0,0 +1,704 @@
+use serde_json::{json, Map, Value};
+
+const PARAMETER_SCHEMA_KEYS: &[&str] = &[
+    "type",
+    "format",
+    "items",
+    "enum",
+    "default",
+    "maximum",
+    "exclusiveMaximum",
+    "minimum",
+    "exclusiveMinimum",
+    "maxLength",
+    "minLength",
+    "pattern",
+    "maxItems",
+    "minItems",
+    "uniqueItems",
+    "multipleOf",
+];
+
+const HTTP_METHODS: &[&str] = &["get", "put", "post", "delete", "options", "head", "patch"];
+
+pub fn is_swagger2(doc: &Value) -> bool {
+    doc.get("swagger").and_then(Value::as_str) == Some("2.0")
+}
+
+/// Converts a Swagger 2.0 document into an equivalent OpenAPI 3.0 document
+pub fn convert(doc: &Value) -> Value {
+    let mut out = Map::new();
+    out.insert("openapi".to_string(), json!("3.0.3"));
+
+    for key in ["info", "tags", "externalDocs", "security"] {
+        if let Some(value) = doc.get(key) {
+            out.insert(key.to_string(), rewrite_refs(value));
+        }
+    }
+    for (key, value) in doc.as_object().into_iter().flatten() {
+        if key.starts_with("x-") {
+            out.insert(key.clone(), rewrite_refs(value));
+        }
+    }
+
+    out.insert("servers".to_string(), Value::Array(servers(doc)));
+
+    let global_consumes = string_list(doc.get("consumes"));
+    let global_produces = string_list(doc.get("produces"));
+
+    let mut paths = Map::new();
+    for (path, item) in doc
+        .get("paths")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        paths.insert(
+            path.clone(),
+            convert_path_item(item, &global_consumes, &global_produces),
+        );
+    }
+    out.insert("paths".to_string(), Value::Object(paths));
+
+    let mut components = Map::new();
+    if let Some(definitions) = doc.get("definitions") {
+        components.insert("schemas".to_string(), convert_schema(definitions));
+    }
+    if let Some(parameters) = doc.get("parameters").and_then(Value::as_object) {
+        let mut converted = Map::new();
+        let mut request_bodies = Map::new();
+        for (name, param) in parameters {
+            match param.get("in").and_then(Value::as_str) {
+                Some("body") => {
+                    request_bodies
+                        .insert(name.clone(), body_to_request_body(param, &global_consumes));
+                }
+                Some("formData") => {}
+                _ => {
+                    converted.insert(name.clone(), convert_parameter(param));
+                }
+            }
+        }
+        components.insert("parameters".to_string(), Value::Object(converted));
+        if !request_bodies.is_empty() {
+            components.insert("requestBodies".to_string(), Value::Object(request_bodies));
+        }
+    }
+    if let Some(responses) = doc.get("responses").and_then(Value::as_object) {
+        let converted: Map<String, Value> = responses
+            .iter()
+            .map(|(name, r)| (name.clone(), convert_response(r, &global_produces)))
+            .collect();
+        components.insert("responses".to_string(), Value::Object(converted));
+    }
+    if let Some(schemes) = doc.get("securityDefinitions").and_then(Value::as_object) {
+        let converted: Map<String, Value> = schemes
+            .iter()
+            .map(|(name, s)| (name.clone(), convert_security_scheme(s)))
+            .collect();
+        components.insert("securitySchemes".to_string(), Value::Object(converted));
+    }
+    if !components.is_empty() {
+        out.insert("components".to_string(), Value::Object(components));
+    }
+
+    Value::Object(out)
+}
+
+fn servers(doc: &Value) -> Vec<Value> {
+    let base_path = doc.get("basePath").and_then(Value::as_str).unwrap_or("");
+    let Some(host) = doc.get("host").and_then(Value::as_str) else {
+        return if base_path.is_empty() {
+            Vec::new()
+        } else {
+            vec![json!({ "url": base_path })]
+        };
+    };
+
+    let schemes = string_list(doc.get("schemes"));
+    let schemes = if schemes.is_empty() {
+        vec!["https".to_string()]
+    } else {
+        schemes
+    };
+    schemes
+        .iter()
+        .map(|scheme| json!({ "url": format!("{}://{}{}", scheme, host, base_path) }))
+        .collect()
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Rewrites Swagger 2.0 `$ref` targets to their OpenAPI 3 component locations
+fn rewrite_refs(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    if k == "$ref" {
+                        if let Some(reference) = v.as_str() {
+                            return (k.clone(), Value::String(rewrite_ref(reference)));
+                        }
+                    }
+                    (k.clone(), rewrite_refs(v))
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(rewrite_refs).collect()),
+        other => other.clone(),
+    }
+}
+
+fn rewrite_ref(reference: &str) -> String {
+    let (doc, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+    let pointer = if let Some(rest) = pointer.strip_prefix("/definitions/") {
+        format!("/components/schemas/{}", rest)
+    } else if let Some(rest) = pointer.strip_prefix("/parameters/") {
+        format!("/components/parameters/{}", rest)
+    } else if let Some(rest) = pointer.strip_prefix("/responses/") {
+        format!("/components/responses/{}", rest)
+    } else {
+        pointer.to_string()
+    };
+    if reference.contains('#') {
+        format!("{}#{}", doc, pointer)
+    } else {
+        reference.to_string()
+    }
+}
+
+/// Converts a Swagger 2.0 schema, mapping `x-nullable` to `nullable` and fixing refs
+fn convert_schema(schema: &Value) -> Value {
+    let mut converted = rewrite_refs(schema);
+    convert_nullable(&mut converted);
+    converted
+}
+
+fn convert_nullable(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            if let Some(nullable) = map.remove("x-nullable") {
+                map.insert("nullable".to_string(), nullable);
+            }
+            for child in map.values_mut() {
+                convert_nullable(child);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(convert_nullable),
+        _ => {}
+    }
+}
+
+fn convert_path_item(item: &Value, consumes: &[String], produces: &[String]) -> Value {
+    let Some(item) = item.as_object() else {
+        return item.clone();
+    };
+
+    let shared_params: Vec<Value> = item
+        .get("parameters")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut out = Map::new();
+    for (key, value) in item {
+        if key == "parameters" {
+            let params: Vec<Value> = shared_params
+                .iter()
+                .filter(|p| !matches!(param_location(p), Some("body") | Some("formData")))
+                .map(convert_parameter)
+                .collect();
+            if !params.is_empty() {
+                out.insert(key.clone(), Value::Array(params));
+            }
+        } else if HTTP_METHODS.contains(&key.as_str()) {
+            out.insert(
+                key.clone(),
+                convert_operation(value, &shared_params, consumes, produces),
+            );
+        } else {
+            out.insert(key.clone(), rewrite_refs(value));
+        }
+    }
+    Value::Object(out)
+}
+
+fn param_location(param: &Value) -> Option<&str> {
+    param.get("in").and_then(Value::as_str)
+}
+
+fn convert_operation(
+    op: &Value,
+    shared_params: &[Value],
+    global_consumes: &[String],
+    global_produces: &[String],
+) -> Value {
+    let Some(op) = op.as_object() else {
+        return op.clone();
+    };
+
+    let consumes = match string_list(op.get("consumes")) {
+        list if list.is_empty() => global_consumes.to_vec(),
+        list => list,
+    };
+    let produces = match string_list(op.get("produces")) {
+        list if list.is_empty() => global_produces.to_vec(),
+        list => list,
+    };
+
+    let own_params: Vec<Value> = op
+        .get("parameters")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    // Path-level body and form parameters become part of each operation's request body;
+    // the others stay on the path item. Operation-level parameters win on conflicts.
+    let mut all_params: Vec<Value> = shared_params
+        .iter()
+        .filter(|shared| matches!(param_location(shared), Some("body") | Some("formData")))
+        .filter(|shared| {
+            !own_params.iter().any(|own| {
+                own.get("name") == shared.get("name") && own.get("in") == shared.get("in")
+            })
+        })
+        .cloned()
+        .collect();
+    all_params.extend(own_params);
+
+    let mut out = Map::new();
+    for (key, value) in op {
+        match key.as_str() {
+            "parameters" | "consumes" | "produces" | "schemes" => {}
+            "responses" => {
+                let responses: Map<String, Value> = value
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(code, r)| (code.clone(), convert_response(r, &produces)))
+                    .collect();
+                out.insert(key.clone(), Value::Object(responses));
+            }
+            _ => {
+                out.insert(key.clone(), rewrite_refs(value));
+            }
+        }
+    }
+
+    let mut parameters = Vec::new();
+    let mut form_params = Vec::new();
+    for param in &all_params {
+        match param_location(param) {
+            Some("body") => {
+                out.insert(
+                    "requestBody".to_string(),
+                    body_to_request_body(param, &consumes),
+                );
+            }
+            Some("formData") => form_params.push(param),
+            _ => parameters.push(convert_parameter(param)),
+        }
+    }
+    if !form_params.is_empty() {
+        out.insert(
+            "requestBody".to_string(),
+            form_to_request_body(&form_params, &consumes),
+        );
+    }
+    if !parameters.is_empty() {
+        out.insert("parameters".to_string(), Value::Array(parameters));
+    }
+
+    Value::Object(out)
+}
+
+fn convert_parameter(param: &Value) -> Value {
+    let Some(param) = param.as_object() else {
+        return param.clone();
+    };
+    if param.contains_key("$ref") {
+        return rewrite_refs(&Value::Object(param.clone()));
+    }
+
+    let location = param.get("in").and_then(Value::as_str);
+    let mut out = Map::new();
+    let mut schema = Map::new();
+    for (key, value) in param {
+        if PARAMETER_SCHEMA_KEYS.contains(&key.as_str()) {
+            schema.insert(key.clone(), convert_schema(value));
+        } else if key == "collectionFormat" {
+            match value.as_str() {
+                Some("multi") => {
+                    out.insert("style".to_string(), json!("form"));
+                    out.insert("explode".to_string(), json!(true));
+                }
+                Some("csv") => {
+                    let style = if location == Some("query") {
+                        "form"
+                    } else {
+                        "simple"
+                    };
+                    out.insert("style".to_string(), json!(style));
+                    out.insert("explode".to_string(), json!(false));
+                }
+                Some("ssv") => {
+                    out.insert("style".to_string(), json!("spaceDelimited"));
+                }
+                Some("pipes") => {
+                    out.insert("style".to_string(), json!("pipeDelimited"));
+                }
+                _ => {}
+            }
+        } else if key == "x-example" {
+            out.insert("example".to_string(), value.clone());
+        } else {
+            out.insert(key.clone(), rewrite_refs(value));
+        }
+    }
+    if !schema.is_empty() {
+        out.insert("schema".to_string(), Value::Object(schema));
+    }
+    Value::Object(out)
+}
+
+fn media_types(list: &[String], fallback: &str) -> Vec<String> {
+    if list.is_empty() {
+        vec![fallback.to_string()]
+    } else {
+        list.to_vec()
+    }
+}
+
+fn body_to_request_body(param: &Value, consumes: &[String]) -> Value {
+    let schema = param.get("schema").map(convert_schema).unwrap_or(json!({}));
+    let mut content = Map::new();
+    for media_type in media_types(consumes, "application/json") {
+        content.insert(media_type, json!({ "schema": schema }));
+    }
+
+    let mut out = Map::new();
+    out.insert("content".to_string(), Value::Object(content));
+    if let Some(description) = param.get("description") {
+        out.insert("description".to_string(), description.clone());
+    }
+    if let Some(required) = param.get("required") {
+        out.insert("required".to_string(), required.clone());
+    }
+    if let Some(name) = param.get("name") {
+        out.insert("x-body-name".to_string(), name.clone());
+    }
+    Value::Object(out)
+}
+
+fn form_to_request_body(params: &[&Value], consumes: &[String]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    let mut has_file = false;
+
+    for param in params {
+        let Some(name) = param.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let mut schema = convert_parameter(param)
+            .get("schema")
+            .cloned()
+            .unwrap_or(json!({}));
+        if schema.get("type").and_then(Value::as_str) == Some("file") {
+            has_file = true;
+            schema = json!({ "type": "string", "format": "binary" });
+        }
+        if let (Some(description), Value::Object(map)) = (param.get("description"), &mut schema) {
+            map.insert("description".to_string(), description.clone());
+        }
+        properties.insert(name.to_string(), schema);
+        if param.get("required").and_then(Value::as_bool) == Some(true) {
+            required.push(json!(name));
+        }
+    }
+
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+    }
+
+    let form_types: Vec<String> = consumes
+        .iter()
+        .filter(|c| c.contains("form"))
+        .cloned()
+        .collect();
+    let fallback = if has_file {
+        "multipart/form-data"
+    } else {
+        "application/x-www-form-urlencoded"
+    };
+
+    let mut content = Map::new();
+    for media_type in media_types(&form_types, fallback) {
+        content.insert(media_type, json!({ "schema": schema }));
+    }
+    json!({ "content": content })
+}
+
+fn convert_response(response: &Value, produces: &[String]) -> Value {
+    let Some(response) = response.as_object() else {
+        return response.clone();
+    };
+    if response.contains_key("$ref") {
+        return rewrite_refs(&Value::Object(response.clone()));
+    }
+
+    let mut out = Map::new();
+    out.insert(
+        "description".to_string(),
+        response.get("description").cloned().unwrap_or(json!("")),
+    );
+
+    if let Some(schema) = response.get("schema") {
+        let schema = convert_schema(schema);
+        let examples = response.get("examples").and_then(Value::as_object);
+        let mut content = Map::new();
+        for media_type in media_types(produces, "application/json") {
+            let mut media = Map::new();
+            media.insert("schema".to_string(), schema.clone());
+            if let Some(example) = examples.and_then(|e| e.get(&media_type)) {
+                media.insert("example".to_string(), example.clone());
+            }
+            content.insert(media_type, Value::Object(media));
+        }
+        out.insert("content".to_string(), Value::Object(content));
+    }
+
+    if let Some(headers) = response.get("headers").and_then(Value::as_object) {
+        let converted: Map<String, Value> = headers
+            .iter()
+            .map(|(name, header)| {
+                let mut header_out = Map::new();
+                let mut schema = Map::new();
+                for (key, value) in header.as_object().into_iter().flatten() {
+                    if PARAMETER_SCHEMA_KEYS.contains(&key.as_str()) {
+                        schema.insert(key.clone(), value.clone());
+                    } else if key != "collectionFormat" {
+                        header_out.insert(key.clone(), value.clone());
+                    }
+                }
+                header_out.insert("schema".to_string(), Value::Object(schema));
+                (name.clone(), Value::Object(header_out))
+            })
+            .collect();
+        out.insert("headers".to_string(), Value::Object(converted));
+    }
+
+    for (key, value) in response {
+        if key.starts_with("x-") {
+            out.insert(key.clone(), value.clone());
+        }
+    }
+
+    Value::Object(out)
+}
+
+fn convert_security_scheme(scheme: &Value) -> Value {
+    match scheme.get("type").and_then(Value::as_str) {
+        Some("basic") => {
+            let mut out = json!({ "type": "http", "scheme": "basic" });
+            if let Some(description) = scheme.get("description") {
+                out["description"] = description.clone();
+            }
+            out
+        }
+        Some("oauth2") => {
+            let scopes = scheme.get("scopes").cloned().unwrap_or(json!({}));
+            let authorization_url = scheme.get("authorizationUrl").cloned();
+            let token_url = scheme.get("tokenUrl").cloned();
+            let (flow_name, flow) = match scheme.get("flow").and_then(Value::as_str) {
+                Some("implicit") => (
+                    "implicit",
+                    json!({ "authorizationUrl": authorization_url, "scopes": scopes }),
+                ),
+                Some("password") => (
+                    "password",
+                    json!({ "tokenUrl": token_url, "scopes": scopes }),
+                ),
+                Some("application") => (
+                    "clientCredentials",
+                    json!({ "tokenUrl": token_url, "scopes": scopes }),
+                ),
+                _ => (
+                    "authorizationCode",
+                    json!({
+                        "authorizationUrl": authorization_url,
+                        "tokenUrl": token_url,
+                        "scopes": scopes
+                    }),
+                ),
+            };
+            let mut out = json!({ "type": "oauth2", "flows": { flow_name: flow } });
+            if let Some(description) = scheme.get("description") {
+                out["description"] = description.clone();
+            }
+            out
+        }
+        _ => scheme.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Value {
+        serde_json::from_str(include_str!("fixtures/petstore-swagger2.json")).unwrap()
+    }
+
+    #[test]
+    fn converts_document_level_fields() {
+        let doc = fixture();
+        assert!(is_swagger2(&doc));
+
+        let out = convert(&doc);
+        assert_eq!(out["openapi"], "3.0.3");
+        assert_eq!(out["info"]["title"], "Petstore");
+        assert_eq!(out["x-owner"], "pets-team");
+        assert_eq!(
+            out["servers"],
+            json!([
+                { "url": "http://petstore.example.com/v1" },
+                { "url": "https://petstore.example.com/v1" }
+            ])
+        );
+    }
+
+    #[test]
+    fn converts_parameters_and_responses() {
+        let out = convert(&fixture());
+        let list = &out["paths"]["/pets"]["get"];
+
+        assert_eq!(
+            list["parameters"][0],
+            json!({
+                "name": "tags",
+                "in": "query",
+                "style": "form",
+                "explode": true,
+                "schema": { "type": "array", "items": { "type": "string" } }
+            })
+        );
+        assert_eq!(list["parameters"][1]["example"], 20);
+        assert_eq!(
+            list["parameters"][1]["schema"],
+            json!({ "type": "integer", "maximum": 100 })
+        );
+
+        let ok = &list["responses"]["200"];
+        assert_eq!(
+            ok["content"]["application/json"]["schema"]["items"]["$ref"],
+            "#/components/schemas/Pet"
+        );
+        assert_eq!(
+            ok["content"]["application/json"]["example"],
+            json!([{ "id": 1, "name": "Rex" }])
+        );
+        assert_eq!(
+            ok["headers"]["X-Rate-Limit"],
+            json!({ "description": "Requests left", "schema": { "type": "integer" } })
+        );
+        assert_eq!(
+            list["responses"]["default"],
+            json!({ "$ref": "#/components/responses/Error" })
+        );
+
+        let by_id = &out["paths"]["/pets/{petId}"];
+        assert_eq!(
+            by_id["parameters"],
+            json!([{ "$ref": "#/components/parameters/petId" }])
+        );
+        assert_eq!(by_id["get"]["security"], json!([{ "api_key": [] }]));
+    }
+
+    #[test]
+    fn converts_form_data_to_a_multipart_body() {
+        let out = convert(&fixture());
+        let upload = &out["paths"]["/pets/{petId}/photo"]["post"];
+
+        assert_eq!(upload["parameters"][0]["name"], "petId");
+        assert_eq!(upload["parameters"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            upload["requestBody"],
+            json!({
+                "content": {
+                    "multipart/form-data": {
+                        "schema": {
+                            "type": "object",
+                            "properties": {
+                                "file": { "type": "string", "format": "binary" },
+                                "caption": {
+                                    "type": "string",
+                                    "description": "Shown under the photo"
+                                }
+                            },
+                            "required": ["file"]
+                        }
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn converts_components() {
+        let out = convert(&fixture());
+        let components = &out["components"];
+
+        assert_eq!(
+            components["schemas"]["Pet"]["properties"]["tag"],
+            json!({ "type": "string", "nullable": true })
+        );
+        assert_eq!(
+            components["parameters"]["petId"]["schema"],
+            json!({ "type": "integer", "format": "int64" })
+        );
+        assert!(components["parameters"].get("PetBody").is_none());
+        assert_eq!(
+            components["requestBodies"]["PetBody"],
+            json!({
+                "content": {
+                    "application/json": { "schema": { "$ref": "#/components/schemas/Pet" } }
+                },
+                "required": true,
+                "x-body-name": "pet"
+            })
+        );
+        assert_eq!(
+            components["responses"]["Error"]["content"]["application/json"]["schema"],
+            json!({ "$ref": "#/components/schemas/Error" })
+        );
+
+        let schemes = &components["securitySchemes"];
+        assert_eq!(
+            schemes["basic"],
+            json!({ "type": "http", "scheme": "basic" })
+        );
+        assert_eq!(
+            schemes["api_key"],
+            json!({ "type": "apiKey", "name": "X-API-Key", "in": "header" })
+        );
+        assert_eq!(
+            schemes["oauth"]["flows"]["clientCredentials"],
+            json!({
+                "tokenUrl": "https://auth.example.com/token",
+                "scopes": { "pets:read": "Read pets" }
+            })
+        );
+    }
+}
@@ -0,0 +1,924 @@
+use crate::secrets::SecretVault;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const WORKSPACE_FILE: &str = "workspace.json";
+pub const SPEC_FILE: &str = "spec.json";
+pub const HEADERS_FILE: &str = "headers.json";
+pub const AUTH_FILE: &str = "auth.json";
+/// Secret variable and auth values; listed in the directory's `.gitignore`
+pub const SECRETS_FILE: &str = "secrets.local.json";
+const ENVIRONMENTS_DIR: &str = "environments";
+const OPERATIONS_DIR: &str = "operations";
+/// Longest file name stem written, leaving room for a collision suffix
+const MAX_STEM_LENGTH: usize = 100;
+
+/// `name, base_url, spec_url, active_environment_id, active_spec_id`
+type WorkspaceRow = (
+    String,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+/// File contents keyed by their `/`-separated path relative to the directory
+pub type Documents = BTreeMap<String, String>;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceFile {
+    name: String,
+    base_url: Option<String>,
+    spec_url: Option<String>,
+    /// Environment name, since ids differ between databases
+    active_environment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnvironmentFile {
+    name: String,
+    #[serde(default)]
+    variables: BTreeMap<String, String>,
+    /// Secret variables set in this environment; their values live in the secrets file
+    #[serde(default)]
+    secret_variables: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthEntry {
+    /// Environment name, or none for values shared by every environment
+    environment: Option<String>,
+    scheme: String,
+    values: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OperationFile {
+    operation_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    form_data: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    assertions: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extraction_rules: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pre_request_script: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    post_response_script: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretsFile {
+    /// Values keyed by environment name, then variable name
+    #[serde(default)]
+    variables: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default)]
+    auth: Vec<AuthEntry>,
+}
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+pub fn hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+fn to_document(value: &impl Serialize) -> Result<String, String> {
+    let mut text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    text.push('\n');
+    Ok(text)
+}
+
+fn from_document<T: for<'de> Deserialize<'de>>(path: &str, text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Maps names to file stems, replacing characters that aren't safe in file names, so
+/// `get:/pets/{id}` becomes `get_pets_{id}`. Names whose stems collide each get a short
+/// hash of the full name appended.
+fn file_stems<'a>(names: impl IntoIterator<Item = &'a str>) -> HashMap<String, String> {
+    let stems: Vec<(&str, String)> = names
+        .into_iter()
+        .map(|name| {
+            let stem: String = name
+                .replacen(":/", "_", 1)
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || "-.{}".contains(c) {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .take(MAX_STEM_LENGTH)
+                .collect();
+            (name, stem)
+        })
+        .collect();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for (_, stem) in &stems {
+        *counts.entry(stem.as_str()).or_default() += 1;
+    }
+    stems
+        .iter()
+        .map(|(name, stem)| {
+            let stem = if counts[stem.as_str()] > 1 {
+                format!("{}-{}", stem, &hash(name)[..8])
+            } else {
+                stem.clone()
+            };
+            (name.to_string(), stem)
+        })
+        .collect()
+}
+
+/// Files that can't delete anything from the database; removing one rewrites it. This
+/// keeps a fresh clone, which has no secrets file, from wiping the secrets.
+pub fn is_singleton(path: &str) -> bool {
+    path == WORKSPACE_FILE || path == SPEC_FILE || path == SECRETS_FILE
+}
+
+/// Order documents are applied to the database in, so environments exist before the files
+/// naming them
+fn apply_rank(path: &str) -> u8 {
+    match path {
+        SPEC_FILE => 0,
+        _ if path.starts_with(ENVIRONMENTS_DIR) => 1,
+        WORKSPACE_FILE => 2,
+        HEADERS_FILE => 3,
+        AUTH_FILE => 4,
+        _ if path.starts_with(OPERATIONS_DIR) => 5,
+        _ => 6,
+    }
+}
+
+pub fn sort_for_apply(paths: &mut [String]) {
+    paths.sort_by_key(|path| (apply_rank(path), path.clone()));
+}
+
+/// Renders a workspace as directory documents. Secret values are only included when
+/// `include_secrets` is set, since revealing them may need the vault.
+pub async fn render(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    include_secrets: bool,
+) -> Result<Documents, String> {
+    let mut documents = Documents::new();
+
+    let workspace: Option<WorkspaceRow> =
+        sqlx::query_as(
+            "SELECT name, base_url, spec_url, active_environment_id, active_spec_id FROM workspaces WHERE id = ?",
+        )
+        .bind(workspace_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (name, base_url, spec_url, active_environment_id, active_spec_id) =
+        workspace.ok_or_else(|| format!("Workspace not found: {}", workspace_id))?;
+
+    let environments: Vec<(String, String)> =
+        sqlx::query_as("SELECT id, name FROM environments WHERE workspace_id = ? ORDER BY name")
+            .bind(workspace_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let environment_names: HashMap<&str, &str> = environments
+        .iter()
+        .map(|(id, name)| (id.as_str(), name.as_str()))
+        .collect();
+    let environment_name = |id: &Option<String>| -> Option<String> {
+        id.as_deref()
+            .and_then(|id| environment_names.get(id))
+            .map(|name| name.to_string())
+    };
+
+    documents.insert(
+        WORKSPACE_FILE.to_string(),
+        to_document(&WorkspaceFile {
+            name,
+            base_url,
+            spec_url,
+            active_environment: environment_name(&active_environment_id),
+        })?,
+    );
+
+    if let Some(spec_id) = &active_spec_id {
+        let spec: Option<(String,)> = sqlx::query_as("SELECT spec_content FROM specs WHERE id = ?")
+            .bind(spec_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Some((content,)) = spec {
+            // Keys keep their document order, so operations stay in the order written
+            let text = match serde_json::from_str::<Value>(&content) {
+                Ok(spec) => to_document(&spec)?,
+                Err(_) => content,
+            };
+            documents.insert(SPEC_FILE.to_string(), text);
+        }
+    }
+
+    let mut environment_files: BTreeMap<&str, EnvironmentFile> = environments
+        .iter()
+        .map(|(id, name)| {
+            (
+                id.as_str(),
+                EnvironmentFile {
+                    name: name.clone(),
+                    variables: BTreeMap::new(),
+                    secret_variables: BTreeSet::new(),
+                },
+            )
+        })
+        .collect();
+    let mut secrets = SecretsFile::default();
+    let values: Vec<(String, String, bool, String)> = sqlx::query_as(
+        "SELECT v.environment_id, k.key_name, k.is_secret, v.value FROM environment_variable_values v
+         INNER JOIN workspace_variable_keys k ON v.variable_key_id = k.id
+         WHERE k.workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    for (environment_id, key_name, is_secret, value) in values {
+        let Some(file) = environment_files.get_mut(environment_id.as_str()) else {
+            continue;
+        };
+        if !is_secret {
+            file.variables.insert(key_name, vault.reveal(&value)?);
+            continue;
+        }
+        if include_secrets {
+            secrets
+                .variables
+                .entry(file.name.clone())
+                .or_default()
+                .insert(key_name.clone(), vault.reveal(&value)?);
+        }
+        file.secret_variables.insert(key_name);
+    }
+    let environment_stems = file_stems(environments.iter().map(|(_, name)| name.as_str()));
+    for file in environment_files.into_values() {
+        documents.insert(
+            format!(
+                "{}/{}.json",
+                ENVIRONMENTS_DIR, environment_stems[&file.name]
+            ),
+            to_document(&file)?,
+        );
+    }
+
+    let headers: Vec<(String, String)> =
+        sqlx::query_as("SELECT key, value FROM global_headers WHERE workspace_id = ?")
+            .bind(workspace_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let headers: BTreeMap<String, String> = headers.into_iter().collect();
+    documents.insert(HEADERS_FILE.to_string(), to_document(&headers)?);
+
+    let auth_rows: Vec<(Option<String>, String, String, bool)> = sqlx::query_as(
+        "SELECT environment_id, scheme_name, value_json, is_secret FROM auth_values WHERE workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let mut auth = Vec::new();
+    for (environment_id, scheme, value_json, is_secret) in auth_rows {
+        if is_secret && !include_secrets {
+            continue;
+        }
+        let environment = environment_name(&environment_id);
+        if environment_id.is_some() && environment.is_none() {
+            continue;
+        }
+        let entry = AuthEntry {
+            environment,
+            scheme,
+            values: serde_json::from_str(&vault.reveal(&value_json)?).map_err(|e| e.to_string())?,
+        };
+        if is_secret {
+            secrets.auth.push(entry);
+        } else {
+            auth.push(entry);
+        }
+    }
+    auth.sort_by(|a, b| (&a.environment, &a.scheme).cmp(&(&b.environment, &b.scheme)));
+    secrets
+        .auth
+        .sort_by(|a, b| (&a.environment, &a.scheme).cmp(&(&b.environment, &b.scheme)));
+    documents.insert(AUTH_FILE.to_string(), to_document(&auth)?);
+
+    if include_secrets && (!secrets.variables.is_empty() || !secrets.auth.is_empty()) {
+        documents.insert(SECRETS_FILE.to_string(), to_document(&secrets)?);
+    }
+
+    let operations = render_operations(pool, workspace_id).await?;
+    let operation_stems = file_stems(operations.keys().map(|key| key.as_str()));
+    for (key, file) in &operations {
+        documents.insert(
+            format!("{}/{}.json", OPERATIONS_DIR, operation_stems[key]),
+            to_document(file)?,
+        );
+    }
+
+    Ok(documents)
+}
+
+async fn render_operations(
+    pool: &SqlitePool,
+    workspace_id: &str,
+) -> Result<BTreeMap<String, OperationFile>, String> {
+    let states: Vec<(String, String)> = sqlx::query_as(
+        "SELECT operation_key, form_data FROM operation_states WHERE workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let assertions: Vec<(String, String)> = sqlx::query_as(
+        "SELECT operation_key, assertions_json FROM operation_assertions WHERE workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let rules: Vec<(String, String)> = sqlx::query_as(
+        "SELECT operation_key, rules_json FROM operation_extractions WHERE workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    let scripts: Vec<(String, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT operation_key, pre_request, post_response FROM operation_scripts WHERE workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let mut operations: BTreeMap<String, OperationFile> = BTreeMap::new();
+    let parse = |text: &str| serde_json::from_str::<Value>(text).map_err(|e| e.to_string());
+    let new_file = |key: &str| OperationFile {
+        operation_key: key.to_string(),
+        form_data: None,
+        assertions: None,
+        extraction_rules: None,
+        pre_request_script: None,
+        post_response_script: None,
+    };
+    for (key, form_data) in states {
+        let file = operations
+            .entry(key.clone())
+            .or_insert_with(|| new_file(&key));
+        file.form_data = Some(parse(&form_data)?);
+    }
+    for (key, json) in assertions {
+        let file = operations
+            .entry(key.clone())
+            .or_insert_with(|| new_file(&key));
+        file.assertions = Some(parse(&json)?);
+    }
+    for (key, json) in rules {
+        let file = operations
+            .entry(key.clone())
+            .or_insert_with(|| new_file(&key));
+        file.extraction_rules = Some(parse(&json)?);
+    }
+    for (key, pre_request, post_response) in scripts {
+        let file = operations
+            .entry(key.clone())
+            .or_insert_with(|| new_file(&key));
+        file.pre_request_script = pre_request;
+        file.post_response_script = post_response;
+    }
+    Ok(operations)
+}
+
+/// Reads the documents in a directory, ignoring files sync doesn't manage
+pub fn read(directory: &Path) -> Result<Documents, String> {
+    let mut documents = Documents::new();
+    let mut read_file = |relative: String| -> Result<(), String> {
+        let path = directory.join(&relative);
+        if path.is_file() {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            // Git may check files out with CRLF line endings on Windows
+            documents.insert(relative, text.replace("\r\n", "\n"));
+        }
+        Ok(())
+    };
+
+    for name in [
+        WORKSPACE_FILE,
+        SPEC_FILE,
+        HEADERS_FILE,
+        AUTH_FILE,
+        SECRETS_FILE,
+    ] {
+        read_file(name.to_string())?;
+    }
+    for dir in [ENVIRONMENTS_DIR, OPERATIONS_DIR] {
+        let Ok(entries) = std::fs::read_dir(directory.join(dir)) else {
+            continue;
+        };
+        for entry in entries {
+            let name = entry.map_err(|e| e.to_string())?.file_name();
+            let name = name.to_string_lossy();
+            if name.ends_with(".json") {
+                read_file(format!("{}/{}", dir, name))?;
+            }
+        }
+    }
+    Ok(documents)
+}
+
+/// Writes a document, or removes its file when `content` is `None`
+pub fn write(directory: &Path, relative: &str, content: Option<&str>) -> Result<(), String> {
+    let path = directory.join(relative);
+    match content {
+        Some(content) => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(&path, content)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+        }
+        None => match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to remove {}: {}", path.display(), e))
+            }
+            _ => Ok(()),
+        },
+    }
+}
+
+/// What applying a document to the database needs besides the connection
+pub struct ApplyContext<'a> {
+    pub workspace_id: &'a str,
+    pub vault: &'a SecretVault,
+    /// Secrets are stored as given when the database has no vault
+    pub vault_initialized: bool,
+}
+
+impl ApplyContext<'_> {
+    fn encode(&self, value: &str, is_secret: bool) -> Result<String, String> {
+        if is_secret && self.vault_initialized {
+            self.vault.seal(value)
+        } else {
+            Ok(value.to_string())
+        }
+    }
+}
+
+async fn environment_id(
+    conn: &mut SqliteConnection,
+    workspace_id: &str,
+    name: &str,
+) -> Result<Option<String>, String> {
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT id FROM environments WHERE workspace_id = ? AND name = ?")
+            .bind(workspace_id)
+            .bind(name)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(row.map(|(id,)| id))
+}
+
+/// Returns a variable key's id and secret flag, creating it with `is_secret` if missing
+async fn variable_key(
+    conn: &mut SqliteConnection,
+    workspace_id: &str,
+    name: &str,
+    is_secret: bool,
+) -> Result<(i64, bool), String> {
+    let row: Option<(i64, bool)> = sqlx::query_as(
+        "SELECT id, is_secret FROM workspace_variable_keys WHERE workspace_id = ? AND key_name = ?",
+    )
+    .bind(workspace_id)
+    .bind(name)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    if let Some(row) = row {
+        return Ok(row);
+    }
+    let id = sqlx::query(
+        "INSERT INTO workspace_variable_keys (workspace_id, key_name, is_secret) VALUES (?, ?, ?)",
+    )
+    .bind(workspace_id)
+    .bind(name)
+    .bind(is_secret)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?
+    .last_insert_rowid();
+    Ok((id, is_secret))
+}
+
+async fn set_variable(
+    conn: &mut SqliteConnection,
+    ctx: &ApplyContext<'_>,
+    environment_id: &str,
+    name: &str,
+    value: &str,
+    is_secret: bool,
+) -> Result<(), String> {
+    let (key_id, is_secret) = variable_key(conn, ctx.workspace_id, name, is_secret).await?;
+    sqlx::query(
+        "INSERT OR REPLACE INTO environment_variable_values (environment_id, variable_key_id, value) VALUES (?, ?, ?)",
+    )
+    .bind(environment_id)
+    .bind(key_id)
+    .bind(ctx.encode(value, is_secret)?)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Replaces the workspace's secret or non-secret auth values with `entries`
+async fn replace_auth(
+    conn: &mut SqliteConnection,
+    ctx: &ApplyContext<'_>,
+    entries: &[AuthEntry],
+    is_secret: bool,
+) -> Result<(), String> {
+    sqlx::query("DELETE FROM auth_values WHERE workspace_id = ? AND is_secret = ?")
+        .bind(ctx.workspace_id)
+        .bind(is_secret)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    for entry in entries {
+        let environment_id = match &entry.environment {
+            Some(name) => match environment_id(conn, ctx.workspace_id, name).await? {
+                Some(id) => Some(id),
+                None => return Err(format!("Auth values name an unknown environment: {}", name)),
+            },
+            None => None,
+        };
+        let value_json = serde_json::to_string(&entry.values).map_err(|e| e.to_string())?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO auth_values (workspace_id, environment_id, scheme_name, value_json, is_secret)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(ctx.workspace_id)
+        .bind(environment_id)
+        .bind(&entry.scheme)
+        .bind(ctx.encode(&value_json, is_secret)?)
+        .bind(is_secret)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Upserts or deletes one per-operation JSON row; `table` and `column` are never user input
+async fn set_operation_json(
+    conn: &mut SqliteConnection,
+    workspace_id: &str,
+    operation_key: &str,
+    (table, column): (&str, &str),
+    value: Option<&Value>,
+    now: i64,
+) -> Result<(), String> {
+    match value {
+        Some(value) => {
+            let sql = format!(
+                "INSERT INTO {table} (workspace_id, operation_key, {column}, last_modified) VALUES (?, ?, ?, ?)
+                 ON CONFLICT(workspace_id, operation_key) DO UPDATE SET {column} = excluded.{column}, last_modified = excluded.last_modified"
+            );
+            sqlx::query(&sql)
+                .bind(workspace_id)
+                .bind(operation_key)
+                .bind(value.to_string())
+                .bind(now)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        None => {
+            let sql = format!("DELETE FROM {table} WHERE workspace_id = ? AND operation_key = ?");
+            sqlx::query(&sql)
+                .bind(workspace_id)
+                .bind(operation_key)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Applies one document to the database. `database` is what the database rendered at the
+/// same path, used to find what a removed file referred to.
+pub async fn apply(
+    conn: &mut SqliteConnection,
+    ctx: &ApplyContext<'_>,
+    path: &str,
+    database: Option<&str>,
+    directory: Option<&str>,
+) -> Result<(), String> {
+    let workspace_id = ctx.workspace_id;
+    match path {
+        WORKSPACE_FILE => {
+            let Some(text) = directory else {
+                return Ok(());
+            };
+            let file: WorkspaceFile = from_document(path, text)?;
+            let active_environment_id = match &file.active_environment {
+                Some(name) => environment_id(conn, workspace_id, name).await?,
+                None => None,
+            };
+            sqlx::query(
+                "UPDATE workspaces SET name = ?, base_url = ?, spec_url = ?, active_environment_id = ? WHERE id = ?",
+            )
+            .bind(&file.name)
+            .bind(&file.base_url)
+            .bind(&file.spec_url)
+            .bind(active_environment_id)
+            .bind(workspace_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        }
+        SPEC_FILE => {
+            let Some(text) = directory else {
+                return Ok(());
+            };
+            serde_json::from_str::<Value>(text).map_err(|e| format!("{}: {}", path, e))?;
+            let (active_spec_id,): (Option<String>,) =
+                sqlx::query_as("SELECT active_spec_id FROM workspaces WHERE id = ?")
+                    .bind(workspace_id)
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+            // Other workspaces may use the same spec row, so it's only rewritten in place
+            // when this workspace is the only one pointing at it
+            let unshared = match active_spec_id {
+                Some(spec_id) => {
+                    let (others,): (i64,) = sqlx::query_as(
+                        "SELECT COUNT(*) FROM workspaces WHERE active_spec_id = ? AND id != ?",
+                    )
+                    .bind(&spec_id)
+                    .bind(workspace_id)
+                    .fetch_one(&mut *conn)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                    (others == 0).then_some(spec_id)
+                }
+                None => None,
+            };
+            let spec_id = unshared.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            sqlx::query(
+                "INSERT INTO specs (id, spec_content) VALUES (?, ?)
+                 ON CONFLICT(id) DO UPDATE SET spec_content = excluded.spec_content",
+            )
+            .bind(&spec_id)
+            .bind(text)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+            sqlx::query("UPDATE workspaces SET active_spec_id = ? WHERE id = ?")
+                .bind(&spec_id)
+                .bind(workspace_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        HEADERS_FILE => {
+            let headers: BTreeMap<String, String> = match directory {
+                Some(text) => from_document(path, text)?,
+                None => BTreeMap::new(),
+            };
+            sqlx::query("DELETE FROM global_headers WHERE workspace_id = ?")
+                .bind(workspace_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            for (key, value) in &headers {
+                sqlx::query(
+                    "INSERT INTO global_headers (workspace_id, key, value) VALUES (?, ?, ?)",
+                )
+                .bind(workspace_id)
+                .bind(key)
+                .bind(value)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        AUTH_FILE => {
+            let entries: Vec<AuthEntry> = match directory {
+                Some(text) => from_document(path, text)?,
+                None => Vec::new(),
+            };
+            replace_auth(conn, ctx, &entries, false).await?;
+        }
+        SECRETS_FILE => {
+            let secrets: SecretsFile = match directory {
+                Some(text) => from_document(path, text)?,
+                None => SecretsFile::default(),
+            };
+            sqlx::query(
+                "DELETE FROM environment_variable_values WHERE variable_key_id IN (
+                     SELECT id FROM workspace_variable_keys WHERE workspace_id = ? AND is_secret = 1
+                 )",
+            )
+            .bind(workspace_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+            for (environment, values) in &secrets.variables {
+                let environment_id = environment_id(conn, workspace_id, environment)
+                    .await?
+                    .ok_or_else(|| {
+                        format!("Secrets name an unknown environment: {}", environment)
+                    })?;
+                for (name, value) in values {
+                    set_variable(conn, ctx, &environment_id, name, value, true).await?;
+                }
+            }
+            replace_auth(conn, ctx, &secrets.auth, true).await?;
+        }
+        _ if path.starts_with(ENVIRONMENTS_DIR) => {
+            apply_environment(conn, ctx, path, database, directory).await?;
+        }
+        _ if path.starts_with(OPERATIONS_DIR) => {
+            apply_operation(conn, ctx, path, database, directory).await?;
+        }
+        _ => return Err(format!("Not a workspace file: {}", path)),
+    }
+    Ok(())
+}
+
+async fn apply_environment(
+    conn: &mut SqliteConnection,
+    ctx: &ApplyContext<'_>,
+    path: &str,
+    database: Option<&str>,
+    directory: Option<&str>,
+) -> Result<(), String> {
+    let workspace_id = ctx.workspace_id;
+    let Some(text) = directory else {
+        let Some(text) = database else {
+            return Ok(());
+        };
+        let file: EnvironmentFile = from_document(path, text)?;
+        sqlx::query("DELETE FROM environments WHERE workspace_id = ? AND name = ?")
+            .bind(workspace_id)
+            .bind(&file.name)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        return Ok(());
+    };
+
+    let file: EnvironmentFile = from_document(path, text)?;
+    let environment_id = match environment_id(conn, workspace_id, &file.name).await? {
+        Some(id) => id,
+        None => {
+            let id = uuid::Uuid::new_v4().to_string();
+            sqlx::query("INSERT INTO environments (id, workspace_id, name) VALUES (?, ?, ?)")
+                .bind(&id)
+                .bind(workspace_id)
+                .bind(&file.name)
+                .execute(&mut *conn)
+                .await
+                .map_err(|e| e.to_string())?;
+            id
+        }
+    };
+
+    for (name, value) in &file.variables {
+        set_variable(conn, ctx, &environment_id, name, value, false).await?;
+    }
+    for name in &file.secret_variables {
+        variable_key(conn, workspace_id, name, true).await?;
+    }
+
+    // Variables the file no longer lists are unset in this environment
+    let listed: BTreeSet<&String> = file
+        .variables
+        .keys()
+        .chain(file.secret_variables.iter())
+        .collect();
+    let current: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT k.id, k.key_name FROM environment_variable_values v
+         INNER JOIN workspace_variable_keys k ON v.variable_key_id = k.id
+         WHERE k.workspace_id = ? AND v.environment_id = ?",
+    )
+    .bind(workspace_id)
+    .bind(&environment_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|e| e.to_string())?;
+    for (key_id, name) in current {
+        if listed.contains(&name) {
+            continue;
+        }
+        sqlx::query(
+            "DELETE FROM environment_variable_values WHERE environment_id = ? AND variable_key_id = ?",
+        )
+        .bind(&environment_id)
+        .bind(key_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+async fn apply_operation(
+    conn: &mut SqliteConnection,
+    ctx: &ApplyContext<'_>,
+    path: &str,
+    database: Option<&str>,
+    directory: Option<&str>,
+) -> Result<(), String> {
+    let workspace_id = ctx.workspace_id;
+    let file = match (directory, database) {
+        (Some(text), _) => from_document::<OperationFile>(path, text)?,
+        // A removed file clears everything saved for the operation it held
+        (None, Some(text)) => OperationFile {
+            operation_key: from_document::<OperationFile>(path, text)?.operation_key,
+            form_data: None,
+            assertions: None,
+            extraction_rules: None,
+            pre_request_script: None,
+            post_response_script: None,
+        },
+        (None, None) => return Ok(()),
+    };
+    let key = &file.operation_key;
+    let now = now_ms();
+
+    let states = ("operation_states", "form_data");
+    set_operation_json(
+        conn,
+        workspace_id,
+        key,
+        states,
+        file.form_data.as_ref(),
+        now,
+    )
+    .await?;
+    let assertions = ("operation_assertions", "assertions_json");
+    set_operation_json(
+        conn,
+        workspace_id,
+        key,
+        assertions,
+        file.assertions.as_ref(),
+        now,
+    )
+    .await?;
+    let extractions = ("operation_extractions", "rules_json");
+    let rules = file.extraction_rules.as_ref();
+    set_operation_json(conn, workspace_id, key, extractions, rules, now).await?;
+
+    if file.pre_request_script.is_some() || file.post_response_script.is_some() {
+        sqlx::query(
+            "INSERT INTO operation_scripts (workspace_id, operation_key, pre_request, post_response, last_modified) VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(workspace_id, operation_key) DO UPDATE SET pre_request = excluded.pre_request,
+                 post_response = excluded.post_response, last_modified = excluded.last_modified",
+        )
+        .bind(workspace_id)
+        .bind(key)
+        .bind(&file.pre_request_script)
+        .bind(&file.post_response_script)
+        .bind(now)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?;
+    } else {
+        sqlx::query("DELETE FROM operation_scripts WHERE workspace_id = ? AND operation_key = ?")
+            .bind(workspace_id)
+            .bind(key)
+            .execute(&mut *conn)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,520 @@
+pub mod files;
+
+use crate::secrets::SecretVault;
+use files::{ApplyContext, Documents, SECRETS_FILE};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Which side wins when a file changed both in the database and on disk
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncSide {
+    Database,
+    Directory,
+}
+
+/// A file changed on both sides since the last sync; `None` means it was deleted there
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub path: String,
+    pub database: Option<String>,
+    pub directory: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    /// Files written to or removed from the directory
+    pub to_directory: Vec<String>,
+    /// Files applied to the database
+    pub to_database: Vec<String>,
+    /// Files left untouched on both sides until the conflict is resolved
+    pub conflicts: Vec<SyncConflict>,
+    /// Whether secret values took part; they're skipped while the vault is locked
+    pub secrets_synced: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncLink {
+    pub workspace_id: String,
+    pub directory: String,
+    pub last_synced_at: Option<i64>,
+}
+
+/// Content hashes of every file as of the last sync, keyed by path
+type SyncState = BTreeMap<String, String>;
+
+/// Links a workspace to a directory, creating it and git-ignoring the secrets file. The
+/// first sync afterwards treats every file as new.
+pub async fn link(pool: &SqlitePool, workspace_id: &str, directory: &str) -> Result<(), String> {
+    let dir = Path::new(directory);
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let gitignore = dir.join(".gitignore");
+    let existing = match std::fs::read_to_string(&gitignore) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.to_string()),
+    };
+    if !existing.lines().any(|line| line.trim() == SECRETS_FILE) {
+        let mut text = existing;
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(SECRETS_FILE);
+        text.push('\n');
+        std::fs::write(&gitignore, text).map_err(|e| e.to_string())?;
+    }
+
+    sqlx::query(
+        "INSERT OR REPLACE INTO workspace_sync (workspace_id, directory, state_json, last_synced_at)
+         VALUES (?, ?, '{}', NULL)",
+    )
+    .bind(workspace_id)
+    .bind(directory)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Forgets a workspace's directory; the files themselves are left in place
+pub async fn unlink(pool: &SqlitePool, workspace_id: &str) -> Result<(), String> {
+    sqlx::query("DELETE FROM workspace_sync WHERE workspace_id = ?")
+        .bind(workspace_id)
+        .execute(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+pub async fn get_link(pool: &SqlitePool, workspace_id: &str) -> Result<Option<SyncLink>, String> {
+    let row: Option<(String, Option<i64>)> = sqlx::query_as(
+        "SELECT directory, last_synced_at FROM workspace_sync WHERE workspace_id = ?",
+    )
+    .bind(workspace_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(row.map(|(directory, last_synced_at)| SyncLink {
+        workspace_id: workspace_id.to_string(),
+        directory,
+        last_synced_at,
+    }))
+}
+
+/// Reconciles a workspace with its linked directory. Each file is compared with its hash
+/// from the last sync: a side that changed is copied to the other, and a file changed on
+/// both sides is a conflict, resolved by `prefer` when given.
+pub async fn sync(
+    pool: &SqlitePool,
+    vault: &SecretVault,
+    workspace_id: &str,
+    prefer: Option<SyncSide>,
+) -> Result<SyncReport, String> {
+    let row: Option<(String, String)> =
+        sqlx::query_as("SELECT directory, state_json FROM workspace_sync WHERE workspace_id = ?")
+            .bind(workspace_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+    let (directory, state_json) =
+        row.ok_or_else(|| "Workspace is not linked to a directory".to_string())?;
+    let dir = Path::new(&directory);
+    let base: SyncState = serde_json::from_str(&state_json).map_err(|e| e.to_string())?;
+
+    let vault_initialized = vault.status(pool).await?.initialized;
+    let secrets_synced = !vault_initialized || vault.is_unlocked();
+
+    let database = files::render(pool, vault, workspace_id, secrets_synced).await?;
+    let mut disk = files::read(dir)?;
+    if !secrets_synced {
+        disk.remove(SECRETS_FILE);
+    }
+
+    let mut report = SyncReport {
+        secrets_synced,
+        ..Default::default()
+    };
+    let mut to_database = Vec::new();
+    let paths: BTreeSet<&String> = database.keys().chain(disk.keys()).collect();
+    for path in paths {
+        let (db_text, disk_text) = (database.get(path), disk.get(path));
+        if db_text == disk_text {
+            continue;
+        }
+        let base_hash = base.get(path);
+        let unchanged = |text: Option<&String>| text.map(|t| files::hash(t)).as_ref() == base_hash;
+        let side = if unchanged(disk_text) || (disk_text.is_none() && files::is_singleton(path)) {
+            SyncSide::Database
+        } else if unchanged(db_text) {
+            SyncSide::Directory
+        } else if let Some(side) = prefer {
+            side
+        } else {
+            report.conflicts.push(SyncConflict {
+                path: path.clone(),
+                database: db_text.cloned(),
+                directory: disk_text.cloned(),
+            });
+            continue;
+        };
+        if side == SyncSide::Directory {
+            to_database.push(path.clone());
+        }
+    }
+
+    // Every change from the directory goes in together, or none does
+    files::sort_for_apply(&mut to_database);
+    let mut tx = pool.begin().await.map_err(|e| e.to_string())?;
+    let ctx = ApplyContext {
+        workspace_id,
+        vault,
+        vault_initialized,
+    };
+    for path in &to_database {
+        let db_text = database.get(path).map(String::as_str);
+        let disk_text = disk.get(path).map(String::as_str);
+        files::apply(&mut tx, &ctx, path, db_text, disk_text).await?;
+    }
+    tx.commit().await.map_err(|e| e.to_string())?;
+    report.to_database = to_database;
+
+    // Rendering again normalizes what was applied, so the directory ends up matching the
+    // database byte for byte everywhere except the conflicts
+    let conflicted: BTreeSet<&str> = report.conflicts.iter().map(|c| c.path.as_str()).collect();
+    let rendered: Documents = if report.to_database.is_empty() {
+        database
+    } else {
+        files::render(pool, vault, workspace_id, secrets_synced).await?
+    };
+    let paths: BTreeSet<&String> = rendered.keys().chain(disk.keys()).collect();
+    for path in paths {
+        if conflicted.contains(path.as_str()) || rendered.get(path) == disk.get(path) {
+            continue;
+        }
+        files::write(dir, path, rendered.get(path).map(String::as_str))?;
+        if !report.to_database.contains(path) {
+            report.to_directory.push(path.clone());
+        }
+    }
+
+    let mut state: SyncState = rendered
+        .iter()
+        .filter(|(path, _)| !conflicted.contains(path.as_str()))
+        .map(|(path, text)| (path.clone(), files::hash(text)))
+        .collect();
+    for path in &conflicted {
+        if let Some(hash) = base.get(*path) {
+            state.insert(path.to_string(), hash.clone());
+        }
+    }
+    if !secrets_synced {
+        if let Some(hash) = base.get(SECRETS_FILE) {
+            state.insert(SECRETS_FILE.to_string(), hash.clone());
+        }
+    }
+
+    sqlx::query(
+        "UPDATE workspace_sync SET state_json = ?, last_synced_at = ? WHERE workspace_id = ?",
+    )
+    .bind(serde_json::to_string(&state).map_err(|e| e.to_string())?)
+    .bind(files::now_ms())
+    .bind(workspace_id)
+    .execute(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::secrets::{store, VaultCredential};
+    use crate::templating::WorkspaceContext;
+    use std::path::PathBuf;
+
+    /// A workspace with a `dev` environment and a global header, linked to a new directory
+    async fn linked(pool: &SqlitePool) -> PathBuf {
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        repository::create_environment(pool, "e1", "w1", "dev")
+            .await
+            .unwrap();
+        repository::set_global_header(pool, "w1", "x-app", "pets")
+            .await
+            .unwrap();
+        let dir = std::env::temp_dir().join(format!("cogeass-sync-{}", uuid::Uuid::new_v4()));
+        link(pool, "w1", dir.to_str().unwrap()).await.unwrap();
+        dir
+    }
+
+    fn read(dir: &Path, relative: &str) -> Option<String> {
+        std::fs::read_to_string(dir.join(relative)).ok()
+    }
+
+    fn write(dir: &Path, relative: &str, content: &str) {
+        files::write(dir, relative, Some(content)).unwrap();
+    }
+
+    async fn header(pool: &SqlitePool) -> String {
+        let (value,): (String,) = sqlx::query_as(
+            "SELECT value FROM global_headers WHERE workspace_id = 'w1' AND key = 'x-app'",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        value
+    }
+
+    #[tokio::test]
+    async fn copies_changes_from_whichever_side_made_them() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        let dir = linked(pool).await;
+
+        let first = sync(pool, &vault, "w1", None).await.unwrap();
+        assert_eq!(
+            first.to_directory,
+            vec![
+                "auth.json",
+                "environments/dev.json",
+                "headers.json",
+                "workspace.json"
+            ]
+        );
+        assert!(read(&dir, ".gitignore").unwrap().contains(SECRETS_FILE));
+        let again = sync(pool, &vault, "w1", None).await.unwrap();
+        assert!(again.to_directory.is_empty() && again.to_database.is_empty());
+
+        repository::set_global_header(pool, "w1", "x-app", "cats")
+            .await
+            .unwrap();
+        let report = sync(pool, &vault, "w1", None).await.unwrap();
+        assert_eq!(report.to_directory, vec!["headers.json"]);
+        assert!(read(&dir, "headers.json").unwrap().contains("cats"));
+
+        write(&dir, "headers.json", r#"{"x-app":"dogs"}"#);
+        let report = sync(pool, &vault, "w1", None).await.unwrap();
+        assert_eq!(report.to_database, vec!["headers.json"]);
+        assert!(report.to_directory.is_empty());
+        assert_eq!(header(pool).await, "dogs");
+        // The applied file is rewritten in the canonical layout
+        assert_eq!(
+            read(&dir, "headers.json"),
+            files::render(pool, &vault, "w1", true)
+                .await
+                .unwrap()
+                .get("headers.json")
+                .cloned()
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn conflicts_wait_for_a_preferred_side() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        let dir = linked(pool).await;
+        sync(pool, &vault, "w1", None).await.unwrap();
+
+        repository::set_global_header(pool, "w1", "x-app", "cats")
+            .await
+            .unwrap();
+        write(&dir, "headers.json", r#"{"x-app":"dogs"}"#);
+        for _ in 0..2 {
+            let report = sync(pool, &vault, "w1", None).await.unwrap();
+            assert_eq!(report.conflicts.len(), 1);
+            assert_eq!(report.conflicts[0].path, "headers.json");
+            assert!(report.to_database.is_empty() && report.to_directory.is_empty());
+            assert_eq!(header(pool).await, "cats");
+            assert_eq!(read(&dir, "headers.json").unwrap(), r#"{"x-app":"dogs"}"#);
+        }
+
+        let report = sync(pool, &vault, "w1", Some(SyncSide::Directory))
+            .await
+            .unwrap();
+        assert!(report.conflicts.is_empty());
+        assert_eq!(header(pool).await, "dogs");
+
+        repository::set_global_header(pool, "w1", "x-app", "birds")
+            .await
+            .unwrap();
+        write(&dir, "headers.json", r#"{"x-app":"fish"}"#);
+        let report = sync(pool, &vault, "w1", Some(SyncSide::Database))
+            .await
+            .unwrap();
+        assert_eq!(report.to_directory, vec!["headers.json"]);
+        assert_eq!(header(pool).await, "birds");
+        assert!(read(&dir, "headers.json").unwrap().contains("birds"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn deleted_files_remove_what_they_held_except_singletons() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        let dir = linked(pool).await;
+        repository::create_environment(pool, "e2", "w1", "prod")
+            .await
+            .unwrap();
+        sync(pool, &vault, "w1", None).await.unwrap();
+
+        std::fs::remove_file(dir.join("environments/prod.json")).unwrap();
+        std::fs::remove_file(dir.join("workspace.json")).unwrap();
+        let report = sync(pool, &vault, "w1", None).await.unwrap();
+        assert_eq!(report.to_database, vec!["environments/prod.json"]);
+        assert_eq!(report.to_directory, vec!["workspace.json"]);
+
+        let environments: Vec<(String,)> =
+            sqlx::query_as("SELECT name FROM environments WHERE workspace_id = 'w1'")
+                .fetch_all(pool)
+                .await
+                .unwrap();
+        assert_eq!(environments, vec![("dev".to_string(),)]);
+        assert!(read(&dir, "workspace.json").unwrap().contains("Pets"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_secrets_file_waits_while_the_vault_is_locked() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        let dir = linked(pool).await;
+        let passphrase = VaultCredential::Passphrase("pw".to_string());
+        vault.unlock(pool, &passphrase).await.unwrap();
+        let token = repository::add_variable_key(pool, "w1", "token")
+            .await
+            .unwrap();
+        store::set_variable_secret(pool, &vault, token, true)
+            .await
+            .unwrap();
+        repository::set_variable_value(pool, &vault, "e1", token, "old-token")
+            .await
+            .unwrap();
+
+        let report = sync(pool, &vault, "w1", None).await.unwrap();
+        assert!(report.secrets_synced);
+        assert!(report.to_directory.contains(&SECRETS_FILE.to_string()));
+        assert!(read(&dir, "environments/dev.json")
+            .unwrap()
+            .contains("token"));
+        assert!(!read(&dir, "environments/dev.json")
+            .unwrap()
+            .contains("old-token"));
+
+        vault.lock();
+        let edited = read(&dir, SECRETS_FILE)
+            .unwrap()
+            .replace("old-token", "new-token");
+        write(&dir, SECRETS_FILE, &edited);
+        let report = sync(pool, &vault, "w1", None).await.unwrap();
+        assert!(!report.secrets_synced);
+        assert!(report.to_database.is_empty() && report.to_directory.is_empty());
+        assert_eq!(read(&dir, SECRETS_FILE).unwrap(), edited);
+
+        vault.unlock(pool, &passphrase).await.unwrap();
+        let report = sync(pool, &vault, "w1", None).await.unwrap();
+        assert_eq!(report.to_database, vec![SECRETS_FILE]);
+        let context = WorkspaceContext::load_for_environment(pool, &vault, "w1", Some("e1"))
+            .await
+            .unwrap();
+        assert_eq!(context.variables["token"], "new-token");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_spec_shared_with_another_workspace_is_copied_before_changing() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        let dir = linked(pool).await;
+        repository::create_workspace(pool, "w2", "Other", 1)
+            .await
+            .unwrap();
+        repository::save_spec(pool, "s1", r#"{"openapi":"3.0.0","paths":{}}"#)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE workspaces SET active_spec_id = 's1'")
+            .execute(pool)
+            .await
+            .unwrap();
+        sync(pool, &vault, "w1", None).await.unwrap();
+
+        let spec_of = |workspace_id: &'static str| async move {
+            let (spec_id, content): (String, String) = sqlx::query_as(
+                "SELECT s.id, s.spec_content FROM workspaces w JOIN specs s ON s.id = w.active_spec_id WHERE w.id = ?",
+            )
+            .bind(workspace_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+            (spec_id, content)
+        };
+
+        write(&dir, files::SPEC_FILE, r#"{"openapi":"3.1.0","paths":{}}"#);
+        let report = sync(pool, &vault, "w1", None).await.unwrap();
+        assert_eq!(report.to_database, vec![files::SPEC_FILE]);
+        let (copied_id, content) = spec_of("w1").await;
+        assert_ne!(copied_id, "s1");
+        assert!(content.contains("3.1.0"));
+        assert_eq!(
+            spec_of("w2").await,
+            (
+                "s1".to_string(),
+                r#"{"openapi":"3.0.0","paths":{}}"#.to_string()
+            )
+        );
+
+        // Once the workspace has a spec of its own it's updated in place
+        write(&dir, files::SPEC_FILE, r#"{"openapi":"3.1.1","paths":{}}"#);
+        sync(pool, &vault, "w1", None).await.unwrap();
+        let (spec_id, content) = spec_of("w1").await;
+        assert_eq!(spec_id, copied_id);
+        assert!(content.contains("3.1.1"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn the_spec_file_keeps_its_path_order() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let vault = SecretVault::new();
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        repository::save_spec(
+            pool,
+            "s1",
+            r#"{"openapi":"3.0.0","paths":{"/zebras":{},"/ants":{}}}"#,
+        )
+        .await
+        .unwrap();
+        sqlx::query("UPDATE workspaces SET active_spec_id = 's1'")
+            .execute(pool)
+            .await
+            .unwrap();
+
+        let documents = files::render(pool, &vault, "w1", false).await.unwrap();
+        assert_eq!(
+            documents[files::SPEC_FILE],
+            "{\n  \"openapi\": \"3.0.0\",\n  \"paths\": {\n    \"/zebras\": {},\n    \"/ants\": {}\n  }\n}\n"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use super::FormData;
+use crate::secrets::{cipher, SecretVault};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+
+/// Everything needed to resolve a request for a workspace in a given environment
+#[derive(Clone)]
+pub struct WorkspaceContext {
+    pub base_url: String,
+    /// The environment variables were loaded from, if the workspace has one
+    pub environment_id: Option<String>,
+    pub spec: Option<Value>,
+    pub variables: HashMap<String, String>,
+    /// Names of the variables marked secret
+    pub secret_variables: HashSet<String>,
+    pub global_headers: HashMap<String, String>,
+    /// Auth values keyed by scheme name, e.g. `{ "bearerAuth": { "token": "..." } }`
+    pub auth_values: HashMap<String, HashMap<String, String>>,
+    /// Secret variables left out of `variables` because the vault is locked
+    pub locked_variables: HashSet<String>,
+    /// Auth schemes left out of `auth_values` because the vault is locked
+    pub locked_auth: HashSet<String>,
+}
+
+impl WorkspaceContext {
+    /// Loads the context for the workspace's active environment
+    pub async fn load(
+        pool: &SqlitePool,
+        vault: &SecretVault,
+        workspace_id: &str,
+    ) -> Result<Self, String> {
+        Self::load_for_environment(pool, vault, workspace_id, None).await
+    }
+
+    /// Loads the context for a specific environment, falling back to the workspace's
+    /// active environment when `environment_id` is `None`
+    pub async fn load_for_environment(
+        pool: &SqlitePool,
+        vault: &SecretVault,
+        workspace_id: &str,
+        environment_id: Option<&str>,
+    ) -> Result<Self, String> {
+        let workspace: Option<(Option<String>, Option<String>, Option<String>)> = sqlx::query_as(
+            "SELECT active_spec_id, active_environment_id, base_url FROM workspaces WHERE id = ?",
+        )
+        .bind(workspace_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+        let (active_spec_id, active_environment_id, base_url) =
+            workspace.ok_or_else(|| format!("Workspace not found: {}", workspace_id))?;
+
+        let environment_id = environment_id.map(str::to_string).or(active_environment_id);
+
+        let spec = match &active_spec_id {
+            Some(spec_id) => {
+                let row: Option<(String,)> =
+                    sqlx::query_as("SELECT spec_content FROM specs WHERE id = ?")
+                        .bind(spec_id)
+                        .fetch_optional(pool)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                match row {
+                    Some((content,)) => {
+                        Some(serde_json::from_str(&content).map_err(|e| e.to_string())?)
+                    }
+                    None => None,
+                }
+            }
+            None => None,
+        };
+
+        // Secrets are only needed by requests that use them, so a locked vault fails those
+        // requests rather than the whole load
+        let locked = |value: &str| cipher::is_encrypted(value) && !vault.is_unlocked();
+        let mut locked_variables = HashSet::new();
+        let mut locked_auth = HashSet::new();
+
+        let mut variables = HashMap::new();
+        let mut secret_variables = HashSet::new();
+        if let Some(env_id) = &environment_id {
+            let rows: Vec<(String, String, bool)> = sqlx::query_as(
+                "SELECT k.key_name, v.value, k.is_secret FROM environment_variable_values v
+                 INNER JOIN workspace_variable_keys k ON v.variable_key_id = k.id
+                 WHERE k.workspace_id = ? AND v.environment_id = ?",
+            )
+            .bind(workspace_id)
+            .bind(env_id)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+            for (key, value, is_secret) in rows {
+                if is_secret {
+                    secret_variables.insert(key.clone());
+                }
+                if locked(&value) {
+                    locked_variables.insert(key);
+                    continue;
+                }
+                variables.insert(key, vault.reveal(&value)?);
+            }
+        }
+
+        let header_rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT key, value FROM global_headers WHERE workspace_id = ?")
+                .bind(workspace_id)
+                .fetch_all(pool)
+                .await
+                .map_err(|e| e.to_string())?;
+        let global_headers = header_rows.into_iter().collect();
+
+        let auth_rows: Vec<(Option<String>, String, String)> = sqlx::query_as(
+            "SELECT environment_id, scheme_name, value_json FROM auth_values WHERE workspace_id = ?",
+        )
+        .bind(workspace_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| e.to_string())?;
+
+        // Environment-specific auth values replace the global ones entirely when present
+        let has_environment_auth = environment_id.is_some()
+            && auth_rows
+                .iter()
+                .any(|(env, _, _)| env.is_some() && *env == environment_id);
+
+        let mut auth_values = HashMap::new();
+        for (env, scheme_name, value_json) in auth_rows {
+            let applies = if has_environment_auth {
+                env == environment_id
+            } else {
+                env.is_none()
+            };
+            if !applies {
+                continue;
+            }
+            if locked(&value_json) {
+                locked_auth.insert(scheme_name);
+                continue;
+            }
+            let plaintext = vault.reveal(&value_json)?;
+            let values: HashMap<String, String> =
+                serde_json::from_str(&plaintext).map_err(|e| e.to_string())?;
+            auth_values.insert(scheme_name, values);
+        }
+
+        Ok(Self {
+            base_url: base_url.unwrap_or_default(),
+            environment_id,
+            spec,
+            variables,
+            secret_variables,
+            global_headers,
+            auth_values,
+            locked_variables,
+            locked_auth,
+        })
+    }
+
+    /// Fails when any of `texts` still holds a `{{name}}` placeholder for a secret the
+    /// locked vault kept out of this context
+    pub fn ensure_unlocked<'a>(
+        &self,
+        texts: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        if self.locked_variables.is_empty() {
+            return Ok(());
+        }
+        for text in texts {
+            if let Some(name) =
+                super::placeholders(text).find(|name| self.locked_variables.contains(*name))
+            {
+                return Err(format!(
+                    "Variable '{}' is secret and the vault is locked; unlock it to send this request",
+                    name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads an operation's form data as last saved from the UI; operations never opened there
+/// get empty form data
+pub async fn saved_form_data(
+    pool: &SqlitePool,
+    workspace_id: &str,
+    operation_key: &str,
+) -> Result<FormData, String> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT form_data FROM operation_states WHERE workspace_id = ? AND operation_key = ?",
+    )
+    .bind(workspace_id)
+    .bind(operation_key)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match row {
+        Some((json,)) => serde_json::from_str(&json).map_err(|e| e.to_string()),
+        None => Ok(FormData::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{repository, Database};
+    use crate::secrets::{store, VaultCredential};
+    use crate::templating::build_request;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn locked_secrets_fail_only_the_requests_that_use_them() {
+        let db = Database::open_in_memory().await.unwrap();
+        let pool = db.pool();
+        let spec = json!({
+            "openapi": "3.0.0",
+            "paths": {
+                "/pets": { "get": {} },
+                "/private": { "get": { "security": [{ "bearer": [] }] } }
+            },
+            "components": {
+                "securitySchemes": { "bearer": { "type": "http", "scheme": "bearer" } }
+            }
+        });
+        repository::create_workspace(pool, "w1", "Pets", 0)
+            .await
+            .unwrap();
+        repository::save_spec(pool, "s1", &spec.to_string())
+            .await
+            .unwrap();
+        repository::create_environment(pool, "e1", "w1", "dev")
+            .await
+            .unwrap();
+        sqlx::query(
+            "UPDATE workspaces SET active_spec_id = 's1', active_environment_id = 'e1', base_url = 'https://{{host}}' WHERE id = 'w1'",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let vault = SecretVault::new();
+        vault
+            .unlock(pool, &VaultCredential::Passphrase("pw".to_string()))
+            .await
+            .unwrap();
+        let host = repository::add_variable_key(pool, "w1", "host")
+            .await
+            .unwrap();
+        let token = repository::add_variable_key(pool, "w1", "token")
+            .await
+            .unwrap();
+        repository::set_variable_value(pool, &vault, "e1", host, "api.test")
+            .await
+            .unwrap();
+        store::set_variable_secret(pool, &vault, token, true)
+            .await
+            .unwrap();
+        repository::set_variable_value(pool, &vault, "e1", token, "t0ken")
+            .await
+            .unwrap();
+        store::set_auth_value(
+            pool,
+            &vault,
+            "w1",
+            None,
+            "bearer",
+            r#"{"token":"t0ken"}"#,
+            true,
+        )
+        .await
+        .unwrap();
+        vault.lock();
+
+        let context = WorkspaceContext::load(pool, &vault, "w1").await.unwrap();
+        assert!(context.locked_variables.contains("token"));
+        assert!(context.locked_auth.contains("bearer"));
+
+        let form = |query: Value| FormData {
+            query_data: query.as_object().cloned(),
+            ..Default::default()
+        };
+        let request =
+            build_request(&context, "get:/pets", &form(json!({ "q": "{{host}}" }))).unwrap();
+        assert_eq!(request.url, "https://api.test/pets?q=api.test");
+
+        let err =
+            build_request(&context, "get:/pets", &form(json!({ "key": "{{token}}" }))).unwrap_err();
+        assert!(err.contains("'token'"), "{}", err);
+        let err = build_request(&context, "get:/private", &FormData::default()).unwrap_err();
+        assert!(err.contains("'bearer'"), "{}", err);
+
+        vault
+            .unlock(pool, &VaultCredential::Passphrase("pw".to_string()))
+            .await
+            .unwrap();
+        let context = WorkspaceContext::load(pool, &vault, "w1").await.unwrap();
+        let request = build_request(
+            &context,
+            "get:/private",
+            &form(json!({ "key": "{{token}}" })),
+        )
+        .unwrap();
+        assert_eq!(request.url, "https://api.test/private?key=t0ken");
+        assert_eq!(request.headers["authorization"], "Bearer t0ken");
+    }
+}
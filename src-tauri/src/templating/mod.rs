@@ -0,0 +1,168 @@
+pub mod context;
+pub mod resolve;
+
+use rand::Rng;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub use context::{saved_form_data, WorkspaceContext};
+pub use resolve::{build_request, build_resolved_request, resolve_form_data, FormData};
+
+/// Evaluates a dynamic `{{$name}}` function, returning `None` for unknown names
+fn dynamic_value(name: &str) -> Option<String> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    match name {
+        "$uuid" | "$guid" | "$randomUUID" => Some(uuid::Uuid::new_v4().to_string()),
+        "$timestamp" => Some(now.as_secs().to_string()),
+        "$timestampMs" => Some(now.as_millis().to_string()),
+        "$randomInt" => Some(rand::thread_rng().gen_range(0..1000).to_string()),
+        _ => None,
+    }
+}
+
+fn lookup(name: &str, context: &HashMap<String, String>) -> Option<String> {
+    if name.starts_with('$') {
+        return dynamic_value(name);
+    }
+    context.get(name).cloned()
+}
+
+/// Names of the `{{name}}` placeholders in a string, trimmed and in order
+pub fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || loop {
+        let start = rest.find("{{")?;
+        let after_open = &rest[start + 2..];
+        let end = after_open.find("}}")?;
+        let inner = &after_open[..end];
+        rest = &after_open[end + 2..];
+        let name = inner.trim();
+        if !name.is_empty() && !inner.contains('}') {
+            return Some(name);
+        }
+    })
+}
+
+/// Resolves `{{variable}}` placeholders in a string.
+/// Variables that don't exist in the context are left unchanged.
+pub fn resolve_string(text: &str, context: &HashMap<String, String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+
+        let Some(end) = after_open.find("}}") else {
+            result.push_str(&rest[start..]);
+            return result;
+        };
+
+        let inner = &after_open[..end];
+        let name = inner.trim();
+        let resolved = if name.is_empty() || inner.contains('}') {
+            None
+        } else {
+            lookup(name, context)
+        };
+
+        match resolved {
+            Some(value) => result.push_str(&value),
+            None => result.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after_open[end + 2..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Recursively resolves placeholders in strings, object keys, and array items
+pub fn resolve_value(value: &Value, context: &HashMap<String, String>) -> Value {
+    match value {
+        Value::String(s) => Value::String(resolve_string(s, context)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| resolve_value(item, context))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (resolve_string(k, context), resolve_value(v, context)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn resolve_string_substitutes_known_variables() {
+        let context = vars(&[("host", "api.test"), ("id", "42")]);
+        assert_eq!(
+            resolve_string("https://{{host}}/pets/{{ id }}", &context),
+            "https://api.test/pets/42"
+        );
+        assert_eq!(resolve_string("{{id}}{{id}}", &context), "4242");
+        assert_eq!(
+            resolve_string("no placeholders", &context),
+            "no placeholders"
+        );
+    }
+
+    #[test]
+    fn resolve_string_leaves_unknown_and_malformed_placeholders() {
+        let context = vars(&[("a", "1")]);
+        assert_eq!(
+            resolve_string("{{missing}}-{{a}}", &context),
+            "{{missing}}-1"
+        );
+        assert_eq!(resolve_string("{{}} {{ }}", &context), "{{}} {{ }}");
+        assert_eq!(resolve_string("{{a} }}", &context), "{{a} }}");
+        assert_eq!(resolve_string("{{a}} {{unclosed", &context), "1 {{unclosed");
+        assert_eq!(resolve_string("{{$nope}}", &context), "{{$nope}}");
+    }
+
+    #[test]
+    fn resolve_string_evaluates_dynamic_values() {
+        let context = HashMap::new();
+        let uuid = resolve_string("{{$uuid}}", &context);
+        assert!(uuid::Uuid::parse_str(&uuid).is_ok());
+        assert!(resolve_string("{{$timestamp}}", &context)
+            .parse::<u64>()
+            .is_ok());
+        let n: u32 = resolve_string("{{$randomInt}}", &context).parse().unwrap();
+        assert!(n < 1000);
+    }
+
+    #[test]
+    fn resolve_value_walks_keys_and_items() {
+        let context = vars(&[("k", "name"), ("v", "Rex")]);
+        let value = serde_json::json!({ "{{k}}": ["{{v}}", 1, null] });
+        assert_eq!(
+            resolve_value(&value, &context),
+            serde_json::json!({ "name": ["Rex", 1, null] })
+        );
+    }
+
+    #[test]
+    fn placeholders_lists_names_in_order() {
+        let names: Vec<&str> = placeholders("{{a}}/{{ b }}/{{}}/{{c} }}/{{$uuid}}").collect();
+        assert_eq!(names, ["a", "b", "$uuid"]);
+        assert_eq!(placeholders("{{open").count(), 0);
+    }
+}
@@ -0,0 +1,451 @@
+use super::context::WorkspaceContext;
+use super::{resolve_string, resolve_value};
+use crate::network::request::PreparedRequest;
+use crate::spec::index::{find_operation, follow_ref, is_webhook_path, split_operation_key};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Characters `encodeURIComponent` leaves untouched
+const URI_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+/// Saved request inputs for an operation, as persisted in `operation_states.form_data`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormData {
+    pub path_data: Option<Map<String, Value>>,
+    pub query_data: Option<Map<String, Value>>,
+    pub header_data: Option<Map<String, Value>>,
+    pub custom_header_data: Option<Map<String, Value>>,
+    pub body_data: Option<Value>,
+}
+
+impl FormData {
+    /// False for a body form nobody filled in: missing, `null`, `{}`, or blank text
+    pub fn has_body(&self) -> bool {
+        match &self.body_data {
+            None | Some(Value::Null) => false,
+            Some(Value::Object(map)) => !map.is_empty(),
+            Some(Value::String(text)) => !text.trim().is_empty(),
+            Some(_) => true,
+        }
+    }
+}
+
+/// Returns the JSON media type of an operation's request body, if it has one
+fn json_body_media_type(spec: &Value, op: &Value) -> Option<&'static str> {
+    let request_body = follow_ref(spec, op.get("requestBody")?);
+    let content = request_body.get("content")?;
+    if content.get("application/json").is_some() || content.get("application/*+json").is_some() {
+        Some("application/json")
+    } else {
+        None
+    }
+}
+
+fn value_to_param(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn map_to_headers(map: Option<&Map<String, Value>>) -> HashMap<String, String> {
+    map.map(|m| {
+        m.iter()
+            .map(|(k, v)| (k.to_lowercase(), value_to_param(v)))
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Headers and query parameters an auth scheme adds to a request
+type AuthParams = (HashMap<String, String>, Vec<(String, String)>);
+
+/// Resolves auth credentials for an operation from its (or the spec's) security requirement
+fn apply_auth(spec: &Value, op: &Value, context: &WorkspaceContext) -> Result<AuthParams, String> {
+    let mut headers = HashMap::new();
+    let mut query = Vec::new();
+
+    // An operation's security requirement overrides the global one
+    let requirement = op
+        .get("security")
+        .and_then(|s| s.get(0))
+        .or_else(|| spec.get("security").and_then(|s| s.get(0)))
+        .and_then(Value::as_object);
+    let Some(requirement) = requirement else {
+        return Ok((headers, query));
+    };
+
+    let schemes = spec
+        .pointer("/components/securitySchemes")
+        .and_then(Value::as_object);
+
+    for scheme_name in requirement.keys() {
+        let Some(scheme) = schemes.and_then(|s| s.get(scheme_name)) else {
+            continue;
+        };
+        if context.locked_auth.contains(scheme_name) {
+            return Err(format!(
+                "Auth values for '{}' are secret and the vault is locked; unlock it to send this request",
+                scheme_name
+            ));
+        }
+        let scheme = follow_ref(spec, scheme);
+        let Some(values) = context.auth_values.get(scheme_name) else {
+            continue;
+        };
+        let non_empty = |field: &str| values.get(field).filter(|v| !v.is_empty());
+
+        match scheme.get("type").and_then(Value::as_str) {
+            Some("apiKey") => {
+                let (Some(api_key), Some(name)) = (
+                    non_empty("apiKey"),
+                    scheme.get("name").and_then(Value::as_str),
+                ) else {
+                    continue;
+                };
+                match scheme.get("in").and_then(Value::as_str) {
+                    Some("header") => {
+                        headers.insert(name.to_lowercase(), api_key.clone());
+                    }
+                    Some("query") => query.push((name.to_string(), api_key.clone())),
+                    _ => {}
+                }
+            }
+            Some("http") => match scheme.get("scheme").and_then(Value::as_str) {
+                Some("bearer") => {
+                    if let Some(token) = non_empty("token") {
+                        headers.insert("authorization".to_string(), format!("Bearer {}", token));
+                    }
+                }
+                Some("basic") => {
+                    if let (Some(username), Some(password)) =
+                        (non_empty("username"), non_empty("password"))
+                    {
+                        let credentials = BASE64.encode(format!("{}:{}", username, password));
+                        headers.insert(
+                            "authorization".to_string(),
+                            format!("Basic {}", credentials),
+                        );
+                    }
+                }
+                _ => {}
+            },
+            // oauth2 and openIdConnect are not handled, matching the frontend
+            _ => {}
+        }
+    }
+
+    Ok((headers, query))
+}
+
+/// Returns the headers an operation's auth scheme adds in this context
+pub fn auth_headers(
+    context: &WorkspaceContext,
+    operation_key: &str,
+) -> Result<HashMap<String, String>, String> {
+    let spec = context
+        .spec
+        .as_ref()
+        .ok_or_else(|| "Workspace has no active spec".to_string())?;
+    let (method, path) = split_operation_key(operation_key)?;
+    let op = find_operation(spec, &method, &path)
+        .ok_or_else(|| format!("Operation not found in spec: {}", operation_key))?;
+    Ok(apply_auth(spec, op, context)?.0)
+}
+
+/// Builds the final URL from the base URL, templated path, and query parameters
+pub fn build_url(
+    base_url: &str,
+    path: &str,
+    path_params: &Map<String, Value>,
+    query_params: &[(String, Value)],
+) -> String {
+    let mut url_path = String::with_capacity(path.len());
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        url_path.push_str(&rest[..start]);
+        let key = &rest[start + 1..start + len];
+        let value = path_params.get(key).map(value_to_param).unwrap_or_default();
+        url_path.push_str(&utf8_percent_encode(&value, URI_COMPONENT).to_string());
+        rest = &rest[start + len + 1..];
+    }
+    url_path.push_str(rest);
+
+    let base = base_url.trim_end_matches('/');
+    let separator = if url_path.is_empty() || url_path.starts_with('/') {
+        ""
+    } else {
+        "/"
+    };
+    let mut url = format!("{}{}{}", base, separator, url_path);
+
+    let mut serializer = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in query_params {
+        match value {
+            Value::Null => continue,
+            Value::String(s) if s.is_empty() => continue,
+            Value::Array(items) => {
+                for item in items.iter().filter(|i| !i.is_null()) {
+                    serializer.append_pair(key, &value_to_param(item));
+                }
+            }
+            other => {
+                serializer.append_pair(key, &value_to_param(other));
+            }
+        }
+    }
+    let query_string = serializer.finish();
+    if !query_string.is_empty() {
+        url.push('?');
+        url.push_str(&query_string);
+    }
+
+    url
+}
+
+/// Substitutes variables and dynamic values throughout an operation's form data
+pub fn resolve_form_data(form_data: &FormData, variables: &HashMap<String, String>) -> FormData {
+    let resolve_map = |map: &Option<Map<String, Value>>| -> Option<Map<String, Value>> {
+        match map {
+            Some(m) => match resolve_value(&Value::Object(m.clone()), variables) {
+                Value::Object(resolved) => Some(resolved),
+                _ => None,
+            },
+            None => None,
+        }
+    };
+
+    FormData {
+        path_data: resolve_map(&form_data.path_data),
+        query_data: resolve_map(&form_data.query_data),
+        header_data: resolve_map(&form_data.header_data),
+        custom_header_data: resolve_map(&form_data.custom_header_data),
+        body_data: form_data
+            .body_data
+            .as_ref()
+            .map(|body| resolve_value(body, variables)),
+    }
+}
+
+/// Resolves an operation's saved form data against the workspace context into a request
+pub fn build_request(
+    context: &WorkspaceContext,
+    operation_key: &str,
+    form_data: &FormData,
+) -> Result<PreparedRequest, String> {
+    let resolved = resolve_form_data(form_data, &context.variables);
+    build_resolved_request(context, operation_key, &resolved)
+}
+
+/// Builds a request from form data that has already gone through [`resolve_form_data`]
+pub fn build_resolved_request(
+    context: &WorkspaceContext,
+    operation_key: &str,
+    form_data: &FormData,
+) -> Result<PreparedRequest, String> {
+    let spec = context
+        .spec
+        .as_ref()
+        .ok_or_else(|| "Workspace has no active spec".to_string())?;
+    let (method, path) = split_operation_key(operation_key)?;
+    let op = find_operation(spec, &method, &path)
+        .ok_or_else(|| format!("Operation not found in spec: {}", operation_key))?;
+
+    let variables = &context.variables;
+    let base_url = resolve_string(&context.base_url, variables);
+    if base_url.is_empty() {
+        return Err("Workspace has no base URL".to_string());
+    }
+
+    // Placeholders for secrets the locked vault held back are still in the resolved inputs
+    let inputs = serde_json::to_string(form_data).map_err(|e| e.to_string())?;
+    let global_headers: HashMap<String, String> = context
+        .global_headers
+        .iter()
+        .map(|(k, v)| (k.to_lowercase(), resolve_string(v, variables)))
+        .collect();
+    context.ensure_unlocked(
+        [base_url.as_str(), inputs.as_str()]
+            .into_iter()
+            .chain(global_headers.values().map(String::as_str)),
+    )?;
+
+    let path_data = form_data.path_data.clone().unwrap_or_default();
+    let query_data = form_data.query_data.clone().unwrap_or_default();
+
+    let (auth_headers, auth_query) = apply_auth(spec, op, context)?;
+
+    // Auth headers should overwrite any others
+    let mut headers = global_headers;
+    headers.extend(map_to_headers(form_data.header_data.as_ref()));
+    headers.extend(map_to_headers(form_data.custom_header_data.as_ref()));
+    headers.extend(auth_headers);
+
+    let mut query: Vec<(String, Value)> = query_data.into_iter().collect();
+    for (key, value) in auth_query {
+        query.retain(|(k, _)| *k != key);
+        query.push((key, Value::String(value)));
+    }
+
+    // Webhooks are delivered to whatever receiver the base URL points at
+    let url_path = if is_webhook_path(&path) { "" } else { &path };
+    let url = build_url(&base_url, url_path, &path_data, &query);
+
+    let upper_method = method.to_uppercase();
+    let mut body = None;
+    if let Some(media_type) = json_body_media_type(spec, op) {
+        let required = follow_ref(spec, &op["requestBody"])
+            .get("required")
+            .and_then(Value::as_bool)
+            == Some(true);
+        // An untouched body form is left off unless the operation requires a body
+        let sends_body = form_data.has_body() || required;
+        if sends_body && upper_method != "GET" && upper_method != "HEAD" {
+            headers
+                .entry("content-type".to_string())
+                .or_insert_with(|| media_type.to_string());
+            let provided = form_data
+                .body_data
+                .as_ref()
+                .filter(|_| form_data.has_body());
+            body = Some(match provided {
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+                None => "{}".to_string(),
+            });
+        }
+    }
+
+    Ok(PreparedRequest {
+        method: upper_method,
+        url,
+        headers,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[test]
+    fn build_url_fills_and_encodes_path_params() {
+        let path = params(json!({ "id": "a b/c", "n": 7 }));
+        assert_eq!(
+            build_url("https://api.test/v1/", "/pets/{id}/toys/{n}", &path, &[]),
+            "https://api.test/v1/pets/a%20b%2Fc/toys/7"
+        );
+        assert_eq!(
+            build_url("https://api.test", "/pets/{missing}", &Map::new(), &[]),
+            "https://api.test/pets/"
+        );
+        assert_eq!(
+            build_url("https://api.test", "pets", &Map::new(), &[]),
+            "https://api.test/pets"
+        );
+        assert_eq!(
+            build_url("https://api.test/", "", &Map::new(), &[]),
+            "https://api.test"
+        );
+    }
+
+    #[test]
+    fn build_url_serializes_query_params() {
+        let query = vec![
+            ("q".to_string(), json!("a&b c")),
+            ("tags".to_string(), json!(["x", null, 2])),
+            ("empty".to_string(), json!("")),
+            ("none".to_string(), Value::Null),
+            ("flag".to_string(), json!(true)),
+            ("filter".to_string(), json!({ "a": 1 })),
+        ];
+        assert_eq!(
+            build_url("https://api.test", "/search", &Map::new(), &query),
+            "https://api.test/search?q=a%26b+c&tags=x&tags=2&flag=true&filter=%7B%22a%22%3A1%7D"
+        );
+    }
+
+    fn context(spec: Value) -> WorkspaceContext {
+        WorkspaceContext {
+            base_url: "https://api.test".to_string(),
+            environment_id: None,
+            spec: Some(spec),
+            variables: HashMap::new(),
+            secret_variables: Default::default(),
+            global_headers: HashMap::new(),
+            auth_values: HashMap::new(),
+            locked_variables: Default::default(),
+            locked_auth: Default::default(),
+        }
+    }
+
+    fn body_spec(required: bool) -> Value {
+        json!({
+            "openapi": "3.0.0",
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "required": required,
+                            "content": { "application/json": { "schema": { "type": "object" } } }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn untouched_bodies_are_sent_only_when_required() {
+        let untouched = FormData {
+            body_data: Some(json!({})),
+            ..Default::default()
+        };
+        assert!(!untouched.has_body());
+
+        let optional = build_request(&context(body_spec(false)), "post:/pets", &untouched).unwrap();
+        assert_eq!(optional.body, None);
+        assert!(!optional.headers.contains_key("content-type"));
+
+        let required = build_request(&context(body_spec(true)), "post:/pets", &untouched).unwrap();
+        assert_eq!(required.body.as_deref(), Some("{}"));
+        assert_eq!(required.headers["content-type"], "application/json");
+
+        let filled = FormData {
+            body_data: Some(json!({ "name": "Rex" })),
+            ..Default::default()
+        };
+        let sent = build_request(&context(body_spec(false)), "post:/pets", &filled).unwrap();
+        assert_eq!(sent.body.as_deref(), Some(r#"{"name":"Rex"}"#));
+
+        let raw = FormData {
+            body_data: Some(json!("[1, 2]")),
+            ..Default::default()
+        };
+        let sent = build_request(&context(body_spec(false)), "post:/pets", &raw).unwrap();
+        assert_eq!(sent.body.as_deref(), Some("[1, 2]"));
+    }
+}
@@ -0,0 +1,128 @@
+pub mod request;
+pub mod response;
+pub mod schema;
+
+pub use request::{validate_request, PreflightReport};
+pub use response::validate_response;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// A single contract violation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    /// What was checked: `status`, `content-type`, `path`, `query`, `header`, or `body`
+    pub location: String,
+    /// JSON pointer into the checked value, e.g. `/items/0/id`, or `/<name>` for headers
+    pub pointer: String,
+    /// JSON pointer to the failing schema keyword, when a schema was involved
+    pub schema_path: Option<String>,
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(location: &str, pointer: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            location: location.to_string(),
+            pointer: pointer.into(),
+            schema_path: None,
+            message: message.into(),
+        }
+    }
+}
+
+/// The outcome of validating one response against its operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub valid: bool,
+    /// The `responses` key that matched the status, e.g. `"200"`, `"2XX"`, or `"default"`
+    pub matched_status: Option<String>,
+    /// The `content` key that matched the response's content type
+    pub matched_media_type: Option<String>,
+    pub violations: Vec<Violation>,
+}
+
+/// Strips parameters from a content type, e.g. `application/json; charset=utf-8`
+pub fn essence(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase()
+}
+
+/// Returns true for `application/json` and structured `+json` media types
+pub fn is_json_media_type(media_type: &str) -> bool {
+    let media_type = essence(media_type);
+    media_type == "application/json" || media_type.ends_with("+json")
+}
+
+/// Picks the entry of a `content` map that best matches `content_type`, preferring an
+/// exact match, then `type/*`, then `*/*`
+pub fn match_media_type<'a>(
+    content: &'a Map<String, Value>,
+    content_type: &str,
+) -> Option<(&'a String, &'a Value)> {
+    let wanted = essence(content_type);
+    let wildcard = wanted
+        .split('/')
+        .next()
+        .map(|t| format!("{}/*", t))
+        .unwrap_or_default();
+
+    content
+        .iter()
+        .find(|(k, _)| essence(k) == wanted)
+        .or_else(|| content.iter().find(|(k, _)| essence(k) == wildcard))
+        .or_else(|| content.iter().find(|(k, _)| essence(k) == "*/*"))
+}
+
+/// Coerces a string from a header, path, or query into the type its schema expects
+pub fn coerce_scalar(raw: &str, schema: &Value) -> Value {
+    let schema_type = schema
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    match schema_type {
+        "integer" => raw
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or_else(|_| Value::String(raw.to_string())),
+        "number" => raw
+            .parse::<f64>()
+            .ok()
+            .and_then(|n| serde_json::Number::from_f64(n).map(Value::Number))
+            .unwrap_or_else(|| Value::String(raw.to_string())),
+        "boolean" => match raw {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(raw.to_string()),
+        },
+        _ => Value::String(raw.to_string()),
+    }
+}
+
+/// Validates `instance` against an OpenAPI schema, appending violations under `location`
+pub fn check_schema(
+    spec: &Value,
+    schema: &Value,
+    instance: &Value,
+    direction: schema::Direction,
+    location: &str,
+    pointer_prefix: &str,
+    violations: &mut Vec<Violation>,
+) -> Result<(), String> {
+    let validator = schema::compile(spec, schema, direction)?;
+    for error in validator.iter_errors(instance) {
+        violations.push(Violation {
+            location: location.to_string(),
+            pointer: format!("{}{}", pointer_prefix, error.instance_path),
+            schema_path: Some(error.schema_path.to_string()),
+            message: error.to_string(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,301 @@
+use super::schema::Direction;
+use super::Violation;
+use super::{check_schema, coerce_scalar, essence, is_json_media_type, match_media_type};
+use crate::network::request::PreparedRequest;
+use crate::spec::index::{find_operation, follow_ref, is_webhook_path, split_operation_key};
+use crate::templating::FormData;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The outcome of checking a resolved request before it is sent
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightReport {
+    pub valid: bool,
+    /// The `requestBody.content` key that matched the request's content type
+    pub matched_media_type: Option<String>,
+    pub warnings: Vec<Violation>,
+}
+
+impl PreflightReport {
+    /// One-line description of every warning, used when strict mode blocks a send
+    pub fn summary(&self) -> String {
+        self.warnings
+            .iter()
+            .map(|w| format!("{} {}: {}", w.location, w.pointer, w.message))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+/// Merges path-level and operation-level parameters; operation ones win on `name` + `in`
+fn collect_parameters<'a>(spec: &'a Value, method: &str, path: &str) -> Vec<&'a Value> {
+    let section = if is_webhook_path(path) {
+        "webhooks"
+    } else {
+        "paths"
+    };
+    let Some(item) = spec.get(section).and_then(|s| s.get(path)) else {
+        return Vec::new();
+    };
+    let item = follow_ref(spec, item);
+
+    let mut params: Vec<&Value> = Vec::new();
+    let sources = [
+        item.get("parameters"),
+        item.get(method).and_then(|op| op.get("parameters")),
+    ];
+    for list in sources.into_iter().flatten().filter_map(Value::as_array) {
+        for param in list {
+            let param = follow_ref(spec, param);
+            let same =
+                |p: &&Value| p.get("name") == param.get("name") && p.get("in") == param.get("in");
+            params.retain(|p| !same(p));
+            params.push(param);
+        }
+    }
+    params
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::String(s) => s.is_empty(),
+        Value::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// Form inputs arrive as strings more often than not, so coerce them to the schema's type
+fn coerce(value: &Value, schema: &Value) -> Value {
+    match value {
+        Value::String(s) => coerce_scalar(s, schema),
+        Value::Array(items) => {
+            let item_schema = schema.get("items").unwrap_or(&Value::Null);
+            Value::Array(items.iter().map(|i| coerce(i, item_schema)).collect())
+        }
+        other => other.clone(),
+    }
+}
+
+/// Checks a resolved request's parameters and body against the operation in `spec`.
+///
+/// `form_data` must already be resolved so path and query values are the ones that will
+/// actually be sent; headers are read from the prepared request since global, custom,
+/// and auth headers all land there.
+pub fn validate_request(
+    spec: &Value,
+    operation_key: &str,
+    form_data: &FormData,
+    request: &PreparedRequest,
+) -> Result<PreflightReport, String> {
+    let (method, path) = split_operation_key(operation_key)?;
+    let op = find_operation(spec, &method, &path)
+        .ok_or_else(|| format!("Operation not found in spec: {}", operation_key))?;
+
+    let mut report = PreflightReport {
+        valid: true,
+        matched_media_type: None,
+        warnings: Vec::new(),
+    };
+
+    let empty = Map::new();
+    let path_data = form_data.path_data.as_ref().unwrap_or(&empty);
+    let query_data = form_data.query_data.as_ref().unwrap_or(&empty);
+
+    for param in collect_parameters(spec, &method, &path) {
+        let Some(name) = param.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let location = param.get("in").and_then(Value::as_str).unwrap_or_default();
+        let value = match location {
+            "path" => path_data.get(name).cloned(),
+            "query" => query_data.get(name).cloned(),
+            "header" => request
+                .headers
+                .get(&name.to_lowercase())
+                .map(|v| Value::String(v.clone())),
+            // Cookies aren't part of the form data
+            _ => continue,
+        };
+        let pointer = format!("/{}", name);
+
+        let required =
+            location == "path" || param.get("required").and_then(Value::as_bool) == Some(true);
+        let value = match value {
+            Some(v) if !is_empty(&v) => v,
+            _ => {
+                if required {
+                    report.warnings.push(Violation::new(
+                        location,
+                        pointer,
+                        format!("Required {} parameter {} is missing", location, name),
+                    ));
+                }
+                continue;
+            }
+        };
+
+        if let Some(schema) = param.get("schema") {
+            let instance = coerce(&value, schema);
+            check_schema(
+                spec,
+                schema,
+                &instance,
+                Direction::Request,
+                location,
+                &pointer,
+                &mut report.warnings,
+            )?;
+        }
+    }
+
+    if let Some(request_body) = op.get("requestBody") {
+        let request_body = follow_ref(spec, request_body);
+        let body = request.body.as_deref().unwrap_or_default();
+        let required = request_body.get("required").and_then(Value::as_bool) == Some(true);
+
+        // A required body the form left untouched still goes out as `{}`
+        if !form_data.has_body() || body.trim().is_empty() {
+            if required {
+                report
+                    .warnings
+                    .push(Violation::new("body", "", "Request body is required"));
+            }
+        } else if let Some(content) = request_body.get("content").and_then(Value::as_object) {
+            let content_type = request
+                .headers
+                .get("content-type")
+                .map(|c| essence(c))
+                .unwrap_or_default();
+
+            match match_media_type(content, &content_type) {
+                None => {
+                    let expected: Vec<&str> = content.keys().map(String::as_str).collect();
+                    report.warnings.push(Violation::new(
+                        "content-type",
+                        "",
+                        format!(
+                            "Content type {:?} is not one of the declared {}",
+                            content_type,
+                            expected.join(", ")
+                        ),
+                    ));
+                }
+                Some((media_type, media)) => {
+                    report.matched_media_type = Some(media_type.clone());
+                    if let (Some(schema), true) =
+                        (media.get("schema"), is_json_media_type(&content_type))
+                    {
+                        match serde_json::from_str::<Value>(body) {
+                            Ok(instance) => check_schema(
+                                spec,
+                                schema,
+                                &instance,
+                                Direction::Request,
+                                "body",
+                                "",
+                                &mut report.warnings,
+                            )?,
+                            Err(e) => report.warnings.push(Violation::new(
+                                "body",
+                                "",
+                                format!("Body is not valid JSON: {}", e),
+                            )),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    report.valid = report.warnings.is_empty();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn spec(required: bool) -> Value {
+        json!({
+            "openapi": "3.0.0",
+            "paths": {
+                "/pets": {
+                    "post": {
+                        "requestBody": {
+                            "required": required,
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "required": ["name"],
+                                        "properties": { "name": { "type": "string" } }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn request(body: Option<&str>) -> PreparedRequest {
+        PreparedRequest {
+            method: "POST".to_string(),
+            url: "https://api.test/pets".to_string(),
+            headers: HashMap::from([("content-type".to_string(), "application/json".to_string())]),
+            body: body.map(str::to_string),
+        }
+    }
+
+    fn form(body: Value) -> FormData {
+        FormData {
+            body_data: Some(body),
+            ..Default::default()
+        }
+    }
+
+    fn messages(report: &PreflightReport) -> Vec<&str> {
+        report.warnings.iter().map(|w| w.message.as_str()).collect()
+    }
+
+    #[test]
+    fn untouched_bodies_are_missing_rather_than_empty_objects() {
+        let report = validate_request(
+            &spec(true),
+            "post:/pets",
+            &form(json!({})),
+            &request(Some("{}")),
+        )
+        .unwrap();
+        assert!(!report.valid);
+        assert_eq!(messages(&report), ["Request body is required"]);
+
+        let report =
+            validate_request(&spec(false), "post:/pets", &form(json!({})), &request(None)).unwrap();
+        assert!(report.valid, "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn filled_bodies_are_checked_against_the_schema() {
+        let body = json!({ "name": 7 });
+        let report = validate_request(
+            &spec(false),
+            "post:/pets",
+            &form(body.clone()),
+            &request(Some(&body.to_string())),
+        )
+        .unwrap();
+        assert!(!report.valid);
+        assert_eq!(
+            report.matched_media_type.as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(report.warnings[0].location, "body");
+        assert_eq!(report.warnings[0].pointer, "/name");
+    }
+}
@@ -0,0 +1,338 @@
+use super::schema::Direction;
+use super::{check_schema, coerce_scalar, essence, is_json_media_type, match_media_type};
+use super::{ValidationReport, Violation};
+use crate::network::request::BackendResponse;
+use crate::spec::index::{find_operation, follow_ref, split_operation_key};
+use serde_json::Value;
+
+/// Finds the `responses` entry for a status code: exact, then range like `2XX`, then `default`
+fn match_status(responses: &Value, status: u16) -> Option<(&String, &Value)> {
+    let responses = responses.as_object()?;
+    let exact = status.to_string();
+    let range = format!("{}XX", status / 100);
+
+    responses
+        .iter()
+        .find(|(k, _)| **k == exact)
+        .or_else(|| responses.iter().find(|(k, _)| k.to_uppercase() == range))
+        .or_else(|| responses.iter().find(|(k, _)| *k == "default"))
+}
+
+/// Validates a response's status, content type, headers, and body against the
+/// operation's declared `responses`
+pub fn validate_response(
+    spec: &Value,
+    operation_key: &str,
+    response: &BackendResponse,
+) -> Result<ValidationReport, String> {
+    let (method, path) = split_operation_key(operation_key)?;
+    let op = find_operation(spec, &method, &path)
+        .ok_or_else(|| format!("Operation not found in spec: {}", operation_key))?;
+
+    let mut report = ValidationReport {
+        valid: true,
+        matched_status: None,
+        matched_media_type: None,
+        violations: Vec::new(),
+    };
+
+    let Some((status_key, declared)) = op
+        .get("responses")
+        .and_then(|r| match_status(r, response.status))
+    else {
+        report.valid = false;
+        report.violations.push(Violation::new(
+            "status",
+            "",
+            format!(
+                "Status {} is not documented for this operation",
+                response.status
+            ),
+        ));
+        return Ok(report);
+    };
+    let declared = follow_ref(spec, declared);
+    report.matched_status = Some(status_key.clone());
+
+    if let Some(headers) = declared.get("headers").and_then(Value::as_object) {
+        for (name, header) in headers {
+            let header = follow_ref(spec, header);
+            let value = response.headers.get(&name.to_lowercase());
+            let pointer = format!("/{}", name);
+            match value {
+                None => {
+                    if header.get("required").and_then(Value::as_bool) == Some(true) {
+                        report.violations.push(Violation::new(
+                            "header",
+                            pointer,
+                            format!("Required header {} is missing", name),
+                        ));
+                    }
+                }
+                Some(raw) => {
+                    if let Some(schema) = header.get("schema") {
+                        let instance = coerce_scalar(raw, schema);
+                        check_schema(
+                            spec,
+                            schema,
+                            &instance,
+                            Direction::Response,
+                            "header",
+                            &pointer,
+                            &mut report.violations,
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+
+    let content_type = response
+        .headers
+        .get("content-type")
+        .map(|c| essence(c))
+        .unwrap_or_default();
+
+    if let Some(content) = declared.get("content").and_then(Value::as_object) {
+        match match_media_type(content, &content_type) {
+            None => {
+                let expected: Vec<&str> = content.keys().map(String::as_str).collect();
+                report.violations.push(Violation::new(
+                    "content-type",
+                    "",
+                    format!(
+                        "Content type {:?} is not one of the declared {}",
+                        content_type,
+                        expected.join(", ")
+                    ),
+                ));
+            }
+            Some((media_type, media)) => {
+                report.matched_media_type = Some(media_type.clone());
+                if let Some(schema) = media.get("schema") {
+                    if is_json_media_type(&content_type) {
+                        match serde_json::from_str::<Value>(&response.body_text) {
+                            Ok(body) => check_schema(
+                                spec,
+                                schema,
+                                &body,
+                                Direction::Response,
+                                "body",
+                                "",
+                                &mut report.violations,
+                            )?,
+                            Err(e) => report.violations.push(Violation::new(
+                                "body",
+                                "",
+                                format!("Body is not valid JSON: {}", e),
+                            )),
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    report.valid = report.violations.is_empty();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec() -> Value {
+        let pet = json!({
+            "type": "object",
+            "required": ["id", "name", "secret"],
+            "properties": {
+                "id": { "type": "integer" },
+                "name": { "type": "string" },
+                "tag": { "type": "string", "nullable": true },
+                "secret": { "type": "string", "writeOnly": true }
+            }
+        });
+        json!({
+            "openapi": "3.0.3",
+            "paths": {
+                "/pets": {
+                    "get": {
+                        "responses": {
+                            "200": {
+                                "description": "OK",
+                                "headers": {
+                                    "X-Rate-Limit": {
+                                        "required": true,
+                                        "schema": { "type": "integer" }
+                                    }
+                                },
+                                "content": { "application/json": { "schema": pet } }
+                            },
+                            "2xx": {
+                                "description": "Other success",
+                                "content": { "text/plain": { "schema": { "type": "string" } } }
+                            },
+                            "default": {
+                                "description": "Error",
+                                "content": {
+                                    "application/*": {
+                                        "schema": {
+                                            "type": "object",
+                                            "required": ["title"],
+                                            "properties": { "title": { "type": "string" } }
+                                        }
+                                    },
+                                    "*/*": {}
+                                }
+                            }
+                        }
+                    }
+                },
+                "/health": {
+                    "get": { "responses": { "204": { "description": "No content" } } }
+                }
+            }
+        })
+    }
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> BackendResponse {
+        let mut response = BackendResponse::not_sent(String::new());
+        response.status = status;
+        response.headers = headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        response.body_text = body.to_string();
+        response
+    }
+
+    fn validate(operation_key: &str, response: &BackendResponse) -> ValidationReport {
+        validate_response(&spec(), operation_key, response).unwrap()
+    }
+
+    #[test]
+    fn prefers_exact_status_then_range_then_default() {
+        let ok = response(
+            200,
+            &[("content-type", "application/json"), ("x-rate-limit", "10")],
+            r#"{"id": 1, "name": "Rex", "tag": null}"#,
+        );
+        let report = validate("get:/pets", &ok);
+        assert!(report.valid, "{:?}", report.violations);
+        assert_eq!(report.matched_status.as_deref(), Some("200"));
+        assert_eq!(
+            report.matched_media_type.as_deref(),
+            Some("application/json")
+        );
+
+        let accepted = response(202, &[("content-type", "text/plain")], "queued");
+        let report = validate("get:/pets", &accepted);
+        assert!(report.valid, "{:?}", report.violations);
+        assert_eq!(report.matched_status.as_deref(), Some("2xx"));
+
+        let failed = response(
+            500,
+            &[("content-type", "application/json")],
+            r#"{"title": "Boom"}"#,
+        );
+        let report = validate("get:/pets", &failed);
+        assert!(report.valid, "{:?}", report.violations);
+        assert_eq!(report.matched_status.as_deref(), Some("default"));
+
+        let report = validate("get:/health", &response(404, &[], ""));
+        assert!(!report.valid);
+        assert_eq!(report.matched_status, None);
+        assert_eq!(report.violations[0].location, "status");
+    }
+
+    #[test]
+    fn checks_declared_headers_and_body() {
+        let wrong = response(
+            200,
+            &[
+                ("content-type", "application/json"),
+                ("x-rate-limit", "lots"),
+            ],
+            r#"{"id": "1", "name": "Rex"}"#,
+        );
+        let report = validate("get:/pets", &wrong);
+        assert!(!report.valid);
+        let locations: Vec<(&str, &str)> = report
+            .violations
+            .iter()
+            .map(|v| (v.location.as_str(), v.pointer.as_str()))
+            .collect();
+        assert!(locations.contains(&("header", "/X-Rate-Limit")));
+        assert!(locations.contains(&("body", "/id")));
+        // `secret` is writeOnly, so a response may leave it out
+        assert!(!report
+            .violations
+            .iter()
+            .any(|v| v.message.contains("secret")));
+
+        let missing = response(
+            200,
+            &[("content-type", "application/json")],
+            r#"{"id": 1, "name": "Rex"}"#,
+        );
+        let report = validate("get:/pets", &missing);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(
+            report.violations[0].message,
+            "Required header X-Rate-Limit is missing"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_wildcard_media_types() {
+        let problem = response(
+            400,
+            &[("content-type", "application/problem+json; charset=utf-8")],
+            r#"{"detail": "no title"}"#,
+        );
+        let report = validate("get:/pets", &problem);
+        assert_eq!(report.matched_media_type.as_deref(), Some("application/*"));
+        assert!(!report.valid);
+        assert_eq!(report.violations[0].location, "body");
+
+        let html = response(503, &[("content-type", "text/html")], "<h1>Down</h1>");
+        let report = validate("get:/pets", &html);
+        assert!(report.valid, "{:?}", report.violations);
+        assert_eq!(report.matched_media_type.as_deref(), Some("*/*"));
+
+        let xml = response(
+            200,
+            &[("content-type", "application/xml"), ("x-rate-limit", "1")],
+            "<pet/>",
+        );
+        let report = validate("get:/pets", &xml);
+        assert!(!report.valid);
+        assert_eq!(report.violations[0].location, "content-type");
+    }
+
+    #[test]
+    fn reports_bodies_that_are_not_json() {
+        let broken = response(
+            200,
+            &[("content-type", "application/json"), ("x-rate-limit", "1")],
+            "<html>",
+        );
+        let report = validate("get:/pets", &broken);
+        assert!(!report.valid);
+        assert_eq!(report.violations[0].location, "body");
+        assert!(report.violations[0]
+            .message
+            .starts_with("Body is not valid JSON"));
+
+        // Bodies of other media types aren't parsed, whatever their schema says
+        let text = response(201, &[("content-type", "text/plain")], "{not json");
+        assert!(validate("get:/pets", &text).valid);
+    }
+
+    #[test]
+    fn rejects_unknown_operations() {
+        let err = validate_response(&spec(), "post:/pets", &response(200, &[], "")).unwrap_err();
+        assert!(err.contains("post:/pets"), "{}", err);
+    }
+}
@@ -0,0 +1,299 @@
+use jsonschema::{Draft, Validator};
+use serde_json::{json, Map, Value};
+
+/// Which side of the exchange a schema is validated for. OpenAPI lets `readOnly`
+/// properties be omitted from requests and `writeOnly` ones from responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Request,
+    Response,
+}
+
+/// Compiles an OpenAPI schema from `spec` into a JSON Schema validator
+pub fn compile(spec: &Value, schema: &Value, direction: Direction) -> Result<Validator, String> {
+    let mut root = to_json_schema(schema, direction);
+
+    // Cyclic schemas keep local refs into components, so carry those along
+    if contains_ref(&root) {
+        if let (Some(object), Some(schemas)) = (
+            root.as_object_mut(),
+            spec.pointer("/components/schemas")
+                .and_then(Value::as_object),
+        ) {
+            let converted: Map<String, Value> = schemas
+                .iter()
+                .map(|(name, s)| (name.clone(), to_json_schema(s, direction)))
+                .collect();
+            object.insert(
+                "components".to_string(),
+                json!({ "schemas": Value::Object(converted) }),
+            );
+        }
+    }
+
+    // 3.0 schemas follow draft 4 semantics; stored 3.1 specs are normalized to draft 7
+    let draft = if crate::spec::openapi31::is_openapi31(spec) {
+        Draft::Draft7
+    } else {
+        Draft::Draft4
+    };
+
+    jsonschema::options()
+        .with_draft(draft)
+        .should_validate_formats(true)
+        // `uuid` is common in OpenAPI specs but only a format from draft 2019-09 on
+        .with_format("uuid", |s| uuid::Uuid::try_parse(s).is_ok())
+        .build(&root)
+        .map_err(|e| format!("Invalid schema: {}", e))
+}
+
+fn contains_ref(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => map.contains_key("$ref") || map.values().any(contains_ref),
+        Value::Array(items) => items.iter().any(contains_ref),
+        _ => false,
+    }
+}
+
+/// Rewrites OpenAPI-only schema keywords into plain JSON Schema
+pub fn to_json_schema(schema: &Value, direction: Direction) -> Value {
+    let Some(map) = schema.as_object() else {
+        return schema.clone();
+    };
+
+    let mut out = Map::with_capacity(map.len());
+    for (key, value) in map {
+        let converted = match key.as_str() {
+            "properties" | "patternProperties" => match value.as_object() {
+                Some(children) => Value::Object(
+                    children
+                        .iter()
+                        .map(|(name, child)| (name.clone(), to_json_schema(child, direction)))
+                        .collect(),
+                ),
+                None => value.clone(),
+            },
+            "items" | "additionalItems" | "additionalProperties" | "not" => match value {
+                Value::Array(children) => Value::Array(
+                    children
+                        .iter()
+                        .map(|child| to_json_schema(child, direction))
+                        .collect(),
+                ),
+                other => to_json_schema(other, direction),
+            },
+            "allOf" | "anyOf" | "oneOf" => match value.as_array() {
+                Some(children) => Value::Array(
+                    children
+                        .iter()
+                        .map(|child| to_json_schema(child, direction))
+                        .collect(),
+                ),
+                None => value.clone(),
+            },
+            // Annotations that JSON Schema validators don't know about
+            "nullable" | "discriminator" | "xml" | "externalDocs" | "example" => continue,
+            _ => value.clone(),
+        };
+        out.insert(key.clone(), converted);
+    }
+
+    drop_directional_required(&mut out, direction);
+
+    if map.get("nullable").and_then(Value::as_bool) == Some(true) {
+        return make_nullable(out);
+    }
+    Value::Object(out)
+}
+
+fn drop_directional_required(schema: &mut Map<String, Value>, direction: Direction) {
+    let skip_flag = match direction {
+        Direction::Request => "readOnly",
+        Direction::Response => "writeOnly",
+    };
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return;
+    };
+    let skipped: Vec<String> = properties
+        .iter()
+        .filter(|(_, p)| p.get(skip_flag).and_then(Value::as_bool) == Some(true))
+        .map(|(name, _)| name.clone())
+        .collect();
+    if skipped.is_empty() {
+        return;
+    }
+    if let Some(Value::Array(required)) = schema.get_mut("required") {
+        required.retain(|r| {
+            !r.as_str()
+                .is_some_and(|name| skipped.iter().any(|s| s == name))
+        });
+    }
+}
+
+fn make_nullable(mut schema: Map<String, Value>) -> Value {
+    if let Some(Value::Array(values)) = schema.get_mut("enum") {
+        if !values.contains(&Value::Null) {
+            values.push(Value::Null);
+        }
+    }
+    match schema.get("type").cloned() {
+        Some(Value::String(t)) => {
+            schema.insert("type".to_string(), json!([t, "null"]));
+            Value::Object(schema)
+        }
+        Some(_) => Value::Object(schema),
+        None => json!({ "anyOf": [Value::Object(schema), { "type": "null" }] }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> Value {
+        json!({
+            "openapi": "3.0.3",
+            "components": {
+                "schemas": {
+                    "Node": {
+                        "type": "object",
+                        "required": ["label"],
+                        "properties": {
+                            "label": { "type": "string", "nullable": true },
+                            "children": {
+                                "type": "array",
+                                "items": { "$ref": "#/components/schemas/Node" }
+                            }
+                        }
+                    }
+                }
+            }
+        })
+    }
+
+    fn is_valid(schema: &Value, direction: Direction, instance: Value) -> bool {
+        compile(&spec(), schema, direction)
+            .unwrap()
+            .is_valid(&instance)
+    }
+
+    #[test]
+    fn nullable_becomes_a_null_type() {
+        assert_eq!(
+            to_json_schema(
+                &json!({ "type": "string", "nullable": true }),
+                Direction::Response
+            ),
+            json!({ "type": ["string", "null"] })
+        );
+        assert_eq!(
+            to_json_schema(
+                &json!({ "type": "string", "enum": ["a"], "nullable": true }),
+                Direction::Response
+            ),
+            json!({ "type": ["string", "null"], "enum": ["a", null] })
+        );
+        assert_eq!(
+            to_json_schema(
+                &json!({ "$ref": "#/components/schemas/Node", "nullable": true }),
+                Direction::Response
+            ),
+            json!({ "anyOf": [{ "$ref": "#/components/schemas/Node" }, { "type": "null" }] })
+        );
+        assert_eq!(
+            to_json_schema(
+                &json!({ "type": "string", "nullable": false }),
+                Direction::Response
+            ),
+            json!({ "type": "string" })
+        );
+    }
+
+    #[test]
+    fn converts_nested_schemas_and_drops_openapi_annotations() {
+        let schema = json!({
+            "type": "object",
+            "discriminator": { "propertyName": "kind" },
+            "example": { "kind": "cat" },
+            "properties": {
+                "tags": { "type": "array", "items": { "type": "string", "nullable": true } },
+                "owner": { "oneOf": [{ "type": "integer", "nullable": true }] }
+            },
+            "additionalProperties": { "type": "number", "xml": { "name": "n" } }
+        });
+
+        assert_eq!(
+            to_json_schema(&schema, Direction::Response),
+            json!({
+                "type": "object",
+                "properties": {
+                    "tags": { "type": "array", "items": { "type": ["string", "null"] } },
+                    "owner": { "oneOf": [{ "type": ["integer", "null"] }] }
+                },
+                "additionalProperties": { "type": "number" }
+            })
+        );
+    }
+
+    #[test]
+    fn read_only_and_write_only_properties_are_required_one_way() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "name", "password"],
+            "properties": {
+                "id": { "type": "integer", "readOnly": true },
+                "name": { "type": "string" },
+                "password": { "type": "string", "writeOnly": true }
+            }
+        });
+
+        assert!(is_valid(
+            &schema,
+            Direction::Request,
+            json!({ "name": "Rex", "password": "pw" })
+        ));
+        assert!(!is_valid(
+            &schema,
+            Direction::Request,
+            json!({ "id": 1, "name": "Rex" })
+        ));
+        assert!(is_valid(
+            &schema,
+            Direction::Response,
+            json!({ "id": 1, "name": "Rex" })
+        ));
+        assert!(!is_valid(
+            &schema,
+            Direction::Response,
+            json!({ "name": "Rex", "password": "pw" })
+        ));
+    }
+
+    #[test]
+    fn compiles_cyclic_schemas_with_converted_components() {
+        let schema = json!({ "$ref": "#/components/schemas/Node" });
+
+        assert!(is_valid(
+            &schema,
+            Direction::Response,
+            json!({ "label": "root", "children": [{ "label": null }] })
+        ));
+        assert!(!is_valid(
+            &schema,
+            Direction::Response,
+            json!({ "label": "root", "children": [{ "label": 3 }] })
+        ));
+    }
+
+    #[test]
+    fn checks_uuid_formats() {
+        let schema = json!({ "type": "string", "format": "uuid" });
+
+        assert!(is_valid(
+            &schema,
+            Direction::Response,
+            json!("0b6c8a52-3c1e-4d4b-9f1a-7b3b1d2c4e5f")
+        ));
+        assert!(!is_valid(&schema, Direction::Response, json!("not-a-uuid")));
+    }
+}
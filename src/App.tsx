@@ -12,7 +12,7 @@ import {
 } from "@/components/ui/card";
 import { Button } from "@/components/ui/button";
 import { ThemeToggle } from "@/components/ThemeToggle";
-import { loadSpec, listOperations } from "@/lib/openapi";
+import { loadSpec, loadOperations } from "@/lib/openapi";
 import { toast } from "sonner";
 import { Toaster } from "@/components/ui/sonner";
 import { Loader2 } from "lucide-react";
@@ -25,7 +25,6 @@ import HeadersPage from "@/pages/HeadersPage";
 import AuthPage from "@/pages/AuthPage";
 import { EnvironmentSelector } from "@/components/EnvironmentSelector";
 import { WorkspaceSelector } from "@/components/WorkspaceSelector";
-import { initDatabase } from "@/lib/storage/sqliteRepository";
 import { useShallow } from "zustand/react/shallow";
 import { WorkspaceCreationForm } from "@/components/WorkspaceCreationForm";
 
@@ -73,16 +72,12 @@ export default function App() {
       ? workspaces[activeWorkspaceId]
       : null;
 
-  // Initialize SQLite database on first load
+  // Load workspaces on first load; the backend has already opened and migrated the database
   useEffect(() => {
     if (!hasHydrated) return;
 
     const init = async () => {
       try {
-        console.log("Initializing database...");
-        await initDatabase();
-        console.log("SQLite database initialized successfully");
-
         console.log("Loading workspaces from database...");
         await initializeAppState();
         console.log("App state initialized successfully");
@@ -124,9 +119,14 @@ export default function App() {
             if (dbSpec) {
               try {
                 const specData = JSON.parse(dbSpec.spec_content);
+                const operations = await loadOperations(
+                  specData,
+                  runtimeSpecId
+                );
+                if (cancelled) return;
                 // Use runtimeSpecUrl which is set by __applyWorkspaceToRoot
                 setSpec(specData, runtimeSpecId, runtimeSpecUrl || undefined);
-                setOperations(listOperations(specData));
+                setOperations(operations);
               } catch (parseError) {
                 console.error("Failed to parse spec content:", parseError);
                 toast.error(
@@ -186,9 +186,9 @@ export default function App() {
     // For manual loading when spec is missing in existing workspace
     setIsAutoLoading(true);
     try {
-      const { spec, id } = await loadSpec(url);
+      const { spec, id, operations } = await loadSpec(url);
       setSpec(spec, id, url);
-      setOperations(listOperations(spec));
+      setOperations(operations);
     } catch {
       toast.error("Failed to load Petstore example");
     } finally {
@@ -200,10 +200,10 @@ export default function App() {
     const url = "https://petstore3.swagger.io/api/v3/openapi.json";
     setIsAutoLoading(true);
     try {
-      const { spec, id } = await loadSpec(url);
+      const { spec, id, operations } = await loadSpec(url);
       createWorkspace("Petstore Example");
       setSpec(spec, id, url);
-      setOperations(listOperations(spec));
+      setOperations(operations);
     } catch {
       toast.error("Failed to load Petstore example");
     } finally {
@@ -1,5 +1,5 @@
 import { useEffect, useRef, useState } from "react";
-import { loadSpec, listOperations } from "@/lib/openapi";
+import { loadSpec } from "@/lib/openapi";
 import { useAppStore } from "@/store/useAppStore";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
@@ -38,11 +38,11 @@ export default function SpecLoader() {
         }
       }
 
-      const { spec, id } = await loadSpec(specInput);
+      const { spec, id, operations } = await loadSpec(specInput);
       const sourceUrl =
         typeof specInput === "string" ? specInput : specInput.name;
       setSpec(spec, id, sourceUrl);
-      setOps(listOperations(spec));
+      setOps(operations);
       // Update local state to match what was loaded
       if (typeof specInput === "string") {
         setUrl(specInput);
@@ -2,7 +2,7 @@ import { useState, useRef } from "react";
 import { Button } from "@/components/ui/button";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
-import { loadSpec, listOperations } from "@/lib/openapi";
+import { loadSpec } from "@/lib/openapi";
 import { useAppStore } from "@/store/useAppStore";
 import { toast } from "sonner";
 import { Loader2, Upload } from "lucide-react";
@@ -31,7 +31,7 @@ export function WorkspaceCreationForm({
     setIsLoading(true);
     try {
       // 1. Load Spec (validates it)
-      const { spec, id, baseUrl } = await loadSpec(specInput);
+      const { spec, id, baseUrl, operations } = await loadSpec(specInput);
 
       // 2. Create Workspace
       const wsName = name.trim() || "New Workspace";
@@ -42,7 +42,7 @@ export function WorkspaceCreationForm({
       const sourceUrl =
         typeof specInput === "string" ? specInput : specInput.name;
       setSpec(spec, id, sourceUrl);
-      setOperations(listOperations(spec));
+      setOperations(operations);
 
       toast.success("Workspace created successfully");
       setName("");
@@ -23,7 +23,7 @@ import {
 } from "lucide-react";
 import { toast } from "sonner";
 import { useAppStore } from "@/store/useAppStore";
-import { loadSpec, listOperations } from "@/lib/openapi";
+import { loadSpec } from "@/lib/openapi";
 import {
   importFile,
   getFormatDisplayName,
@@ -185,9 +185,9 @@ export function ImportDialog({ open, onOpenChange }: ImportDialogProps) {
       });
 
       // Load the spec
-      const { spec, id } = await loadSpec(specFile);
+      const { spec, id, operations } = await loadSpec(specFile);
       setSpec(spec, id, `imported:${result.metadata.sourceName}`);
-      setOps(listOperations(spec));
+      setOps(operations);
 
       // Import environments
       if (importEnvironments && result.environments.length > 0) {
@@ -5,6 +5,7 @@ import { send, buildCurlFromParts } from "@/lib/request";
 import { resolveOperationAuth } from "@/lib/auth";
 import { resolveVariables } from "@/lib/templating";
 import { debounce } from "@/lib/utils";
+import { canResolveInBackend, resolveAndSend } from "@/lib/http";
 import type { StreamEvent } from "@/lib/http/HttpClient";
 
 // Create stable empty object references to prevent infinite loops
@@ -35,6 +36,7 @@ export function useRequestBuilderState() {
   const authState = useAuthState();
   const environments = useEnvironments();
   const activeEnvironmentId = useActiveEnvironmentId();
+  const activeWorkspaceId = useAppStore((s) => s.activeWorkspaceId);
 
   const operationKey = useMemo(
     () => (selected ? `${selected.method}:${selected.path}` : ""),
@@ -213,25 +215,46 @@ export function useRequestBuilderState() {
       ...appliedAuth.queryParams,
     };
 
+    const onStreamEvent = (event: StreamEvent) => {
+      if (!abortController.signal.aborted) {
+        setIsStreaming(true);
+        setLiveStreamEvents((prev) => [...prev, event]);
+      }
+    };
+
     try {
-      const r = await send({
-        baseUrl: resolvedData.baseUrl,
-        path,
-        method,
-        pathParams: resolvedData.pathData as Record<string, string | number>,
-        queryParams: mergedQueryParams,
-        headers: mergedHeaders,
-        body: bodySchema.schema ? resolvedData.bodyData : undefined,
-        mediaType: bodySchema.mediaType ?? undefined,
-        timeoutMs: 600000,
-        signal: abortController.signal,
-        onStreamEvent: (event) => {
-          if (!abortController.signal.aborted) {
-            setIsStreaming(true);
-            setLiveStreamEvents((prev) => [...prev, event]);
-          }
-        },
-      });
+      // In the desktop app the backend resolves variables, secrets, and auth itself, so
+      // secret values never pass through the webview
+      const r =
+        canResolveInBackend && activeWorkspaceId
+          ? await resolveAndSend({
+              workspaceId: activeWorkspaceId,
+              operationKey,
+              formData: {
+                pathData,
+                queryData,
+                headerData,
+                customHeaderData,
+                bodyData,
+              },
+              onStreamEvent,
+            })
+          : await send({
+              baseUrl: resolvedData.baseUrl,
+              path,
+              method,
+              pathParams: resolvedData.pathData as Record<
+                string,
+                string | number
+              >,
+              queryParams: mergedQueryParams,
+              headers: mergedHeaders,
+              body: bodySchema.schema ? resolvedData.bodyData : undefined,
+              mediaType: bodySchema.mediaType ?? undefined,
+              timeoutMs: 600000,
+              signal: abortController.signal,
+              onStreamEvent,
+            });
 
       // Only set response if this request wasn't aborted
       if (!abortController.signal.aborted) {
@@ -249,7 +272,7 @@ export function useRequestBuilderState() {
         };
         setIsStreaming(false);
         setOperationResponse(operationKey, responseData);
-        addResponseHistoryEntry(operationKey, responseData);
+        addResponseHistoryEntry(operationKey, responseData, r.request);
 
         // Add to history after successful request
         if (selected) {
@@ -289,6 +312,12 @@ export function useRequestBuilderState() {
     method,
     bodySchema,
     appliedAuth,
+    activeWorkspaceId,
+    pathData,
+    queryData,
+    headerData,
+    customHeaderData,
+    bodyData,
     operationKey,
     setOperationResponse,
     addResponseHistoryEntry,
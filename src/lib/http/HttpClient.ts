@@ -29,8 +29,17 @@ export interface HttpResponse {
   bodySizeBytes?: number;
   streamEvents?: StreamEvent[];
   sessionId?: string;
+  /** The request as it went on the wire, kept with the response history for replay */
+  request?: SentRequest;
 }
 
+export type SentRequest = {
+  method: string;
+  url: string;
+  headers: Record<string, string>;
+  body: string | null;
+};
+
 export interface HttpClient {
   send(parts: {
     method: string;
@@ -1,7 +1,12 @@
 // src/lib/http/TauriHttpClient.ts
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
-import type { HttpClient, HttpResponse, StreamEvent } from "./HttpClient";
+import type {
+  HttpClient,
+  HttpResponse,
+  SentRequest,
+  StreamEvent,
+} from "./HttpClient";
 
 type TauriTimings = {
   prepare_ms: number;
@@ -23,6 +28,13 @@ type TauriSseEvent = {
   elapsed_ms: number;
 };
 
+type TauriScriptRun = {
+  console: string[];
+  tests: { name: string; passed: boolean; message: string | null }[];
+  error: string | null;
+  durationMs: number;
+};
+
 type TauriResponse = {
   status: number;
   status_text: string;
@@ -32,75 +44,186 @@ type TauriResponse = {
   wire_size_bytes: number;
   body_size_bytes: number;
   session_id: string | null;
+  changed_variables: string[];
+  script: {
+    preRequest: TauriScriptRun | null;
+    postResponse: TauriScriptRun | null;
+  } | null;
+  request?: SentRequest | null;
+  validation?: {
+    valid: boolean;
+    matchedStatus: string | null;
+    matchedMediaType: string | null;
+    violations: {
+      location: string;
+      pointer: string;
+      schemaPath: string | null;
+      message: string;
+    }[];
+  } | null;
+  assertions?: {
+    assertion: { type: string } & Record<string, unknown>;
+    passed: boolean;
+    actual: unknown;
+    message: string | null;
+  }[];
+  errors?: string[];
+};
+
+/** Saved, unresolved inputs for an operation; the backend resolves them */
+export type OperationFormData = {
+  pathData?: Record<string, unknown>;
+  queryData?: Record<string, unknown>;
+  headerData?: Record<string, unknown>;
+  customHeaderData?: Record<string, unknown>;
+  bodyData?: unknown;
 };
 
+function toHttpResponse(
+  tauriResponse: TauriResponse,
+  events: StreamEvent[],
+  request: SentRequest | undefined
+): HttpResponse {
+  let json: unknown = null;
+  try {
+    json = JSON.parse(tauriResponse.body_text);
+  } catch {
+    // Ignore JSON parse errors - json will remain null
+  }
+
+  return {
+    status: tauriResponse.status,
+    statusText: tauriResponse.status_text,
+    headers: tauriResponse.headers,
+    bodyText: tauriResponse.body_text,
+    bodyJson: json,
+    timings: {
+      prepareMs: tauriResponse.timings.prepare_ms,
+      dnsLookupMs: tauriResponse.timings.dns_lookup_ms,
+      tcpConnectMs: tauriResponse.timings.tcp_connect_ms,
+      tlsHandshakeMs: tauriResponse.timings.tls_handshake_ms,
+      ttfbMs: tauriResponse.timings.ttfb_ms,
+      downloadMs: tauriResponse.timings.download_ms,
+      processMs: tauriResponse.timings.process_ms,
+      totalMs: tauriResponse.timings.total_ms,
+    },
+    wireSizeBytes: tauriResponse.wire_size_bytes,
+    bodySizeBytes: tauriResponse.body_size_bytes,
+    streamEvents: events.length > 0 ? events : undefined,
+    sessionId: tauriResponse.session_id ?? undefined,
+    request,
+  };
+}
+
+function errorResponse(error: unknown): HttpResponse {
+  const errorMsg =
+    typeof error === "string" ? error : "An unknown error occurred";
+  return {
+    status: 500,
+    statusText: "Tauri Command Error",
+    headers: {},
+    bodyText: errorMsg,
+    bodyJson: { error: errorMsg },
+  };
+}
+
+/** Collects the SSE events of one session until the returned function is called */
+async function listenForStream(
+  sessionId: string,
+  events: StreamEvent[],
+  onStreamEvent?: (event: StreamEvent) => void
+) {
+  return listen<TauriSseEvent>("sse_event", (event) => {
+    if (event.payload.session_id === sessionId) {
+      const streamEvent: StreamEvent = {
+        eventId: event.payload.event_id,
+        eventType: event.payload.event_type,
+        data: event.payload.data,
+        timestamp: event.payload.timestamp,
+        elapsedMs: event.payload.elapsed_ms,
+      };
+      events.push(streamEvent);
+      onStreamEvent?.(streamEvent);
+    }
+  });
+}
+
+/**
+ * Sends an operation with variables, secrets, and auth resolved in the backend, so secret
+ * values never reach the webview. The returned request has secrets as `{{name}}` placeholders.
+ */
+export async function resolveAndSend(parts: {
+  workspaceId: string;
+  operationKey: string;
+  formData: OperationFormData;
+  sessionId?: string;
+  onStreamEvent?: (event: StreamEvent) => void;
+}): Promise<HttpResponse> {
+  const sessionId = parts.sessionId ?? crypto.randomUUID();
+  const events: StreamEvent[] = [];
+  const unlisten = await listenForStream(
+    sessionId,
+    events,
+    parts.onStreamEvent
+  );
+
+  try {
+    const tauriResponse = await invoke<TauriResponse>("resolve_and_send", {
+      workspaceId: parts.workspaceId,
+      operationKey: parts.operationKey,
+      formData: parts.formData,
+      sessionId,
+    });
+    if (tauriResponse.status === 0) {
+      // Never sent, e.g. the pre-request script failed; show why with the script's output
+      const error = (tauriResponse.errors ?? []).join("\n");
+      const output = tauriResponse.script?.preRequest?.console ?? [];
+      return {
+        ...errorResponse(error),
+        statusText: tauriResponse.status_text,
+        bodyText: [error, ...output].join("\n"),
+        bodyJson: { error, console: output },
+      };
+    }
+    return toHttpResponse(
+      tauriResponse,
+      events,
+      tauriResponse.request ?? undefined
+    );
+  } catch (error) {
+    return errorResponse(error);
+  } finally {
+    unlisten();
+  }
+}
+
 class TauriHttpClient implements HttpClient {
   async send(parts: Parameters<HttpClient["send"]>[0]): Promise<HttpResponse> {
     const sessionId = parts.sessionId ?? crypto.randomUUID();
     const events: StreamEvent[] = [];
 
     // Set up SSE event listener before invoking the command
-    const unlisten = await listen<TauriSseEvent>("sse_event", (event) => {
-      if (event.payload.session_id === sessionId) {
-        const streamEvent: StreamEvent = {
-          eventId: event.payload.event_id,
-          eventType: event.payload.event_type,
-          data: event.payload.data,
-          timestamp: event.payload.timestamp,
-          elapsedMs: event.payload.elapsed_ms,
-        };
-        events.push(streamEvent);
-        parts.onStreamEvent?.(streamEvent);
-      }
-    });
+    const unlisten = await listenForStream(
+      sessionId,
+      events,
+      parts.onStreamEvent
+    );
+
+    const request: SentRequest = {
+      method: parts.method,
+      url: parts.url,
+      headers: parts.headers,
+      body: parts.body ?? null,
+    };
 
     try {
       const tauriResponse = await invoke<TauriResponse>("make_request", {
-        method: parts.method,
-        url: parts.url,
-        headers: parts.headers,
-        body: parts.body,
+        ...request,
         sessionId,
       });
-
-      let json: unknown = null;
-      try {
-        json = JSON.parse(tauriResponse.body_text);
-      } catch {
-        // Ignore JSON parse errors - json will remain null
-      }
-
-      return {
-        status: tauriResponse.status,
-        statusText: tauriResponse.status_text,
-        headers: tauriResponse.headers,
-        bodyText: tauriResponse.body_text,
-        bodyJson: json,
-        timings: {
-          prepareMs: tauriResponse.timings.prepare_ms,
-          dnsLookupMs: tauriResponse.timings.dns_lookup_ms,
-          tcpConnectMs: tauriResponse.timings.tcp_connect_ms,
-          tlsHandshakeMs: tauriResponse.timings.tls_handshake_ms,
-          ttfbMs: tauriResponse.timings.ttfb_ms,
-          downloadMs: tauriResponse.timings.download_ms,
-          processMs: tauriResponse.timings.process_ms,
-          totalMs: tauriResponse.timings.total_ms,
-        },
-        wireSizeBytes: tauriResponse.wire_size_bytes,
-        bodySizeBytes: tauriResponse.body_size_bytes,
-        streamEvents: events.length > 0 ? events : undefined,
-        sessionId: tauriResponse.session_id ?? undefined,
-      };
+      return toHttpResponse(tauriResponse, events, request);
     } catch (error) {
-      const errorMsg =
-        typeof error === "string" ? error : "An unknown error occurred";
-      return {
-        status: 500,
-        statusText: "Tauri Command Error",
-        headers: {},
-        bodyText: errorMsg,
-        bodyJson: { error: errorMsg },
-      };
+      return errorResponse(error);
     } finally {
       unlisten();
     }
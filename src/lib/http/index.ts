@@ -12,6 +12,10 @@ const isTauri = () => {
 
 export const httpClient = isTauri() ? tauriHttpClient : fetchHttpClient;
 
+/** Whether requests can be resolved in the backend, keeping secrets out of the webview */
+export const canResolveInBackend = httpClient === tauriHttpClient;
+export { resolveAndSend } from "./TauriHttpClient";
+
 console.log(
   "[HTTP Client] Using:",
   isTauri() ? "TauriHttpClient (Rust backend)" : "FetchHttpClient (Browser)"
@@ -1,5 +1,10 @@
 import { describe, it, expect } from "vitest";
-import { isOAS31, listOperations, type DerefSpec } from "./openapi";
+import {
+  isOAS31,
+  operationRefs,
+  type DerefSpec,
+  type OperationEntry,
+} from "./openapi";
 import type { OpenAPIV3 } from "openapi-types";
 
 function createBaseSpec(overrides: Partial<DerefSpec> = {}): DerefSpec {
@@ -63,7 +68,51 @@ describe("isOAS31", () => {
   });
 });
 
-describe("listOperations", () => {
+const METHODS = [
+  "get",
+  "post",
+  "put",
+  "patch",
+  "delete",
+  "head",
+  "options",
+  "trace",
+] as const;
+
+/** Builds the index the backend would return for the spec's paths */
+function indexOf(spec: DerefSpec): OperationEntry[] {
+  return Object.entries(spec.paths || {}).flatMap(([path, item]) =>
+    METHODS.filter((method) => item?.[method]).map((method) => {
+      const tags = item?.[method]?.tags ?? [];
+      return entry(method, path, { tags, tag: tags[0] ?? "default" });
+    })
+  );
+}
+
+function entry(
+  method: string,
+  path: string,
+  overrides: Partial<OperationEntry> = {}
+): OperationEntry {
+  return {
+    key: `${method}:${path}`,
+    method,
+    path,
+    operationId: null,
+    summary: null,
+    tag: "default",
+    tags: [],
+    deprecated: false,
+    webhook: false,
+    ...overrides,
+  };
+}
+
+function listOperations(spec: DerefSpec) {
+  return operationRefs(spec, indexOf(spec));
+}
+
+describe("operationRefs", () => {
   describe("basic operation listing", () => {
     it("returns empty array for spec with no paths", () => {
       const spec = createBaseSpec({ paths: {} });
@@ -160,8 +209,8 @@ describe("listOperations", () => {
     });
   });
 
-  describe("tag handling", () => {
-    it("uses first tag when operation has tags", () => {
+  describe("index entries", () => {
+    it("takes the tag from the index entry", () => {
       const spec = createBaseSpec({
         paths: {
           "/users": {
@@ -172,38 +221,62 @@ describe("listOperations", () => {
         },
       });
 
-      const ops = listOperations(spec);
-      expect(ops[0].tag).toBe("Users");
+      const ops = operationRefs(spec, [
+        entry("get", "/users", { tag: "Admin" }),
+      ]);
+      expect(ops[0].tag).toBe("Admin");
     });
 
-    it("uses 'default' tag when operation has no tags", () => {
+    it("keeps the order of the index", () => {
       const spec = createBaseSpec({
         paths: {
-          "/users": {
-            get: createOperation({
-              tags: undefined,
-            }),
-          },
+          "/users": { get: createOperation(), post: createOperation() },
+          "/products": { get: createOperation() },
         },
       });
 
-      const ops = listOperations(spec);
-      expect(ops[0].tag).toBe("default");
+      const ops = operationRefs(spec, [
+        entry("get", "/products"),
+        entry("post", "/users"),
+        entry("get", "/users"),
+      ]);
+      expect(ops.map((o) => `${o.method}:${o.path}`)).toEqual([
+        "get:/products",
+        "post:/users",
+        "get:/users",
+      ]);
     });
 
-    it("uses 'default' tag when operation has empty tags array", () => {
+    it("skips entries missing from the spec", () => {
       const spec = createBaseSpec({
         paths: {
-          "/users": {
-            get: createOperation({
-              tags: [],
-            }),
-          },
+          "/users": { get: createOperation() },
         },
       });
 
-      const ops = listOperations(spec);
-      expect(ops[0].tag).toBe("default");
+      const ops = operationRefs(spec, [
+        entry("post", "/users"),
+        entry("get", "/missing"),
+        entry("get", "/users"),
+      ]);
+      expect(ops).toHaveLength(1);
+      expect(ops[0].method).toBe("get");
+    });
+
+    it("looks up webhooks by name", () => {
+      const spec = createBaseSpec({
+        openapi: "3.1.0",
+        webhooks: {
+          nodeChanged: { post: createOperation({ operationId: "onChange" }) },
+        },
+      } as Partial<DerefSpec>);
+
+      const ops = operationRefs(spec, [
+        entry("post", "nodeChanged", { webhook: true }),
+      ]);
+      expect(ops).toHaveLength(1);
+      expect(ops[0].path).toBe("nodeChanged");
+      expect(ops[0].op.operationId).toBe("onChange");
     });
   });
 
@@ -313,24 +386,28 @@ describe("listOperations", () => {
     });
   });
 
-  describe("deduplication", () => {
-    it("deduplicates operations by method:path key", () => {
-      // This shouldn't happen in valid specs, but tests the dedup logic
+  describe("repeated calls", () => {
+    it("does not add path-level parameters to the spec's operations", () => {
       const spec = createBaseSpec({
         paths: {
-          "/users": {
+          "/users/{id}": {
+            parameters: [
+              {
+                name: "id",
+                in: "path",
+                required: true,
+                schema: { type: "string" },
+              },
+            ],
             get: createOperation(),
           },
         },
       });
 
-      // First call
-      const ops1 = listOperations(spec);
-      expect(ops1).toHaveLength(1);
-
-      // Second call should still return 1 (not accumulate)
-      const ops2 = listOperations(spec);
-      expect(ops2).toHaveLength(1);
+      listOperations(spec);
+      const ops = listOperations(spec);
+      expect(ops[0].op.parameters).toHaveLength(1);
+      expect(spec.paths?.["/users/{id}"]?.get?.parameters).toBeUndefined();
     });
   });
 
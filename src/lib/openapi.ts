@@ -1,114 +1,82 @@
-import SwaggerParser from "@apidevtools/swagger-parser";
-import type { OpenAPI, OpenAPIV3, OpenAPIV3_1 } from "openapi-types";
-import converter from "swagger2openapi";
-import { saveSpec } from "@/lib/storage/sqliteRepository";
+import type { OpenAPIV3, OpenAPIV3_1 } from "openapi-types";
 import { invoke } from "@tauri-apps/api/core";
-import yaml from "js-yaml";
+import type { OperationRef } from "@/store/types";
 
 export type DerefSpec = OpenAPIV3.Document | OpenAPIV3_1.Document;
 
-const isTauri = () =>
-  typeof window !== "undefined" &&
-  "__TAURI_INTERNALS__" in window;
+/** An operation as listed by the backend's spec index */
+export type OperationEntry = {
+  /** `method:path`, or `method:name` for webhooks */
+  key: string;
+  method: string;
+  path: string;
+  operationId: string | null;
+  summary: string | null;
+  tag: string;
+  tags: string[];
+  deprecated: boolean;
+  webhook: boolean;
+};
+
+type LoadedSpec = {
+  id: string;
+  spec: DerefSpec;
+  operations: OperationEntry[];
+  openapiVersion: string;
+  convertedFrom: string | null;
+};
+
+type SpecSource =
+  | { kind: "Url"; value: string }
+  | { kind: "File"; value: string }
+  | { kind: "Content"; value: { id: string; content: string } };
+
+async function specSource(input: string | File): Promise<SpecSource> {
+  if (typeof input !== "string") {
+    return {
+      kind: "Content",
+      value: {
+        id: `${input.name}-${input.size}-${input.lastModified}`,
+        content: await input.text(),
+      },
+    };
+  }
+  if (input.startsWith("http://") || input.startsWith("https://")) {
+    return { kind: "Url", value: input };
+  }
+  return { kind: "File", value: input };
+}
 
 /**
- * Loads and processes an OpenAPI specification from a URL or File object.
- * This function automatically detects if the spec is Swagger 2.0 and, if so,
- * converts it to OpenAPI 3.0 before dereferencing. When running in a Tauri
- * environment, it uses the Rust backend to fetch URLs.
+ * Loads an OpenAPI specification from a URL, a file path or an uploaded File.
+ * The backend fetches it, resolves its `$ref`s, converts Swagger 2.0 to
+ * OpenAPI 3.0 and caches the result, so large specs don't block the UI.
  *
- * @param input - A URL string or a File object.
- * @returns A fully dereferenced OpenAPI 3.0+ specification.
+ * @param input - A URL or path string, or a File object.
+ * @returns The dereferenced OpenAPI 3.0+ specification and its operations.
  */
-export async function loadSpec(
-  input: string | File
-): Promise<{ spec: DerefSpec; id: string; baseUrl: string }> {
+export async function loadSpec(input: string | File): Promise<{
+  spec: DerefSpec;
+  id: string;
+  baseUrl: string;
+  operations: OperationRef[];
+}> {
   try {
-    let specObject: OpenAPI.Document;
-    let baseUrl: string | undefined;
-
-    const isHttpUrl =
-      typeof input === "string" &&
-      (input.startsWith("http://") || input.startsWith("https://"));
-
-    if (isHttpUrl && isTauri()) {
-      console.log("Tauri environment detected, fetching spec via backend...");
-      try {
-        // Using invoke to call the Rust command, as recommended.
-        const specContent = await invoke<string>("load_spec_from_url", {
-          url: input,
-        });
-        console.log("Spec fetched from backend successfully");
-        try {
-          specObject = JSON.parse(specContent) as OpenAPI.Document;
-          console.log("Parsed as JSON");
-        } catch {
-          // Fallback to YAML parsing if JSON fails
-          specObject = yaml.load(specContent) as OpenAPI.Document;
-          console.log("Parsed as YAML");
-        }
-        baseUrl = input;
-      } catch (error) {
-        console.error("Error fetching/parsing spec from backend:", error);
-        throw error;
-      }
-    } else {
-      console.log("Using SwaggerParser.parse for local file or URL");
-      try {
-        const specUrlOrObject =
-          typeof input === "string" ? input : await fileToObjectUrl(input);
-        specObject = await SwaggerParser.parse(specUrlOrObject);
-        baseUrl = specUrlOrObject;
-        console.log("SwaggerParser.parse completed successfully");
-      } catch (error) {
-        console.error("Error in SwaggerParser.parse:", error);
-        throw error;
-      }
-    }
-
-    let specToProcess = specObject;
-
-    if ("swagger" in specToProcess && specToProcess.swagger === "2.0") {
-      console.log("Detected Swagger 2.0 spec, attempting conversion...");
-      try {
-        const { openapi } = await converter.convertObj(specToProcess, {
-          patch: true,
-          warnOnly: true,
-          resolve: false, // Don't resolve external references
-        });
-        specToProcess = openapi;
-        console.log("Conversion to OpenAPI 3.0 successful.");
-      } catch (error) {
-        console.error("Error during Swagger 2.0 conversion:", error);
-        throw error;
-      }
-    }
-
-    console.log("Starting dereference...");
-    try {
-      const dereferencedDoc = (await SwaggerParser.dereference(specToProcess, {
-        resolve: {
-          external: false, // Disable external reference resolution for local files
-        },
-      })) as unknown as DerefSpec;
-      console.log("Dereference completed successfully");
-
-      const specId =
-        typeof input === "string"
-          ? input
-          : `${input.name}-${input.size}-${input.lastModified}`;
-
-      await saveSpec(specId, JSON.stringify(dereferencedDoc));
-
-      return {
-        spec: dereferencedDoc,
-        id: specId,
-        baseUrl: baseUrl || "",
-      };
-    } catch (error) {
-      console.error("Error during dereference:", error);
-      throw error;
+    const loaded = await invoke<LoadedSpec>("load_spec", {
+      source: await specSource(input),
+      refresh: true,
+    });
+    if (loaded.convertedFrom) {
+      console.log(
+        `Converted spec from ${loaded.convertedFrom} to OpenAPI ${loaded.openapiVersion}`
+      );
     }
+    return {
+      spec: loaded.spec,
+      id: loaded.id,
+      baseUrl: typeof input === "string" ? input : "",
+      operations: operationRefs(loaded.spec, loaded.operations),
+    };
   } catch (error) {
     console.error("Failed during spec loading or conversion:", error);
     throw new Error(
@@ -117,57 +85,57 @@ export async function loadSpec(
   }
 }
 
-async function fileToObjectUrl(file: File): Promise<string> {
-  return URL.createObjectURL(file);
+/**
+ * Lists the operations of a spec already cached by the backend.
+ */
+export async function loadOperations(
+  spec: DerefSpec,
+  specId: string
+): Promise<OperationRef[]> {
+  const entries = await invoke<OperationEntry[]>("get_operation_index", {
+    specId,
+  });
+  return operationRefs(spec, entries);
 }
 
 export function isOAS31(spec: DerefSpec): spec is OpenAPIV3_1.Document {
   return spec.openapi.startsWith("3.1");
 }
 
-export function listOperations(spec: DerefSpec) {
-  const ops: Array<{
-    method: string;
-    path: string;
-    op: OpenAPIV3.OperationObject | OpenAPIV3_1.OperationObject;
-    tag: string;
-  }> = [];
-  const seen = new Set<string>();
-
-  for (const [path, item] of Object.entries(spec.paths || {})) {
-    const pathItem = item as
+/**
+ * Pairs the backend's operation index with the operation objects in the spec,
+ * adding path-level parameters ahead of each operation's own.
+ */
+export function operationRefs(
+  spec: DerefSpec,
+  entries: OperationEntry[]
+): OperationRef[] {
+  const ops: OperationRef[] = [];
+
+  for (const entry of entries) {
+    const items = entry.webhook
+      ? (spec as OpenAPIV3_1.Document).webhooks
+      : spec.paths;
+    const pathItem = items?.[entry.path] as
       | OpenAPIV3.PathItemObject
-      | OpenAPIV3_1.PathItemObject;
-
-    for (const method of [
-      "get",
-      "post",
-      "put",
-      "patch",
-      "delete",
-      "head",
-      "options",
-      "trace",
-    ] as const) {
-      const op = pathItem?.[method];
-      if (!op) continue;
-
-      // Deduplicate by method:path key
-      const key = `${method}:${path}`;
-      if (seen.has(key)) continue;
-      seen.add(key);
-
-      const combinedParameters = [
-        ...(pathItem.parameters || []),
-        ...(op.parameters || []),
-      ];
-      if (combinedParameters.length > 0) {
-        op.parameters = combinedParameters;
-      }
-
-      const tag = op.tags?.[0] ?? "default";
-      ops.push({ method, path, op, tag });
-    }
+      | OpenAPIV3_1.PathItemObject
+      | undefined;
+    const op = pathItem?.[entry.method as OpenAPIV3.HttpMethods];
+    if (!pathItem || !op) continue;
+
+    const combinedParameters = [
+      ...(pathItem.parameters || []),
+      ...(op.parameters || []),
+    ];
+    ops.push({
+      method: entry.method,
+      path: entry.path,
+      op:
+        combinedParameters.length > 0
+          ? { ...op, parameters: combinedParameters }
+          : op,
+      tag: entry.tag,
+    });
   }
   return ops;
 }
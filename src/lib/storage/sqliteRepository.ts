@@ -1,66 +1,22 @@
 /**
  * SQLite Repository
  *
- * Central interface for all database operations using the Tauri SQL plugin.
- * This module provides type-safe wrappers around SQL queries for all 8 tables.
+ * Central interface for all database operations. Queries run in the backend's
+ * repository commands, which own transactions and data integrity. The backend
+ * migrates the database when the app starts, before the webview loads, so the
+ * frontend has no SQL access of its own.
  */
 
-import Database from "@tauri-apps/plugin-sql";
+import { invoke } from "@tauri-apps/api/core";
 import type {
   DbWorkspace,
   DbSpec,
-  DbEnvironment,
-  DbVariableKey,
-  DbVariableValue,
-  DbGlobalHeader,
-  DbAuthValue,
   DbOperationState,
-  DbHistoryEntry,
   DbResponseHistoryEntry,
   InitialData,
   FullWorkspaceData,
 } from "@/types/backend";
-
-let db: Database | null = null;
-
-/**
- * Initialize the database connection.
- * Must be called before any other database operations.
- */
-export async function initDatabase(): Promise<void> {
-  if (!db) {
-    try {
-      console.log("Loading SQLite database: sqlite:cogeass.db");
-      db = await Database.load("sqlite:cogeass.db");
-      console.log("SQLite database loaded successfully");
-
-      // Test the connection by running a simple query
-      await db.execute("SELECT 1");
-      console.log("Database connection verified");
-    } catch (error) {
-      console.error("Failed to initialize SQLite database:", error);
-      console.error(
-        "Error details:",
-        error instanceof Error ? error.message : String(error)
-      );
-      throw new Error(
-        `Database initialization failed: ${error instanceof Error ? error.message : String(error)
-        }`
-      );
-    }
-  }
-}
-
-/**
- * Get the database instance.
- * Throws if database is not initialized.
- */
-function getDb(): Database {
-  if (!db) {
-    throw new Error("Database not initialized. Call initDatabase() first.");
-  }
-  return db;
-}
+import type { SentRequest } from "@/lib/http/HttpClient";
 
 // ============================================================================
 // INITIAL LOAD
@@ -72,12 +28,9 @@ function getDb(): Database {
  */
 export async function loadInitialData(): Promise<InitialData> {
   try {
-    console.log("Querying workspaces table...");
-    const workspaces = await getDb().select<DbWorkspace[]>(
-      "SELECT * FROM workspaces ORDER BY sort_order ASC"
-    );
-    console.log(`Found ${workspaces.length} workspaces`);
-    return { workspaces };
+    const data = await invoke<InitialData>("load_initial_data");
+    console.log(`Found ${data.workspaces.length} workspaces`);
+    return data;
   } catch (error) {
     console.error("Failed to load initial data:", error);
     console.error(
@@ -99,44 +52,23 @@ export async function createWorkspace(
   name: string,
   sortOrder: number
 ): Promise<void> {
-  await getDb().execute(
-    "INSERT INTO workspaces (id, name, sort_order) VALUES (?, ?, ?)",
-    [id, name, sortOrder]
-  );
+  await invoke("create_workspace", { id, name, sortOrder });
 }
 
 /**
- * Update a workspace's details.
+ * Update a workspace's details. The backend rejects an active environment
+ * from another workspace or a missing active spec.
  */
 export async function updateWorkspace(workspace: DbWorkspace): Promise<void> {
-  await getDb().execute(
-    `UPDATE workspaces
-     SET name = ?,
-         active_spec_id = ?,
-         active_environment_id = ?,
-         base_url = ?,
-         selected_operation_key = ?,
-         sort_order = ?,
-         spec_url = ?
-     WHERE id = ?`,
-    [
-      workspace.name,
-      workspace.active_spec_id,
-      workspace.active_environment_id,
-      workspace.base_url,
-      workspace.selected_operation_key,
-      workspace.sort_order,
-      workspace.spec_url,
-      workspace.id,
-    ]
-  );
+  await invoke("update_workspace", { workspace });
 }
 
 /**
- * Delete a workspace and all its related data (CASCADE).
+ * Delete a workspace and all its related data (CASCADE), along with its spec
+ * when no other workspace uses it. A spec shared with another workspace is kept.
  */
 export async function deleteWorkspace(id: string): Promise<void> {
-  await getDb().execute("DELETE FROM workspaces WHERE id = ?", [id]);
+  await invoke("delete_workspace", { id });
 }
 
 /**
@@ -148,89 +80,28 @@ export async function getFullWorkspaceData(
 ): Promise<FullWorkspaceData | null> {
   try {
     console.log(`Fetching workspace data for: ${workspaceId}`);
-
-    // Fetch workspace details
-    const workspaces = await getDb().select<DbWorkspace[]>(
-      "SELECT * FROM workspaces WHERE id = ?",
-      [workspaceId]
+    const data = await invoke<FullWorkspaceData | null>(
+      "get_full_workspace_data",
+      { workspaceId }
     );
 
-    if (workspaces.length === 0) {
+    if (!data) {
       console.warn(`Workspace not found: ${workspaceId}`);
       return null;
     }
 
-    const workspace = workspaces[0];
-    console.log(`Workspace found: ${workspace.name}`);
-
-    // Fetch spec if active_spec_id is set
-    let spec: DbSpec | null = null;
-    if (workspace.active_spec_id) {
-      const specs = await getDb().select<DbSpec[]>(
-        "SELECT * FROM specs WHERE id = ?",
-        [workspace.active_spec_id]
-      );
-      spec = specs.length > 0 ? specs[0] : null;
-    }
-
-    // Fetch all related data in parallel
-    const [
-      environments,
-      variableKeys,
-      variableValues,
-      globalHeaders,
-      authValues,
-      history,
-    ] = await Promise.all([
-      getDb().select<DbEnvironment[]>(
-        "SELECT * FROM environments WHERE workspace_id = ?",
-        [workspaceId]
-      ),
-      getDb().select<DbVariableKey[]>(
-        "SELECT * FROM workspace_variable_keys WHERE workspace_id = ? ORDER BY key_name ASC",
-        [workspaceId]
-      ),
-      getDb().select<DbVariableValue[]>(
-        `SELECT evv.* FROM environment_variable_values evv
-       INNER JOIN environments e ON evv.environment_id = e.id
-       WHERE e.workspace_id = ?`,
-        [workspaceId]
-      ),
-      getDb().select<DbGlobalHeader[]>(
-        "SELECT * FROM global_headers WHERE workspace_id = ?",
-        [workspaceId]
-      ),
-      getDb().select<DbAuthValue[]>(
-        "SELECT * FROM auth_values WHERE workspace_id = ?",
-        [workspaceId]
-      ),
-      getDb().select<DbHistoryEntry[]>(
-        "SELECT * FROM history WHERE workspace_id = ? ORDER BY timestamp DESC LIMIT 50",
-        [workspaceId]
-      ),
-    ]);
-
     console.log(`Loaded workspace data:`, {
-      workspaceName: workspace.name,
-      hasSpec: !!spec,
-      environmentCount: environments.length,
-      variableKeyCount: variableKeys.length,
-      variableValueCount: variableValues.length,
-      globalHeaderCount: globalHeaders.length,
-      authValueCount: authValues.length,
-      historyCount: history.length,
+      workspaceName: data.workspace.name,
+      hasSpec: !!data.spec,
+      environmentCount: data.environments.length,
+      variableKeyCount: data.variableKeys.length,
+      variableValueCount: data.variableValues.length,
+      globalHeaderCount: data.globalHeaders.length,
+      authValueCount: data.authValues.length,
+      historyCount: data.history.length,
     });
 
-    return {
-      workspace,
-      spec,
-      environments,
-      variableKeys,
-      variableValues,
-      globalHeaders,
-      authValues,
-      history,
-    };
+    return data;
   } catch (error) {
     console.error(
       `Failed to get full workspace data for ${workspaceId}:`,
@@ -244,32 +115,18 @@ export async function getFullWorkspaceData(
 // SPEC MANAGEMENT
 // ============================================================================
 
-/**
- * Save or update a spec.
- */
-export async function saveSpec(id: string, specContent: string): Promise<void> {
-  await getDb().execute(
-    "INSERT OR REPLACE INTO specs (id, spec_content) VALUES (?, ?)",
-    [id, specContent]
-  );
-}
-
 /**
  * Get a spec by ID.
  */
 export async function getSpec(id: string): Promise<DbSpec | null> {
-  const specs = await getDb().select<DbSpec[]>(
-    "SELECT * FROM specs WHERE id = ?",
-    [id]
-  );
-  return specs.length > 0 ? specs[0] : null;
+  return invoke<DbSpec | null>("get_spec", { id });
 }
 
 /**
- * Delete a spec.
+ * Delete a spec, unsetting it on workspaces that had it active.
  */
 export async function deleteSpec(id: string): Promise<void> {
-  await getDb().execute("DELETE FROM specs WHERE id = ?", [id]);
+  await invoke("delete_spec", { id });
 }
 
 // ============================================================================
@@ -284,10 +141,7 @@ export async function createEnvironment(
   workspaceId: string,
   name: string
 ): Promise<void> {
-  await getDb().execute(
-    "INSERT INTO environments (id, workspace_id, name) VALUES (?, ?, ?)",
-    [id, workspaceId, name]
-  );
+  await invoke("create_environment", { id, workspaceId, name });
 }
 
 /**
@@ -297,17 +151,14 @@ export async function updateEnvironment(
   id: string,
   name: string
 ): Promise<void> {
-  await getDb().execute("UPDATE environments SET name = ? WHERE id = ?", [
-    name,
-    id,
-  ]);
+  await invoke("update_environment", { id, name });
 }
 
 /**
  * Delete an environment (CASCADE deletes variable values).
  */
 export async function deleteEnvironment(id: string): Promise<void> {
-  await getDb().execute("DELETE FROM environments WHERE id = ?", [id]);
+  await invoke("delete_environment", { id });
 }
 
 // ============================================================================
@@ -321,20 +172,14 @@ export async function addVariableKey(
   workspaceId: string,
   keyName: string
 ): Promise<number> {
-  const result = await getDb().execute(
-    "INSERT INTO workspace_variable_keys (workspace_id, key_name) VALUES (?, ?)",
-    [workspaceId, keyName]
-  );
-  return result.lastInsertId ?? 0;
+  return invoke<number>("add_variable_key", { workspaceId, keyName });
 }
 
 /**
  * Remove a variable key (CASCADE deletes all values).
  */
 export async function removeVariableKey(keyId: number): Promise<void> {
-  await getDb().execute("DELETE FROM workspace_variable_keys WHERE id = ?", [
-    keyId,
-  ]);
+  await invoke("remove_variable_key", { keyId });
 }
 
 /**
@@ -344,26 +189,19 @@ export async function renameVariableKey(
   keyId: number,
   newKeyName: string
 ): Promise<void> {
-  await getDb().execute(
-    "UPDATE workspace_variable_keys SET key_name = ? WHERE id = ?",
-    [newKeyName, keyId]
-  );
+  await invoke("rename_variable_key", { keyId, keyName: newKeyName });
 }
 
 /**
  * Set or update a variable value for a specific environment.
+ * Values of secret keys are encrypted by the backend.
  */
 export async function setVariableValue(
   environmentId: string,
   variableKeyId: number,
   value: string
 ): Promise<void> {
-  await getDb().execute(
-    `INSERT OR REPLACE INTO environment_variable_values
-     (environment_id, variable_key_id, value)
-     VALUES (?, ?, ?)`,
-    [environmentId, variableKeyId, value]
-  );
+  await invoke("set_variable_value", { environmentId, variableKeyId, value });
 }
 
 /**
@@ -373,10 +211,7 @@ export async function deleteVariableValue(
   environmentId: string,
   variableKeyId: number
 ): Promise<void> {
-  await getDb().execute(
-    "DELETE FROM environment_variable_values WHERE environment_id = ? AND variable_key_id = ?",
-    [environmentId, variableKeyId]
-  );
+  await invoke("delete_variable_value", { environmentId, variableKeyId });
 }
 
 // ============================================================================
@@ -391,10 +226,7 @@ export async function setGlobalHeader(
   key: string,
   value: string
 ): Promise<void> {
-  await getDb().execute(
-    "INSERT OR REPLACE INTO global_headers (workspace_id, key, value) VALUES (?, ?, ?)",
-    [workspaceId, key, value]
-  );
+  await invoke("set_global_header", { workspaceId, key, value });
 }
 
 /**
@@ -404,28 +236,17 @@ export async function deleteGlobalHeader(
   workspaceId: string,
   key: string
 ): Promise<void> {
-  await getDb().execute(
-    "DELETE FROM global_headers WHERE workspace_id = ? AND key = ?",
-    [workspaceId, key]
-  );
+  await invoke("delete_global_header", { workspaceId, key });
 }
 
 /**
- * Set all global headers for a workspace (replaces existing).
+ * Set all global headers for a workspace (replaces existing in one transaction).
  */
 export async function setAllGlobalHeaders(
   workspaceId: string,
   headers: Record<string, string>
 ): Promise<void> {
-  // Delete existing headers
-  await getDb().execute("DELETE FROM global_headers WHERE workspace_id = ?", [
-    workspaceId,
-  ]);
-
-  // Insert new headers
-  for (const [key, value] of Object.entries(headers)) {
-    await setGlobalHeader(workspaceId, key, value);
-  }
+  await invoke("set_all_global_headers", { workspaceId, headers });
 }
 
 // ============================================================================
@@ -434,6 +255,7 @@ export async function setAllGlobalHeaders(
 
 /**
  * Set or update an auth value (global or per-environment).
+ * A value already marked secret stays encrypted.
  */
 export async function setAuthValue(
   workspaceId: string,
@@ -441,12 +263,12 @@ export async function setAuthValue(
   schemeName: string,
   valueJson: string
 ): Promise<void> {
-  await getDb().execute(
-    `INSERT OR REPLACE INTO auth_values
-     (workspace_id, environment_id, scheme_name, value_json)
-     VALUES (?, ?, ?, ?)`,
-    [workspaceId, environmentId, schemeName, valueJson]
-  );
+  await invoke("set_auth_value", {
+    workspaceId,
+    environmentId,
+    schemeName,
+    valueJson,
+  });
 }
 
 /**
@@ -457,13 +279,7 @@ export async function deleteAuthValue(
   environmentId: string | null,
   schemeName: string
 ): Promise<void> {
-  await getDb().execute(
-    `DELETE FROM auth_values
-     WHERE workspace_id = ?
-     AND environment_id IS ?
-     AND scheme_name = ?`,
-    [workspaceId, environmentId, schemeName]
-  );
+  await invoke("delete_auth_value", { workspaceId, environmentId, schemeName });
 }
 
 // ============================================================================
@@ -479,13 +295,12 @@ export async function saveOperationState(
   formData: string,
   response: string | null
 ): Promise<void> {
-  const lastModified = Date.now();
-  await getDb().execute(
-    `INSERT OR REPLACE INTO operation_states
-     (workspace_id, operation_key, form_data, response, last_modified)
-     VALUES (?, ?, ?, ?, ?)`,
-    [workspaceId, operationKey, formData, response, lastModified]
-  );
+  await invoke("save_operation_state", {
+    workspaceId,
+    operationKey,
+    formData,
+    response,
+  });
 }
 
 /**
@@ -495,11 +310,10 @@ export async function getOperationState(
   workspaceId: string,
   operationKey: string
 ): Promise<DbOperationState | null> {
-  const states = await getDb().select<DbOperationState[]>(
-    "SELECT * FROM operation_states WHERE workspace_id = ? AND operation_key = ?",
-    [workspaceId, operationKey]
-  );
-  return states.length > 0 ? states[0] : null;
+  return invoke<DbOperationState | null>("get_operation_state", {
+    workspaceId,
+    operationKey,
+  });
 }
 
 /**
@@ -509,21 +323,14 @@ export async function deleteOperationState(
   workspaceId: string,
   operationKey: string
 ): Promise<void> {
-  await getDb().execute(
-    "DELETE FROM operation_states WHERE workspace_id = ? AND operation_key = ?",
-    [workspaceId, operationKey]
-  );
+  await invoke("delete_operation_state", { workspaceId, operationKey });
 }
 
 /**
  * Clean up old operation responses.
  */
 export async function cleanupOldResponses(maxAgeMs: number): Promise<void> {
-  const cutoffTime = Date.now() - maxAgeMs;
-  await getDb().execute(
-    "DELETE FROM operation_states WHERE last_modified < ?",
-    [cutoffTime]
-  );
+  await invoke("cleanup_old_responses", { maxAgeMs });
 }
 
 // ============================================================================
@@ -531,17 +338,13 @@ export async function cleanupOldResponses(maxAgeMs: number): Promise<void> {
 // ============================================================================
 
 /**
- * Add a history entry.
+ * Add a history entry. The backend keeps only the 50 most recent entries.
  */
 export async function addHistoryEntry(
   workspaceId: string,
   operationKey: string
 ): Promise<void> {
-  const timestamp = Date.now();
-  await getDb().execute(
-    "INSERT INTO history (workspace_id, operation_key, timestamp) VALUES (?, ?, ?)",
-    [workspaceId, operationKey, timestamp]
-  );
+  await invoke("add_history_entry", { workspaceId, operationKey });
 }
 
 /**
@@ -551,85 +354,53 @@ export async function pruneHistory(
   workspaceId: string,
   keepCount: number
 ): Promise<void> {
-  await getDb().execute(
-    `DELETE FROM history
-     WHERE workspace_id = ?
-     AND id NOT IN (
-       SELECT id FROM history
-       WHERE workspace_id = ?
-       ORDER BY timestamp DESC
-       LIMIT ?
-     )`,
-    [workspaceId, workspaceId, keepCount]
-  );
+  await invoke("prune_history", { workspaceId, keepCount });
 }
 
 /**
  * Clear all history for a workspace.
  */
 export async function clearHistory(workspaceId: string): Promise<void> {
-  await getDb().execute("DELETE FROM history WHERE workspace_id = ?", [
-    workspaceId,
-  ]);
+  await invoke("clear_history", { workspaceId });
 }
 
 // ============================================================================
 // RESPONSE HISTORY
 // ============================================================================
 
-const RESPONSE_HISTORY_LIMIT = 25;
-
 /**
- * Add a response history entry and prune old entries beyond the limit.
+ * Add a response history entry. The backend compresses large responses and
+ * prunes old entries according to the workspace's retention policy. The sent
+ * request, when given, is stored with its secrets redacted for replay.
  */
 export async function addResponseHistoryEntry(
   workspaceId: string,
   operationKey: string,
-  responseJson: string
+  responseJson: string,
+  request?: SentRequest
 ): Promise<number> {
-  const timestamp = Date.now();
-  const result = await getDb().execute(
-    `INSERT INTO response_history (workspace_id, operation_key, response_json, timestamp)
-     VALUES (?, ?, ?, ?)`,
-    [workspaceId, operationKey, responseJson, timestamp]
-  );
-
-  // Prune: keep only the most recent entries per operation
-  await getDb().execute(
-    `DELETE FROM response_history
-     WHERE workspace_id = ? AND operation_key = ?
-     AND id NOT IN (
-       SELECT id FROM response_history
-       WHERE workspace_id = ? AND operation_key = ?
-       ORDER BY timestamp DESC
-       LIMIT ?
-     )`,
-    [
-      workspaceId,
-      operationKey,
-      workspaceId,
-      operationKey,
-      RESPONSE_HISTORY_LIMIT,
-    ]
-  );
-
-  return result.lastInsertId ?? 0;
+  return invoke<number>("add_response_history", {
+    workspaceId,
+    operationKey,
+    responseJson,
+    request: request ?? null,
+    timestamp: Date.now(),
+  });
 }
 
 /**
- * Get response history for an operation (most recent first).
+ * Get response history for an operation (most recent first), decompressed by the backend.
+ * Every entry the workspace's retention policy kept is returned.
  */
 export async function getResponseHistory(
   workspaceId: string,
   operationKey: string
 ): Promise<DbResponseHistoryEntry[]> {
-  return getDb().select<DbResponseHistoryEntry[]>(
-    `SELECT * FROM response_history
-     WHERE workspace_id = ? AND operation_key = ?
-     ORDER BY timestamp DESC
-     LIMIT ?`,
-    [workspaceId, operationKey, RESPONSE_HISTORY_LIMIT]
-  );
+  return invoke<DbResponseHistoryEntry[]>("get_response_history", {
+    workspaceId,
+    operationKey,
+    limit: null,
+  });
 }
 
 /**
@@ -639,8 +410,5 @@ export async function clearResponseHistory(
   workspaceId: string,
   operationKey: string
 ): Promise<void> {
-  await getDb().execute(
-    "DELETE FROM response_history WHERE workspace_id = ? AND operation_key = ?",
-    [workspaceId, operationKey]
-  );
+  await invoke("clear_response_history", { workspaceId, operationKey });
 }
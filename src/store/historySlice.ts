@@ -52,17 +52,11 @@ export const createHistorySlice: StateCreator<
       }));
     }
 
-    // 2. Persist to SQLite database
+    // 2. Persist to SQLite database (the backend keeps the 50 most recent)
     sqlite
       .addHistoryEntry(wsId, key)
       .then(() => {
         console.log("History entry added to database:", key);
-
-        // Prune old history entries (keep only 50 most recent)
-        return sqlite.pruneHistory(wsId, 50);
-      })
-      .then(() => {
-        console.log("History pruned to 50 entries");
       })
       .catch((error) => {
         console.error("Failed to add history entry to database:", error);
@@ -45,30 +45,21 @@ export const createResponseHistorySlice: StateCreator<
     }
   },
 
-  addResponseHistoryEntry: async (operationKey, response) => {
+  addResponseHistoryEntry: async (operationKey, response, request) => {
     const wsId = get().activeWorkspaceId;
     if (!wsId) return;
 
     const responseJson = JSON.stringify(response);
-    const timestamp = response.timestamp || Date.now();
 
     try {
-      const id = await sqlite.addResponseHistoryEntry(
+      await sqlite.addResponseHistoryEntry(
         wsId,
         operationKey,
-        responseJson
+        responseJson,
+        request
       );
-
-      const current = get().responseHistory[operationKey] || [];
-      const newEntry: ResponseHistoryEntry = { id, response, timestamp };
-      const updated = [newEntry, ...current].slice(0, 25);
-
-      set((state) => ({
-        responseHistory: {
-          ...state.responseHistory,
-          [operationKey]: updated,
-        },
-      }));
+      // Reload rather than trimming locally, so the list matches what retention kept
+      await get().loadResponseHistory(operationKey);
     } catch (error) {
       console.error("Failed to add response history entry:", error);
     }
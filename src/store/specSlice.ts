@@ -72,16 +72,7 @@ export const createSpecSlice: StateCreator<AppState, [], [], SpecSlice> = (
       workspaces: nextWorkspaces,
     });
 
-    // Persist spec to SQLite (only if valid)
-    if (spec && id) {
-      try {
-        const specContent = JSON.stringify(spec);
-        await sqlite.saveSpec(id, specContent);
-        console.log("Spec saved to database:", id);
-      } catch (error) {
-        console.error("Failed to save spec to database:", error);
-      }
-    }
+    // `load_spec` already stored the spec in the `specs` table
 
     // Persist workspace update to database
     if (activeId) {
@@ -1,5 +1,9 @@
 import type { DerefSpec } from "@/lib/openapi";
-import type { ResponseTimings, StreamEvent } from "@/lib/http/HttpClient";
+import type {
+  ResponseTimings,
+  SentRequest,
+  StreamEvent,
+} from "@/lib/http/HttpClient";
 import type { OpenAPIV3, OpenAPIV3_1 } from "openapi-types";
 
 export type OperationRef = {
@@ -144,7 +148,8 @@ export interface ResponseHistorySlice {
   loadResponseHistory: (operationKey: string) => Promise<void>;
   addResponseHistoryEntry: (
     operationKey: string,
-    response: NonNullable<OperationState["response"]>
+    response: NonNullable<OperationState["response"]>,
+    request?: SentRequest
   ) => Promise<void>;
   clearResponseHistory: (operationKey: string) => Promise<void>;
 }
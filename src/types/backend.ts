@@ -45,6 +45,7 @@ export interface DbVariableKey {
   id: number;
   workspace_id: string;
   key_name: string;
+  is_secret: number; // 1 when values are encrypted by the backend vault
 }
 
 /**
@@ -54,7 +55,7 @@ export interface DbVariableKey {
 export interface DbVariableValue {
   environment_id: string;
   variable_key_id: number;
-  value: string;
+  value: string; // "••••••••" for secret values, which stay in the backend
 }
 
 /**
@@ -75,7 +76,8 @@ export interface DbAuthValue {
   workspace_id: string;
   environment_id: string | null;
   scheme_name: string;
-  value_json: string; // JSON string
+  value_json: string; // JSON string; fields of secret values read "••••••••"
+  is_secret: number;
 }
 
 /**
@@ -110,9 +112,24 @@ export interface DbResponseHistoryEntry {
   workspace_id: string;
   operation_key: string;
   response_json: string; // JSON string of the response object
+  request_json: string | null; // JSON fingerprint of the request, set for replay recordings
+  request_snapshot: DbRequestSnapshot | null; // The sent request, secrets redacted
   timestamp: number;
 }
 
+/**
+ * The request behind a response history entry, with secret values replaced by
+ * `{{name}}` placeholders and auth headers left empty. Filled back in on replay.
+ */
+export interface DbRequestSnapshot {
+  method: string;
+  url: string;
+  headers: Record<string, string>;
+  body: string | null;
+  environmentId: string | null;
+  authHeaders: string[];
+}
+
 /**
  * Composite payload returned by get_full_workspace_data().
  * This is the primary data structure for workspace switching.